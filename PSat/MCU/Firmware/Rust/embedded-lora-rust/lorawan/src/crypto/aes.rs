@@ -0,0 +1,119 @@
+//! A small software implementation of the AES-128 block cipher
+//!
+//! # Note
+//! LoRaWAN only ever uses the AES encryption direction (even to "decrypt" a join-accept), so the decryption direction
+//! is not implemented to keep the code size down.
+
+/// The AES substitution box
+const SBOX: [u8; 256] = [
+    0x63, 0x7C, 0x77, 0x7B, 0xF2, 0x6B, 0x6F, 0xC5, 0x30, 0x01, 0x67, 0x2B, 0xFE, 0xD7, 0xAB, 0x76, 0xCA, 0x82, 0xC9,
+    0x7D, 0xFA, 0x59, 0x47, 0xF0, 0xAD, 0xD4, 0xA2, 0xAF, 0x9C, 0xA4, 0x72, 0xC0, 0xB7, 0xFD, 0x93, 0x26, 0x36, 0x3F,
+    0xF7, 0xCC, 0x34, 0xA5, 0xE5, 0xF1, 0x71, 0xD8, 0x31, 0x15, 0x04, 0xC7, 0x23, 0xC3, 0x18, 0x96, 0x05, 0x9A, 0x07,
+    0x12, 0x80, 0xE2, 0xEB, 0x27, 0xB2, 0x75, 0x09, 0x83, 0x2C, 0x1A, 0x1B, 0x6E, 0x5A, 0xA0, 0x52, 0x3B, 0xD6, 0xB3,
+    0x29, 0xE3, 0x2F, 0x84, 0x53, 0xD1, 0x00, 0xED, 0x20, 0xFC, 0xB1, 0x5B, 0x6A, 0xCB, 0xBE, 0x39, 0x4A, 0x4C, 0x58,
+    0xCF, 0xD0, 0xEF, 0xAA, 0xFB, 0x43, 0x4D, 0x33, 0x85, 0x45, 0xF9, 0x02, 0x7F, 0x50, 0x3C, 0x9F, 0xA8, 0x51, 0xA3,
+    0x40, 0x8F, 0x92, 0x9D, 0x38, 0xF5, 0xBC, 0xB6, 0xDA, 0x21, 0x10, 0xFF, 0xF3, 0xD2, 0xCD, 0x0C, 0x13, 0xEC, 0x5F,
+    0x97, 0x44, 0x17, 0xC4, 0xA7, 0x7E, 0x3D, 0x64, 0x5D, 0x19, 0x73, 0x60, 0x81, 0x4F, 0xDC, 0x22, 0x2A, 0x90, 0x88,
+    0x46, 0xEE, 0xB8, 0x14, 0xDE, 0x5E, 0x0B, 0xDB, 0xE0, 0x32, 0x3A, 0x0A, 0x49, 0x06, 0x24, 0x5C, 0xC2, 0xD3, 0xAC,
+    0x62, 0x91, 0x95, 0xE4, 0x79, 0xE7, 0xC8, 0x37, 0x6D, 0x8D, 0xD5, 0x4E, 0xA9, 0x6C, 0x56, 0xF4, 0xEA, 0x65, 0x7A,
+    0xAE, 0x08, 0xBA, 0x78, 0x25, 0x2E, 0x1C, 0xA6, 0xB4, 0xC6, 0xE8, 0xDD, 0x74, 0x1F, 0x4B, 0xBD, 0x8B, 0x8A, 0x70,
+    0x3E, 0xB5, 0x66, 0x48, 0x03, 0xF6, 0x0E, 0x61, 0x35, 0x57, 0xB9, 0x86, 0xC1, 0x1D, 0x9E, 0xE1, 0xF8, 0x98, 0x11,
+    0x69, 0xD9, 0x8E, 0x94, 0x9B, 0x1E, 0x87, 0xE9, 0xCE, 0x55, 0x28, 0xDF, 0x8C, 0xA1, 0x89, 0x0D, 0xBF, 0xE6, 0x42,
+    0x68, 0x41, 0x99, 0x2D, 0x0F, 0xB0, 0x54, 0xBB, 0x16,
+];
+/// The round constants for the key schedule
+const RCON: [u8; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1B, 0x36];
+
+/// Substitutes a single byte via the S-box
+#[inline]
+fn sub_byte(byte: u8) -> u8 {
+    #[allow(clippy::indexing_slicing, reason = "A `u8` can never exceed the 256 entry table")]
+    SBOX[byte as usize]
+}
+
+/// Multiplies the given byte with `x` in `GF(2^8)`
+#[inline]
+const fn xtime(byte: u8) -> u8 {
+    match byte & 0x80 {
+        0 => byte << 1,
+        _ => (byte << 1) ^ 0x1B,
+    }
+}
+
+/// An AES-128 cipher with an expanded key
+#[derive(Clone)]
+pub struct Aes128 {
+    /// The round keys
+    round_keys: [[u8; 16]; 11],
+}
+impl Aes128 {
+    /// Creates a new AES-128 cipher by expanding the given key
+    pub fn new(key: &[u8; 16]) -> Self {
+        let mut round_keys = [*key; 11];
+        let mut round_key = *key;
+
+        // Derive each round key from the previous one
+        for (slot, rcon) in round_keys.iter_mut().skip(1).zip(RCON) {
+            // The first word is derived from the rotated and substituted last word of the previous key
+            let [.., a, b, c, d] = round_key;
+            let mut word = [sub_byte(b) ^ rcon, sub_byte(c), sub_byte(d), sub_byte(a)];
+            for previous in round_key.chunks_exact_mut(4) {
+                // Each word is the previous word XORed with the word at the same position in the previous key
+                for (byte, previous) in word.iter_mut().zip(previous) {
+                    *byte ^= *previous;
+                    *previous = *byte;
+                }
+            }
+            *slot = round_key;
+        }
+        Self { round_keys }
+    }
+
+    /// Encrypts a single block in place
+    pub fn encrypt_block(&self, block: &mut [u8; 16]) {
+        let [first, rounds @ .., last] = &self.round_keys;
+        Self::add_round_key(block, first);
+        for round_key in rounds {
+            Self::sub_bytes(block);
+            Self::shift_rows(block);
+            Self::mix_columns(block);
+            Self::add_round_key(block, round_key);
+        }
+        Self::sub_bytes(block);
+        Self::shift_rows(block);
+        Self::add_round_key(block, last);
+    }
+
+    /// XORs the round key into the state
+    fn add_round_key(state: &mut [u8; 16], round_key: &[u8; 16]) {
+        for (byte, key) in state.iter_mut().zip(round_key) {
+            *byte ^= key;
+        }
+    }
+    /// Substitutes each byte of the state
+    fn sub_bytes(state: &mut [u8; 16]) {
+        for byte in state.iter_mut() {
+            *byte = sub_byte(*byte);
+        }
+    }
+    /// Rotates row `n` of the column-major state left by `n` positions
+    fn shift_rows(state: &mut [u8; 16]) {
+        let [s0, s1, s2, s3, s4, s5, s6, s7, s8, s9, s10, s11, s12, s13, s14, s15] = *state;
+        *state = [s0, s5, s10, s15, s4, s9, s14, s3, s8, s13, s2, s7, s12, s1, s6, s11];
+    }
+    /// Mixes each column of the state
+    fn mix_columns(state: &mut [u8; 16]) {
+        for column in state.chunks_exact_mut(4) {
+            let [b0, b1, b2, b3] = column else {
+                // `chunks_exact_mut` only yields complete columns
+                continue;
+            };
+            let [a0, a1, a2, a3] = [*b0, *b1, *b2, *b3];
+            let all = a0 ^ a1 ^ a2 ^ a3;
+            *b0 = a0 ^ all ^ xtime(a0 ^ a1);
+            *b1 = a1 ^ all ^ xtime(a1 ^ a2);
+            *b2 = a2 ^ all ^ xtime(a2 ^ a3);
+            *b3 = a3 ^ all ^ xtime(a3 ^ a0);
+        }
+    }
+}
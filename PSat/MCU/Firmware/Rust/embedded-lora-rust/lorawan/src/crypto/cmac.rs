@@ -0,0 +1,100 @@
+//! AES-CMAC as specified in RFC 4493
+
+use crate::crypto::aes::Aes128;
+
+/// Doubles the given block in `GF(2^128)` to derive a CMAC subkey
+fn double(block: &[u8; 16]) -> [u8; 16] {
+    /// The reduction constant for 128 bit blocks
+    const RB: u8 = 0x87;
+
+    // Shift the entire block left by one bit
+    let mut doubled = [0; 16];
+    let mut carry = 0;
+    for (slot, byte) in doubled.iter_mut().zip(block).rev() {
+        *slot = (byte << 1) | carry;
+        carry = byte >> 7;
+    }
+
+    // Reduce if the most significant bit has been shifted out
+    let [.., last] = &mut doubled;
+    if carry != 0 {
+        *last ^= RB;
+    }
+    doubled
+}
+
+/// A streaming AES-CMAC computation
+#[derive(Clone)]
+pub struct Cmac {
+    /// The block cipher
+    cipher: Aes128,
+    /// The CBC-MAC state over all processed blocks
+    state: [u8; 16],
+    /// The pending, possibly incomplete last block
+    buffer: [u8; 16],
+    /// The amount of bytes in `buffer`
+    buffered: usize,
+}
+impl Cmac {
+    /// Creates a new CMAC computation with the given key
+    pub fn new(key: &[u8; 16]) -> Self {
+        Self { cipher: Aes128::new(key), state: [0; 16], buffer: [0; 16], buffered: 0 }
+    }
+
+    /// Processes the given data
+    pub fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            // Only process a full buffer once more data arrives, as the final block is treated specially
+            if self.buffered == self.buffer.len() {
+                self.process_buffer();
+            }
+
+            // Append the byte to the buffer
+            let Some(slot) = self.buffer.get_mut(self.buffered) else {
+                // The buffer has just been processed
+                continue;
+            };
+            *slot = *byte;
+            self.buffered = self.buffered.saturating_add(1);
+        }
+    }
+
+    /// Completes the computation and returns the tag
+    pub fn finalize(mut self) -> [u8; 16] {
+        // Derive the subkeys
+        let mut subkey = [0; 16];
+        self.cipher.encrypt_block(&mut subkey);
+        let mut subkey = double(&subkey);
+
+        // A complete last block is masked with the first subkey, an incomplete one is padded and masked with the second
+        if self.buffered < self.buffer.len() {
+            subkey = double(&subkey);
+            for (index, slot) in self.buffer.iter_mut().enumerate().skip(self.buffered) {
+                *slot = if index == self.buffered { 0x80 } else { 0x00 };
+            }
+        }
+        for (byte, key) in self.buffer.iter_mut().zip(subkey) {
+            *byte ^= key;
+        }
+
+        // Process the last block
+        self.process_buffer();
+        self.state
+    }
+
+    /// Chains the buffered block into the state and empties the buffer
+    fn process_buffer(&mut self) {
+        for (state, byte) in self.state.iter_mut().zip(self.buffer) {
+            *state ^= byte;
+        }
+        self.cipher.encrypt_block(&mut self.state);
+        self.buffered = 0;
+    }
+}
+
+/// Computes the AES-CMAC of the given message
+pub fn cmac(key: &[u8; 16], message: &[u8]) -> [u8; 16] {
+    let mut cmac = Cmac::new(key);
+    cmac.update(message);
+    cmac.finalize()
+}
@@ -0,0 +1,127 @@
+//! LoRaWAN 1.0.x cryptography
+
+pub mod aes;
+pub mod cmac;
+
+use crate::crypto::aes::Aes128;
+use crate::crypto::cmac::Cmac;
+use crate::frame::{DevAddr, Direction};
+use core::fmt::{Debug, Formatter};
+
+/// An AES-128 key, e.g. the `AppKey`, `NwkSKey` or `AppSKey`
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct AesKey([u8; 16]);
+impl AesKey {
+    /// Create a new key from the given raw key, in the byte order it is usually displayed in
+    pub const fn new(key: [u8; 16]) -> Self {
+        Self(key)
+    }
+
+    /// The raw key
+    pub const fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+}
+impl From<[u8; 16]> for AesKey {
+    fn from(value: [u8; 16]) -> Self {
+        Self(value)
+    }
+}
+impl Debug for AesKey {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_tuple("AesKey").field(&"<redacted>").finish()
+    }
+}
+
+/// Truncates a CMAC tag to the 4 byte MIC
+fn truncate(tag: [u8; 16]) -> [u8; 4] {
+    let [a, b, c, d, ..] = tag;
+    [a, b, c, d]
+}
+
+/// Computes the MIC of a join-request or join-accept message
+///
+/// # Note
+/// The message is everything from the `MHDR` up to the `MIC`; for a join-accept it must already be decrypted.
+pub fn join_mic(key: &AesKey, message: &[u8]) -> [u8; 4] {
+    truncate(cmac::cmac(key.as_bytes(), message))
+}
+
+/// Computes the MIC of a data message
+///
+/// # Note
+/// The message is everything from the `MHDR` up to the `MIC`, and must not exceed 255 bytes.
+pub fn data_mic(key: &AesKey, direction: Direction, dev_addr: DevAddr, fcnt: u32, message: &[u8]) -> [u8; 4] {
+    // Build the B0 block
+    let [a0, a1, a2, a3] = dev_addr.as_u32().to_le_bytes();
+    let [f0, f1, f2, f3] = fcnt.to_le_bytes();
+    let b0 = [0x49, 0, 0, 0, 0, direction as u8, a0, a1, a2, a3, f0, f1, f2, f3, 0, message.len() as u8];
+
+    // Compute the MIC over B0 and the message
+    let mut cmac = Cmac::new(key.as_bytes());
+    cmac.update(&b0);
+    cmac.update(message);
+    truncate(cmac.finalize())
+}
+
+/// Encrypts or decrypts a `FRMPayload` in place
+///
+/// # Note
+/// The payload is encrypted with the `NwkSKey` if `FPort` is `0`, and with the `AppSKey` otherwise. As the encryption
+/// is a simple XOR with a keystream, encryption and decryption are the same operation.
+pub fn crypt_payload(key: &AesKey, direction: Direction, dev_addr: DevAddr, fcnt: u32, payload: &mut [u8]) {
+    let cipher = Aes128::new(key.as_bytes());
+    let [a0, a1, a2, a3] = dev_addr.as_u32().to_le_bytes();
+    let [f0, f1, f2, f3] = fcnt.to_le_bytes();
+
+    for (index, chunk) in payload.chunks_mut(16).enumerate() {
+        // Build the keystream block `S_i = aes(A_i)`; the block counter starts at 1
+        let counter = (index as u8).wrapping_add(1);
+        let mut keystream = [0x01, 0, 0, 0, 0, direction as u8, a0, a1, a2, a3, f0, f1, f2, f3, 0, counter];
+        cipher.encrypt_block(&mut keystream);
+
+        // Apply the keystream
+        for (byte, key) in chunk.iter_mut().zip(keystream) {
+            *byte ^= key;
+        }
+    }
+}
+
+/// Decrypts a join-accept message in place
+///
+/// # Note
+/// The message is everything after the `MHDR`, including the `MIC`, and is either 16 or 32 bytes long. The network
+/// server encrypts it with the AES decryption, so the device decrypts it with the AES encryption.
+pub fn decrypt_join_accept(key: &AesKey, message: &mut [u8]) -> Result<(), &'static str> {
+    let (16 | 32) = message.len() else {
+        // The join-accept has an invalid length
+        return Err("Invalid join-accept length");
+    };
+
+    let cipher = Aes128::new(key.as_bytes());
+    for chunk in message.chunks_exact_mut(16) {
+        let Ok(block) = <&mut [u8; 16]>::try_from(chunk) else {
+            // `chunks_exact_mut` only yields complete blocks
+            continue;
+        };
+        cipher.encrypt_block(block);
+    }
+    Ok(())
+}
+
+/// Derives the `NwkSKey` and `AppSKey` from an accepted join
+pub fn derive_session_keys(app_key: &AesKey, app_nonce: [u8; 3], net_id: [u8; 3], dev_nonce: u16) -> (AesKey, AesKey) {
+    let cipher = Aes128::new(app_key.as_bytes());
+    let [j0, j1, j2] = app_nonce;
+    let [n0, n1, n2] = net_id;
+    let [d0, d1] = dev_nonce.to_le_bytes();
+
+    // Both keys are the encrypted join parameters, prefixed with the key type
+    let derive = |key_type: u8| {
+        let mut key = [key_type, j0, j1, j2, n0, n1, n2, d0, d1, 0, 0, 0, 0, 0, 0, 0];
+        cipher.encrypt_block(&mut key);
+        AesKey::new(key)
+    };
+    (derive(0x01), derive(0x02))
+}
@@ -0,0 +1,749 @@
+//! A LoRaWAN Class A end-device
+
+use crate::crypto::{self, AesKey};
+use crate::frame::{DevAddr, Downlink, Eui, JoinAccept, JoinRequest, Uplink, FOPTS_MAX, PHY_PAYLOAD_MAX};
+use crate::mac::{Answer, Answers, Command, Commands};
+use crate::region::{ChannelMask, Region, CHANNELS, CHANNELS_125KHZ};
+use core::time::Duration;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::OutputPin;
+use embedded_hal::spi::SpiBus;
+use embedded_lora_rfm95::lora::airtime;
+use embedded_lora_rfm95::lora::types::{
+    Bandwidth, CodingRate, CrcMode, Frequency, HeaderMode, PayloadLength, Polarity, PreambleLength, SyncWord,
+};
+use embedded_lora_rfm95::rfm95::Rfm95Driver;
+
+/// The delay between TX done and the RX1 window of a join-accept
+const JOIN_ACCEPT_DELAY1_MS: u32 = 5_000;
+/// The delay between the RX1 and RX2 windows
+const RX2_AFTER_RX1_MS: u32 = 1_000;
+/// How early a receive window is opened to compensate for clock drift and the modem startup time
+const RX_WINDOW_MARGIN_MS: u32 = 20;
+/// The amount of preamble symbols the modem needs to detect to lock onto a downlink
+const RX_WINDOW_SYMBOLS: u32 = 8;
+/// The maximum time to wait for a TX operation to complete
+const TX_TIMEOUT_MS: u32 = 10_000;
+/// The maximum time to wait for an RX operation to complete, including a long packet that is being received
+const RX_TIMEOUT_MS: u32 = 5_000;
+
+/// The session state of an activated device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    /// The device address
+    dev_addr: DevAddr,
+    /// The network session key
+    nwk_skey: AesKey,
+    /// The application session key
+    app_skey: AesKey,
+    /// The next uplink frame counter
+    fcnt_up: u32,
+    /// The next expected downlink frame counter
+    fcnt_down: u32,
+}
+impl Session {
+    /// Creates a new session with the given address and keys, and frame counters starting at `0`
+    pub const fn new(dev_addr: DevAddr, nwk_skey: AesKey, app_skey: AesKey) -> Self {
+        Self { dev_addr, nwk_skey, app_skey, fcnt_up: 0, fcnt_down: 0 }
+    }
+
+    /// Restores the frame counters, e.g. from non-volatile memory after a reset
+    ///
+    /// # Important
+    /// The network rejects uplinks with an already used frame counter, so ABP devices must persist the uplink frame
+    /// counter across resets.
+    pub const fn with_frame_counters(mut self, fcnt_up: u32, fcnt_down: u32) -> Self {
+        self.fcnt_up = fcnt_up;
+        self.fcnt_down = fcnt_down;
+        self
+    }
+
+    /// The device address
+    pub const fn dev_addr(&self) -> DevAddr {
+        self.dev_addr
+    }
+    /// The next uplink frame counter
+    pub const fn fcnt_up(&self) -> u32 {
+        self.fcnt_up
+    }
+    /// The next expected downlink frame counter
+    pub const fn fcnt_down(&self) -> u32 {
+        self.fcnt_down
+    }
+}
+
+/// A downlink that has been received after an uplink
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Received {
+    /// Whether the network has acknowledged the confirmed uplink
+    pub ack: bool,
+    /// Whether the network has more data pending and the device should send another uplink soon
+    pub fpending: bool,
+    /// The application port, or `None` if the downlink only carried MAC commands
+    pub port: Option<u8>,
+    /// The length of the application payload that has been copied into the downlink buffer
+    pub len: usize,
+}
+
+/// The activation method of a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Activation {
+    /// Over-the-air activation
+    Otaa {
+        /// The device EUI
+        dev_eui: Eui,
+        /// The join server EUI
+        join_eui: Eui,
+        /// The root key
+        app_key: AesKey,
+    },
+    /// Activation by personalization
+    Abp,
+}
+
+/// A receive window
+#[derive(Debug, Clone, Copy)]
+struct RxWindow {
+    /// The time after TX done at which the window opens
+    open_ms: u32,
+    /// The frequency
+    frequency: Frequency,
+    /// The data rate
+    data_rate: u8,
+}
+
+/// A decoded data downlink whose payload has been moved to the start of the receive buffer
+#[derive(Debug, Clone, Copy)]
+struct DataFrame {
+    /// Whether the downlink must be acknowledged
+    confirmed: bool,
+    /// Whether the last confirmed uplink is acknowledged
+    ack: bool,
+    /// Whether the network has more data pending
+    fpending: bool,
+    /// The full downlink frame counter
+    fcnt: u32,
+    /// The MAC commands in `FOpts`
+    fopts: [u8; FOPTS_MAX],
+    /// The length of `fopts`
+    fopts_len: usize,
+    /// The port
+    port: Option<u8>,
+    /// The length of the payload
+    len: usize,
+}
+
+/// A LoRaWAN Class A end-device on top of an RFM95 modem
+///
+/// # Class A
+/// The device only listens for downlinks in the two receive windows after each uplink. All operations are blocking;
+/// an uplink with both receive windows takes a few seconds, a join takes up to seven seconds.
+#[derive(Debug, Clone)]
+pub struct Device {
+    /// The channel plan
+    region: Region,
+    /// The activation method
+    activation: Activation,
+    /// The current session, if the device is activated
+    session: Option<Session>,
+    /// The next device nonce for OTAA
+    dev_nonce: u16,
+    /// The enabled uplink channels
+    channels: ChannelMask,
+    /// The uplink data rate
+    data_rate: u8,
+    /// Whether the network may control the data rate
+    adr: bool,
+    /// The offset between the uplink data rate and the RX1 data rate
+    rx1_dr_offset: u8,
+    /// The RX2 data rate
+    rx2_data_rate: u8,
+    /// The RX2 frequency
+    rx2_frequency: Frequency,
+    /// The delay between TX done and RX1 in seconds
+    rx_delay: u8,
+    /// The maximum aggregated duty cycle as exponent, i.e. `1 / 2^max_duty_cycle`
+    max_duty_cycle: u8,
+    /// The battery level reported via `DevStatusAns`
+    battery: u8,
+    /// The result of the last link check as `(margin, gateway_count)`
+    link_check: Option<(u8, u8)>,
+    /// Whether the next uplink must acknowledge a confirmed downlink
+    ack_pending: bool,
+    /// The pending MAC answers for the next uplink
+    answers: Answers,
+    /// The state of the channel selection PRNG
+    rng: u32,
+}
+impl Device {
+    /// Creates a new device that joins via OTAA
+    pub fn otaa<E, K>(region: Region, dev_eui: E, join_eui: E, app_key: K) -> Self
+    where
+        E: Into<Eui>,
+        K: Into<AesKey>,
+    {
+        let (dev_eui, join_eui) = (dev_eui.into(), join_eui.into());
+        let activation = Activation::Otaa { dev_eui, join_eui, app_key: app_key.into() };
+        Self::with_activation(region, activation, None, dev_eui.as_u64() as u32)
+    }
+
+    /// Creates a new device that has been activated by personalization
+    pub fn abp(region: Region, session: Session) -> Self {
+        Self::with_activation(region, Activation::Abp, Some(session), session.dev_addr.as_u32())
+    }
+
+    /// Creates a new device with the default parameters
+    fn with_activation(region: Region, activation: Activation, session: Option<Session>, seed: u32) -> Self {
+        Self {
+            region,
+            activation,
+            session,
+            dev_nonce: 0,
+            channels: ChannelMask::all(),
+            data_rate: region.default_data_rate(),
+            adr: false,
+            rx1_dr_offset: 0,
+            rx2_data_rate: Region::RX2_DATA_RATE,
+            rx2_frequency: Region::RX2_FREQUENCY,
+            rx_delay: 1,
+            max_duty_cycle: 0,
+            battery: 0xFF,
+            link_check: None,
+            ack_pending: false,
+            answers: Answers::new(),
+            rng: seed | 1,
+        }
+    }
+
+    /// The current session, if the device is activated
+    pub const fn session(&self) -> Option<&Session> {
+        self.session.as_ref()
+    }
+
+    /// The next device nonce for OTAA
+    pub const fn dev_nonce(&self) -> u16 {
+        self.dev_nonce
+    }
+    /// Sets the next device nonce for OTAA
+    ///
+    /// # Important
+    /// The network rejects join-requests with an already used nonce, so the nonce should be persisted across resets.
+    pub fn set_dev_nonce(&mut self, dev_nonce: u16) {
+        self.dev_nonce = dev_nonce;
+    }
+
+    /// The enabled uplink channels
+    pub const fn channels(&self) -> &ChannelMask {
+        &self.channels
+    }
+    /// Sets the enabled uplink channels
+    pub fn set_channels(&mut self, channels: ChannelMask) {
+        self.channels = channels;
+    }
+    /// Restricts the uplink channels to the given sub-band (see [`ChannelMask::sub_band`])
+    pub fn set_sub_band(&mut self, sub_band: u8) -> Result<(), &'static str> {
+        self.channels = ChannelMask::sub_band(sub_band)?;
+        Ok(())
+    }
+
+    /// The uplink data rate
+    pub const fn data_rate(&self) -> u8 {
+        self.data_rate
+    }
+    /// Sets the uplink data rate
+    pub fn set_data_rate(&mut self, data_rate: u8) -> Result<(), &'static str> {
+        let true = data_rate <= self.region.max_uplink_data_rate() else {
+            return Err("Invalid uplink data rate");
+        };
+        self.data_rate = data_rate;
+        Ok(())
+    }
+
+    /// Whether the network may control the data rate via ADR
+    pub const fn adr(&self) -> bool {
+        self.adr
+    }
+    /// Sets whether the network may control the data rate via ADR
+    pub fn set_adr(&mut self, adr: bool) {
+        self.adr = adr;
+    }
+
+    /// The maximum aggregated duty cycle requested by the network, as `1 / 2^max_duty_cycle`
+    ///
+    /// # Note
+    /// The duty cycle is not enforced by the device; the application must space its uplinks accordingly.
+    pub const fn max_duty_cycle(&self) -> u8 {
+        self.max_duty_cycle
+    }
+
+    /// Sets the battery level reported to the network; `0` means external power and `255` means unknown
+    pub fn set_battery_level(&mut self, battery: u8) {
+        self.battery = battery;
+    }
+
+    /// Requests a link check with the next uplink
+    pub fn request_link_check(&mut self) -> Result<(), &'static str> {
+        self.answers.push(Answer::LinkCheckReq)
+    }
+    /// The result of the last link check as `(margin, gateway_count)`
+    pub const fn link_check(&self) -> Option<(u8, u8)> {
+        self.link_check
+    }
+
+    /// Seeds the channel selection; mixing in some entropy avoids that several devices hop in lockstep
+    pub fn set_random_seed(&mut self, seed: u32) {
+        self.rng = seed | 1;
+    }
+
+    /// Joins the network via OTAA
+    ///
+    /// # Blocking
+    /// This function blocks until the join-accept has been received, or until the second receive window has passed.
+    pub fn join<Bus, Select, D>(
+        &mut self,
+        rfm95: &mut Rfm95Driver<Bus, Select>,
+        delay: &mut D,
+    ) -> Result<(), &'static str>
+    where
+        Bus: SpiBus,
+        Select: OutputPin,
+        D: DelayNs,
+    {
+        let Activation::Otaa { dev_eui, join_eui, app_key } = self.activation else {
+            return Err("ABP devices cannot join");
+        };
+
+        // Assemble the join-request and never reuse the nonce, even if the join fails
+        let dev_nonce = self.dev_nonce;
+        let request = JoinRequest { join_eui, dev_eui, dev_nonce }.encode(&app_key);
+        self.dev_nonce = self.dev_nonce.wrapping_add(1);
+
+        // Send the join-request and wait for the join-accept
+        let data_rate = self.region.default_data_rate();
+        let channel = self.pick_channel(data_rate)?;
+        self.transmit(rfm95, delay, channel, data_rate, &request)?;
+        let windows = self.rx_windows(channel, data_rate, JOIN_ACCEPT_DELAY1_MS)?;
+        let mut buf = [0; PHY_PAYLOAD_MAX];
+        let accept = self.receive(rfm95, delay, windows, &mut buf, |packet| JoinAccept::decode(packet, &app_key))?;
+        let accept = accept.ok_or("No join-accept received")?;
+
+        // Validate and apply the new settings
+        self.region.rx1_data_rate(data_rate, accept.rx1_dr_offset)?;
+        self.region.data_rate(accept.rx2_data_rate)?;
+        if let Some(cf_list) = accept.cf_list {
+            self.apply_cf_list(&cf_list)?;
+        }
+        self.rx1_dr_offset = accept.rx1_dr_offset;
+        self.rx2_data_rate = accept.rx2_data_rate;
+        self.rx_delay = accept.rx_delay.max(1);
+
+        // Start the new session
+        let (nwk_skey, app_skey) = crypto::derive_session_keys(&app_key, accept.join_nonce, accept.net_id, dev_nonce);
+        self.session = Some(Session::new(accept.dev_addr, nwk_skey, app_skey));
+        self.data_rate = data_rate;
+        self.ack_pending = false;
+        self.answers.clear();
+        Ok(())
+    }
+
+    /// Sends an uplink on the given application port and listens for a downlink
+    ///
+    /// # Downlink
+    /// If a downlink is received, its application payload is copied into `downlink` and its metadata is returned. MAC
+    /// commands are processed transparently and answered with the next uplink.
+    ///
+    /// # Confirmed uplinks
+    /// Confirmed uplinks are sent only once; if [`Received::ack`] is not set, it is up to the application to retry.
+    ///
+    /// # Blocking
+    /// This function blocks until a downlink has been received, or until the second receive window has passed.
+    #[allow(clippy::too_many_arguments, reason = "Mirrors the uplink fields")]
+    pub fn send<Bus, Select, D>(
+        &mut self,
+        rfm95: &mut Rfm95Driver<Bus, Select>,
+        delay: &mut D,
+        port: u8,
+        payload: &[u8],
+        confirmed: bool,
+        downlink: &mut [u8],
+    ) -> Result<Option<Received>, &'static str>
+    where
+        Bus: SpiBus,
+        Select: OutputPin,
+        D: DelayNs,
+    {
+        let 1..=223 = port else {
+            return Err("Invalid application port");
+        };
+        let Some(mut session) = self.session else {
+            return Err("Device is not activated");
+        };
+
+        // Validate the size against the data rate; the MACPayload consists of FHDR, FPort and FRMPayload
+        let mac_payload_len = 8usize.saturating_add(self.answers.as_bytes().len()).saturating_add(payload.len());
+        let true = mac_payload_len <= self.region.max_mac_payload(self.data_rate)? else {
+            return Err("Payload is too long for the data rate");
+        };
+
+        // Assemble the uplink
+        let uplink = Uplink {
+            confirmed,
+            dev_addr: session.dev_addr,
+            adr: self.adr,
+            adr_ack_req: false,
+            ack: self.ack_pending,
+            fcnt: session.fcnt_up,
+            fopts: self.answers.as_bytes(),
+            port: Some(port),
+            payload,
+        };
+        let mut phy = [0; PHY_PAYLOAD_MAX];
+        let phy_len = uplink.encode(&session.nwk_skey, &session.app_skey, &mut phy)?;
+        let phy = phy.get(..phy_len).unwrap_or_default();
+
+        // Never reuse a frame counter, even if the transmission fails
+        session.fcnt_up = session.fcnt_up.checked_add(1).ok_or("Uplink frame counter exhausted")?;
+        self.session = Some(session);
+        self.ack_pending = false;
+        self.answers.clear();
+
+        // Send the uplink and wait for a downlink
+        let data_rate = self.data_rate;
+        let channel = self.pick_channel(data_rate)?;
+        self.transmit(rfm95, delay, channel, data_rate, phy)?;
+        let rx1_delay_ms = u32::from(self.rx_delay).saturating_mul(1000);
+        let windows = self.rx_windows(channel, data_rate, rx1_delay_ms)?;
+        let frame = self.receive(rfm95, delay, windows, downlink, |packet| Self::decode_downlink(packet, &session))?;
+        let Some(frame) = frame else {
+            return Ok(None);
+        };
+
+        // Update the session and process the MAC commands
+        session.fcnt_down = frame.fcnt.saturating_add(1);
+        self.session = Some(session);
+        self.ack_pending = frame.confirmed;
+        let snr = rfm95.get_packet_snr()?;
+        let fopts = frame.fopts.get(..frame.fopts_len).unwrap_or_default();
+        self.process_commands(Commands::new(fopts), snr);
+        if frame.port == Some(0) {
+            let commands = downlink.get(..frame.len).unwrap_or_default();
+            self.process_commands(Commands::new(commands), snr);
+        }
+
+        // Only application payloads are passed to the caller
+        let len = match frame.port {
+            Some(1..) => frame.len,
+            _ => 0,
+        };
+        Ok(Some(Received { ack: frame.ack, fpending: frame.fpending, port: frame.port.filter(|port| *port != 0), len }))
+    }
+
+    /// Decodes a data downlink and moves the payload to the start of the buffer
+    fn decode_downlink(packet: &mut [u8], session: &Session) -> Result<DataFrame, &'static str> {
+        let downlink =
+            Downlink::decode(packet, session.dev_addr, &session.nwk_skey, &session.app_skey, session.fcnt_down)?;
+
+        // Copy the header fields, as the packet buffer is reused for the payload
+        let mut fopts = [0; FOPTS_MAX];
+        let fopts_len = downlink.fopts.len();
+        fopts.iter_mut().zip(downlink.fopts).for_each(|(slot, byte)| *slot = *byte);
+        let mut frame = DataFrame {
+            confirmed: downlink.confirmed,
+            ack: downlink.ack,
+            fpending: downlink.fpending,
+            fcnt: downlink.fcnt,
+            fopts,
+            fopts_len,
+            port: downlink.port,
+            len: downlink.payload.len(),
+        };
+
+        // The payload follows the 8 byte header, `FOpts` and the port
+        if frame.port.is_some() {
+            let start = fopts_len.saturating_add(9);
+            let end = start.saturating_add(frame.len);
+            let true = end <= packet.len() else {
+                return Err("Truncated downlink");
+            };
+            packet.copy_within(start..end, 0);
+        } else {
+            frame.len = 0;
+        }
+        Ok(frame)
+    }
+
+    /// Processes MAC commands from the network and queues the answers
+    fn process_commands(&mut self, commands: Commands, snr: i8) {
+        // Parsing stops at the first unknown command, as its length is unknown
+        for command in commands.map_while(Result::ok) {
+            let answer = match command {
+                Command::LinkCheckAns { margin, gateway_count } => {
+                    self.link_check = Some((margin, gateway_count));
+                    continue;
+                }
+                Command::LinkAdrReq { data_rate, tx_power, ch_mask, ch_mask_cntl, nb_trans: _ } => {
+                    self.link_adr(data_rate, tx_power, ch_mask, ch_mask_cntl)
+                }
+                Command::DutyCycleReq { max_duty_cycle } => {
+                    self.max_duty_cycle = max_duty_cycle;
+                    Answer::DutyCycleAns
+                }
+                Command::RxParamSetupReq { rx1_dr_offset, rx2_data_rate, frequency } => {
+                    self.rx_param_setup(rx1_dr_offset, rx2_data_rate, frequency)
+                }
+                Command::DevStatusReq => Answer::DevStatusAns { battery: self.battery, margin: snr.clamp(-32, 31) },
+                Command::NewChannelReq { .. } => {
+                    // Fixed channel plans have no configurable channels
+                    Answer::NewChannelAns { data_rate_range_ok: false, channel_frequency_ok: false }
+                }
+                Command::RxTimingSetupReq { delay } => {
+                    self.rx_delay = delay.max(1);
+                    Answer::RxTimingSetupAns
+                }
+                Command::TxParamSetupReq { .. } => match self.region {
+                    // US915 does not implement this command and must not answer
+                    Region::Us915 => continue,
+                    Region::Au915 => Answer::TxParamSetupAns,
+                },
+                Command::DlChannelReq { .. } => {
+                    // Fixed channel plans have no configurable channels
+                    Answer::DlChannelAns { channel_frequency_ok: false, uplink_frequency_exists: false }
+                }
+            };
+
+            // If `FOpts` is full, the answer is dropped and the network repeats the request
+            let _ = self.answers.push(answer);
+        }
+    }
+
+    /// Validates and applies a `LinkADRReq`
+    ///
+    /// # TX power
+    /// The RFM95 driver always transmits at maximum power, so only the maximum power (`0`) or "unchanged" (`15`) are
+    /// accepted.
+    fn link_adr(&mut self, data_rate: u8, tx_power: u8, ch_mask: u16, ch_mask_cntl: u8) -> Answer {
+        // A data rate or TX power of 15 means "keep the current value"
+        let data_rate = if data_rate == 0xF { self.data_rate } else { data_rate };
+        let data_rate_ack = data_rate <= self.region.max_uplink_data_rate();
+        let power_ack = matches!(tx_power, 0 | 0xF);
+
+        // The new channel mask must leave at least one channel for the data rate
+        let mut channels = self.channels;
+        let channel_mask_ack = channels.apply(ch_mask, ch_mask_cntl).is_ok() && channels.count(0..CHANNELS) > 0;
+
+        // The request is applied atomically
+        if power_ack && data_rate_ack && channel_mask_ack {
+            self.data_rate = data_rate;
+            self.channels = channels;
+        }
+        Answer::LinkAdrAns { power_ack, data_rate_ack, channel_mask_ack }
+    }
+
+    /// Validates and applies a `RXParamSetupReq`
+    fn rx_param_setup(&mut self, rx1_dr_offset: u8, rx2_data_rate: u8, frequency: u32) -> Answer {
+        let rx1_dr_offset_ack = self.region.rx1_data_rate(0, rx1_dr_offset).is_ok();
+        let rx2_data_rate_ack = rx2_data_rate >= 8 && self.region.data_rate(rx2_data_rate).is_ok();
+        let channel_ack = (923_300_000..=927_500_000).contains(&frequency);
+
+        // The request is applied atomically
+        if rx1_dr_offset_ack && rx2_data_rate_ack && channel_ack {
+            self.rx1_dr_offset = rx1_dr_offset;
+            self.rx2_data_rate = rx2_data_rate;
+            self.rx2_frequency = Frequency::hz(frequency);
+        }
+        Answer::RxParamSetupAns { rx1_dr_offset_ack, rx2_data_rate_ack, channel_ack }
+    }
+
+    /// Applies a channel mask `CFList` (`CFListType` 1) from a join-accept
+    fn apply_cf_list(&mut self, cf_list: &[u8; 16]) -> Result<(), &'static str> {
+        let [masks @ .., _rfu0, _rfu1, _rfu2, _rfu3, _rfu4, cf_list_type] = cf_list;
+        let 1 = *cf_list_type else {
+            // Fixed channel plans only use channel mask lists
+            return Err("Unsupported CFList type");
+        };
+
+        let mut channels = ChannelMask::none();
+        for (ch_mask_cntl, mask) in masks.chunks_exact(2).enumerate() {
+            let [m0, m1] = mask else {
+                // `chunks_exact` only yields complete masks
+                continue;
+            };
+            channels.apply(u16::from_le_bytes([*m0, *m1]), ch_mask_cntl as u8)?;
+        }
+        self.channels = channels;
+        Ok(())
+    }
+
+    /// Picks a random enabled channel for the given data rate
+    fn pick_channel(&mut self, data_rate: u8) -> Result<usize, &'static str> {
+        // 500 kHz data rates use the 500 kHz channels
+        let channels = match self.region.data_rate(data_rate)? {
+            (_, Bandwidth::B500) => CHANNELS_125KHZ..CHANNELS,
+            _ => 0..CHANNELS_125KHZ,
+        };
+        let count = self.channels.count(channels.clone());
+        let 1.. = count else {
+            return Err("No enabled channel for data rate");
+        };
+
+        // Advance the xorshift PRNG
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        let nth = (self.rng as usize).checked_rem(count).unwrap_or_default();
+        self.channels.nth_enabled(channels, nth).ok_or("No enabled channel for data rate")
+    }
+
+    /// The receive windows after an uplink on the given channel
+    fn rx_windows(&self, channel: usize, data_rate: u8, rx1_delay_ms: u32) -> Result<[RxWindow; 2], &'static str> {
+        let rx1 = RxWindow {
+            open_ms: rx1_delay_ms,
+            frequency: self.region.rx1_frequency(channel)?,
+            data_rate: self.region.rx1_data_rate(data_rate, self.rx1_dr_offset)?,
+        };
+        let rx2 = RxWindow {
+            open_ms: rx1_delay_ms.saturating_add(RX2_AFTER_RX1_MS),
+            frequency: self.rx2_frequency,
+            data_rate: self.rx2_data_rate,
+        };
+        Ok([rx1, rx2])
+    }
+
+    /// Configures the modem for an uplink or downlink
+    fn configure<Bus, Select>(
+        &self,
+        rfm95: &mut Rfm95Driver<Bus, Select>,
+        frequency: Frequency,
+        data_rate: u8,
+        polarity: Polarity,
+    ) -> Result<(), &'static str>
+    where
+        Bus: SpiBus,
+        Select: OutputPin,
+    {
+        // Uplinks carry a payload CRC, downlinks don't
+        let crc_mode = match polarity {
+            Polarity::Normal => CrcMode::Enabled,
+            Polarity::Inverted => CrcMode::Disabled,
+        };
+
+        let (spreading_factor, bandwidth) = self.region.data_rate(data_rate)?;
+        rfm95.set_spreading_factor(spreading_factor)?;
+        rfm95.set_bandwidth(bandwidth)?;
+        rfm95.set_coding_rate(CodingRate::C4_5)?;
+        rfm95.set_polarity(polarity)?;
+        rfm95.set_header_mode(HeaderMode::Explicit)?;
+        rfm95.set_payload_length(PayloadLength::MAX)?;
+        rfm95.set_crc_mode(crc_mode)?;
+        rfm95.set_sync_word(SyncWord::PUBLIC)?;
+        rfm95.set_preamble_len(PreambleLength::L8)?;
+        rfm95.set_frequency(frequency)?;
+        Ok(())
+    }
+
+    /// Sends the given PHY payload and waits until the TX operation is done
+    fn transmit<Bus, Select, D>(
+        &self,
+        rfm95: &mut Rfm95Driver<Bus, Select>,
+        delay: &mut D,
+        channel: usize,
+        data_rate: u8,
+        data: &[u8],
+    ) -> Result<(), &'static str>
+    where
+        Bus: SpiBus,
+        Select: OutputPin,
+        D: DelayNs,
+    {
+        let frequency = self.region.uplink_frequency(channel)?;
+        self.configure(rfm95, frequency, data_rate, Polarity::Normal)?;
+        rfm95.start_tx(data)?;
+
+        // The receive windows are timed from TX done, so poll in small steps
+        for _ in 0..TX_TIMEOUT_MS {
+            if rfm95.complete_tx()?.is_some() {
+                return Ok(());
+            }
+            delay.delay_ms(1);
+        }
+        Err("TX timeout")
+    }
+
+    /// Listens in the given receive windows, and returns the first downlink that is accepted by `accept`
+    ///
+    /// # Timing
+    /// This function must be called right after TX done, as the windows are timed relative to the call.
+    fn receive<Bus, Select, D, T, F>(
+        &self,
+        rfm95: &mut Rfm95Driver<Bus, Select>,
+        delay: &mut D,
+        windows: [RxWindow; 2],
+        buf: &mut [u8],
+        mut accept: F,
+    ) -> Result<Option<T>, &'static str>
+    where
+        Bus: SpiBus,
+        Select: OutputPin,
+        D: DelayNs,
+        F: FnMut(&mut [u8]) -> Result<T, &'static str>,
+    {
+        let mut clock_ms = 0u32;
+        for window in windows {
+            // Wait until the window opens; if we are already too late, the window is missed
+            let open_ms = window.open_ms.saturating_sub(RX_WINDOW_MARGIN_MS);
+            let Some(wait_ms) = open_ms.checked_sub(clock_ms) else {
+                continue;
+            };
+            delay.delay_ms(wait_ms);
+            clock_ms = open_ms;
+
+            // Listen and pass the downlink on; foreign or invalid downlinks are ignored
+            let Some(len) = self.receive_window(rfm95, delay, window, buf, &mut clock_ms)? else {
+                continue;
+            };
+            let Some(packet) = buf.get_mut(..len) else {
+                return Err("Downlink buffer is too small");
+            };
+            if let Ok(accepted) = accept(packet) {
+                return Ok(Some(accepted));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Listens in a single receive window and returns the length of the received packet, if any
+    fn receive_window<Bus, Select, D>(
+        &self,
+        rfm95: &mut Rfm95Driver<Bus, Select>,
+        delay: &mut D,
+        window: RxWindow,
+        buf: &mut [u8],
+        clock_ms: &mut u32,
+    ) -> Result<Option<usize>, &'static str>
+    where
+        Bus: SpiBus,
+        Select: OutputPin,
+        D: DelayNs,
+    {
+        self.configure(rfm95, window.frequency, window.data_rate, Polarity::Inverted)?;
+
+        // Keep the window open long enough to catch the preamble despite the early start
+        let (spreading_factor, bandwidth) = self.region.data_rate(window.data_rate)?;
+        let preamble = airtime::symbol_airtime(spreading_factor, bandwidth).saturating_mul(RX_WINDOW_SYMBOLS);
+        let margin = Duration::from_millis(u64::from(RX_WINDOW_MARGIN_MS).saturating_mul(2));
+        let timeout = preamble.saturating_add(margin).min(rfm95.rx_timeout_max()?);
+        rfm95.start_rx(timeout)?;
+
+        for _ in 0..RX_TIMEOUT_MS {
+            match rfm95.complete_rx(buf) {
+                Ok(Some(len)) => return Ok(Some(len)),
+                Ok(None) => (),
+                Err("RX timeout" | "RX CRC error") => return Ok(None),
+                Err(e) => return Err(e),
+            }
+            delay.delay_ms(1);
+            *clock_ms = clock_ms.saturating_add(1);
+        }
+        Err("RX operation did not complete")
+    }
+}
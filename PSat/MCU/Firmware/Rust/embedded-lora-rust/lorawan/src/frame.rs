@@ -0,0 +1,409 @@
+//! LoRaWAN 1.0.x PHY payload encoding and decoding
+
+use crate::crypto::{self, AesKey};
+
+/// The maximum size of a PHY payload
+pub const PHY_PAYLOAD_MAX: usize = 255;
+/// The maximum size of the `FOpts` field
+pub const FOPTS_MAX: usize = 15;
+/// The maximum gap between two downlink frame counters that is still accepted
+pub const MAX_FCNT_GAP: u32 = 16_384;
+
+/// The direction of a message, as used for cryptography
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Direction {
+    /// A message from the device to the network
+    Uplink = 0,
+    /// A message from the network to the device
+    Downlink = 1,
+}
+
+/// The message type in the `MHDR`
+///
+/// # Representation
+/// The message type can be represented as `u8`, where the value is the 3 bit `MType` field. The representation is
+/// compatible to the on-air representation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MType {
+    /// Join-request
+    JoinRequest = 0b000,
+    /// Join-accept
+    JoinAccept = 0b001,
+    /// Unconfirmed data uplink
+    UnconfirmedDataUp = 0b010,
+    /// Unconfirmed data downlink
+    UnconfirmedDataDown = 0b011,
+    /// Confirmed data uplink
+    ConfirmedDataUp = 0b100,
+    /// Confirmed data downlink
+    ConfirmedDataDown = 0b101,
+    /// Proprietary message
+    Proprietary = 0b111,
+}
+impl MType {
+    /// Assembles the `MHDR` for this message type and LoRaWAN major version 1
+    pub const fn mhdr(self) -> u8 {
+        (self as u8) << 5
+    }
+}
+impl TryFrom<u8> for MType {
+    type Error = &'static str;
+
+    /// Parses the message type from a `MHDR`
+    fn try_from(mhdr: u8) -> Result<Self, Self::Error> {
+        // Validate the major version
+        let 0b00 = mhdr & 0b11 else {
+            return Err("Unsupported LoRaWAN major version");
+        };
+
+        match mhdr >> 5 {
+            mtype if mtype == Self::JoinRequest as u8 => Ok(Self::JoinRequest),
+            mtype if mtype == Self::JoinAccept as u8 => Ok(Self::JoinAccept),
+            mtype if mtype == Self::UnconfirmedDataUp as u8 => Ok(Self::UnconfirmedDataUp),
+            mtype if mtype == Self::UnconfirmedDataDown as u8 => Ok(Self::UnconfirmedDataDown),
+            mtype if mtype == Self::ConfirmedDataUp as u8 => Ok(Self::ConfirmedDataUp),
+            mtype if mtype == Self::ConfirmedDataDown as u8 => Ok(Self::ConfirmedDataDown),
+            mtype if mtype == Self::Proprietary as u8 => Ok(Self::Proprietary),
+            _ => Err("Invalid message type"),
+        }
+    }
+}
+
+/// A 32 bit device address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct DevAddr(u32);
+impl DevAddr {
+    /// Create a new device address from the given raw address, as it is usually displayed
+    pub const fn new(addr: u32) -> Self {
+        Self(addr)
+    }
+
+    /// The device address as `u32`
+    pub const fn as_u32(self) -> u32 {
+        self.0
+    }
+}
+impl From<u32> for DevAddr {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+impl From<DevAddr> for u32 {
+    fn from(value: DevAddr) -> Self {
+        value.0
+    }
+}
+
+/// A 64 bit extended unique identifier, e.g. the `DevEUI` or `JoinEUI`/`AppEUI`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct Eui(u64);
+impl Eui {
+    /// Create a new EUI from the given raw EUI, as it is usually displayed
+    pub const fn new(eui: u64) -> Self {
+        Self(eui)
+    }
+
+    /// The EUI as `u64`
+    pub const fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+impl From<u64> for Eui {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+impl From<Eui> for u64 {
+    fn from(value: Eui) -> Self {
+        value.0
+    }
+}
+
+/// A join-request message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JoinRequest {
+    /// The join server EUI (`AppEUI` in LoRaWAN 1.0.2)
+    pub join_eui: Eui,
+    /// The device EUI
+    pub dev_eui: Eui,
+    /// The device nonce; must never be reused with the same `JoinEUI`
+    pub dev_nonce: u16,
+}
+impl JoinRequest {
+    /// The size of an encoded join-request
+    pub const SIZE: usize = 23;
+
+    /// Encodes and signs the join-request
+    pub fn encode(&self, app_key: &AesKey) -> [u8; Self::SIZE] {
+        let [j0, j1, j2, j3, j4, j5, j6, j7] = self.join_eui.as_u64().to_le_bytes();
+        let [d0, d1, d2, d3, d4, d5, d6, d7] = self.dev_eui.as_u64().to_le_bytes();
+        let [n0, n1] = self.dev_nonce.to_le_bytes();
+        let mhdr = MType::JoinRequest.mhdr();
+        let message = [mhdr, j0, j1, j2, j3, j4, j5, j6, j7, d0, d1, d2, d3, d4, d5, d6, d7, n0, n1];
+
+        let [m0, m1, m2, m3] = crypto::join_mic(app_key, &message);
+        [mhdr, j0, j1, j2, j3, j4, j5, j6, j7, d0, d1, d2, d3, d4, d5, d6, d7, n0, n1, m0, m1, m2, m3]
+    }
+}
+
+/// A decoded join-accept message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JoinAccept {
+    /// The join nonce (`AppNonce` in LoRaWAN 1.0.2)
+    pub join_nonce: [u8; 3],
+    /// The network ID
+    pub net_id: [u8; 3],
+    /// The assigned device address
+    pub dev_addr: DevAddr,
+    /// The offset between the uplink data rate and the RX1 data rate
+    pub rx1_dr_offset: u8,
+    /// The RX2 data rate
+    pub rx2_data_rate: u8,
+    /// The delay between TX done and RX1 in seconds; `0` means `1`
+    pub rx_delay: u8,
+    /// The optional channel frequency or channel mask list
+    pub cf_list: Option<[u8; 16]>,
+}
+impl JoinAccept {
+    /// Decrypts, authenticates and decodes a join-accept in place
+    pub fn decode(data: &mut [u8], app_key: &AesKey) -> Result<Self, &'static str> {
+        // Validate the message type and decrypt everything after the MHDR
+        let Some((mhdr, message)) = data.split_first_mut() else {
+            return Err("Empty join-accept");
+        };
+        let MType::JoinAccept = MType::try_from(*mhdr)? else {
+            return Err("Not a join-accept");
+        };
+        crypto::decrypt_join_accept(app_key, message)?;
+
+        // Validate the MIC
+        let Some(body_len) = data.len().checked_sub(4) else {
+            return Err("Truncated join-accept");
+        };
+        let (body, mic) = data.split_at(body_len);
+        let true = crypto::join_mic(app_key, body) == mic else {
+            return Err("Invalid join-accept MIC");
+        };
+
+        // Decode the fields
+        let [_mhdr, n0, n1, n2, i0, i1, i2, a0, a1, a2, a3, dl_settings, rx_delay, cf_list @ ..] = body else {
+            return Err("Truncated join-accept");
+        };
+        let cf_list = match cf_list {
+            [] => None,
+            cf_list => Some(<[u8; 16]>::try_from(cf_list).map_err(|_| "Invalid CFList length")?),
+        };
+        Ok(Self {
+            join_nonce: [*n0, *n1, *n2],
+            net_id: [*i0, *i1, *i2],
+            dev_addr: DevAddr::new(u32::from_le_bytes([*a0, *a1, *a2, *a3])),
+            rx1_dr_offset: (dl_settings >> 4) & 0b111,
+            rx2_data_rate: dl_settings & 0b1111,
+            rx_delay: rx_delay & 0b1111,
+            cf_list,
+        })
+    }
+}
+
+/// An uplink data message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Uplink<'a> {
+    /// Whether the network must acknowledge the uplink
+    pub confirmed: bool,
+    /// The device address
+    pub dev_addr: DevAddr,
+    /// Whether the network may control the data rate via ADR
+    pub adr: bool,
+    /// Whether the device requests a downlink to validate its ADR settings
+    pub adr_ack_req: bool,
+    /// Whether the last confirmed downlink is acknowledged
+    pub ack: bool,
+    /// The full 32 bit uplink frame counter
+    pub fcnt: u32,
+    /// The unencrypted MAC commands piggybacked in the header
+    pub fopts: &'a [u8],
+    /// The port, or `None` if there is no `FRMPayload`; port `0` carries MAC commands
+    pub port: Option<u8>,
+    /// The unencrypted payload
+    pub payload: &'a [u8],
+}
+impl Uplink<'_> {
+    /// Encodes, encrypts and signs the uplink into `buf` and returns the length of the PHY payload
+    pub fn encode(&self, nwk_skey: &AesKey, app_skey: &AesKey, buf: &mut [u8]) -> Result<usize, &'static str> {
+        // Validate the field combinations
+        let fopts_len @ 0..=FOPTS_MAX = self.fopts.len() else {
+            return Err("FOpts is too long");
+        };
+        match (self.port, self.payload.is_empty(), self.fopts.is_empty()) {
+            (None, false, _) => return Err("Payload without port"),
+            (Some(0), _, false) => return Err("MAC commands in both FOpts and payload"),
+            _ => (),
+        }
+
+        // Assemble the header
+        let mtype = if self.confirmed { MType::ConfirmedDataUp } else { MType::UnconfirmedDataUp };
+        let fctrl =
+            ((self.adr as u8) << 7) | ((self.adr_ack_req as u8) << 6) | ((self.ack as u8) << 5) | fopts_len as u8;
+        let [a0, a1, a2, a3] = self.dev_addr.as_u32().to_le_bytes();
+        let [f0, f1, ..] = self.fcnt.to_le_bytes();
+        let header = [mtype.mhdr(), a0, a1, a2, a3, fctrl, f0, f1];
+
+        // Write the message into the buffer
+        let mut writer = Writer::new(buf);
+        writer.write(&header)?;
+        writer.write(self.fopts)?;
+        if let Some(port) = self.port {
+            writer.write(&[port])?;
+            let payload = writer.write(self.payload)?;
+            let key = if port == 0 { nwk_skey } else { app_skey };
+            crypto::crypt_payload(key, Direction::Uplink, self.dev_addr, self.fcnt, payload);
+        }
+
+        // Sign the message
+        let mic = crypto::data_mic(nwk_skey, Direction::Uplink, self.dev_addr, self.fcnt, writer.written());
+        writer.write(&mic)?;
+        Ok(writer.len)
+    }
+}
+
+/// A decoded downlink data message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Downlink<'a> {
+    /// Whether the device must acknowledge the downlink
+    pub confirmed: bool,
+    /// Whether the network accepts ADR
+    pub adr: bool,
+    /// Whether the last confirmed uplink is acknowledged
+    pub ack: bool,
+    /// Whether the network has more data pending
+    pub fpending: bool,
+    /// The full 32 bit downlink frame counter
+    pub fcnt: u32,
+    /// The MAC commands piggybacked in the header
+    pub fopts: &'a [u8],
+    /// The port, or `None` if there is no `FRMPayload`; port `0` carries MAC commands
+    pub port: Option<u8>,
+    /// The decrypted payload
+    pub payload: &'a [u8],
+}
+impl<'a> Downlink<'a> {
+    /// Decodes, authenticates and decrypts a downlink in place
+    ///
+    /// # Frame counter
+    /// Only the lower 16 bit of the frame counter are sent over the air; the upper bits are reconstructed from
+    /// `fcnt_down`, which is the next expected downlink frame counter. Frames more than [`MAX_FCNT_GAP`] ahead are
+    /// rejected, as are replayed frames.
+    pub fn decode(
+        data: &'a mut [u8],
+        dev_addr: DevAddr,
+        nwk_skey: &AesKey,
+        app_skey: &AesKey,
+        fcnt_down: u32,
+    ) -> Result<Self, &'static str> {
+        // Split the message and the MIC
+        let Some(message_len) = data.len().checked_sub(4) else {
+            return Err("Truncated downlink");
+        };
+        let (message, mic) = data.split_at_mut(message_len);
+
+        // Decode the header
+        let [mhdr, a0, a1, a2, a3, fctrl, f0, f1, ..] = message else {
+            return Err("Truncated downlink");
+        };
+        let confirmed = match MType::try_from(*mhdr)? {
+            MType::UnconfirmedDataDown => false,
+            MType::ConfirmedDataDown => true,
+            _ => return Err("Not a data downlink"),
+        };
+        let true = u32::from_le_bytes([*a0, *a1, *a2, *a3]) == dev_addr.as_u32() else {
+            return Err("Downlink for another device");
+        };
+
+        // Reconstruct the full frame counter and validate it
+        let fcnt_low = u16::from_le_bytes([*f0, *f1]);
+        let fcnt = reconstruct_fcnt(fcnt_down, fcnt_low)?;
+        let (fctrl, fopts_len) = (*fctrl, (*fctrl & 0b1111) as usize);
+
+        // Validate the MIC before we touch the payload
+        let expected_mic = crypto::data_mic(nwk_skey, Direction::Downlink, dev_addr, fcnt, message);
+        let true = expected_mic == *mic else {
+            return Err("Invalid downlink MIC");
+        };
+
+        // Split FOpts, FPort and FRMPayload, and decrypt the payload
+        let [_, _, _, _, _, _, _, _, rest @ ..] = message else {
+            return Err("Truncated downlink");
+        };
+        let Some((fopts, rest)) = rest.split_at_mut_checked(fopts_len) else {
+            return Err("Truncated FOpts");
+        };
+        let (port, payload) = match rest.split_first_mut() {
+            None => (None, &mut [][..]),
+            Some((0, _)) if !fopts.is_empty() => return Err("MAC commands in both FOpts and payload"),
+            Some((port, payload)) => {
+                let key = if *port == 0 { nwk_skey } else { app_skey };
+                crypto::crypt_payload(key, Direction::Downlink, dev_addr, fcnt, payload);
+                (Some(*port), payload)
+            }
+        };
+
+        Ok(Self {
+            confirmed,
+            adr: fctrl & 0b1000_0000 != 0,
+            ack: fctrl & 0b0010_0000 != 0,
+            fpending: fctrl & 0b0001_0000 != 0,
+            fcnt,
+            fopts,
+            port,
+            payload,
+        })
+    }
+}
+
+/// Reconstructs the full 32 bit frame counter from the next expected counter and the lower 16 bit from the air
+fn reconstruct_fcnt(expected: u32, low: u16) -> Result<u32, &'static str> {
+    // Assume the same upper bits first, and roll over if the lower bits are behind
+    let mut fcnt = (expected & 0xFFFF_0000) | low as u32;
+    if fcnt < expected {
+        fcnt = fcnt.checked_add(0x1_0000).ok_or("Frame counter exhausted")?;
+    }
+
+    // Reject frames that are too far ahead
+    let true = fcnt.saturating_sub(expected) < MAX_FCNT_GAP else {
+        return Err("Frame counter gap too large");
+    };
+    Ok(fcnt)
+}
+
+/// A simple bounds-checked writer into a byte buffer
+struct Writer<'a> {
+    /// The underlying buffer
+    buf: &'a mut [u8],
+    /// The amount of bytes written so far
+    len: usize,
+}
+impl<'a> Writer<'a> {
+    /// Creates a new writer
+    fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    /// Appends the given data, and returns the written slice
+    fn write(&mut self, data: &[u8]) -> Result<&mut [u8], &'static str> {
+        let start = self.len;
+        let end = start.checked_add(data.len()).ok_or("Buffer is too small")?;
+        let slot = self.buf.get_mut(start..end).ok_or("Buffer is too small")?;
+        slot.copy_from_slice(data);
+        self.len = end;
+        Ok(slot)
+    }
+
+    /// All bytes written so far
+    fn written(&self) -> &[u8] {
+        self.buf.get(..self.len).unwrap_or_default()
+    }
+}
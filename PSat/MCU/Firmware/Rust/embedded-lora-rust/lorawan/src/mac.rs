@@ -0,0 +1,285 @@
+//! LoRaWAN 1.0.x MAC commands
+
+use crate::frame::FOPTS_MAX;
+
+/// A MAC command that has been sent by the network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// The answer to a `LinkCheckReq`
+    LinkCheckAns {
+        /// The link margin in dB above the demodulation floor
+        margin: u8,
+        /// The amount of gateways that received the request
+        gateway_count: u8,
+    },
+    /// Requests a data rate, TX power, channel mask and repetition change
+    LinkAdrReq {
+        /// The data rate
+        data_rate: u8,
+        /// The TX power index
+        tx_power: u8,
+        /// The channel mask for the block selected by `ch_mask_cntl`
+        ch_mask: u16,
+        /// The channel mask control
+        ch_mask_cntl: u8,
+        /// The amount of transmissions per uplink
+        nb_trans: u8,
+    },
+    /// Limits the aggregated duty cycle to `1 / 2^max_duty_cycle`
+    DutyCycleReq {
+        /// The duty cycle exponent
+        max_duty_cycle: u8,
+    },
+    /// Changes the RX parameters
+    RxParamSetupReq {
+        /// The offset between the uplink data rate and the RX1 data rate
+        rx1_dr_offset: u8,
+        /// The RX2 data rate
+        rx2_data_rate: u8,
+        /// The RX2 frequency in Hz
+        frequency: u32,
+    },
+    /// Requests the battery level and the demodulation margin
+    DevStatusReq,
+    /// Creates or modifies a channel; not supported by fixed channel plans
+    NewChannelReq {
+        /// The channel index
+        index: u8,
+        /// The frequency in Hz
+        frequency: u32,
+        /// The raw data rate range
+        dr_range: u8,
+    },
+    /// Changes the delay between TX done and RX1
+    RxTimingSetupReq {
+        /// The delay in seconds; `0` means `1`
+        delay: u8,
+    },
+    /// Changes the dwell time and maximum EIRP
+    TxParamSetupReq {
+        /// The raw EIRP and dwell time settings
+        settings: u8,
+    },
+    /// Changes a downlink frequency; not supported by fixed channel plans
+    DlChannelReq {
+        /// The channel index
+        index: u8,
+        /// The frequency in Hz
+        frequency: u32,
+    },
+}
+
+/// An iterator over the MAC commands in `FOpts` or in an `FRMPayload` with port `0`
+#[derive(Debug, Clone)]
+pub struct Commands<'a> {
+    /// The remaining unparsed data
+    data: &'a [u8],
+}
+impl<'a> Commands<'a> {
+    /// Creates a new iterator over the given data
+    pub const fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Decodes a 24 bit frequency in units of 100 Hz
+    fn frequency(raw: [u8; 3]) -> u32 {
+        let [f0, f1, f2] = raw;
+        u32::from_le_bytes([f0, f1, f2, 0]).saturating_mul(100)
+    }
+
+    /// Parses the next command and returns the amount of bytes consumed
+    fn parse(data: &[u8]) -> Result<(Command, usize), &'static str> {
+        let command = match data {
+            [0x02, margin, gateway_count, ..] => {
+                (Command::LinkCheckAns { margin: *margin, gateway_count: *gateway_count }, 3)
+            }
+            [0x03, dr_power, m0, m1, redundancy, ..] => {
+                let command = Command::LinkAdrReq {
+                    data_rate: dr_power >> 4,
+                    tx_power: dr_power & 0b1111,
+                    ch_mask: u16::from_le_bytes([*m0, *m1]),
+                    ch_mask_cntl: (redundancy >> 4) & 0b111,
+                    nb_trans: redundancy & 0b1111,
+                };
+                (command, 5)
+            }
+            [0x04, max_duty_cycle, ..] => (Command::DutyCycleReq { max_duty_cycle: max_duty_cycle & 0b1111 }, 2),
+            [0x05, dl_settings, f0, f1, f2, ..] => {
+                let command = Command::RxParamSetupReq {
+                    rx1_dr_offset: (dl_settings >> 4) & 0b111,
+                    rx2_data_rate: dl_settings & 0b1111,
+                    frequency: Self::frequency([*f0, *f1, *f2]),
+                };
+                (command, 5)
+            }
+            [0x06, ..] => (Command::DevStatusReq, 1),
+            [0x07, index, f0, f1, f2, dr_range, ..] => {
+                let frequency = Self::frequency([*f0, *f1, *f2]);
+                (Command::NewChannelReq { index: *index, frequency, dr_range: *dr_range }, 6)
+            }
+            [0x08, settings, ..] => (Command::RxTimingSetupReq { delay: settings & 0b1111 }, 2),
+            [0x09, settings, ..] => (Command::TxParamSetupReq { settings: *settings }, 2),
+            [0x0A, index, f0, f1, f2, ..] => {
+                (Command::DlChannelReq { index: *index, frequency: Self::frequency([*f0, *f1, *f2]) }, 5)
+            }
+            [0x02..=0x0A, ..] => return Err("Truncated MAC command"),
+            _ => return Err("Unknown MAC command"),
+        };
+        Ok(command)
+    }
+}
+impl Iterator for Commands<'_> {
+    type Item = Result<Command, &'static str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        match Self::parse(self.data) {
+            Ok((command, len)) => {
+                self.data = self.data.get(len..).unwrap_or_default();
+                Some(Ok(command))
+            }
+            Err(e) => {
+                // The length of unknown commands is unknown, so we cannot continue parsing
+                self.data = &[];
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// A MAC command that is sent by the device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Answer {
+    /// Requests a link check from the network
+    LinkCheckReq,
+    /// Answers a `LinkADRReq` with the power, data rate and channel mask acknowledgements
+    LinkAdrAns {
+        /// Whether the TX power has been accepted
+        power_ack: bool,
+        /// Whether the data rate has been accepted
+        data_rate_ack: bool,
+        /// Whether the channel mask has been accepted
+        channel_mask_ack: bool,
+    },
+    /// Acknowledges a `DutyCycleReq`
+    DutyCycleAns,
+    /// Answers a `RXParamSetupReq` with the RX1 offset, RX2 data rate and channel acknowledgements
+    RxParamSetupAns {
+        /// Whether the RX1 data rate offset has been accepted
+        rx1_dr_offset_ack: bool,
+        /// Whether the RX2 data rate has been accepted
+        rx2_data_rate_ack: bool,
+        /// Whether the RX2 frequency has been accepted
+        channel_ack: bool,
+    },
+    /// Answers a `DevStatusReq`
+    DevStatusAns {
+        /// The battery level; `0` means external power, `1..=254` is the level, and `255` means unknown
+        battery: u8,
+        /// The demodulation margin of the last `DevStatusReq` in dB, as signed 6 bit value
+        margin: i8,
+    },
+    /// Answers a `NewChannelReq` with the data rate range and frequency acknowledgements
+    NewChannelAns {
+        /// Whether the data rate range has been accepted
+        data_rate_range_ok: bool,
+        /// Whether the frequency has been accepted
+        channel_frequency_ok: bool,
+    },
+    /// Acknowledges a `RXTimingSetupReq`
+    RxTimingSetupAns,
+    /// Acknowledges a `TxParamSetupReq`
+    TxParamSetupAns,
+    /// Answers a `DlChannelReq` with the frequency and uplink frequency acknowledgements
+    DlChannelAns {
+        /// Whether the frequency has been accepted
+        channel_frequency_ok: bool,
+        /// Whether the uplink frequency exists
+        uplink_frequency_exists: bool,
+    },
+}
+impl Answer {
+    /// Encodes the answer and returns the buffer and the encoded length
+    pub fn encode(&self) -> ([u8; 3], usize) {
+        /// Packs up to three acknowledgement bits
+        fn bits(bit2: bool, bit1: bool, bit0: bool) -> u8 {
+            ((bit2 as u8) << 2) | ((bit1 as u8) << 1) | (bit0 as u8)
+        }
+
+        match *self {
+            Self::LinkCheckReq => ([0x02, 0, 0], 1),
+            Self::LinkAdrAns { power_ack, data_rate_ack, channel_mask_ack } => {
+                ([0x03, bits(power_ack, data_rate_ack, channel_mask_ack), 0], 2)
+            }
+            Self::DutyCycleAns => ([0x04, 0, 0], 1),
+            Self::RxParamSetupAns { rx1_dr_offset_ack, rx2_data_rate_ack, channel_ack } => {
+                ([0x05, bits(rx1_dr_offset_ack, rx2_data_rate_ack, channel_ack), 0], 2)
+            }
+            Self::DevStatusAns { battery, margin } => ([0x06, battery, (margin as u8) & 0b0011_1111], 3),
+            Self::NewChannelAns { data_rate_range_ok, channel_frequency_ok } => {
+                ([0x07, bits(false, data_rate_range_ok, channel_frequency_ok), 0], 2)
+            }
+            Self::RxTimingSetupAns => ([0x08, 0, 0], 1),
+            Self::TxParamSetupAns => ([0x09, 0, 0], 1),
+            Self::DlChannelAns { channel_frequency_ok, uplink_frequency_exists } => {
+                ([0x0A, bits(false, uplink_frequency_exists, channel_frequency_ok), 0], 2)
+            }
+        }
+    }
+}
+
+/// A queue of encoded answers that are piggybacked in the `FOpts` of the next uplink
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Answers {
+    /// The encoded answers
+    buf: [u8; FOPTS_MAX],
+    /// The amount of bytes used in `buf`
+    len: usize,
+}
+impl Answers {
+    /// Creates a new, empty queue
+    pub const fn new() -> Self {
+        Self { buf: [0; FOPTS_MAX], len: 0 }
+    }
+
+    /// Appends an answer
+    ///
+    /// # Note
+    /// Answers that do not fit into `FOpts` anymore are dropped and an error is returned; the network will repeat the
+    /// request.
+    pub fn push(&mut self, answer: Answer) -> Result<(), &'static str> {
+        let (encoded, len) = answer.encode();
+        let encoded = encoded.get(..len).unwrap_or_default();
+
+        let end = self.len.saturating_add(len);
+        let Some(slot) = self.buf.get_mut(self.len..end) else {
+            return Err("FOpts is full");
+        };
+        slot.copy_from_slice(encoded);
+        self.len = end;
+        Ok(())
+    }
+
+    /// The encoded answers
+    pub fn as_bytes(&self) -> &[u8] {
+        self.buf.get(..self.len).unwrap_or_default()
+    }
+
+    /// Whether the queue is empty
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes all answers, e.g. once they have been sent
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+impl Default for Answers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,239 @@
+//! Regional parameters for the fixed 72-channel plans
+
+use embedded_lora_rfm95::lora::types::{Bandwidth, Frequency, SpreadingFactor};
+
+/// The amount of uplink channels
+pub const CHANNELS: usize = 72;
+/// The amount of 125 kHz uplink channels; the remaining channels are 500 kHz channels
+pub const CHANNELS_125KHZ: usize = 64;
+
+/// A supported regional channel plan
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    /// The US 902-928 MHz channel plan
+    Us915,
+    /// The Australian 915-928 MHz channel plan
+    Au915,
+}
+impl Region {
+    /// The RX2 frequency
+    pub const RX2_FREQUENCY: Frequency = Frequency::hz(923_300_000);
+    /// The RX2 data rate
+    pub const RX2_DATA_RATE: u8 = 8;
+
+    /// The spreading factor and bandwidth for the given data rate
+    pub fn data_rate(self, data_rate: u8) -> Result<(SpreadingFactor, Bandwidth), &'static str> {
+        use Bandwidth::{B125, B500};
+        use SpreadingFactor::{S10, S11, S12, S7, S8, S9};
+
+        // Both plans share the 500 kHz downlink data rates
+        match (self, data_rate) {
+            (Self::Us915, 0) => Ok((S10, B125)),
+            (Self::Us915, 1) => Ok((S9, B125)),
+            (Self::Us915, 2) => Ok((S8, B125)),
+            (Self::Us915, 3) => Ok((S7, B125)),
+            (Self::Us915, 4) => Ok((S8, B500)),
+            (Self::Au915, 0) => Ok((S12, B125)),
+            (Self::Au915, 1) => Ok((S11, B125)),
+            (Self::Au915, 2) => Ok((S10, B125)),
+            (Self::Au915, 3) => Ok((S9, B125)),
+            (Self::Au915, 4) => Ok((S8, B125)),
+            (Self::Au915, 5) => Ok((S7, B125)),
+            (Self::Au915, 6) => Ok((S8, B500)),
+            (_, 8) => Ok((S12, B500)),
+            (_, 9) => Ok((S11, B500)),
+            (_, 10) => Ok((S10, B500)),
+            (_, 11) => Ok((S9, B500)),
+            (_, 12) => Ok((S8, B500)),
+            (_, 13) => Ok((S7, B500)),
+            _ => Err("Invalid data rate"),
+        }
+    }
+
+    /// The highest uplink data rate
+    pub const fn max_uplink_data_rate(self) -> u8 {
+        match self {
+            Self::Us915 => 4,
+            Self::Au915 => 6,
+        }
+    }
+
+    /// The data rate that is used for joins and as initial uplink data rate
+    ///
+    /// # Note
+    /// This is `SF10/125kHz` in both plans, which is the slowest data rate that still carries a useful payload in US915.
+    pub const fn default_data_rate(self) -> u8 {
+        match self {
+            Self::Us915 => 0,
+            Self::Au915 => 2,
+        }
+    }
+
+    /// The maximum `MACPayload` size (`FHDR`, `FPort` and `FRMPayload`) for the given uplink data rate, assuming no
+    /// dwell time limit
+    pub fn max_mac_payload(self, data_rate: u8) -> Result<usize, &'static str> {
+        match (self, data_rate) {
+            (Self::Us915, 0) => Ok(19),
+            (Self::Us915, 1) => Ok(61),
+            (Self::Us915, 2) => Ok(133),
+            (Self::Us915, 3 | 4) => Ok(250),
+            (Self::Au915, 0..=2) => Ok(59),
+            (Self::Au915, 3) => Ok(123),
+            (Self::Au915, 4..=6) => Ok(250),
+            _ => Err("Invalid uplink data rate"),
+        }
+    }
+
+    /// The RX1 data rate for the given uplink data rate and RX1 data rate offset
+    pub fn rx1_data_rate(self, uplink_data_rate: u8, rx1_dr_offset: u8) -> Result<u8, &'static str> {
+        // The downlink data rate is the uplink data rate shifted into the 500 kHz range, clamped to `DR8..=DR13`
+        let (base, max_offset) = match self {
+            Self::Us915 => (10, 3),
+            Self::Au915 => (8, 5),
+        };
+        let true = uplink_data_rate <= self.max_uplink_data_rate() else {
+            return Err("Invalid uplink data rate");
+        };
+        let true = rx1_dr_offset <= max_offset else {
+            return Err("Invalid RX1 data rate offset");
+        };
+
+        let data_rate = (uplink_data_rate.saturating_add(base)).saturating_sub(rx1_dr_offset);
+        Ok(data_rate.clamp(8, 13))
+    }
+
+    /// The uplink frequency of the given channel
+    pub fn uplink_frequency(self, channel: usize) -> Result<Frequency, &'static str> {
+        let (base_125khz, base_500khz) = match self {
+            Self::Us915 => (902_300_000, 903_000_000),
+            Self::Au915 => (915_200_000, 915_900_000),
+        };
+
+        // 125 kHz channels are spaced by 200 kHz, 500 kHz channels by 1.6 MHz
+        let frequency = match channel {
+            channel @ 0..CHANNELS_125KHZ => {
+                (channel as u32).checked_mul(200_000).and_then(|offset| offset.checked_add(base_125khz))
+            }
+            channel @ CHANNELS_125KHZ..CHANNELS => (channel.saturating_sub(CHANNELS_125KHZ) as u32)
+                .checked_mul(1_600_000)
+                .and_then(|offset| offset.checked_add(base_500khz)),
+            _ => None,
+        };
+        frequency.map(Frequency::hz).ok_or("Invalid channel")
+    }
+
+    /// The RX1 frequency for the given uplink channel
+    pub fn rx1_frequency(self, channel: usize) -> Result<Frequency, &'static str> {
+        let 0..CHANNELS = channel else {
+            return Err("Invalid channel");
+        };
+
+        // Uplink channels are mapped round-robin onto the 8 downlink channels, spaced by 600 kHz
+        let downlink_channel = (channel % 8) as u32;
+        let frequency = downlink_channel.saturating_mul(600_000).saturating_add(923_300_000);
+        Ok(Frequency::hz(frequency))
+    }
+}
+
+/// The set of enabled uplink channels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelMask {
+    /// The channel bits, in blocks of 16 channels as in `LinkADRReq`
+    blocks: [u16; 5],
+}
+impl ChannelMask {
+    /// A mask with all channels enabled
+    pub const fn all() -> Self {
+        Self { blocks: [0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x00FF] }
+    }
+
+    /// A mask with all channels disabled
+    pub const fn none() -> Self {
+        Self { blocks: [0; 5] }
+    }
+
+    /// A mask with only the given sub-band enabled
+    ///
+    /// # Sub-bands
+    /// The sub-bands are numbered from `1` to `8`; each sub-band consists of eight 125 kHz channels and one 500 kHz
+    /// channel. Most 8-channel gateways only listen on a single sub-band.
+    pub fn sub_band(sub_band: u8) -> Result<Self, &'static str> {
+        let sub_band @ 1..=8 = sub_band else {
+            return Err("Invalid sub-band");
+        };
+
+        // Enable the eight 125 kHz channels and the 500 kHz channel
+        let index = sub_band.saturating_sub(1) as usize;
+        let mut mask = Self::none();
+        for channel in (index.saturating_mul(8)..).take(8) {
+            mask.set(channel, true)?;
+        }
+        mask.set(CHANNELS_125KHZ.saturating_add(index), true)?;
+        Ok(mask)
+    }
+
+    /// Whether the given channel is enabled
+    pub fn is_enabled(&self, channel: usize) -> bool {
+        let (block, bit) = (channel / 16, channel % 16);
+        match self.blocks.get(block) {
+            Some(bits) if channel < CHANNELS => bits & (1 << bit) != 0,
+            _ => false,
+        }
+    }
+
+    /// Enables or disables the given channel
+    pub fn set(&mut self, channel: usize, enabled: bool) -> Result<(), &'static str> {
+        let (block, bit) = (channel / 16, channel % 16);
+        let (0..CHANNELS, Some(bits)) = (channel, self.blocks.get_mut(block)) else {
+            return Err("Invalid channel");
+        };
+
+        match enabled {
+            true => *bits |= 1 << bit,
+            false => *bits &= !(1 << bit),
+        }
+        Ok(())
+    }
+
+    /// The amount of enabled channels within the given channel range
+    pub fn count(&self, channels: core::ops::Range<usize>) -> usize {
+        channels.filter(|channel| self.is_enabled(*channel)).count()
+    }
+
+    /// The `nth` enabled channel within the given channel range
+    pub fn nth_enabled(&self, channels: core::ops::Range<usize>, nth: usize) -> Option<usize> {
+        channels.filter(|channel| self.is_enabled(*channel)).nth(nth)
+    }
+
+    /// Applies a `ChMask`/`ChMaskCntl` pair from a `LinkADRReq`
+    ///
+    /// # Fixed channel plans
+    /// `ChMaskCntl` `0..=4` replaces the given block of 16 channels, `6` enables all 125 kHz channels and `7` disables all
+    /// 125 kHz channels; for `6` and `7`, `ChMask` controls the 500 kHz channels.
+    pub fn apply(&mut self, ch_mask: u16, ch_mask_cntl: u8) -> Result<(), &'static str> {
+        match ch_mask_cntl {
+            0..=3 => {
+                let Some(block) = self.blocks.get_mut(ch_mask_cntl as usize) else {
+                    return Err("Invalid channel mask control");
+                };
+                *block = ch_mask;
+            }
+            4 => {
+                let [.., last] = &mut self.blocks;
+                *last = ch_mask & 0x00FF;
+            }
+            6 | 7 => {
+                let enabled = if ch_mask_cntl == 6 { 0xFFFF } else { 0x0000 };
+                let [b0, b1, b2, b3, b4] = &mut self.blocks;
+                (*b0, *b1, *b2, *b3, *b4) = (enabled, enabled, enabled, enabled, ch_mask & 0x00FF);
+            }
+            _ => return Err("Invalid channel mask control"),
+        }
+        Ok(())
+    }
+}
+impl Default for ChannelMask {
+    fn default() -> Self {
+        Self::all()
+    }
+}
@@ -0,0 +1,187 @@
+//! Class A join and uplink tests against a simulated gateway
+
+#[path = "../../rfm95/tests/common/mod.rs"]
+mod common;
+
+use common::{Ether, SimDelay, SimDriver};
+use embedded_lora_lorawan::crypto::{self, AesKey};
+use embedded_lora_lorawan::device::{Device, Session};
+use embedded_lora_lorawan::frame::{DevAddr, Direction};
+use embedded_lora_lorawan::region::Region;
+use embedded_lora_rfm95::lora::config::Config;
+use embedded_lora_rfm95::lora::types::{
+    AgcMode, Bandwidth, CodingRate, CrcMode, Frequency, HeaderMode, LnaBoost, LnaGain, PayloadLength, Polarity,
+    PreambleLength, SpreadingFactor, SyncWord,
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Decodes a hex string
+fn hex(hex: &str) -> Vec<u8> {
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect()
+}
+/// Decodes a hex key
+fn key(hex_key: &str) -> AesKey {
+    AesKey::new(hex(hex_key).try_into().expect("invalid key length"))
+}
+
+/// The device address assigned by the join-accept
+const DEV_ADDR: DevAddr = DevAddr::new(0x26011234);
+/// The session keys derived from the join-accept
+const NWK_SKEY: &str = "090bc182f2c3d00ee33025ea87c96fd4";
+const APP_SKEY: &str = "da703bf897f0d534ae9a44920e3fd7cb";
+
+/// Queues a gateway downlink on all eight RX1 frequencies, so it is heard regardless of the uplink channel
+fn queue_rx1_downlink(ether: &Rc<RefCell<Ether>>, spreading_factor: SpreadingFactor, payload: &[u8]) {
+    let mut gateway = common::driver(ether);
+    for channel in 0..8 {
+        let config = Config::builder()
+            .set_spreading_factor(spreading_factor)
+            .set_bandwidth(Bandwidth::B500)
+            .set_coding_rate(CodingRate::C4_5)
+            .set_polarity(Polarity::Inverted)
+            .set_header_mode(HeaderMode::Explicit)
+            .set_crc_mode(CrcMode::Disabled)
+            .set_sync_word(SyncWord::PUBLIC)
+            .set_preamble_length(PreambleLength::L8)
+            .set_frequency(Region::Au915.rx1_frequency(channel).unwrap())
+            .set_lna_gain(LnaGain::G1)
+            .set_lna_boost(LnaBoost::On)
+            .set_agc_mode(AgcMode::Auto)
+            .set_payload_length(PayloadLength::MAX);
+        gateway.set_config(&config).expect("failed to configure gateway");
+        common::transmit(&mut gateway, payload);
+    }
+}
+
+/// Builds an unconfirmed data downlink from the network server
+fn data_downlink(fcnt: u32, fctrl: u8, fopts: &[u8], port: u8, payload: &[u8]) -> Vec<u8> {
+    let mut message = vec![0x60];
+    message.extend(DEV_ADDR.as_u32().to_le_bytes());
+    message.push(fctrl | fopts.len() as u8);
+    message.extend(&fcnt.to_le_bytes()[..2]);
+    message.extend(fopts);
+    message.push(port);
+
+    let mut payload = payload.to_vec();
+    let payload_key = if port == 0 { key(NWK_SKEY) } else { key(APP_SKEY) };
+    crypto::crypt_payload(&payload_key, Direction::Downlink, DEV_ADDR, fcnt, &mut payload);
+    message.extend(payload);
+
+    let mic = crypto::data_mic(&key(NWK_SKEY), Direction::Downlink, DEV_ADDR, fcnt, &message);
+    message.extend(mic);
+    message
+}
+
+/// The uplinks the node has sent so far
+fn uplinks(ether: &Rc<RefCell<Ether>>) -> Vec<Vec<u8>> {
+    let ether = ether.borrow();
+    ether.transmissions.iter().filter(|t| !t.iq_inverted).map(|t| t.payload.clone()).collect()
+}
+
+/// Creates a node and joins it via a queued join-accept
+fn joined_node(ether: &Rc<RefCell<Ether>>) -> (SimDriver, Device) {
+    let mut rfm95 = common::driver(ether);
+    let mut device =
+        Device::otaa(Region::Au915, 0x0004A30B001C0530, 0x70B3D57ED0000001, key("2b7e151628aed2a6abf7158809cf4f3c"));
+    device.set_sub_band(2).expect("failed to set sub-band");
+
+    // The join uses DR2 (SF10/125kHz), so RX1 uses DR10 (SF10/500kHz)
+    queue_rx1_downlink(ether, SpreadingFactor::S10, &hex("209475ca75bd256c560dfc46e5b75ea2bd"));
+    device.join(&mut rfm95, &mut SimDelay).expect("failed to join");
+    (rfm95, device)
+}
+
+#[test]
+fn otaa_join() {
+    let ether = Ether::new();
+    let (_rfm95, device) = joined_node(&ether);
+
+    // The node must have sent the expected join-request
+    assert_eq!(uplinks(&ether), [hex("00010000d07ed5b37030051c000ba3040000005484d702")]);
+    assert_eq!(device.dev_nonce(), 1);
+
+    let session = device.session().expect("device is not activated");
+    assert_eq!(session.dev_addr(), DEV_ADDR);
+    assert_eq!((session.fcnt_up(), session.fcnt_down()), (0, 0));
+}
+
+#[test]
+fn join_without_accept_fails() {
+    let ether = Ether::new();
+    let mut rfm95 = common::driver(&ether);
+    let mut device = Device::otaa(Region::Us915, 1, 2, key("2b7e151628aed2a6abf7158809cf4f3c"));
+
+    assert_eq!(device.join(&mut rfm95, &mut SimDelay), Err("No join-accept received"));
+    assert!(device.session().is_none());
+    assert_eq!(device.dev_nonce(), 1);
+}
+
+#[test]
+fn uplink_uses_sub_band() {
+    let ether = Ether::new();
+    let (mut rfm95, mut device) = joined_node(&ether);
+
+    let mut downlink = [0; 255];
+    for _ in 0..16 {
+        let received = device.send(&mut rfm95, &mut SimDelay, 1, b"Testolope", false, &mut downlink);
+        assert_eq!(received, Ok(None));
+    }
+
+    // Sub-band 2 consists of the channels 8 to 15, i.e. 916.8 MHz to 918.2 MHz
+    let sub_band: Vec<_> = (8..16).map(|channel| Region::Au915.uplink_frequency(channel).unwrap()).collect();
+    let ether = ether.borrow();
+    for transmission in ether.transmissions.iter().filter(|t| !t.iq_inverted) {
+        let [msb, mid, lsb] = transmission.frequency;
+        let frf = u64::from_be_bytes([0, 0, 0, 0, 0, msb, mid, lsb]);
+        let frequency = Frequency::hz((frf * 61_035 / 1000) as u32);
+        assert!(sub_band.iter().any(|f| f.as_u32().abs_diff(frequency.as_u32()) < 100), "{frequency:?}");
+    }
+    assert_eq!(device.session().unwrap().fcnt_up(), 16);
+}
+
+#[test]
+fn downlink_with_mac_commands() {
+    let ether = Ether::new();
+    let (mut rfm95, mut device) = joined_node(&ether);
+
+    // The network answers with an application payload, a link check and a device status request
+    let downlink = data_downlink(0, 0b0010_0000, &[0x02, 0x07, 0x01, 0x06], 5, b"hi");
+    queue_rx1_downlink(&ether, SpreadingFactor::S10, &downlink);
+    let mut buf = [0; 255];
+    let received = device.send(&mut rfm95, &mut SimDelay, 1, b"Testolope", true, &mut buf).expect("failed to send");
+    let received = received.expect("no downlink received");
+    assert!(received.ack);
+    assert_eq!(received.port, Some(5));
+    assert_eq!(&buf[..received.len], b"hi");
+    assert_eq!(device.link_check(), Some((7, 1)));
+    assert_eq!(device.session().unwrap().fcnt_down(), 1);
+
+    // The next uplink must carry the DevStatusAns in FOpts
+    device.send(&mut rfm95, &mut SimDelay, 1, b"Testolope", false, &mut buf).expect("failed to send");
+    let uplinks = uplinks(&ether);
+    let last = uplinks.last().unwrap();
+    assert_eq!(last[5] & 0b1111, 3, "FOptsLen");
+    assert_eq!(&last[8..11], [0x06, 0xFF, 8]);
+}
+
+#[test]
+fn abp_uplink_frame_counter() {
+    let ether = Ether::new();
+    let mut rfm95 = common::driver(&ether);
+    let session = Session::new(DEV_ADDR, key(NWK_SKEY), key(APP_SKEY)).with_frame_counters(41, 0);
+    let mut device = Device::abp(Region::Us915, session);
+    let mut buf = [0; 255];
+
+    device.send(&mut rfm95, &mut SimDelay, 1, b"Testolope", false, &mut buf).expect("failed to send");
+    let uplinks = uplinks(&ether);
+    assert_eq!(uplinks[0][6..8], 41u16.to_le_bytes());
+    assert_eq!(device.session().unwrap().fcnt_up(), 42);
+
+    // US915 DR0 carries at most 11 bytes of application payload
+    assert_eq!(
+        device.send(&mut rfm95, &mut SimDelay, 1, &[0; 12], false, &mut buf),
+        Err("Payload is too long for the data rate")
+    );
+    assert_eq!(device.join(&mut rfm95, &mut SimDelay), Err("ABP devices cannot join"));
+}
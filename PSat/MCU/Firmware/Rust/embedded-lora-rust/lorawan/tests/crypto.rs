@@ -0,0 +1,54 @@
+//! Cryptography tests against published test vectors
+
+use embedded_lora_lorawan::crypto::aes::Aes128;
+use embedded_lora_lorawan::crypto::cmac;
+
+/// Decodes a hex string
+fn hex<const N: usize>(hex: &str) -> [u8; N] {
+    let bytes: Vec<u8> = (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect();
+    bytes.try_into().expect("invalid hex length")
+}
+
+/// The RFC 4493 key
+const RFC4493_KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+/// The RFC 4493 message; the examples use prefixes of it
+const RFC4493_MESSAGE: &str = concat!(
+    "6bc1bee22e409f96e93d7e117393172a",
+    "ae2d8a571e03ac9c9eb76fac45af8e51",
+    "30c81c46a35ce411e5fbc1191a0a52ef",
+    "f69f2445df4f9b17ad2b417be66c3710"
+);
+
+#[test]
+fn aes128_fips197() {
+    let cipher = Aes128::new(&hex("000102030405060708090a0b0c0d0e0f"));
+    let mut block = hex("00112233445566778899aabbccddeeff");
+    cipher.encrypt_block(&mut block);
+    assert_eq!(block, hex::<16>("69c4e0d86a7b0430d8cdb78070b4c55a"));
+}
+
+#[test]
+fn cmac_rfc4493() {
+    let key = hex(RFC4493_KEY);
+    let message = hex::<64>(RFC4493_MESSAGE);
+    for (len, tag) in [
+        (0, "bb1d6929e95937287fa37d129b756746"),
+        (16, "070a16b46b4d4144f79bdd9dd04a287c"),
+        (40, "dfa66747de9ae63030ca32611497c827"),
+        (64, "51f0bebf7e3b9d92fc49741779363cfe"),
+    ] {
+        assert_eq!(cmac::cmac(&key, &message[..len]), hex::<16>(tag), "message length {len}");
+    }
+}
+
+#[test]
+fn cmac_streaming_matches_oneshot() {
+    let key = hex(RFC4493_KEY);
+    let message = hex::<64>(RFC4493_MESSAGE);
+    for split in 0..=message.len() {
+        let mut streaming = cmac::Cmac::new(&key);
+        streaming.update(&message[..split]);
+        streaming.update(&message[split..]);
+        assert_eq!(streaming.finalize(), cmac::cmac(&key, &message), "split at {split}");
+    }
+}
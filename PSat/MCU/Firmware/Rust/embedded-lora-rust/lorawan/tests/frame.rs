@@ -0,0 +1,160 @@
+//! Frame encoding and decoding tests against independently computed messages
+
+use embedded_lora_lorawan::crypto::{self, AesKey};
+use embedded_lora_lorawan::frame::{DevAddr, Downlink, Eui, JoinAccept, JoinRequest, Uplink, PHY_PAYLOAD_MAX};
+use embedded_lora_lorawan::mac::{Answer, Answers, Command, Commands};
+
+/// Decodes a hex string
+fn hex(hex: &str) -> Vec<u8> {
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect()
+}
+/// Decodes a hex key
+fn key(hex_key: &str) -> AesKey {
+    AesKey::new(hex(hex_key).try_into().expect("invalid key length"))
+}
+
+/// The root key for the join tests
+const APP_KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+
+#[test]
+fn uplink_known_vector() {
+    let nwk_skey = key("44024241ed4ce9a68c6a8bc055233fd3");
+    let app_skey = key("ec925802ae430ca77fd3dd73cb2cc588");
+    let uplink = Uplink {
+        confirmed: false,
+        dev_addr: DevAddr::new(0x49BE7DF1),
+        adr: false,
+        adr_ack_req: false,
+        ack: false,
+        fcnt: 2,
+        fopts: &[],
+        port: Some(1),
+        payload: b"test",
+    };
+
+    let mut buf = [0; PHY_PAYLOAD_MAX];
+    let len = uplink.encode(&nwk_skey, &app_skey, &mut buf).expect("failed to encode uplink");
+    assert_eq!(&buf[..len], hex("40F17DBE4900020001954378762B11FF0D"));
+}
+
+#[test]
+fn uplink_rejects_invalid_fields() {
+    let key = key(APP_KEY);
+    let mut uplink = Uplink {
+        confirmed: true,
+        dev_addr: DevAddr::new(1),
+        adr: false,
+        adr_ack_req: false,
+        ack: false,
+        fcnt: 0,
+        fopts: &[0x02; 16],
+        port: Some(1),
+        payload: b"",
+    };
+    let mut buf = [0; PHY_PAYLOAD_MAX];
+    assert_eq!(uplink.encode(&key, &key, &mut buf), Err("FOpts is too long"));
+
+    uplink.fopts = &[0x02];
+    uplink.port = Some(0);
+    assert_eq!(uplink.encode(&key, &key, &mut buf), Err("MAC commands in both FOpts and payload"));
+
+    uplink.port = Some(1);
+    assert_eq!(uplink.encode(&key, &key, &mut buf[..12]), Err("Buffer is too small"));
+}
+
+#[test]
+fn join_request_known_vector() {
+    let request =
+        JoinRequest { join_eui: Eui::new(0x70B3D57ED0000001), dev_eui: Eui::new(0x0004A30B001C0530), dev_nonce: 0 };
+    assert_eq!(request.encode(&key(APP_KEY)).to_vec(), hex("00010000d07ed5b37030051c000ba3040000005484d702"));
+}
+
+#[test]
+fn join_accept_known_vector() {
+    let mut message = hex("209475ca75bd256c560dfc46e5b75ea2bd");
+    let accept = JoinAccept::decode(&mut message, &key(APP_KEY)).expect("failed to decode join-accept");
+    assert_eq!(accept.join_nonce, [0x01, 0x02, 0x03]);
+    assert_eq!(accept.net_id, [0x13, 0x00, 0x00]);
+    assert_eq!(accept.dev_addr, DevAddr::new(0x26011234));
+    assert_eq!((accept.rx1_dr_offset, accept.rx2_data_rate, accept.rx_delay), (0, 8, 1));
+    assert_eq!(accept.cf_list, None);
+
+    let (nwk_skey, app_skey) = crypto::derive_session_keys(&key(APP_KEY), accept.join_nonce, accept.net_id, 0);
+    assert_eq!(nwk_skey, key("090bc182f2c3d00ee33025ea87c96fd4"));
+    assert_eq!(app_skey, key("da703bf897f0d534ae9a44920e3fd7cb"));
+}
+
+#[test]
+fn join_accept_with_cf_list() {
+    let mut message = hex("207bee6587c77f8342456f4e42508457d2533668b10b1456dc98fcbf1f9a324663");
+    let accept = JoinAccept::decode(&mut message, &key(APP_KEY)).expect("failed to decode join-accept");
+    let cf_list = accept.cf_list.expect("missing CFList");
+    assert_eq!(cf_list[..2], [0x00, 0xFF]);
+    assert_eq!(cf_list[15], 0x01);
+}
+
+#[test]
+fn join_accept_rejects_wrong_key() {
+    let mut message = hex("209475ca75bd256c560dfc46e5b75ea2bd");
+    let result = JoinAccept::decode(&mut message, &key("000102030405060708090a0b0c0d0e0f"));
+    assert_eq!(result, Err("Invalid join-accept MIC"));
+}
+
+#[test]
+fn downlink_known_vector() {
+    let nwk_skey = key("090bc182f2c3d00ee33025ea87c96fd4");
+    let app_skey = key("da703bf897f0d534ae9a44920e3fd7cb");
+    let mut message = hex("603412012623010002070105f461bba25ca9");
+
+    let downlink = Downlink::decode(&mut message, DevAddr::new(0x26011234), &nwk_skey, &app_skey, 0)
+        .expect("failed to decode downlink");
+    assert!(!downlink.confirmed);
+    assert!(downlink.ack);
+    assert_eq!(downlink.fcnt, 1);
+    assert_eq!(downlink.port, Some(5));
+    assert_eq!(downlink.payload, b"hi");
+
+    let commands: Vec<_> = Commands::new(downlink.fopts).collect();
+    assert_eq!(commands, [Ok(Command::LinkCheckAns { margin: 7, gateway_count: 1 })]);
+}
+
+#[test]
+fn downlink_rejects_replay_and_foreign_address() {
+    let nwk_skey = key("090bc182f2c3d00ee33025ea87c96fd4");
+    let app_skey = key("da703bf897f0d534ae9a44920e3fd7cb");
+    let message = hex("603412012623010002070105f461bba25ca9");
+
+    // The frame counter 1 has already been seen, so the counter rolls over into a gap that is too large
+    let mut replayed = message.clone();
+    let result = Downlink::decode(&mut replayed, DevAddr::new(0x26011234), &nwk_skey, &app_skey, 2);
+    assert_eq!(result.err(), Some("Frame counter gap too large"));
+
+    let mut foreign = message.clone();
+    let result = Downlink::decode(&mut foreign, DevAddr::new(0x26011235), &nwk_skey, &app_skey, 0);
+    assert_eq!(result.err(), Some("Downlink for another device"));
+
+    let mut tampered = message.clone();
+    tampered[12] ^= 0x01;
+    let result = Downlink::decode(&mut tampered, DevAddr::new(0x26011234), &nwk_skey, &app_skey, 0);
+    assert_eq!(result.err(), Some("Invalid downlink MIC"));
+}
+
+#[test]
+fn mac_commands_roundtrip() {
+    let data = [0x03, 0x25, 0xFF, 0x00, 0x01, 0x06, 0x05, 0x72, 0x68, 0xE2, 0x8C, 0x0D, 0x00];
+    let commands: Vec<_> = Commands::new(&data).collect();
+    assert_eq!(
+        commands,
+        [
+            Ok(Command::LinkAdrReq { data_rate: 2, tx_power: 5, ch_mask: 0x00FF, ch_mask_cntl: 0, nb_trans: 1 }),
+            Ok(Command::DevStatusReq),
+            Ok(Command::RxParamSetupReq { rx1_dr_offset: 7, rx2_data_rate: 2, frequency: 923_300_000 }),
+            Err("Unknown MAC command"),
+        ]
+    );
+
+    let mut answers = Answers::new();
+    answers.push(Answer::LinkAdrAns { power_ack: true, data_rate_ack: true, channel_mask_ack: false }).unwrap();
+    answers.push(Answer::DevStatusAns { battery: 0xFF, margin: -3 }).unwrap();
+    assert_eq!(answers.as_bytes(), [0x03, 0b110, 0x06, 0xFF, 0b11_1101]);
+}
@@ -138,3 +138,14 @@ pub fn airtime(payload_len: usize, config: Config) -> Duration {
     #[allow(clippy::arithmetic_side_effects, reason = "This will never overflow")]
     Duration::from_micros(preamble_airtime + payload_airtime)
 }
+
+/// Computes the airtime of the longest message the config permits
+///
+/// # Note
+/// In [`crate::lora::types::HeaderMode::Implicit`], every message has exactly the configured payload length, so this
+/// is the airtime of every message sent or received with this config.
+#[must_use]
+pub fn max_airtime(config: Config) -> Duration {
+    let payload_len = u8::from(config.payload_len()) as usize;
+    airtime(payload_len, config)
+}
@@ -1,7 +1,9 @@
 //! A LoRa radio config object with builder pattern for initial initialization
+#![allow(clippy::type_complexity, reason = "The builder types are complex by design, one type parameter per field")]
 
 use crate::lora::types::{
-    Bandwidth, CodingRate, CrcMode, Frequency, HeaderMode, Polarity, PreambleLength, SpreadingFactor, SyncWord,
+    AgcMode, Bandwidth, CodingRate, CrcMode, Frequency, HeaderMode, LnaBoost, LnaGain, PayloadLength, Polarity,
+    PreambleLength, SpreadingFactor, SyncWord,
 };
 
 /// An LoRa `Config` builder
@@ -14,7 +16,21 @@ use crate::lora::types::{
 // Note: We use 1-letter abbreviations for the config fields to keep the code readable and to not bloat the file with
 // dozens of repetitions
 #[derive(Debug, Clone, Copy)]
-pub struct Builder<S = (), B = (), R = (), P = (), H = (), C = (), W = (), L = (), F = ()> {
+pub struct Builder<
+    S = (),
+    B = (),
+    R = (),
+    P = (),
+    H = (),
+    C = (),
+    W = (),
+    L = (),
+    F = (),
+    G = (),
+    O = (),
+    A = (),
+    N = (),
+> {
     /// Spreading factor
     s: S,
     /// Bandwidth
@@ -33,70 +49,300 @@ pub struct Builder<S = (), B = (), R = (), P = (), H = (), C = (), W = (), L = (
     l: L,
     /// Frequency
     f: F,
+    /// LNA gain
+    g: G,
+    /// LNA boost
+    o: O,
+    /// AGC mode
+    a: A,
+    /// Payload length
+    n: N,
 }
-impl<B, R, P, H, C, W, L, F> Builder<(), B, R, P, H, C, W, L, F> {
+impl<B, R, P, H, C, W, L, F, G, O, A, N> Builder<(), B, R, P, H, C, W, L, F, G, O, A, N> {
     /// Sets the spreading factor
-    pub fn set_spreading_factor(self, s: SpreadingFactor) -> Builder<SpreadingFactor, B, R, P, H, C, W, L, F> {
-        Builder { s, b: self.b, r: self.r, p: self.p, h: self.h, c: self.c, w: self.w, l: self.l, f: self.f }
+    pub fn set_spreading_factor(
+        self,
+        s: SpreadingFactor,
+    ) -> Builder<SpreadingFactor, B, R, P, H, C, W, L, F, G, O, A, N> {
+        Builder {
+            s,
+            b: self.b,
+            r: self.r,
+            p: self.p,
+            h: self.h,
+            c: self.c,
+            w: self.w,
+            l: self.l,
+            f: self.f,
+            g: self.g,
+            o: self.o,
+            a: self.a,
+            n: self.n,
+        }
     }
 }
-impl<S, R, P, H, C, W, L, F> Builder<S, (), R, P, H, C, W, L, F> {
+impl<S, R, P, H, C, W, L, F, G, O, A, N> Builder<S, (), R, P, H, C, W, L, F, G, O, A, N> {
     /// Sets the bandwidth
-    pub fn set_bandwidth(self, b: Bandwidth) -> Builder<S, Bandwidth, R, P, H, C, W, L, F> {
-        Builder { s: self.s, b, r: self.r, p: self.p, h: self.h, c: self.c, w: self.w, l: self.l, f: self.f }
+    pub fn set_bandwidth(self, b: Bandwidth) -> Builder<S, Bandwidth, R, P, H, C, W, L, F, G, O, A, N> {
+        Builder {
+            s: self.s,
+            b,
+            r: self.r,
+            p: self.p,
+            h: self.h,
+            c: self.c,
+            w: self.w,
+            l: self.l,
+            f: self.f,
+            g: self.g,
+            o: self.o,
+            a: self.a,
+            n: self.n,
+        }
     }
 }
-impl<S, B, P, H, C, W, L, F> Builder<S, B, (), P, H, C, W, L, F> {
+impl<S, B, P, H, C, W, L, F, G, O, A, N> Builder<S, B, (), P, H, C, W, L, F, G, O, A, N> {
     /// Sets the coding rate
-    pub fn set_coding_rate(self, r: CodingRate) -> Builder<S, B, CodingRate, P, H, C, W, L, F> {
-        Builder { s: self.s, b: self.b, r, p: self.p, h: self.h, c: self.c, w: self.w, l: self.l, f: self.f }
+    pub fn set_coding_rate(self, r: CodingRate) -> Builder<S, B, CodingRate, P, H, C, W, L, F, G, O, A, N> {
+        Builder {
+            s: self.s,
+            b: self.b,
+            r,
+            p: self.p,
+            h: self.h,
+            c: self.c,
+            w: self.w,
+            l: self.l,
+            f: self.f,
+            g: self.g,
+            o: self.o,
+            a: self.a,
+            n: self.n,
+        }
     }
 }
-impl<S, B, R, H, C, W, L, F> Builder<S, B, R, (), H, C, W, L, F> {
+impl<S, B, R, H, C, W, L, F, G, O, A, N> Builder<S, B, R, (), H, C, W, L, F, G, O, A, N> {
     /// Sets the P polarity
-    pub fn set_polarity(self, p: Polarity) -> Builder<S, B, R, Polarity, H, C, W, L, F> {
-        Builder { s: self.s, b: self.b, r: self.r, p, h: self.h, c: self.c, w: self.w, l: self.l, f: self.f }
+    pub fn set_polarity(self, p: Polarity) -> Builder<S, B, R, Polarity, H, C, W, L, F, G, O, A, N> {
+        Builder {
+            s: self.s,
+            b: self.b,
+            r: self.r,
+            p,
+            h: self.h,
+            c: self.c,
+            w: self.w,
+            l: self.l,
+            f: self.f,
+            g: self.g,
+            o: self.o,
+            a: self.a,
+            n: self.n,
+        }
     }
 }
-impl<S, B, R, P, C, W, L, F> Builder<S, B, R, P, (), C, W, L, F> {
+impl<S, B, R, P, C, W, L, F, G, O, A, N> Builder<S, B, R, P, (), C, W, L, F, G, O, A, N> {
     /// Sets the header mode
-    pub fn set_header_mode(self, h: HeaderMode) -> Builder<S, B, R, P, HeaderMode, C, W, L, F> {
-        Builder { s: self.s, b: self.b, r: self.r, p: self.p, h, c: self.c, w: self.w, l: self.l, f: self.f }
+    pub fn set_header_mode(self, h: HeaderMode) -> Builder<S, B, R, P, HeaderMode, C, W, L, F, G, O, A, N> {
+        Builder {
+            s: self.s,
+            b: self.b,
+            r: self.r,
+            p: self.p,
+            h,
+            c: self.c,
+            w: self.w,
+            l: self.l,
+            f: self.f,
+            g: self.g,
+            o: self.o,
+            a: self.a,
+            n: self.n,
+        }
     }
 }
-impl<S, B, R, P, H, W, L, F> Builder<S, B, R, P, H, (), W, L, F> {
+impl<S, B, R, P, H, W, L, F, G, O, A, N> Builder<S, B, R, P, H, (), W, L, F, G, O, A, N> {
     /// Sets the CC mode
-    pub fn set_crc_mode(self, c: CrcMode) -> Builder<S, B, R, P, H, CrcMode, W, L, F> {
-        Builder { s: self.s, b: self.b, r: self.r, p: self.p, h: self.h, c, w: self.w, l: self.l, f: self.f }
+    pub fn set_crc_mode(self, c: CrcMode) -> Builder<S, B, R, P, H, CrcMode, W, L, F, G, O, A, N> {
+        Builder {
+            s: self.s,
+            b: self.b,
+            r: self.r,
+            p: self.p,
+            h: self.h,
+            c,
+            w: self.w,
+            l: self.l,
+            f: self.f,
+            g: self.g,
+            o: self.o,
+            a: self.a,
+            n: self.n,
+        }
     }
 }
-impl<S, B, R, P, H, C, L, F> Builder<S, B, R, P, H, C, (), L, F> {
+impl<S, B, R, P, H, C, L, F, G, O, A, N> Builder<S, B, R, P, H, C, (), L, F, G, O, A, N> {
     /// Sets the sync word
-    pub fn set_sync_word(self, w: SyncWord) -> Builder<S, B, R, P, H, C, SyncWord, L, F> {
-        Builder { s: self.s, b: self.b, r: self.r, p: self.p, h: self.h, c: self.c, w, l: self.l, f: self.f }
+    pub fn set_sync_word(self, w: SyncWord) -> Builder<S, B, R, P, H, C, SyncWord, L, F, G, O, A, N> {
+        Builder {
+            s: self.s,
+            b: self.b,
+            r: self.r,
+            p: self.p,
+            h: self.h,
+            c: self.c,
+            w,
+            l: self.l,
+            f: self.f,
+            g: self.g,
+            o: self.o,
+            a: self.a,
+            n: self.n,
+        }
     }
 }
-impl<S, B, R, P, H, C, W, F> Builder<S, B, R, P, H, C, W, (), F> {
+impl<S, B, R, P, H, C, W, F, G, O, A, N> Builder<S, B, R, P, H, C, W, (), F, G, O, A, N> {
     /// Sets the preamble length
-    pub fn set_preamble_length(self, l: PreambleLength) -> Builder<S, B, R, P, H, C, W, PreambleLength, F> {
-        Builder { s: self.s, b: self.b, r: self.r, p: self.p, h: self.h, c: self.c, w: self.w, l, f: self.f }
+    pub fn set_preamble_length(self, l: PreambleLength) -> Builder<S, B, R, P, H, C, W, PreambleLength, F, G, O, A, N> {
+        Builder {
+            s: self.s,
+            b: self.b,
+            r: self.r,
+            p: self.p,
+            h: self.h,
+            c: self.c,
+            w: self.w,
+            l,
+            f: self.f,
+            g: self.g,
+            o: self.o,
+            a: self.a,
+            n: self.n,
+        }
     }
 }
-impl<S, B, R, P, H, C, W, L> Builder<S, B, R, P, H, C, W, L, ()> {
+impl<S, B, R, P, H, C, W, L, G, O, A, N> Builder<S, B, R, P, H, C, W, L, (), G, O, A, N> {
     /// Sets the frequency
-    pub fn set_frequency(self, f: Frequency) -> Builder<S, B, R, P, H, C, W, L, Frequency> {
-        Builder { s: self.s, b: self.b, r: self.r, p: self.p, h: self.h, c: self.c, w: self.w, l: self.l, f }
+    pub fn set_frequency(self, f: Frequency) -> Builder<S, B, R, P, H, C, W, L, Frequency, G, O, A, N> {
+        Builder {
+            s: self.s,
+            b: self.b,
+            r: self.r,
+            p: self.p,
+            h: self.h,
+            c: self.c,
+            w: self.w,
+            l: self.l,
+            f,
+            g: self.g,
+            o: self.o,
+            a: self.a,
+            n: self.n,
+        }
+    }
+}
+impl<S, B, R, P, H, C, W, L, F, O, A, N> Builder<S, B, R, P, H, C, W, L, F, (), O, A, N> {
+    /// Sets the LNA gain
+    pub fn set_lna_gain(self, g: LnaGain) -> Builder<S, B, R, P, H, C, W, L, F, LnaGain, O, A, N> {
+        Builder {
+            s: self.s,
+            b: self.b,
+            r: self.r,
+            p: self.p,
+            h: self.h,
+            c: self.c,
+            w: self.w,
+            l: self.l,
+            f: self.f,
+            g,
+            o: self.o,
+            a: self.a,
+            n: self.n,
+        }
+    }
+}
+impl<S, B, R, P, H, C, W, L, F, G, A, N> Builder<S, B, R, P, H, C, W, L, F, G, (), A, N> {
+    /// Sets the LNA boost
+    pub fn set_lna_boost(self, o: LnaBoost) -> Builder<S, B, R, P, H, C, W, L, F, G, LnaBoost, A, N> {
+        Builder {
+            s: self.s,
+            b: self.b,
+            r: self.r,
+            p: self.p,
+            h: self.h,
+            c: self.c,
+            w: self.w,
+            l: self.l,
+            f: self.f,
+            g: self.g,
+            o,
+            a: self.a,
+            n: self.n,
+        }
+    }
+}
+impl<S, B, R, P, H, C, W, L, F, G, O, N> Builder<S, B, R, P, H, C, W, L, F, G, O, (), N> {
+    /// Sets the AGC mode
+    pub fn set_agc_mode(self, a: AgcMode) -> Builder<S, B, R, P, H, C, W, L, F, G, O, AgcMode, N> {
+        Builder {
+            s: self.s,
+            b: self.b,
+            r: self.r,
+            p: self.p,
+            h: self.h,
+            c: self.c,
+            w: self.w,
+            l: self.l,
+            f: self.f,
+            g: self.g,
+            o: self.o,
+            a,
+            n: self.n,
+        }
+    }
+}
+impl<S, B, R, P, H, C, W, L, F, G, O, A> Builder<S, B, R, P, H, C, W, L, F, G, O, A, ()> {
+    /// Sets the payload length (the exact length in implicit header mode, the maximum length in explicit header mode)
+    pub fn set_payload_length(self, n: PayloadLength) -> Builder<S, B, R, P, H, C, W, L, F, G, O, A, PayloadLength> {
+        Builder {
+            s: self.s,
+            b: self.b,
+            r: self.r,
+            p: self.p,
+            h: self.h,
+            c: self.c,
+            w: self.w,
+            l: self.l,
+            f: self.f,
+            g: self.g,
+            o: self.o,
+            a: self.a,
+            n,
+        }
     }
 }
 
 /// A LoRa radio config
-pub type Config =
-    Builder<SpreadingFactor, Bandwidth, CodingRate, Polarity, HeaderMode, CrcMode, SyncWord, PreambleLength, Frequency>;
+pub type Config = Builder<
+    SpreadingFactor,
+    Bandwidth,
+    CodingRate,
+    Polarity,
+    HeaderMode,
+    CrcMode,
+    SyncWord,
+    PreambleLength,
+    Frequency,
+    LnaGain,
+    LnaBoost,
+    AgcMode,
+    PayloadLength,
+>;
 impl Config {
     /// Creates a new config builder
     #[allow(clippy::self_named_constructors, reason = "Mislint due to type alias")]
     pub fn builder() -> Builder {
-        Builder { s: (), b: (), r: (), p: (), h: (), c: (), w: (), l: (), f: () }
+        Builder { s: (), b: (), r: (), p: (), h: (), c: (), w: (), l: (), f: (), g: (), o: (), a: (), n: () }
     }
 
     /// The spreading factor
@@ -135,4 +381,24 @@ impl Config {
     pub const fn frequency(&self) -> Frequency {
         self.f
     }
+    /// The LNA gain
+    pub const fn lna_gain(&self) -> LnaGain {
+        self.g
+    }
+    /// The LNA boost
+    pub const fn lna_boost(&self) -> LnaBoost {
+        self.o
+    }
+    /// The AGC mode
+    pub const fn agc_mode(&self) -> AgcMode {
+        self.a
+    }
+    /// The payload length
+    ///
+    /// # Note
+    /// In [`HeaderMode::Implicit`], this is the exact length of every packet. In [`HeaderMode::Explicit`], this is the
+    /// maximum accepted payload length, and longer packets are dropped by the modem.
+    pub const fn payload_len(&self) -> PayloadLength {
+        self.n
+    }
 }
@@ -123,9 +123,12 @@ impl TryFrom<u8> for CodingRate {
 
 /// The IQ polarity
 ///
+/// # Note
+/// The polarity applies to both the RX and the TX path, so a modem can only hear packets sent with the same polarity.
+///
 /// # Representation
 /// The polarity can be represented as `u8`, where `Normal => 0`, `Inverted => 1`. The representation is
-/// compatible to the modem representation.
+/// compatible to the modem representation of the RX path.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Polarity {
@@ -196,6 +199,97 @@ impl TryFrom<u8> for CrcMode {
     }
 }
 
+/// The LNA gain
+///
+/// # Note
+/// If [`AgcMode::Auto`] is enabled, the configured gain is ignored and the AGC picks the gain instead. Reading the gain
+/// back from the modem always yields the gain that is currently in use.
+///
+/// # Representation
+/// The LNA gain can be represented as `u8`, where the value is the index of the gain step (i.e. `G1 => 1`, `G6 => 6`).
+/// The representation is compatible to the modem representation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum LnaGain {
+    /// Maximum gain
+    G1 = 0b001,
+    /// Maximum gain - 6 dB
+    G2 = 0b010,
+    /// Maximum gain - 12 dB
+    G3 = 0b011,
+    /// Maximum gain - 24 dB
+    G4 = 0b100,
+    /// Maximum gain - 36 dB
+    G5 = 0b101,
+    /// Maximum gain - 48 dB
+    G6 = 0b110,
+}
+impl TryFrom<u8> for LnaGain {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            gain if gain == Self::G1 as u8 => Ok(Self::G1),
+            gain if gain == Self::G2 as u8 => Ok(Self::G2),
+            gain if gain == Self::G3 as u8 => Ok(Self::G3),
+            gain if gain == Self::G4 as u8 => Ok(Self::G4),
+            gain if gain == Self::G5 as u8 => Ok(Self::G5),
+            gain if gain == Self::G6 as u8 => Ok(Self::G6),
+            _ => Err("Invalid LNA gain"),
+        }
+    }
+}
+
+/// The LNA boost for the high-frequency (RFM95) input
+///
+/// # Representation
+/// The LNA boost can be represented as `u8`, where `Off => 0b00`, `On => 0b11`. The representation is compatible to
+/// the modem representation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum LnaBoost {
+    /// Default LNA current
+    Off = 0b00,
+    /// Boost on, 150% LNA current
+    On = 0b11,
+}
+impl TryFrom<u8> for LnaBoost {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            boost if boost == Self::Off as u8 => Ok(Self::Off),
+            boost if boost == Self::On as u8 => Ok(Self::On),
+            _ => Err("Invalid LNA boost"),
+        }
+    }
+}
+
+/// The automatic gain control mode
+///
+/// # Representation
+/// The AGC mode can be represented as `u8`, where `Manual => 0`, `Auto => 1`. The representation is compatible to the
+/// modem representation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum AgcMode {
+    /// The LNA gain is set manually via [`LnaGain`]
+    Manual = 0,
+    /// The LNA gain is set by the internal AGC loop
+    Auto = 1,
+}
+impl TryFrom<u8> for AgcMode {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            mode if mode == Self::Manual as u8 => Ok(Self::Manual),
+            mode if mode == Self::Auto as u8 => Ok(Self::Auto),
+            _ => Err("Invalid AGC mode"),
+        }
+    }
+}
+
 /// The LoRa sync word to use
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(transparent)]
@@ -256,6 +350,123 @@ impl From<PreambleLength> for u16 {
     }
 }
 
+/// The payload length in bytes
+///
+/// # Note
+/// In implicit header mode, this is the exact length of every packet; in explicit header mode, this is the maximum
+/// length of an accepted packet. A length of `0` is not permitted by the modem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(transparent)]
+pub struct PayloadLength(u8);
+impl PayloadLength {
+    /// The maximum payload length of 255 bytes, which accepts every packet in explicit header mode
+    pub const MAX: Self = Self(u8::MAX);
+
+    /// Create a new payload length from the given raw length
+    pub const fn new(len: u8) -> Self {
+        Self(len)
+    }
+
+    /// The payload length as `u8`
+    pub const fn as_u8(self) -> u8 {
+        self.0
+    }
+}
+impl From<u8> for PayloadLength {
+    fn from(value: u8) -> Self {
+        Self(value)
+    }
+}
+impl From<PayloadLength> for u8 {
+    fn from(value: PayloadLength) -> Self {
+        value.0
+    }
+}
+
+/// The modem status
+///
+/// # Representation
+/// The modem status is represented as the raw `RegModemStat` register value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct ModemStatus(u8);
+impl ModemStatus {
+    /// Create a new modem status from the given raw register value
+    pub const fn new(status: u8) -> Self {
+        Self(status)
+    }
+
+    /// A LoRa preamble has been detected
+    pub const fn signal_detected(self) -> bool {
+        self.0 & 0b0000_0001 != 0
+    }
+    /// The modem has synchronized to the LoRa preamble
+    pub const fn signal_synchronized(self) -> bool {
+        self.0 & 0b0000_0010 != 0
+    }
+    /// A reception is ongoing
+    pub const fn rx_ongoing(self) -> bool {
+        self.0 & 0b0000_0100 != 0
+    }
+    /// A valid header has been received, and the header info is available
+    pub const fn header_info_valid(self) -> bool {
+        self.0 & 0b0000_1000 != 0
+    }
+    /// The modem is clear
+    pub const fn modem_clear(self) -> bool {
+        self.0 & 0b0001_0000 != 0
+    }
+    /// The coding rate of the last header received
+    pub fn rx_coding_rate(self) -> Result<CodingRate, &'static str> {
+        CodingRate::try_from(self.0 >> 5)
+    }
+
+    /// The modem status as `u8`
+    pub const fn as_u8(self) -> u8 {
+        self.0
+    }
+}
+impl From<u8> for ModemStatus {
+    fn from(value: u8) -> Self {
+        Self(value)
+    }
+}
+impl From<ModemStatus> for u8 {
+    fn from(value: ModemStatus) -> Self {
+        value.0
+    }
+}
+
+/// The header info of a packet that is currently being received
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderInfo {
+    /// The payload length announced by the header
+    payload_len: u8,
+    /// The coding rate announced by the header
+    coding_rate: CodingRate,
+    /// The CRC mode announced by the header
+    crc_mode: CrcMode,
+}
+impl HeaderInfo {
+    /// Create a new header info from the given fields
+    pub const fn new(payload_len: u8, coding_rate: CodingRate, crc_mode: CrcMode) -> Self {
+        Self { payload_len, coding_rate, crc_mode }
+    }
+
+    /// The payload length in bytes
+    pub const fn payload_len(&self) -> u8 {
+        self.payload_len
+    }
+    /// The coding rate of the payload
+    pub const fn coding_rate(&self) -> CodingRate {
+        self.coding_rate
+    }
+    /// Whether the payload is followed by a CRC
+    pub const fn crc_mode(&self) -> CrcMode {
+        self.crc_mode
+    }
+}
+
 /// The frequency in Hz
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(transparent)]
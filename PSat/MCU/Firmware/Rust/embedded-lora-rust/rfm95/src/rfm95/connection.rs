@@ -5,6 +5,48 @@ use core::fmt::{Debug, Formatter};
 use embedded_hal::digital::OutputPin;
 use embedded_hal::spi::SpiBus;
 
+/// A register read operation
+pub(super) const RO: u8 = 0b0000_0000;
+/// A register write operation
+pub(super) const RW: u8 = 0b1000_0000;
+
+/// Builds the SPI command for a register access
+pub(super) const fn command(operation: u8, address: u8, payload: u8) -> [u8; 2] {
+    let address = address & 0b0111_1111;
+    [operation | address, payload]
+}
+
+/// Extracts the (partial) value from the register value
+pub(super) fn extract<T>(register: &T, register_value: u8) -> u8
+where
+    T: Register,
+{
+    (register_value & register.mask()) >> register.offset()
+}
+
+/// Applies a (partial) update to the previous register value
+pub(super) fn merge<T>(register: &T, old_value: u8, value: u8) -> u8
+where
+    T: Register,
+{
+    (old_value & !register.mask()) | (value << register.offset())
+}
+
+/// Calls the SPI debug callback if the `debug` feature is enabled
+#[allow(unused_variables, reason = "The arguments are only used with the debug feature")]
+pub(super) fn debug(operation: u8, address: u8, input: u8, output: u8) {
+    #[cfg(feature = "debug")]
+    unsafe {
+        extern "Rust" {
+            /// Debug callback
+            fn embeddedrfm95_spidebug_AwiUzTRu(operation: u8, address: u8, input: u8, output: u8);
+        }
+
+        // Call debug callback
+        embeddedrfm95_spidebug_AwiUzTRu(operation, address, input, output);
+    }
+}
+
 /// A RFM95 SPI connection
 pub struct Rfm95Connection<Bus, Select>
 where
@@ -21,11 +63,6 @@ where
     Bus: SpiBus,
     Select: OutputPin,
 {
-    /// A register read operation
-    const RO: u8 = 0b0000_0000;
-    /// A register write operation
-    const RW: u8 = 0b1000_0000;
-
     /// Creates a new RFM95 SPI connection
     pub const fn init(bus: Bus, select: Select) -> Self {
         Self { bus, select }
@@ -37,8 +74,8 @@ where
         T: Register,
     {
         // Read register and extract (partial) value
-        let register_value = self.register(Self::RO, register.address(), 0x00)?;
-        Ok((register_value & register.mask()) >> register.offset())
+        let register_value = self.register(RO, register.address(), 0x00)?;
+        Ok(extract(&register, register_value))
     }
     /// Updates a RFM95 register via SPI
     pub fn write<T>(&mut self, register: T, value: u8) -> Result<(), &'static str>
@@ -48,12 +85,11 @@ where
         // Write the register
         if register.mask() == u8::MAX {
             // Fast-path as we overwrite the entire register
-            self.register(Self::RW, register.address(), value)?;
+            self.register(RW, register.address(), value)?;
         } else {
             // Read-Modify-Write of the register value to apply a partial update
-            let old_value = self.register(Self::RO, register.address(), 0x00)?;
-            let value = (old_value & !register.mask()) | (value << register.offset());
-            self.register(Self::RW, register.address(), value)?;
+            let old_value = self.register(RO, register.address(), 0x00)?;
+            self.register(RW, register.address(), merge(&register, old_value, value))?;
         }
 
         // Operation successful
@@ -62,26 +98,12 @@ where
 
     /// Performs RFM95-specific SPI register access
     fn register(&mut self, operation: u8, address: u8, payload: u8) -> Result<u8, &'static str> {
-        // Build command
-        let address = address & 0b0111_1111;
-        let mut command = [operation | address, payload];
-
         // Do transaction
+        let mut command = command(operation, address, payload);
         self.select.set_low().map_err(|_| "Failed to pull chip-select line to low")?;
         self.bus.transfer_in_place(&mut command).map_err(|_| "Failed to do SPI transaction")?;
         self.select.set_high().map_err(|_| "Failed to pull chip-select line to high")?;
-
-        // SPI debug callback
-        #[cfg(feature = "debug")]
-        unsafe {
-            extern "Rust" {
-                /// Debug callback
-                fn embeddedrfm95_spidebug_AwiUzTRu(operation: u8, address: u8, input: u8, output: u8);
-            }
-
-            // Call debug callback
-            embeddedrfm95_spidebug_AwiUzTRu(operation, address, payload, command[1]);
-        }
+        debug(operation, address, payload, command[1]);
 
         // Return the previous register value
         Ok(command[1])
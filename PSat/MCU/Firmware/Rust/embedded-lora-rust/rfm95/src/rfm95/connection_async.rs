@@ -0,0 +1,74 @@
+//! Async RFM95 SPI connection
+//!
+//! The register access is shared with the blocking connection; only the SPI transactions are awaited.
+
+use crate::rfm95::connection::{command, debug, extract, merge, RO, RW};
+use crate::rfm95::registers::Register;
+use core::fmt::{Debug, Formatter};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiBus;
+
+/// An async RFM95 SPI connection
+pub struct Rfm95AsyncConnection<Bus, Select>
+where
+    Bus: SpiBus,
+    Select: OutputPin,
+{
+    /// The SPI bus
+    bus: Bus,
+    /// The chip select line
+    select: Select,
+}
+impl<Bus, Select> Rfm95AsyncConnection<Bus, Select>
+where
+    Bus: SpiBus,
+    Select: OutputPin,
+{
+    /// Creates a new async RFM95 SPI connection
+    pub const fn init(bus: Bus, select: Select) -> Self {
+        Self { bus, select }
+    }
+
+    /// Reads a RFM95 register via SPI
+    pub async fn read<T>(&mut self, register: T) -> Result<u8, &'static str>
+    where
+        T: Register,
+    {
+        let register_value = self.register(RO, register.address(), 0x00).await?;
+        Ok(extract(&register, register_value))
+    }
+    /// Updates a RFM95 register via SPI
+    pub async fn write<T>(&mut self, register: T, value: u8) -> Result<(), &'static str>
+    where
+        T: Register,
+    {
+        if register.mask() == u8::MAX {
+            // Fast-path as we overwrite the entire register
+            self.register(RW, register.address(), value).await?;
+        } else {
+            // Read-Modify-Write of the register value to apply a partial update
+            let old_value = self.register(RO, register.address(), 0x00).await?;
+            self.register(RW, register.address(), merge(&register, old_value, value)).await?;
+        }
+        Ok(())
+    }
+
+    /// Performs RFM95-specific SPI register access
+    async fn register(&mut self, operation: u8, address: u8, payload: u8) -> Result<u8, &'static str> {
+        let mut command = command(operation, address, payload);
+        self.select.set_low().map_err(|_| "Failed to pull chip-select line to low")?;
+        self.bus.transfer_in_place(&mut command).await.map_err(|_| "Failed to do SPI transaction")?;
+        self.select.set_high().map_err(|_| "Failed to pull chip-select line to high")?;
+        debug(operation, address, payload, command[1]);
+        Ok(command[1])
+    }
+}
+impl<Bus, Select> Debug for Rfm95AsyncConnection<Bus, Select>
+where
+    Bus: SpiBus,
+    Select: OutputPin,
+{
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_struct("Rfm95AsyncConnection").field("bus", &"<SpiBus>").field("select", &"<OutputPin>").finish()
+    }
+}
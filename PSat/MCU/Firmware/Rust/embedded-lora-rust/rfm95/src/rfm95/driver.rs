@@ -27,27 +27,6 @@ where
     Bus: SpiBus,
     Select: OutputPin,
 {
-    /// Supported silicon revisions for compatibility check
-    #[cfg(not(feature = "debug"))]
-    const SUPPORTED_SILICON_REVISIONS: [u8; 2] = [0x11, 0x12];
-    /// The frequency divider to compute the frequency in milli-hertz
-    const FREQUENCY_DIVIDER_MILLIHZ: u64 = 61_035;
-    /// The threshold for switching between low-frequency mode (below 525 MHz) and high frequency mode (above 779 MHz)
-    const HIGH_FREQUENCY_THRESHOLD: Frequency = Frequency::hz(652_000_000);
-
-    /// The register value to put the device to LoRa mode
-    const REG_OPMODE_LONGRANGEMODE_LORA: u8 = 0b1;
-    /// The register value to set the shared registers to LoRa mode
-    const REG_OPMODE_ACCESSSHAREDREG_LORA: u8 = 0b0;
-    /// The pre-assembled register value for the operation mode register to put the device to sleep
-    const REG_OPMODE_MODE_SLEEP: u8 = 0b000;
-    /// The pre-assembled register value for the operation mode register to go into standby during LoRa mode
-    const REG_OPMODE_MODE_STANDBY: u8 = 0b001;
-    /// The pre-assembled register value for the operation mode register to start a single LoRa TX transmission
-    const REG_OPMODE_MODE_TXSINGLE: u8 = 0b011;
-    /// The pre-assembled register value for the operation mode register to start a single LoRa RX reception
-    const REG_OPMODE_MODE_RXSINGLE: u8 = 0b110;
-
     /// Creates a new raw SPI command interface for RFM95
     ///
     /// # Blocking
@@ -57,7 +36,34 @@ where
     /// # Important
     /// The RFM95 modem is initialized to LoRa-mode and put to standby. All other configurations are left untouched, so
     /// you probably want to configure the modem initially (also see [`Self::set_config`]).
-    pub fn new<R, T>(bus: Bus, select: Select, mut reset: R, mut timer: T) -> Result<Self, &'static str>
+    pub fn new<R, T>(bus: Bus, select: Select, reset: R, timer: T) -> Result<Self, &'static str>
+    where
+        R: OutputPin,
+        T: DelayNs,
+    {
+        let mut driver = Self::new_uninit(bus, select);
+        driver.reset(reset, timer)?;
+        Ok(driver)
+    }
+
+    /// Creates a new raw SPI command interface for RFM95 without touching the modem
+    ///
+    /// # Important
+    /// The modem must be initialized with [`Self::reset`] before it is used. Unlike [`Self::new`], this keeps the bus
+    /// if the modem does not respond yet, so the reset can be retried later.
+    pub fn new_uninit(bus: Bus, select: Select) -> Self {
+        Self { spi: Rfm95Connection::init(bus, select) }
+    }
+
+    /// Performs a hardware reset and re-initializes the modem, e.g. to recover from a stuck modem
+    ///
+    /// # Blocking
+    /// This function blocks for at least `11ms` plus additional time for the modem transactions.
+    ///
+    /// # Important
+    /// The reset restores all modem registers to their defaults, so the config must be re-applied afterwards (also see
+    /// [`Self::set_config`]).
+    pub fn reset<R, T>(&mut self, mut reset: R, mut timer: T) -> Result<(), &'static str>
     where
         R: OutputPin,
         T: DelayNs,
@@ -71,43 +77,34 @@ where
         timer.delay_ms(10);
 
         // Validate chip revision to assure the protocol matches
-        let mut wire = Rfm95Connection::init(bus, select);
         #[cfg(not(feature = "debug"))]
         {
             // Get chip revision
-            let silicon_revision = wire.read(RegVersion)?;
-            let true = Self::SUPPORTED_SILICON_REVISIONS.contains(&silicon_revision) else {
+            let silicon_revision = self.spi.read(RegVersion)?;
+            let true = SUPPORTED_SILICON_REVISIONS.contains(&silicon_revision) else {
                 // Raise an error here since other revisions may be incompatible
                 return Err("Unsupported silicon revision");
             };
         }
 
         // Go to sleep, switch to LoRa and enter standby
-        wire.write(RegOpModeMode, Self::REG_OPMODE_MODE_SLEEP)?;
-        wire.write(RegOpModeLongRangeMode, Self::REG_OPMODE_LONGRANGEMODE_LORA)?;
-        wire.write(RegOpModeMode, Self::REG_OPMODE_MODE_STANDBY)?;
-        wire.write(RegOpModeAccessSharedReg, Self::REG_OPMODE_ACCESSSHAREDREG_LORA)?;
+        self.spi.write(RegOpModeMode, REG_OPMODE_MODE_SLEEP)?;
+        self.spi.write(RegOpModeLongRangeMode, REG_OPMODE_LONGRANGEMODE_LORA)?;
+        self.spi.write(RegOpModeMode, REG_OPMODE_MODE_STANDBY)?;
+        self.spi.write(RegOpModeAccessSharedReg, REG_OPMODE_ACCESSSHAREDREG_LORA)?;
 
         // Set TX and RX base address to 0 to use the entire available FIFO space, and the power amplifier to max
-        wire.write(RegFifoTxBaseAddr, 0x00)?;
-        wire.write(RegFifoRxBaseAddr, 0x00)?;
-        wire.write(RegPaConfig, 0xFF)?;
-
-        // Init self
-        Ok(Self { spi: wire })
+        self.spi.write(RegFifoTxBaseAddr, 0x00)?;
+        self.spi.write(RegFifoRxBaseAddr, 0x00)?;
+        self.spi.write(RegPaConfig, 0xFF)?;
+        Ok(())
     }
 
     /// Applies the given config (useful for initialization)
     pub fn set_config(&mut self, config: &Config) -> Result<(), &'static str> {
-        self.set_spreading_factor(config.spreading_factor())?;
-        self.set_bandwidth(config.bandwidth())?;
-        self.set_coding_rate(config.coding_rate())?;
-        self.set_polarity(config.polarity())?;
-        self.set_header_mode(config.header_mode())?;
-        self.set_crc_mode(config.crc_mode())?;
-        self.set_sync_word(config.sync_word())?;
-        self.set_preamble_len(config.preamble_len())?;
-        self.set_frequency(config.frequency())?;
+        for (register, value) in config_to_registers(config)? {
+            self.spi.write(register, value)?;
+        }
         Ok(())
     }
 
@@ -169,16 +166,34 @@ where
 
     /// The current IQ polarity
     pub fn polarity(&mut self) -> Result<Polarity, &'static str> {
-        let polarity = self.spi.read(RegInvertIQ)?;
-        Polarity::try_from(polarity)
+        // Read the RX and TX path settings, where the TX bit is active-low
+        let polarity_rx = self.spi.read(RegInvertIQRx)?;
+        let polarity_tx = self.spi.read(RegInvertIQTx)? ^ 0b1;
+        let true = polarity_rx == polarity_tx else {
+            // The RX and TX paths have been configured differently
+            return Err("Inconsistent IQ polarity");
+        };
+        Polarity::try_from(polarity_rx)
     }
-    /// Sets the IQ polarity
+    /// Sets the IQ polarity for both the RX and the TX path
+    ///
+    /// # Implementation details
+    /// Inverting the IQ signals requires the RX path bit and the (active-low) TX path bit in `RegInvertIQ`, and the
+    /// matching optimization value in `RegInvertIQ2`; otherwise inverted packets can neither be sent nor received.
     pub fn set_polarity<T>(&mut self, polarity: T) -> Result<(), &'static str>
     where
         T: Into<Polarity>,
     {
         let polarity = polarity.into();
-        self.spi.write(RegInvertIQ, polarity as u8)
+        let invert_iq2 = match polarity {
+            Polarity::Normal => REG_INVERTIQ2_NORMAL,
+            Polarity::Inverted => REG_INVERTIQ2_INVERTED,
+        };
+
+        // Set registers
+        self.spi.write(RegInvertIQRx, polarity as u8)?;
+        self.spi.write(RegInvertIQTx, polarity as u8 ^ 0b1)?;
+        self.spi.write(RegInvertIQ2, invert_iq2)
     }
 
     /// The current header mode
@@ -195,6 +210,35 @@ where
         self.spi.write(RegModemConfig1ImplicitHeaderModeOn, header_mode as u8)
     }
 
+    /// The current payload length
+    ///
+    /// # Note
+    /// In [`HeaderMode::Implicit`], this is the exact length of every packet. In [`HeaderMode::Explicit`], this is the
+    /// maximum accepted payload length.
+    pub fn payload_len(&mut self) -> Result<PayloadLength, &'static str> {
+        let payload_len = self.spi.read(RegMaxPayloadLength)?;
+        Ok(PayloadLength::new(payload_len))
+    }
+    /// Sets the payload length
+    ///
+    /// # Note
+    /// In [`HeaderMode::Implicit`], the modem has no way to learn the packet length over the air, so every received
+    /// packet is expected to have exactly this length, and every transmitted packet must have exactly this length. In
+    /// [`HeaderMode::Explicit`], packets with a longer payload are rejected by the modem.
+    pub fn set_payload_length<T>(&mut self, len: T) -> Result<(), &'static str>
+    where
+        T: Into<PayloadLength>,
+    {
+        let len @ 1..=u8::MAX = u8::from(len.into()) else {
+            // The modem does not permit empty payloads
+            return Err("Invalid payload length");
+        };
+
+        // Set the expected length for implicit mode and the filter length for explicit mode
+        self.spi.write(RegPayloadLength, len)?;
+        self.spi.write(RegMaxPayloadLength, len)
+    }
+
     /// The current CRC mode
     pub fn crc_mode(&mut self) -> Result<CrcMode, &'static str> {
         let crc_mode = self.spi.read(RegModemConfig2RxPayloadCrcOn)?;
@@ -249,13 +293,9 @@ where
         let frequency_msb = self.spi.read(RegFrMsb)?;
         let frequency_mid = self.spi.read(RegFrMid)?;
         let frequency_lsb = self.spi.read(RegFrLsb)?;
-        let frequency_raw = u64::from_be_bytes([0, 0, 0, 0, 0, frequency_msb, frequency_mid, frequency_lsb]);
 
         // Translate crystal native frequency into Hz
-        #[allow(clippy::arithmetic_side_effects, reason = "Can never overflow")]
-        let frequency_khz = frequency_raw * Self::FREQUENCY_DIVIDER_MILLIHZ;
-        let frequency = (frequency_khz / 1000) as u32;
-        Ok(Frequency::hz(frequency))
+        Ok(frf_to_frequency([frequency_msb, frequency_mid, frequency_lsb]))
     }
     /// Sets the frequency
     pub fn set_frequency<T>(&mut self, frequency: T) -> Result<(), &'static str>
@@ -264,15 +304,11 @@ where
     {
         // Set the modem to high- or low-frequency mode (low-frequency is `1`)
         let frequency = frequency.into();
-        let frequency_mode = (frequency < Self::HIGH_FREQUENCY_THRESHOLD) as u8;
+        let frequency_mode = (frequency < HIGH_FREQUENCY_THRESHOLD) as u8;
         self.spi.write(RegOpModeLowFrequencyModeOn, frequency_mode)?;
 
         // Translate the frequency into the crystal native frequency
-        // Note: We go via kHz/mHz to keep higher precision without floats
-        #[allow(clippy::arithmetic_side_effects, reason = "Can never overflow")]
-        let frequency_khz = u32::from(frequency) as u64 * 1000;
-        let [_, _, _, _, _, frequency_msb, frequency_mid, frequency_lsb] =
-            (frequency_khz / Self::FREQUENCY_DIVIDER_MILLIHZ).to_be_bytes();
+        let [frequency_msb, frequency_mid, frequency_lsb] = frequency_to_frf(frequency);
 
         // Write the frequency to the registers
         self.spi.write(RegFrMsb, frequency_msb)?;
@@ -281,6 +317,55 @@ where
         Ok(())
     }
 
+    /// The current LNA gain
+    ///
+    /// # Note
+    /// If [`AgcMode::Auto`] is enabled, this returns the gain that the AGC has currently selected, which is not
+    /// necessarily the gain that has been configured via [`Self::set_lna_gain`].
+    pub fn lna_gain(&mut self) -> Result<LnaGain, &'static str> {
+        let lna_gain = self.spi.read(RegLnaGain)?;
+        LnaGain::try_from(lna_gain)
+    }
+    /// Sets the LNA gain
+    ///
+    /// # Note
+    /// The gain is only effective if the AGC is disabled (see [`Self::set_agc_mode`]).
+    pub fn set_lna_gain<T>(&mut self, lna_gain: T) -> Result<(), &'static str>
+    where
+        T: Into<LnaGain>,
+    {
+        let lna_gain = lna_gain.into();
+        self.spi.write(RegLnaGain, lna_gain as u8)
+    }
+
+    /// The current LNA boost
+    pub fn lna_boost(&mut self) -> Result<LnaBoost, &'static str> {
+        let lna_boost = self.spi.read(RegLnaBoostHf)?;
+        LnaBoost::try_from(lna_boost)
+    }
+    /// Sets the LNA boost
+    pub fn set_lna_boost<T>(&mut self, lna_boost: T) -> Result<(), &'static str>
+    where
+        T: Into<LnaBoost>,
+    {
+        let lna_boost = lna_boost.into();
+        self.spi.write(RegLnaBoostHf, lna_boost as u8)
+    }
+
+    /// The current AGC mode
+    pub fn agc_mode(&mut self) -> Result<AgcMode, &'static str> {
+        let agc_mode = self.spi.read(RegModemConfig3AgcAutoOn)?;
+        AgcMode::try_from(agc_mode)
+    }
+    /// Sets the AGC mode
+    pub fn set_agc_mode<T>(&mut self, agc_mode: T) -> Result<(), &'static str>
+    where
+        T: Into<AgcMode>,
+    {
+        let agc_mode = agc_mode.into();
+        self.spi.write(RegModemConfig3AgcAutoOn, agc_mode as u8)
+    }
+
     /// Schedules a single TX operation with the given data and returns immediately
     ///
     /// # Non-Blocking
@@ -292,6 +377,13 @@ where
             // The message is empty or too long
             return Err("Invalid TX data length");
         };
+        if self.header_mode()? == HeaderMode::Implicit {
+            // The receiver cannot learn the length over the air, so the packet must match the configured length
+            let payload_len = self.spi.read(RegPayloadLength)?;
+            let true = data.len() == payload_len as usize else {
+                return Err("TX data length does not match implicit payload length");
+            };
+        }
 
         // Copy packet into FIFO...
         for (index, byte) in data.iter().enumerate() {
@@ -307,7 +399,7 @@ where
         self.spi.write(RegIrqFlagsTxDone, 1)?;
 
         // Start TX
-        self.spi.write(RegOpModeMode, Self::REG_OPMODE_MODE_TXSINGLE)?;
+        self.spi.write(RegOpModeMode, REG_OPMODE_MODE_TXSINGLE)?;
         Ok(())
     }
     /// Checks if a single TX operation has completed, and returns the amount of bytes sent
@@ -326,6 +418,15 @@ where
         Ok(Some(written as usize))
     }
 
+    /// Aborts any TX or RX operation in progress and puts the modem to standby
+    ///
+    /// # Non-Blocking
+    /// This function is non-blocking. Use it if an operation has not completed in time, so that the modem can be used
+    /// again.
+    pub fn standby(&mut self) -> Result<(), &'static str> {
+        self.spi.write(RegOpModeMode, REG_OPMODE_MODE_STANDBY)
+    }
+
     /// Computes the maximum RX timeout for the current configured spreading factor and bandwidth
     ///
     /// # Maximum Timeout
@@ -379,16 +480,42 @@ where
         self.spi.write(RegIrqFlagsMaskRxDoneMask, 0)?;
         self.spi.write(RegIrqFlagsMaskRxTimeoutMask, 0)?;
         self.spi.write(RegIrqFlagsMaskPayloadCrcErrorMask, 0)?;
+        self.spi.write(RegIrqFlagsMaskValidHeaderMask, 0)?;
 
         // Reset possible old interrupts
         self.spi.write(RegIrqFlagsRxDone, 1)?;
         self.spi.write(RegIrqFlagsRxTimeout, 1)?;
         self.spi.write(RegIrqFlagsPayloadCrcError, 1)?;
+        self.spi.write(RegIrqFlagsValidHeader, 1)?;
 
         // Start RX
-        self.spi.write(RegOpModeMode, Self::REG_OPMODE_MODE_RXSINGLE)?;
+        self.spi.write(RegOpModeMode, REG_OPMODE_MODE_RXSINGLE)?;
         Ok(())
     }
+    /// Checks if a valid header has been received during the current RX operation, and returns the header info
+    ///
+    /// # Non-Blocking
+    /// This function is non-blocking. If no header has been received yet, it returns `Ok(None)`. The header is reported
+    /// only once per packet; the payload is still being received and must be collected via [`Self::complete_rx`].
+    ///
+    /// # Implicit header mode
+    /// In [`HeaderMode::Implicit`], there is no header to receive, so this function always returns `Ok(None)`.
+    pub fn rx_header(&mut self) -> Result<Option<HeaderInfo>, &'static str> {
+        // Check for a valid header
+        let 0b1 = self.spi.read(RegIrqFlagsValidHeader)? else {
+            // No header has been received yet
+            return Ok(None);
+        };
+
+        // Get the header info
+        let payload_len = self.spi.read(RegRxNbBytes)?;
+        let coding_rate = CodingRate::try_from(self.spi.read(RegModemStatRxCodingRate)?)?;
+        let crc_mode = CrcMode::try_from(self.spi.read(RegHopChannelCrcOnPayload)?)?;
+
+        // Clear only the valid header flag, as a read-modify-write could also clear a concurrent RX done
+        self.spi.write(RegIrqFlags, RegIrqFlagsValidHeader.mask())?;
+        Ok(Some(HeaderInfo::new(payload_len, coding_rate, crc_mode)))
+    }
     /// Checks if a single RX operation has completed, copies the message into `buf` and returns the amount of bytes
     /// received
     ///
@@ -435,6 +562,16 @@ where
         Ok(Some(len as usize))
     }
 
+    /// The current modem status
+    ///
+    /// # Note
+    /// This can be polled during an RX operation to learn early whether a packet is incoming, i.e. if a preamble has
+    /// been detected or the modem has synchronized to it.
+    pub fn modem_status(&mut self) -> Result<ModemStatus, &'static str> {
+        let modem_status = self.spi.read(RegModemStat)?;
+        Ok(ModemStatus::new(modem_status))
+    }
+
     /// When operating in the high frequency range the RSSI register values are offset by this much. 
     const HF_RSSI_OFFSET: i16 = -157;
 
@@ -450,6 +587,7 @@ where
     pub fn get_packet_strength(&mut self) -> Result<i16, &'static str> {
         let offset = if self.frequency()? >= Self::HF_LF_BOUNDARY_FREQ {Self::HF_RSSI_OFFSET} else {Self::LF_RSSI_OFFSET};
         let snr = self.get_packet_snr()?;
+        #[allow(clippy::arithmetic_side_effects, reason = "Register values are 8 bit, so this can never overflow")]
         if snr >= 0 {
             Ok( self.spi.read(RegRssiValue)? as i16 + offset )
         }
@@ -459,8 +597,11 @@ where
     }
 
     /// Get a Relative Signal Strength Indicator (RSSI) of the last recieved packet.
+    /// 
+    /// The RSSI is measured after the LNA, so it should be read together with [`Self::get_packet_lna_gain`].
     pub fn get_rssi(&mut self) -> Result<i16, &'static str> {
         let offset = if self.frequency()? > Self::HF_LF_BOUNDARY_FREQ {Self::HF_RSSI_OFFSET} else {Self::LF_RSSI_OFFSET};
+        #[allow(clippy::arithmetic_side_effects, reason = "Register values are 8 bit, so this can never overflow")]
         if self.get_packet_snr()? >= 0 {
             Ok( self.spi.read(RegPktRssiValue)? as i16 * 16/15 + offset )
         }
@@ -475,6 +616,14 @@ where
         Ok( (self.spi.read(RegPktSnrValue)? as i8 ) / 4)
     }
 
+    /// Get the LNA gain that was in use when the last packet was recieved.
+    /// 
+    /// With [`AgcMode::Auto`] the AGC settles on a gain during the preamble and keeps it for the rest of the packet, so
+    /// reading it right after RX completes tells you which gain step the RSSI values were measured at.
+    pub fn get_packet_lna_gain(&mut self) -> Result<LnaGain, &'static str> {
+        self.lna_gain()
+    }
+
     /// Dumps all used registers; usefule for debugging purposes
     #[cfg(feature = "debug")]
     pub fn dump_registers(&mut self) -> Result<[u8; REGISTER_MAX as usize + 1], &'static str> {
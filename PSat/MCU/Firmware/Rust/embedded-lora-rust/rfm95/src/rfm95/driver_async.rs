@@ -0,0 +1,273 @@
+//! Async RFM95 driver for LoRa operations
+
+use crate::lora::airtime;
+use crate::lora::config::Config;
+use crate::lora::types::*;
+use crate::rfm95::connection_async::Rfm95AsyncConnection;
+use crate::rfm95::registers::*;
+use crate::rfm95::RFM95_FIFO_SIZE;
+use core::fmt::{Debug, Formatter};
+use core::future::{poll_fn, Future};
+use core::pin::pin;
+use core::task::Poll;
+use core::time::Duration;
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::digital::Wait;
+use embedded_hal_async::spi::SpiBus;
+
+/// Async RFM95 driver that awaits the DIO interrupt lines instead of polling the modem
+///
+/// # DIO lines
+/// `DIO0` signals TX done and RX done, `DIO1` signals an RX timeout. Both lines are mapped by the driver itself.
+///
+/// # Timer
+/// The modem has no TX timeout of its own, so the timer bounds the wait for TX done.
+pub struct Rfm95AsyncDriver<Bus, Select, Dio0, Dio1, Timer>
+where
+    Bus: SpiBus,
+    Select: OutputPin,
+    Dio0: Wait,
+    Dio1: Wait,
+    Timer: DelayNs,
+{
+    /// The SPI connection to the RFM95 radio
+    spi: Rfm95AsyncConnection<Bus, Select>,
+    /// The DIO0 line
+    dio0: Dio0,
+    /// The DIO1 line
+    dio1: Dio1,
+    /// The timer for the TX timeout
+    timer: Timer,
+}
+impl<Bus, Select, Dio0, Dio1, Timer> Rfm95AsyncDriver<Bus, Select, Dio0, Dio1, Timer>
+where
+    Bus: SpiBus,
+    Select: OutputPin,
+    Dio0: Wait,
+    Dio1: Wait,
+    Timer: DelayNs,
+{
+    /// Creates a new async RFM95 driver
+    ///
+    /// # Important
+    /// The RFM95 modem is initialized to LoRa-mode and put to standby. All other configurations are left untouched, so
+    /// you probably want to configure the modem initially (also see [`Self::set_config`]).
+    pub async fn new<R>(
+        bus: Bus,
+        select: Select,
+        dio0: Dio0,
+        dio1: Dio1,
+        mut reset: R,
+        mut timer: Timer,
+    ) -> Result<Self, &'static str>
+    where
+        R: OutputPin,
+    {
+        // Pull reset to low and wait until the reset is triggered
+        reset.set_low().map_err(|_| "Failed to pull reset line to low")?;
+        timer.delay_ms(1).await;
+
+        // Pull reset to high again and give the chip some time to boot
+        reset.set_high().map_err(|_| "Failed to pull reset line to high")?;
+        timer.delay_ms(10).await;
+
+        // Validate chip revision to assure the protocol matches
+        let mut wire = Rfm95AsyncConnection::init(bus, select);
+        #[cfg(not(feature = "debug"))]
+        {
+            // Get chip revision
+            let silicon_revision = wire.read(RegVersion).await?;
+            let true = SUPPORTED_SILICON_REVISIONS.contains(&silicon_revision) else {
+                // Raise an error here since other revisions may be incompatible
+                return Err("Unsupported silicon revision");
+            };
+        }
+
+        // Go to sleep, switch to LoRa and enter standby
+        wire.write(RegOpModeMode, REG_OPMODE_MODE_SLEEP).await?;
+        wire.write(RegOpModeLongRangeMode, REG_OPMODE_LONGRANGEMODE_LORA).await?;
+        wire.write(RegOpModeMode, REG_OPMODE_MODE_STANDBY).await?;
+        wire.write(RegOpModeAccessSharedReg, REG_OPMODE_ACCESSSHAREDREG_LORA).await?;
+
+        // Set TX and RX base address to 0 to use the entire available FIFO space, and the power amplifier to max
+        wire.write(RegFifoTxBaseAddr, 0x00).await?;
+        wire.write(RegFifoRxBaseAddr, 0x00).await?;
+        wire.write(RegPaConfig, 0xFF).await?;
+
+        // Init self
+        Ok(Self { spi: wire, dio0, dio1, timer })
+    }
+
+    /// Applies the given config
+    pub async fn set_config(&mut self, config: &Config) -> Result<(), &'static str> {
+        for (register, value) in config_to_registers(config)? {
+            self.spi.write(register, value).await?;
+        }
+        Ok(())
+    }
+
+    /// Transmits the given data and returns the amount of bytes sent once the TX operation is done
+    ///
+    /// # Timeout
+    /// If the modem does not signal TX done within `timeout`, e.g. because DIO0 is not connected, the TX operation is
+    /// aborted and the modem is put back to standby.
+    pub async fn transmit(&mut self, data: &[u8], timeout: Duration) -> Result<usize, &'static str> {
+        let timeout_micros = u32::try_from(timeout.as_micros()).map_err(|_| "Timeout is too long")?;
+
+        // Validate input length
+        let 1..=RFM95_FIFO_SIZE = data.len() else {
+            // The message is empty or too long
+            return Err("Invalid TX data length");
+        };
+        if self.spi.read(RegModemConfig1ImplicitHeaderModeOn).await? == HeaderMode::Implicit as u8 {
+            // The receiver cannot learn the length over the air, so the packet must match the configured length
+            let payload_len = self.spi.read(RegPayloadLength).await?;
+            let true = data.len() == payload_len as usize else {
+                return Err("TX data length does not match implicit payload length");
+            };
+        }
+
+        // Copy packet into FIFO, which advances the address pointer on every access, and set packet length
+        self.spi.write(RegFifoAddrPtr, 0x00).await?;
+        for byte in data {
+            self.spi.write(RegFifo, *byte).await?;
+        }
+        self.spi.write(RegPayloadLength, data.len() as u8).await?;
+
+        // Route TX done to DIO0, enable and reset possible old interrupt
+        self.spi.write(RegDioMapping1Dio0Mapping, REG_DIOMAPPING1_DIO0_TXDONE).await?;
+        self.spi.write(RegIrqFlagsMaskTxDoneMask, 0).await?;
+        self.spi.write(RegIrqFlags, RegIrqFlagsTxDone.mask()).await?;
+
+        // Start TX and wait until it is done or has timeouted
+        self.spi.write(RegOpModeMode, REG_OPMODE_MODE_TXSINGLE).await?;
+        let tx_done = Self::wait_for_timeout(&mut self.dio0, &mut self.timer, timeout_micros).await;
+        if let Err(e) = tx_done {
+            // Abort TX, so that the modem can be used again
+            self.spi.write(RegOpModeMode, REG_OPMODE_MODE_STANDBY).await?;
+            return Err(e);
+        }
+        self.spi.write(RegIrqFlags, RegIrqFlagsTxDone.mask()).await?;
+        Ok(data.len())
+    }
+
+    /// Receives a single message, copies it into `buf` and returns the amount of bytes received
+    ///
+    /// # Maximum Timeout
+    /// The RFM95 timeout counter works by counting symbols, and is thus dependent on the configured spreading factor
+    /// and bandwidth. The maximum timeout is `1023` symbols.
+    pub async fn receive(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, &'static str> {
+        // Get the current symbol airtime in microseconds
+        let spreading_factor = SpreadingFactor::try_from(self.spi.read(RegModemConfig2SpreadingFactor).await?)?;
+        let bandwidth = Bandwidth::try_from(self.spi.read(RegModemConfig1Bw).await?)?;
+        let symbol_airtime = airtime::symbol_airtime(spreading_factor, bandwidth);
+        let symbol_airtime_micros = symbol_airtime.as_micros() as i32;
+
+        // Compute the raw timeout
+        let timeout_micros = i32::try_from(timeout.as_micros()).map_err(|_| "Timeout is too long")?;
+        let timeout_symbols @ 0..1024 = airtime::ceildiv(timeout_micros, symbol_airtime_micros) as u32 else {
+            // This timeout is too large to be configured
+            return Err("Effective timeout is too large");
+        };
+
+        // Configure the timeout and reset the address pointer
+        self.spi.write(RegModemConfig2SymbTimeout98, (timeout_symbols >> 8) as u8).await?;
+        self.spi.write(RegSymbTimeoutLsb, timeout_symbols as u8).await?;
+        self.spi.write(RegFifoAddrPtr, 0x00).await?;
+
+        // Route RX done to DIO0 and the timeout to DIO1, enable and reset possible old interrupts
+        let irq_flags = RegIrqFlagsRxDone.mask() | RegIrqFlagsRxTimeout.mask() | RegIrqFlagsPayloadCrcError.mask();
+        self.spi.write(RegDioMapping1Dio0Mapping, REG_DIOMAPPING1_DIO0_RXDONE).await?;
+        self.spi.write(RegDioMapping1Dio1Mapping, REG_DIOMAPPING1_DIO1_RXTIMEOUT).await?;
+        self.spi.write(RegIrqFlagsMaskRxDoneMask, 0).await?;
+        self.spi.write(RegIrqFlagsMaskRxTimeoutMask, 0).await?;
+        self.spi.write(RegIrqFlagsMaskPayloadCrcErrorMask, 0).await?;
+        self.spi.write(RegIrqFlags, irq_flags).await?;
+
+        // Start RX and wait until either a packet has been received or the operation has timeouted
+        self.spi.write(RegOpModeMode, REG_OPMODE_MODE_RXSINGLE).await?;
+        Self::wait_for_either(&mut self.dio0, &mut self.dio1).await?;
+        let timeout = self.spi.read(RegIrqFlagsRxTimeout).await?;
+        let crc_error = self.spi.read(RegIrqFlagsPayloadCrcError).await?;
+        self.spi.write(RegIrqFlags, irq_flags).await?;
+
+        // Check for errors
+        let 0b0 = timeout else {
+            // The RX operation has timeouted
+            return Err("RX timeout");
+        };
+        let 0b0 = crc_error else {
+            // The RX operation has failed
+            return Err("RX CRC error");
+        };
+
+        // Copy data from FIFO, which advances the address pointer on every access
+        let start = self.spi.read(RegFifoRxCurrentAddr).await?;
+        let len = self.spi.read(RegRxNbBytes).await?;
+        self.spi.write(RegFifoAddrPtr, start).await?;
+        for slot in buf.iter_mut().take(len as usize) {
+            *slot = self.spi.read(RegFifo).await?;
+        }
+
+        // Return the amount of bytes received
+        Ok(len as usize)
+    }
+
+    /// Get the Signal to Noise Ratio (SNR) of the last received packet.
+    pub async fn get_packet_snr(&mut self) -> Result<i8, &'static str> {
+        // The value is stored in two's complement form in the register, so the cast to i8 is fine
+        Ok((self.spi.read(RegPktSnrValue).await? as i8) / 4)
+    }
+
+    /// Waits until the given line goes high, or fails once `timeout_micros` have elapsed
+    async fn wait_for_timeout(dio0: &mut Dio0, timer: &mut Timer, timeout_micros: u32) -> Result<(), &'static str> {
+        let mut dio0 = pin!(dio0.wait_for_high());
+        let mut timeout = pin!(timer.delay_us(timeout_micros));
+        poll_fn(|context| {
+            // Poll the line first, so that a TX done wins against a simultaneous timeout
+            if let Poll::Ready(result) = dio0.as_mut().poll(context) {
+                return Poll::Ready(result.map_err(|_| "Failed to wait for DIO0"));
+            }
+            if timeout.as_mut().poll(context).is_ready() {
+                return Poll::Ready(Err("TX timeout"));
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Waits until either of the given lines goes high
+    async fn wait_for_either(dio0: &mut Dio0, dio1: &mut Dio1) -> Result<(), &'static str> {
+        let mut dio0 = pin!(dio0.wait_for_high());
+        let mut dio1 = pin!(dio1.wait_for_high());
+        poll_fn(|context| {
+            // Poll both lines, so that both wakers are registered
+            if let Poll::Ready(result) = dio0.as_mut().poll(context) {
+                return Poll::Ready(result.map_err(|_| "Failed to wait for DIO0"));
+            }
+            if let Poll::Ready(result) = dio1.as_mut().poll(context) {
+                return Poll::Ready(result.map_err(|_| "Failed to wait for DIO1"));
+            }
+            Poll::Pending
+        })
+        .await
+    }
+}
+impl<Bus, Select, Dio0, Dio1, Timer> Debug for Rfm95AsyncDriver<Bus, Select, Dio0, Dio1, Timer>
+where
+    Bus: SpiBus,
+    Select: OutputPin,
+    Dio0: Wait,
+    Dio1: Wait,
+    Timer: DelayNs,
+{
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_struct("Rfm95AsyncDriver")
+            .field("spi", &self.spi)
+            .field("dio0", &"<Wait>")
+            .field("dio1", &"<Wait>")
+            .field("timer", &"<DelayNs>")
+            .finish()
+    }
+}
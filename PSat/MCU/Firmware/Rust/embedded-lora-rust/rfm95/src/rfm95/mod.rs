@@ -1,7 +1,11 @@
 //! RFM95 LoRa implementation
 
 mod connection;
+#[cfg(feature = "async")]
+mod connection_async;
 mod driver;
+#[cfg(feature = "async")]
+mod driver_async;
 mod registers;
 
 use crate::lora::types::Frequency;
@@ -18,3 +22,5 @@ pub const RFM95_FIFO_SIZE: usize = 0xFF;
 
 // Expose the driver implementation
 pub use crate::rfm95::driver::Rfm95Driver;
+#[cfg(feature = "async")]
+pub use crate::rfm95::driver_async::Rfm95AsyncDriver;
@@ -1,5 +1,9 @@
 //! The modem register map
 
+use crate::lora::airtime;
+use crate::lora::config::Config;
+use crate::lora::types::{Frequency, Polarity};
+
 /// A RFM95 register
 pub trait Register {
     /// The register address
@@ -41,6 +45,37 @@ macro_rules! register {
     };
 }
 
+/// A register of any type, e.g. to list the writes to different registers
+#[derive(Debug, Clone, Copy)]
+pub struct AnyRegister {
+    /// The register address
+    address: u8,
+    /// The bitfield offset
+    offset: u8,
+    /// The bitfield mask
+    mask: u8,
+}
+impl AnyRegister {
+    /// Erases the type of the given register
+    pub fn new<T>(register: T) -> Self
+    where
+        T: Register,
+    {
+        Self { address: register.address(), offset: register.offset(), mask: register.mask() }
+    }
+}
+impl Register for AnyRegister {
+    fn address(&self) -> u8 {
+        self.address
+    }
+    fn offset(&self) -> u8 {
+        self.offset
+    }
+    fn mask(&self) -> u8 {
+        self.mask
+    }
+}
+
 // Register definitions
 register! {
     "LoRa base-band FIFO data input/output; FIFO is cleared an not accessible when device is in SLEEP mode",
@@ -78,6 +113,14 @@ register! {
     "RegPaConfig (see datasheet for more info)",
     RegPaConfig<0x09, 0, 8>
 }
+register! {
+    "LNA gain setting (see datasheet for more info); reading this register returns the gain currently in use",
+    RegLnaGain<0x0C, 5, 3>
+}
+register! {
+    "High frequency (RFM95) LNA current adjustment: 00 -> Default LNA current, 11 -> Boost on, 150% LNA current",
+    RegLnaBoostHf<0x0C, 0, 2>
+}
 register! {
     "SPI interface address pointer in FIFO data buffer",
     RegFifoAddrPtr<0x0D, 0, 8>
@@ -106,10 +149,18 @@ register! {
     "Payload CRC error interrupt mask: setting this bit masks thecorresponding IRQ in RegIrqFlags",
     RegIrqFlagsMaskPayloadCrcErrorMask<0x11, 5, 1>
 }
+register! {
+    "Valid header received in Rx interrupt mask: setting this bit masks the corresponding IRQ in RegIrqFlags",
+    RegIrqFlagsMaskValidHeaderMask<0x11, 4, 1>
+}
 register! {
     "FIFO Payload transmission complete interrupt mask: setting this bit masks the corresponding IRQ in RegIrqFlags",
     RegIrqFlagsMaskTxDoneMask<0x11, 3, 1>
 }
+register! {
+    "All interrupt flags: writing a 1 to a bit clears the corresponding IRQ, writing a 0 has no effect",
+    RegIrqFlags<0x12, 0, 8>
+}
 register! {
     "Timeout interrupt: writing a 1 clears the IRQ",
     RegIrqFlagsRxTimeout<0x12, 7, 1>
@@ -122,6 +173,10 @@ register! {
     "Payload CRC error interrupt: writing a 1 clears the IRQ",
     RegIrqFlagsPayloadCrcError<0x12, 5, 1>
 }
+register! {
+    "Valid header received in Rx interrupt: writing a 1 clears the IRQ",
+    RegIrqFlagsValidHeader<0x12, 4, 1>
+}
 register! {
     "FIFO Payload transmission complete interrupt: writing a 1 clears the IRQ",
     RegIrqFlagsTxDone<0x12, 3, 1>
@@ -130,6 +185,14 @@ register! {
     "Number of payload bytes of latest packet received",
     RegRxNbBytes<0x13, 0, 8>
 }
+register! {
+    "Live LoRa modem status: signal detected, signal synchronized, RX on-going, header info valid and modem clear",
+    RegModemStat<0x18, 0, 8>
+}
+register! {
+    "Coding rate of last header received",
+    RegModemStatRxCodingRate<0x18, 5, 3>
+}
 register! {
     "SNR of last packet recieved",
     RegPktSnrValue<0x19, 0, 8>
@@ -142,6 +205,10 @@ register! {
     "Current RSSI value",
     RegRssiValue<0x1B, 0, 8>
 }
+register! {
+    "CRC information extracted from the received packet header: 0 -> Header indicates CRC off, 1 -> Header indicates CRC on",
+    RegHopChannelCrcOnPayload<0x1C, 6, 1>
+}
 register! {
     "Signal bandwidth (see datasheet for more info)",
     RegModemConfig1Bw<0x1D, 4, 4>
@@ -182,24 +249,151 @@ register! {
     "Payload length in bytes; the register needs to be set in implicit header mode for the expected packet length (a `0` value is not permitted)",
     RegPayloadLength<0x22, 0, 8>
 }
+register! {
+    "Maximum payload length; if header payload length exceeds value a header CRC error is generated",
+    RegMaxPayloadLength<0x23, 0, 8>
+}
 register! {
     "0 -> Disabled, 1 -> Enabled; mandated for when the symbol length exceeds 16ms",
     RegModemConfig3LowDataRateOptimize<0x26, 3, 1>
 }
 register! {
-    "Invert the LoRa I and Q signals; 0 -> normal mode, 1 -> I and Q signals are inverted",
-    RegInvertIQ<0x33, 6, 1>
+    "0 -> LNA gain set by register LnaGain, 1 -> LNA gain set by the internal AGC loop",
+    RegModemConfig3AgcAutoOn<0x26, 2, 1>
+}
+register! {
+    "Invert the LoRa I and Q signals in RX path; 0 -> normal mode, 1 -> I and Q signals are inverted",
+    RegInvertIQRx<0x33, 6, 1>
+}
+register! {
+    "Invert the LoRa I and Q signals in TX path; 0 -> I and Q signals are inverted, 1 -> normal mode",
+    RegInvertIQTx<0x33, 0, 1>
 }
 register! {
     "LoRa Sync Word; value 0x34 is used for LoRaWAN networks",
     RegSyncWord<0x39, 0, 8>
 }
+register! {
+    "Optimize for inverted IQ; 0x19 -> I and Q signals are inverted, 0x1D -> normal mode",
+    RegInvertIQ2<0x3B, 0, 8>
+}
+#[cfg(feature = "async")]
+register! {
+    "Mapping of pin DIO0; in LoRa mode 00 -> RxDone, 01 -> TxDone, 10 -> CadDone",
+    RegDioMapping1Dio0Mapping<0x40, 6, 2>
+}
+#[cfg(feature = "async")]
+register! {
+    "Mapping of pin DIO1; in LoRa mode 00 -> RxTimeout, 01 -> FhssChangeChannel, 10 -> CadDetected",
+    RegDioMapping1Dio1Mapping<0x40, 4, 2>
+}
 #[cfg(not(feature = "debug"))]
 register! {
     "Semtech ID relating the silicon revision",
     RegVersion<0x42, 0, 8>
 }
 
+/// Supported silicon revisions for compatibility check
+#[cfg(not(feature = "debug"))]
+pub const SUPPORTED_SILICON_REVISIONS: [u8; 2] = [0x11, 0x12];
+/// The frequency divider to compute the frequency in milli-hertz
+const FREQUENCY_DIVIDER_MILLIHZ: u64 = 61_035;
+/// The threshold for switching between low-frequency mode (below 525 MHz) and high frequency mode (above 779 MHz)
+pub const HIGH_FREQUENCY_THRESHOLD: Frequency = Frequency::hz(652_000_000);
+
+/// The register value to put the device to LoRa mode
+pub const REG_OPMODE_LONGRANGEMODE_LORA: u8 = 0b1;
+/// The register value to set the shared registers to LoRa mode
+pub const REG_OPMODE_ACCESSSHAREDREG_LORA: u8 = 0b0;
+/// The pre-assembled register value for the operation mode register to put the device to sleep
+pub const REG_OPMODE_MODE_SLEEP: u8 = 0b000;
+/// The pre-assembled register value for the operation mode register to go into standby during LoRa mode
+pub const REG_OPMODE_MODE_STANDBY: u8 = 0b001;
+/// The pre-assembled register value for the operation mode register to start a single LoRa TX transmission
+pub const REG_OPMODE_MODE_TXSINGLE: u8 = 0b011;
+/// The pre-assembled register value for the operation mode register to start a single LoRa RX reception
+pub const REG_OPMODE_MODE_RXSINGLE: u8 = 0b110;
+/// The register value to optimize the modem for normal IQ signals
+pub const REG_INVERTIQ2_NORMAL: u8 = 0x1D;
+/// The register value to optimize the modem for inverted IQ signals
+pub const REG_INVERTIQ2_INVERTED: u8 = 0x19;
+/// The DIO0 mapping to signal RX done
+#[cfg(feature = "async")]
+pub const REG_DIOMAPPING1_DIO0_RXDONE: u8 = 0b00;
+/// The DIO0 mapping to signal TX done
+#[cfg(feature = "async")]
+pub const REG_DIOMAPPING1_DIO0_TXDONE: u8 = 0b01;
+/// The DIO1 mapping to signal an RX timeout
+#[cfg(feature = "async")]
+pub const REG_DIOMAPPING1_DIO1_RXTIMEOUT: u8 = 0b00;
+
+/// Translates a frequency into the crystal native `RegFrMsb`, `RegFrMid` and `RegFrLsb` values
+pub fn frequency_to_frf(frequency: Frequency) -> [u8; 3] {
+    // Note: We go via kHz/mHz to keep higher precision without floats
+    #[allow(clippy::arithmetic_side_effects, reason = "Can never overflow")]
+    let frequency_khz = u32::from(frequency) as u64 * 1000;
+    let [_, _, _, _, _, frequency_msb, frequency_mid, frequency_lsb] =
+        (frequency_khz / FREQUENCY_DIVIDER_MILLIHZ).to_be_bytes();
+    [frequency_msb, frequency_mid, frequency_lsb]
+}
+/// Translates the crystal native `RegFrMsb`, `RegFrMid` and `RegFrLsb` values into a frequency
+pub fn frf_to_frequency(frf: [u8; 3]) -> Frequency {
+    let [frequency_msb, frequency_mid, frequency_lsb] = frf;
+    let frequency_raw = u64::from_be_bytes([0, 0, 0, 0, 0, frequency_msb, frequency_mid, frequency_lsb]);
+
+    #[allow(clippy::arithmetic_side_effects, reason = "Can never overflow")]
+    let frequency_khz = frequency_raw * FREQUENCY_DIVIDER_MILLIHZ;
+    Frequency::hz((frequency_khz / 1000) as u32)
+}
+
+/// The amount of register writes that apply a config
+pub const CONFIG_REGISTERS: usize = 21;
+/// Translates a config into the register writes that apply it, in order
+pub fn config_to_registers(config: &Config) -> Result<[(AnyRegister, u8); CONFIG_REGISTERS], &'static str> {
+    // The low data rate optimization depends on the modulation, and the TX path IQ bit is active-low
+    let needs_ldo = airtime::needs_ldo(config.spreading_factor(), config.bandwidth());
+    let polarity = config.polarity() as u8;
+    let invert_iq2 = match config.polarity() {
+        Polarity::Normal => REG_INVERTIQ2_NORMAL,
+        Polarity::Inverted => REG_INVERTIQ2_INVERTED,
+    };
+
+    // The payload length is the expected length in implicit mode and the filter length in explicit mode
+    let payload_len @ 1..=u8::MAX = config.payload_len().as_u8() else {
+        // The modem does not permit empty payloads
+        return Err("Invalid payload length");
+    };
+    let [preamble_len_msb, preamble_len_lsb] = config.preamble_len().as_u16().to_be_bytes();
+
+    // The low-frequency mode is `1`
+    let frequency_mode = (config.frequency() < HIGH_FREQUENCY_THRESHOLD) as u8;
+    let [frequency_msb, frequency_mid, frequency_lsb] = frequency_to_frf(config.frequency());
+
+    Ok([
+        (AnyRegister::new(RegModemConfig2SpreadingFactor), config.spreading_factor() as u8),
+        (AnyRegister::new(RegModemConfig1Bw), config.bandwidth() as u8),
+        (AnyRegister::new(RegModemConfig3LowDataRateOptimize), needs_ldo as u8),
+        (AnyRegister::new(RegModemConfig1CodingRate), config.coding_rate() as u8),
+        (AnyRegister::new(RegInvertIQRx), polarity),
+        (AnyRegister::new(RegInvertIQTx), polarity ^ 0b1),
+        (AnyRegister::new(RegInvertIQ2), invert_iq2),
+        (AnyRegister::new(RegModemConfig1ImplicitHeaderModeOn), config.header_mode() as u8),
+        (AnyRegister::new(RegPayloadLength), payload_len),
+        (AnyRegister::new(RegMaxPayloadLength), payload_len),
+        (AnyRegister::new(RegModemConfig2RxPayloadCrcOn), config.crc_mode() as u8),
+        (AnyRegister::new(RegSyncWord), config.sync_word().as_u8()),
+        (AnyRegister::new(RegPreambleMsb), preamble_len_msb),
+        (AnyRegister::new(RegPreambleLsb), preamble_len_lsb),
+        (AnyRegister::new(RegOpModeLowFrequencyModeOn), frequency_mode),
+        (AnyRegister::new(RegFrMsb), frequency_msb),
+        (AnyRegister::new(RegFrMid), frequency_mid),
+        (AnyRegister::new(RegFrLsb), frequency_lsb),
+        (AnyRegister::new(RegLnaGain), config.lna_gain() as u8),
+        (AnyRegister::new(RegLnaBoostHf), config.lna_boost() as u8),
+        (AnyRegister::new(RegModemConfig3AgcAutoOn), config.agc_mode() as u8),
+    ])
+}
+
 /// The highest reasonable register address for dumping
 #[cfg(feature = "debug")]
 pub const REGISTER_MAX: u8 = 0x64;
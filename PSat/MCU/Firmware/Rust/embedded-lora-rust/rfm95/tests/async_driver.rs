@@ -0,0 +1,59 @@
+//! Async driver tests against the simulated modem
+#![cfg(feature = "async")]
+
+#[path = "common/asynchronous.rs"]
+mod asynchronous;
+mod common;
+
+use asynchronous::block_on;
+use common::Ether;
+use core::time::Duration;
+use embedded_lora_rfm95::lora::types::Polarity;
+
+#[test]
+fn async_to_blocking() {
+    let ether = Ether::new();
+    let mut sender = asynchronous::async_driver(&ether);
+    let mut receiver = common::driver(&ether);
+    block_on(sender.set_config(&common::config(Polarity::Normal))).expect("failed to configure sender");
+    receiver.set_config(&common::config(Polarity::Normal)).expect("failed to configure receiver");
+
+    let sent = block_on(sender.transmit(b"Testolope", Duration::from_millis(100))).expect("failed to transmit");
+    assert_eq!(sent, 9);
+    assert_eq!(common::receive(&mut receiver).as_deref(), Ok(&b"Testolope"[..]));
+}
+
+#[test]
+fn blocking_to_async() {
+    let ether = Ether::new();
+    let mut sender = common::driver(&ether);
+    let mut receiver = asynchronous::async_driver(&ether);
+    sender.set_config(&common::config(Polarity::Inverted)).expect("failed to configure sender");
+    block_on(receiver.set_config(&common::config(Polarity::Inverted))).expect("failed to configure receiver");
+
+    common::transmit(&mut sender, b"Testolope");
+    let mut buf = [0; 32];
+    let len = block_on(receiver.receive(&mut buf, Duration::from_millis(100))).expect("failed to receive");
+    assert_eq!(&buf[..len], b"Testolope");
+}
+
+#[test]
+fn async_receive_timeout() {
+    let ether = Ether::new();
+    let mut receiver = asynchronous::async_driver(&ether);
+    block_on(receiver.set_config(&common::config(Polarity::Normal))).expect("failed to configure receiver");
+
+    let mut buf = [0; 32];
+    let result = block_on(receiver.receive(&mut buf, Duration::from_millis(100)));
+    assert_eq!(result, Err("RX timeout"));
+}
+
+#[test]
+fn async_transmit_timeout() {
+    let ether = Ether::new();
+    let mut sender = asynchronous::async_driver_with_dio0(&ether, asynchronous::StuckDio);
+    block_on(sender.set_config(&common::config(Polarity::Normal))).expect("failed to configure sender");
+
+    let result = block_on(sender.transmit(b"Testolope", Duration::from_millis(100)));
+    assert_eq!(result, Err("TX timeout"));
+}
@@ -0,0 +1,134 @@
+//! Async adapters for the simulated modem
+#![allow(dead_code, reason = "Not every test uses every helper")]
+
+use crate::common::{Ether, SimModem, SimPin};
+use core::convert::Infallible;
+use embedded_hal::digital::ErrorType as PinErrorType;
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::digital::Wait;
+use embedded_hal_async::spi::SpiBus;
+use embedded_lora_rfm95::rfm95::Rfm95AsyncDriver;
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+impl SpiBus for SimModem {
+    async fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        embedded_hal::spi::SpiBus::read(self, words)
+    }
+    async fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        embedded_hal::spi::SpiBus::write(self, words)
+    }
+    async fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        embedded_hal::spi::SpiBus::transfer(self, read, write)
+    }
+    async fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        embedded_hal::spi::SpiBus::transfer_in_place(self, words)
+    }
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// A DIO line that is always high, as the simulated modem completes every operation instantly
+///
+/// The line yields once before it reports high, to exercise the executor.
+#[derive(Debug, Default)]
+pub struct SimDio;
+impl PinErrorType for SimDio {
+    type Error = Infallible;
+}
+impl Wait for SimDio {
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        yield_once().await;
+        Ok(())
+    }
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// A DIO line that never goes high, like an unconnected line
+#[derive(Debug, Default)]
+pub struct StuckDio;
+impl PinErrorType for StuckDio {
+    type Error = Infallible;
+}
+impl Wait for StuckDio {
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        std::future::pending().await
+    }
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        std::future::pending().await
+    }
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        std::future::pending().await
+    }
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        std::future::pending().await
+    }
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        std::future::pending().await
+    }
+}
+
+/// A delay that yields once per started millisecond, so that a DIO line that is high wins against any timeout
+#[derive(Debug, Default)]
+pub struct SimAsyncDelay;
+impl DelayNs for SimAsyncDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        for _ in 0..ns.div_ceil(1_000_000) {
+            yield_once().await;
+        }
+    }
+}
+
+/// Returns `Pending` once and wakes itself
+async fn yield_once() {
+    let mut yielded = false;
+    std::future::poll_fn(|context| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        context.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
+
+/// Runs the given future to completion
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut context = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+/// An async driver for a simulated modem
+pub type SimAsyncDriver<Dio0 = SimDio> = Rfm95AsyncDriver<SimModem, SimPin, Dio0, SimDio, SimAsyncDelay>;
+
+/// Creates an async driver for a new simulated modem that is connected to the given ether
+pub fn async_driver(ether: &Rc<RefCell<Ether>>) -> SimAsyncDriver {
+    async_driver_with_dio0(ether, SimDio)
+}
+
+/// Creates an async driver with the given DIO0 line for a new simulated modem that is connected to the given ether
+pub fn async_driver_with_dio0<Dio0: Wait>(ether: &Rc<RefCell<Ether>>, dio0: Dio0) -> SimAsyncDriver<Dio0> {
+    let driver = Rfm95AsyncDriver::new(SimModem::new(ether), SimPin, dio0, SimDio, SimPin, SimAsyncDelay);
+    block_on(driver).expect("failed to init simulated modem")
+}
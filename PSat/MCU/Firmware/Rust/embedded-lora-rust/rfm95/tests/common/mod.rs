@@ -0,0 +1,371 @@
+//! A simulated RFM95/SX1276 modem for host tests
+//!
+//! The simulated modems implement the register map used by the driver and share an [`Ether`]; a packet sent by one
+//! modem is heard by another modem if their radio parameters match, just like on air. Packets are queued in the ether,
+//! so a transmission only needs to happen before the receiver starts listening.
+#![allow(dead_code, reason = "Not every test uses every helper")]
+
+use core::convert::Infallible;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{ErrorType as PinErrorType, OutputPin};
+use embedded_hal::spi::{ErrorType as SpiErrorType, SpiBus};
+use embedded_lora_rfm95::lora::config::Config;
+use embedded_lora_rfm95::lora::types::{
+    AgcMode, Bandwidth, CodingRate, CrcMode, Frequency, HeaderMode, LnaBoost, LnaGain, PayloadLength, Polarity,
+    PreambleLength, SpreadingFactor, SyncWord,
+};
+use embedded_lora_rfm95::rfm95::Rfm95Driver;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// The register addresses the simulation cares about
+mod reg {
+    pub const FIFO: usize = 0x00;
+    pub const OP_MODE: usize = 0x01;
+    pub const FIFO_ADDR_PTR: usize = 0x0D;
+    pub const FIFO_TX_BASE_ADDR: usize = 0x0E;
+    pub const FIFO_RX_BASE_ADDR: usize = 0x0F;
+    pub const FIFO_RX_CURRENT_ADDR: usize = 0x10;
+    pub const IRQ_FLAGS: usize = 0x12;
+    pub const RX_NB_BYTES: usize = 0x13;
+    pub const MODEM_STAT: usize = 0x18;
+    pub const PKT_SNR_VALUE: usize = 0x19;
+    pub const PKT_RSSI_VALUE: usize = 0x1A;
+    pub const RSSI_VALUE: usize = 0x1B;
+    pub const HOP_CHANNEL: usize = 0x1C;
+    pub const MODEM_CONFIG_1: usize = 0x1D;
+    pub const MODEM_CONFIG_2: usize = 0x1E;
+    pub const PAYLOAD_LENGTH: usize = 0x22;
+    pub const MAX_PAYLOAD_LENGTH: usize = 0x23;
+    pub const INVERT_IQ: usize = 0x33;
+    pub const SYNC_WORD: usize = 0x39;
+    pub const INVERT_IQ2: usize = 0x3B;
+    pub const VERSION: usize = 0x42;
+}
+
+/// IRQ flag bits
+mod irq {
+    pub const RX_TIMEOUT: u8 = 0b1000_0000;
+    pub const RX_DONE: u8 = 0b0100_0000;
+    pub const VALID_HEADER: u8 = 0b0001_0000;
+    pub const TX_DONE: u8 = 0b0000_1000;
+}
+
+/// A packet on air, together with the radio parameters it was sent with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transmission {
+    /// The raw carrier frequency registers
+    pub frequency: [u8; 3],
+    /// The raw spreading factor
+    pub spreading_factor: u8,
+    /// The raw bandwidth
+    pub bandwidth: u8,
+    /// The raw coding rate
+    pub coding_rate: u8,
+    /// Whether the packet has been sent in implicit header mode
+    pub implicit_header: bool,
+    /// Whether a payload CRC has been sent
+    pub crc: bool,
+    /// The sync word
+    pub sync_word: u8,
+    /// Whether the IQ signals have been inverted
+    pub iq_inverted: bool,
+    /// The payload
+    pub payload: Vec<u8>,
+}
+
+/// The shared medium between simulated modems
+#[derive(Debug, Default)]
+pub struct Ether {
+    /// All packets that have been sent so far
+    pub transmissions: Vec<Transmission>,
+}
+impl Ether {
+    /// Creates a new, empty ether
+    pub fn new() -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(Self::default()))
+    }
+}
+
+/// A simulated modem that is connected via SPI
+#[derive(Debug)]
+pub struct SimModem {
+    /// The shared ether
+    ether: Rc<RefCell<Ether>>,
+    /// The amount of transmissions in the ether this modem has already seen
+    seen: usize,
+    /// The register file
+    regs: [u8; 0x80],
+    /// The FIFO
+    fifo: [u8; 0x100],
+}
+impl SimModem {
+    /// Creates a new simulated modem with reset register values
+    pub fn new(ether: &Rc<RefCell<Ether>>) -> Self {
+        let mut regs = [0; 0x80];
+        for (address, value) in [
+            (reg::OP_MODE, 0x09),
+            (0x06, 0x6C),
+            (0x07, 0x80),
+            (0x09, 0x4F),
+            (0x0C, 0x20),
+            (reg::FIFO_TX_BASE_ADDR, 0x80),
+            (reg::MODEM_CONFIG_1, 0x72),
+            (reg::MODEM_CONFIG_2, 0x70),
+            (0x1F, 0x64),
+            (0x21, 0x08),
+            (reg::PAYLOAD_LENGTH, 0x01),
+            (reg::MAX_PAYLOAD_LENGTH, 0xFF),
+            (0x26, 0x04),
+            (reg::INVERT_IQ, 0x27),
+            (reg::SYNC_WORD, 0x12),
+            (reg::INVERT_IQ2, 0x1D),
+            (reg::VERSION, 0x12),
+        ] {
+            regs[address] = value;
+        }
+
+        // Skip everything that has been sent before this modem was powered on
+        let seen = ether.borrow().transmissions.len();
+        Self { ether: ether.clone(), seen, regs, fifo: [0; 0x100] }
+    }
+
+    /// Performs a single register access and returns the previous register value
+    fn access(&mut self, command: u8, value: u8) -> u8 {
+        let write = command & 0b1000_0000 != 0;
+        let address = (command & 0b0111_1111) as usize;
+        match (address, write) {
+            (reg::FIFO, _) => {
+                // Access the FIFO at the address pointer and advance the pointer
+                let pointer = self.regs[reg::FIFO_ADDR_PTR];
+                let previous = self.fifo[pointer as usize];
+                if write {
+                    self.fifo[pointer as usize] = value;
+                }
+                self.regs[reg::FIFO_ADDR_PTR] = pointer.wrapping_add(1);
+                previous
+            }
+            (reg::IRQ_FLAGS, true) => {
+                // Writing a 1 clears the flag
+                let previous = self.regs[reg::IRQ_FLAGS];
+                self.regs[reg::IRQ_FLAGS] &= !value;
+                previous
+            }
+            (reg::OP_MODE, true) => {
+                let previous = self.regs[reg::OP_MODE];
+                self.regs[reg::OP_MODE] = value;
+                self.mode_changed();
+                previous
+            }
+            (address, true) => {
+                let previous = self.regs[address];
+                self.regs[address] = value;
+                previous
+            }
+            (address, false) => self.regs[address],
+        }
+    }
+
+    /// Executes the single TX or RX operation that has been requested via the mode bits
+    fn mode_changed(&mut self) {
+        const MODE_MASK: u8 = 0b0000_0111;
+        const MODE_STANDBY: u8 = 0b001;
+        const MODE_TXSINGLE: u8 = 0b011;
+        const MODE_RXSINGLE: u8 = 0b110;
+
+        let mode = self.regs[reg::OP_MODE] & MODE_MASK;
+        match mode {
+            MODE_TXSINGLE => self.transmit(),
+            MODE_RXSINGLE => self.receive(),
+            _ => return,
+        }
+
+        // Single operations return to standby once done
+        self.regs[reg::OP_MODE] = (self.regs[reg::OP_MODE] & !MODE_MASK) | MODE_STANDBY;
+    }
+
+    /// Sends the FIFO contents to the ether
+    fn transmit(&mut self) {
+        let start = self.regs[reg::FIFO_TX_BASE_ADDR];
+        let len = self.regs[reg::PAYLOAD_LENGTH];
+        let payload = (0..len).map(|offset| self.fifo[start.wrapping_add(offset) as usize]).collect();
+
+        let transmission = Transmission {
+            payload,
+            // The TX path bit is active-low
+            iq_inverted: self.regs[reg::INVERT_IQ] & 0b0000_0001 == 0,
+            ..self.air_parameters()
+        };
+        self.ether.borrow_mut().transmissions.push(transmission);
+        self.regs[reg::IRQ_FLAGS] |= irq::TX_DONE;
+    }
+
+    /// Receives the first matching packet from the ether, or times out
+    fn receive(&mut self) {
+        // Take all packets that have been sent since the last RX operation
+        let ether = self.ether.clone();
+        let ether = ether.borrow();
+        let pending = ether.transmissions.get(self.seen..).unwrap_or_default();
+        self.seen = ether.transmissions.len();
+
+        let Some(transmission) = pending.iter().find(|transmission| self.hears(transmission)) else {
+            self.regs[reg::IRQ_FLAGS] |= irq::RX_TIMEOUT;
+            return;
+        };
+
+        // Copy the packet into the FIFO and report it
+        let start = self.regs[reg::FIFO_RX_BASE_ADDR];
+        for (offset, byte) in transmission.payload.iter().enumerate() {
+            self.fifo[start.wrapping_add(offset as u8) as usize] = *byte;
+        }
+        self.regs[reg::FIFO_RX_CURRENT_ADDR] = start;
+        self.regs[reg::RX_NB_BYTES] = transmission.payload.len() as u8;
+        self.regs[reg::MODEM_STAT] = (transmission.coding_rate << 5) | 0b0001_0000;
+        self.regs[reg::HOP_CHANNEL] = (transmission.crc as u8) << 6;
+        self.regs[reg::PKT_SNR_VALUE] = 8 * 4;
+        self.regs[reg::PKT_RSSI_VALUE] = 100;
+        self.regs[reg::RSSI_VALUE] = 40;
+        self.regs[reg::IRQ_FLAGS] |= match transmission.implicit_header {
+            true => irq::RX_DONE,
+            false => irq::RX_DONE | irq::VALID_HEADER,
+        };
+    }
+
+    /// Whether this modem can demodulate the given transmission with its current configuration
+    fn hears(&self, transmission: &Transmission) -> bool {
+        // Inverted RX only works together with the matching IQ optimization
+        let rx_inverted = self.regs[reg::INVERT_IQ] & 0b0100_0000 != 0;
+        let rx_optimized_for_inverted = self.regs[reg::INVERT_IQ2] == 0x19;
+        if rx_inverted != rx_optimized_for_inverted {
+            return false;
+        }
+
+        // The receiver must know the length in implicit mode, and filters by length in explicit mode
+        let len = transmission.payload.len();
+        let length_ok = match transmission.implicit_header {
+            true => len == self.regs[reg::PAYLOAD_LENGTH] as usize,
+            false => len <= self.regs[reg::MAX_PAYLOAD_LENGTH] as usize,
+        };
+
+        let own = self.air_parameters();
+        own.frequency == transmission.frequency
+            && own.spreading_factor == transmission.spreading_factor
+            && own.bandwidth == transmission.bandwidth
+            && own.implicit_header == transmission.implicit_header
+            && own.sync_word == transmission.sync_word
+            && rx_inverted == transmission.iq_inverted
+            && length_ok
+    }
+
+    /// The current air parameters, without payload and IQ inversion
+    fn air_parameters(&self) -> Transmission {
+        Transmission {
+            frequency: [self.regs[0x06], self.regs[0x07], self.regs[0x08]],
+            spreading_factor: self.regs[reg::MODEM_CONFIG_2] >> 4,
+            bandwidth: self.regs[reg::MODEM_CONFIG_1] >> 4,
+            coding_rate: (self.regs[reg::MODEM_CONFIG_1] >> 1) & 0b111,
+            implicit_header: self.regs[reg::MODEM_CONFIG_1] & 0b1 != 0,
+            crc: self.regs[reg::MODEM_CONFIG_2] & 0b100 != 0,
+            sync_word: self.regs[reg::SYNC_WORD],
+            iq_inverted: false,
+            payload: Vec::new(),
+        }
+    }
+}
+impl SpiErrorType for SimModem {
+    type Error = Infallible;
+}
+impl SpiBus for SimModem {
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        words.fill(0x00);
+        words.chunks_mut(2).try_for_each(|word| self.transfer_in_place(word))
+    }
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        words.chunks(2).try_for_each(|word| self.transfer_in_place(&mut word.to_vec()))
+    }
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        for (read, write) in read.chunks_mut(2).zip(write.chunks(2)) {
+            let mut word = write.to_vec();
+            self.transfer_in_place(&mut word)?;
+            read.copy_from_slice(&word[..read.len()]);
+        }
+        Ok(())
+    }
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        let [command, value] = words else {
+            panic!("Unexpected SPI transaction length {}", words.len());
+        };
+        *value = self.access(*command, *value);
+        Ok(())
+    }
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// A no-op chip-select or reset line
+#[derive(Debug, Default)]
+pub struct SimPin;
+impl PinErrorType for SimPin {
+    type Error = Infallible;
+}
+impl OutputPin for SimPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// A delay that returns immediately
+#[derive(Debug, Default)]
+pub struct SimDelay;
+impl DelayNs for SimDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+/// A driver for a simulated modem
+pub type SimDriver = Rfm95Driver<SimModem, SimPin>;
+
+/// Creates a driver for a new simulated modem that is connected to the given ether
+pub fn driver(ether: &Rc<RefCell<Ether>>) -> SimDriver {
+    Rfm95Driver::new(SimModem::new(ether), SimPin, SimPin, SimDelay).expect("failed to init simulated modem")
+}
+
+/// A config for the given polarity; all other parameters are fixed
+pub fn config(polarity: Polarity) -> Config {
+    Config::builder()
+        .set_spreading_factor(SpreadingFactor::S7)
+        .set_bandwidth(Bandwidth::B125)
+        .set_coding_rate(CodingRate::C4_5)
+        .set_polarity(polarity)
+        .set_header_mode(HeaderMode::Explicit)
+        .set_crc_mode(CrcMode::Enabled)
+        .set_sync_word(SyncWord::PRIVATE)
+        .set_preamble_length(PreambleLength::L8)
+        .set_frequency(Frequency::hz(915_000_000))
+        .set_lna_gain(LnaGain::G1)
+        .set_lna_boost(LnaBoost::On)
+        .set_agc_mode(AgcMode::Auto)
+        .set_payload_length(PayloadLength::MAX)
+}
+
+/// Sends the given data and waits until the TX operation is done
+pub fn transmit(driver: &mut SimDriver, data: &[u8]) {
+    driver.start_tx(data).expect("failed to start TX");
+    while driver.complete_tx().expect("failed to complete TX").is_none() {}
+}
+
+/// Listens for a single packet and returns it, or the error that ended the RX operation
+pub fn receive(driver: &mut SimDriver) -> Result<Vec<u8>, &'static str> {
+    let timeout = driver.rx_timeout_max()?;
+    driver.start_rx(timeout)?;
+
+    let mut buf = [0; 0xFF];
+    loop {
+        if let Some(len) = driver.complete_rx(&mut buf)? {
+            return Ok(buf[..len].to_vec());
+        }
+    }
+}
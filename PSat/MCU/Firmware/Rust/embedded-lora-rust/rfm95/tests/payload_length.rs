@@ -0,0 +1,65 @@
+//! Payload length tests against the simulated modem
+
+mod common;
+
+use common::{Ether, SimDriver};
+use embedded_lora_rfm95::lora::types::{HeaderMode, PayloadLength, Polarity};
+
+/// Configures the modem with the given header mode and payload length
+fn configure(driver: &mut SimDriver, header_mode: HeaderMode, payload_len: u8) {
+    driver.set_config(&common::config(Polarity::Normal)).expect("failed to configure modem");
+    driver.set_header_mode(header_mode).expect("failed to set header mode");
+    driver.set_payload_length(payload_len).expect("failed to set payload length");
+}
+
+#[test]
+fn payload_length_roundtrip() {
+    let ether = Ether::new();
+    let mut modem = common::driver(&ether);
+    modem.set_payload_length(PayloadLength::new(42)).expect("failed to set payload length");
+    assert_eq!(modem.payload_len(), Ok(PayloadLength::new(42)));
+    assert_eq!(modem.set_payload_length(0), Err("Invalid payload length"));
+}
+
+#[test]
+fn implicit_tx_rejects_wrong_length() {
+    let ether = Ether::new();
+    let mut sender = common::driver(&ether);
+    configure(&mut sender, HeaderMode::Implicit, 9);
+
+    let expected = Err("TX data length does not match implicit payload length");
+    assert_eq!(sender.start_tx(b"Testolopes"), expected);
+    assert_eq!(sender.start_tx(b"Testolop"), expected);
+    assert!(ether.borrow().transmissions.is_empty(), "nothing must be sent");
+}
+
+#[test]
+fn implicit_fixed_length_roundtrip() {
+    let ether = Ether::new();
+    let mut sender = common::driver(&ether);
+    let mut receiver = common::driver(&ether);
+    configure(&mut sender, HeaderMode::Implicit, 9);
+    configure(&mut receiver, HeaderMode::Implicit, 9);
+
+    common::transmit(&mut sender, b"Testolope");
+    assert_eq!(common::receive(&mut receiver).as_deref(), Ok(&b"Testolope"[..]));
+
+    // The payload length survives a transmission, so the next packet can be sent as well
+    common::transmit(&mut sender, b"Antelopes");
+    assert_eq!(common::receive(&mut receiver).as_deref(), Ok(&b"Antelopes"[..]));
+}
+
+#[test]
+fn explicit_filters_packets_over_max_length() {
+    let ether = Ether::new();
+    let mut sender = common::driver(&ether);
+    let mut receiver = common::driver(&ether);
+    configure(&mut sender, HeaderMode::Explicit, u8::MAX);
+    configure(&mut receiver, HeaderMode::Explicit, 9);
+
+    common::transmit(&mut sender, b"Testolopes");
+    assert_eq!(common::receive(&mut receiver), Err("RX timeout"));
+
+    common::transmit(&mut sender, b"Testolope");
+    assert_eq!(common::receive(&mut receiver).as_deref(), Ok(&b"Testolope"[..]));
+}
@@ -0,0 +1,55 @@
+//! IQ polarity tests against the simulated modem
+
+mod common;
+
+use common::Ether;
+use embedded_lora_rfm95::lora::types::Polarity;
+
+/// Sends a packet from a gateway with the given polarity to a node with the given polarity
+fn gateway_to_node(gateway_polarity: Polarity, node_polarity: Polarity) -> Result<Vec<u8>, &'static str> {
+    let ether = Ether::new();
+    let mut gateway = common::driver(&ether);
+    let mut node = common::driver(&ether);
+    gateway.set_config(&common::config(gateway_polarity)).expect("failed to configure gateway");
+    node.set_config(&common::config(node_polarity)).expect("failed to configure node");
+
+    common::transmit(&mut gateway, b"Testolope");
+    common::receive(&mut node)
+}
+
+#[test]
+fn normal_hears_normal() {
+    let packet = gateway_to_node(Polarity::Normal, Polarity::Normal).expect("packet was not received");
+    assert_eq!(packet, b"Testolope");
+}
+
+#[test]
+fn inverted_hears_inverted() {
+    let packet = gateway_to_node(Polarity::Inverted, Polarity::Inverted).expect("packet was not received");
+    assert_eq!(packet, b"Testolope");
+}
+
+#[test]
+fn normal_node_cannot_hear_inverted_gateway() {
+    let result = gateway_to_node(Polarity::Inverted, Polarity::Normal);
+    assert_eq!(result, Err("RX timeout"));
+}
+
+#[test]
+fn inverted_node_cannot_hear_normal_gateway() {
+    let result = gateway_to_node(Polarity::Normal, Polarity::Inverted);
+    assert_eq!(result, Err("RX timeout"));
+}
+
+#[test]
+fn polarity_roundtrip() {
+    let ether = Ether::new();
+    let mut modem = common::driver(&ether);
+    assert_eq!(modem.polarity(), Ok(Polarity::Normal));
+
+    modem.set_polarity(Polarity::Inverted).expect("failed to set polarity");
+    assert_eq!(modem.polarity(), Ok(Polarity::Inverted));
+
+    modem.set_polarity(Polarity::Normal).expect("failed to set polarity");
+    assert_eq!(modem.polarity(), Ok(Polarity::Normal));
+}
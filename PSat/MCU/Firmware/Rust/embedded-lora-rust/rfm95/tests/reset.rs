@@ -0,0 +1,44 @@
+//! Modem reset tests against the simulated modem
+
+mod common;
+
+use common::{Ether, SimDelay, SimModem, SimPin};
+use embedded_lora_rfm95::rfm95::Rfm95Driver;
+use embedded_lora_rfm95::lora::types::Polarity;
+
+#[test]
+fn reset_and_reconfigure_keeps_modem_usable() {
+    let ether = Ether::new();
+    let mut gateway = common::driver(&ether);
+    let mut node = common::driver(&ether);
+    gateway.set_config(&common::config(Polarity::Normal)).expect("failed to configure gateway");
+    node.set_config(&common::config(Polarity::Normal)).expect("failed to configure node");
+
+    // Reset the node and re-apply the config
+    node.reset(SimPin, SimDelay).expect("failed to reset node");
+    node.set_config(&common::config(Polarity::Normal)).expect("failed to reconfigure node");
+
+    common::transmit(&mut gateway, b"Testolope");
+    let packet = common::receive(&mut node).expect("packet was not received");
+    assert_eq!(packet, b"Testolope");
+}
+
+#[test]
+fn uninit_driver_is_usable_after_reset() {
+    let ether = Ether::new();
+    let mut gateway = common::driver(&ether);
+    gateway.set_config(&common::config(Polarity::Normal)).expect("failed to configure gateway");
+
+    // The modem is only initialized by the reset, e.g. once it responds after a failed boot
+    let mut node = Rfm95Driver::new_uninit(SimModem::new(&ether), SimPin);
+    node.reset(SimPin, SimDelay).expect("failed to reset node");
+    node.set_config(&common::config(Polarity::Normal)).expect("failed to configure node");
+
+    // An aborted operation leaves the modem usable
+    let timeout = node.rx_timeout_max().expect("failed to get RX timeout");
+    node.start_rx(timeout).expect("failed to start RX");
+    node.standby().expect("failed to enter standby");
+    common::transmit(&mut gateway, b"Testolope");
+    let packet = common::receive(&mut node).expect("packet was not received");
+    assert_eq!(packet, b"Testolope");
+}
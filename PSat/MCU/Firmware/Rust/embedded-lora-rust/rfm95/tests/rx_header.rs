@@ -0,0 +1,27 @@
+//! Explicit header tests against the simulated modem
+
+mod common;
+
+use common::Ether;
+use embedded_lora_rfm95::lora::types::{CodingRate, CrcMode, HeaderInfo, Polarity};
+
+#[test]
+fn rx_header_once_before_complete_rx() {
+    let ether = Ether::new();
+    let mut sender = common::driver(&ether);
+    let mut receiver = common::driver(&ether);
+    sender.set_config(&common::config(Polarity::Normal)).expect("failed to configure sender");
+    receiver.set_config(&common::config(Polarity::Normal)).expect("failed to configure receiver");
+    common::transmit(&mut sender, b"Testolope");
+
+    let timeout = receiver.rx_timeout_max().expect("failed to get timeout");
+    receiver.start_rx(timeout).expect("failed to start RX");
+    let header = receiver.rx_header().expect("failed to read header");
+    assert_eq!(header, Some(HeaderInfo::new(9, CodingRate::C4_5, CrcMode::Enabled)));
+
+    // The header is only reported once, and clearing it must leave RX done set
+    assert_eq!(receiver.rx_header(), Ok(None));
+    let mut buf = [0; 32];
+    let len = receiver.complete_rx(&mut buf).expect("failed to complete RX");
+    assert_eq!(len.map(|len| &buf[..len]), Some(&b"Testolope"[..]));
+}
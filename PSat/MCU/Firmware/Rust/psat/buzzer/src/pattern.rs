@@ -0,0 +1,199 @@
+//! The built-in tone patterns, and their numbers on the uplink
+//!
+//! | Number      | Pattern                                                      |
+//! |-------------|--------------------------------------------------------------|
+//! | 0           | Off                                                          |
+//! | 1           | Boot OK: a rising triad, once                                |
+//! | 2           | Armed warning: a short beep every second                     |
+//! | 3           | Locator: a loud chirp every few seconds                      |
+//! | 4           | Payload ID in Morse code, once                               |
+//! | 0x11 - 0x1F | Error code 1 to 15: that many long beeps, three times        |
+
+/// A tone or a rest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tone {
+    /// The frequency, or 0 for a rest
+    pub frequency_hz: u16,
+    /// How long it lasts
+    pub duration_ms: u32,
+}
+impl Tone {
+    /// A tone
+    #[must_use]
+    pub const fn new(frequency_hz: u16, duration_ms: u32) -> Self {
+        Self { frequency_hz, duration_ms }
+    }
+
+    /// A rest
+    #[must_use]
+    pub const fn rest(duration_ms: u32) -> Self {
+        Self { frequency_hz: 0, duration_ms }
+    }
+}
+
+/// How often a pattern is played
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repeat {
+    /// Played the given number of times
+    Times(u8),
+    /// Played until another pattern is selected
+    Forever,
+}
+
+/// The most common resonance of small piezo buzzers, which is where they are loudest
+pub const RESONANCE_HZ: u16 = 4000;
+/// The length of a Morse dot
+pub const MORSE_UNIT_MS: u32 = 60;
+/// The pitch of Morse code, which is easier to read by ear than the resonance
+const MORSE_HZ: u16 = 2000;
+
+/// A built-in pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    /// Silent
+    Off,
+    /// The payload has booted and its sensors are working
+    BootOk,
+    /// The payload is armed, so keep clear
+    ArmedWarning,
+    /// The payload has landed, and can be found by ear
+    Locator,
+    /// The payload ID in Morse code
+    PayloadId,
+    /// A numbered fault, from 1 to 15
+    ErrorCode(u8),
+}
+impl Pattern {
+    /// The number on the uplink
+    #[must_use]
+    pub const fn id(&self) -> u8 {
+        match self {
+            Self::Off => 0,
+            Self::BootOk => 1,
+            Self::ArmedWarning => 2,
+            Self::Locator => 3,
+            Self::PayloadId => 4,
+            Self::ErrorCode(code) => 0x10 | *code,
+        }
+    }
+
+    /// How often the pattern is played
+    #[must_use]
+    pub const fn repeat(&self) -> Repeat {
+        match self {
+            Self::Off | Self::BootOk | Self::PayloadId => Repeat::Times(1),
+            Self::ErrorCode(_) => Repeat::Times(3),
+            Self::ArmedWarning | Self::Locator => Repeat::Forever,
+        }
+    }
+
+    /// A step of the pattern, or `None` past its end
+    #[must_use]
+    pub fn tone(&self, step: u16, locator_period_ms: u32, payload_id: &str) -> Option<Tone> {
+        match *self {
+            Self::Off => None,
+            // C6, E6, G6
+            Self::BootOk => {
+                [Tone::new(1047, 100), Tone::new(1319, 100), Tone::new(1568, 200)].get(usize::from(step)).copied()
+            }
+            Self::ArmedWarning => [Tone::new(RESONANCE_HZ, 100), Tone::rest(900)].get(usize::from(step)).copied(),
+            Self::Locator => {
+                let chirp = [Tone::new(3000, 50), Tone::new(3500, 50), Tone::new(RESONANCE_HZ, 50)];
+                let chirp_ms = chirp.iter().map(|tone| tone.duration_ms).sum::<u32>();
+                let rest = Tone::rest(locator_period_ms.saturating_sub(chirp_ms));
+                chirp.into_iter().chain([rest]).nth(usize::from(step))
+            }
+            Self::PayloadId => morse(payload_id).nth(usize::from(step)),
+            // Long beeps with short gaps, and a longer gap before the next repetition
+            Self::ErrorCode(code) => {
+                let beeps = u16::from(code).saturating_mul(2);
+                match step {
+                    step if step.saturating_add(1) == beeps => Some(Tone::rest(1000)),
+                    step if step >= beeps => None,
+                    step if step % 2 == 0 => Some(Tone::new(880, 400)),
+                    _ => Some(Tone::rest(200)),
+                }
+            }
+        }
+    }
+}
+impl TryFrom<u8> for Pattern {
+    type Error = &'static str;
+
+    fn try_from(id: u8) -> Result<Self, Self::Error> {
+        match id {
+            0 => Ok(Self::Off),
+            1 => Ok(Self::BootOk),
+            2 => Ok(Self::ArmedWarning),
+            3 => Ok(Self::Locator),
+            4 => Ok(Self::PayloadId),
+            0x11..=0x1F => Ok(Self::ErrorCode(id & 0x0F)),
+            _ => Err("Unknown buzzer pattern"),
+        }
+    }
+}
+
+/// The tones of a text in Morse code, with the standard spacing, and a word gap at the end so it can be repeated.
+/// Characters without a Morse code are skipped.
+fn morse(text: &str) -> impl Iterator<Item = Tone> + '_ {
+    text.bytes()
+        .filter_map(|byte| match byte {
+            b' ' => Some(b"" as &[u8]),
+            byte => code(byte),
+        })
+        .flat_map(|code| {
+            // Every symbol is followed by a one unit gap, which is stretched to three units after a character, and a
+            // space stretches that to seven
+            let gap_units = if code.is_empty() { 4 } else { 2 };
+            let symbols = code.iter().flat_map(|&symbol| {
+                let units = if symbol == b'-' { 3 } else { 1 };
+                [Tone::new(MORSE_HZ, MORSE_UNIT_MS.saturating_mul(units)), Tone::rest(MORSE_UNIT_MS)]
+            });
+            symbols.chain([Tone::rest(MORSE_UNIT_MS.saturating_mul(gap_units))])
+        })
+        .chain([Tone::rest(MORSE_UNIT_MS.saturating_mul(4))])
+}
+
+/// The Morse code of a letter or digit
+fn code(byte: u8) -> Option<&'static [u8]> {
+    let code: &[u8] = match byte.to_ascii_uppercase() {
+        b'A' => b".-",
+        b'B' => b"-...",
+        b'C' => b"-.-.",
+        b'D' => b"-..",
+        b'E' => b".",
+        b'F' => b"..-.",
+        b'G' => b"--.",
+        b'H' => b"....",
+        b'I' => b"..",
+        b'J' => b".---",
+        b'K' => b"-.-",
+        b'L' => b".-..",
+        b'M' => b"--",
+        b'N' => b"-.",
+        b'O' => b"---",
+        b'P' => b".--.",
+        b'Q' => b"--.-",
+        b'R' => b".-.",
+        b'S' => b"...",
+        b'T' => b"-",
+        b'U' => b"..-",
+        b'V' => b"...-",
+        b'W' => b".--",
+        b'X' => b"-..-",
+        b'Y' => b"-.--",
+        b'Z' => b"--..",
+        b'0' => b"-----",
+        b'1' => b".----",
+        b'2' => b"..---",
+        b'3' => b"...--",
+        b'4' => b"....-",
+        b'5' => b".....",
+        b'6' => b"-....",
+        b'7' => b"--...",
+        b'8' => b"---..",
+        b'9' => b"----.",
+        _ => return None,
+    };
+    Some(code)
+}
@@ -0,0 +1,106 @@
+//! Plays patterns without blocking
+//!
+//! The player is a state machine that is updated with the time, e.g. from a millisecond timer interrupt, and says when
+//! the buzzer's frequency must change. Driving the buzzer is left to the caller, so the timing can be tested on the
+//! host.
+
+use crate::pattern::{Pattern, Repeat, Tone};
+
+/// The settings of the patterns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// The time from one locator chirp to the next
+    pub locator_period_ms: u32,
+    /// The payload ID sent in Morse code
+    pub payload_id: &'static str,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Self { locator_period_ms: 10_000, payload_id: "PSAT" }
+    }
+}
+
+/// Plays one pattern at a time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Player {
+    /// The pattern settings
+    config: Config,
+    /// The pattern being played, or [`Pattern::Off`] once it has finished
+    pattern: Pattern,
+    /// The step of the pattern
+    step: u16,
+    /// How many times the pattern has been played
+    played: u8,
+    /// When the step ends
+    step_end_ms: u32,
+    /// The frequency of the step, or 0 for silence
+    frequency_hz: u16,
+}
+impl Player {
+    /// A silent player
+    #[must_use]
+    pub const fn new(config: Config) -> Self {
+        Self { config, pattern: Pattern::Off, step: 0, played: 0, step_end_ms: 0, frequency_hz: 0 }
+    }
+
+    /// Starts a pattern from the beginning, replacing the one being played, and returns the frequency to play
+    pub fn play(&mut self, pattern: Pattern, now_ms: u32) -> u16 {
+        self.pattern = pattern;
+        self.step = 0;
+        self.played = 0;
+        self.start_step(now_ms);
+        self.frequency_hz
+    }
+
+    /// Moves the pattern on, and returns the new frequency if it has changed. A late update skips the steps that
+    /// were missed, so the pattern keeps its timing.
+    pub fn update(&mut self, now_ms: u32) -> Option<u16> {
+        let frequency_hz = self.frequency_hz;
+        // Compared as a difference, so a wrap of the millisecond clock is harmless
+        while self.pattern != Pattern::Off && now_ms.wrapping_sub(self.step_end_ms) < u32::MAX / 2 {
+            self.step = self.step.saturating_add(1);
+            self.start_step(self.step_end_ms);
+        }
+        (self.frequency_hz != frequency_hz).then_some(self.frequency_hz)
+    }
+
+    /// The pattern being played, or [`Pattern::Off`] once it has finished
+    #[must_use]
+    pub const fn pattern(&self) -> Pattern {
+        self.pattern
+    }
+
+    /// The frequency being played, or 0 for silence
+    #[must_use]
+    pub const fn frequency_hz(&self) -> u16 {
+        self.frequency_hz
+    }
+
+    /// Starts the current step at `start_ms`, moving on to the next repetition or stopping at the end of the pattern
+    fn start_step(&mut self, start_ms: u32) {
+        loop {
+            if let Some(tone) = self.tone() {
+                self.frequency_hz = tone.frequency_hz;
+                self.step_end_ms = start_ms.wrapping_add(tone.duration_ms);
+                return;
+            }
+            self.played = self.played.saturating_add(1);
+            let finished = match self.pattern.repeat() {
+                Repeat::Times(times) => self.played >= times,
+                Repeat::Forever => false,
+            };
+            // An empty pattern, e.g. a payload ID without any Morse characters, would never end a step
+            if finished || self.step == 0 {
+                self.pattern = Pattern::Off;
+                self.frequency_hz = 0;
+                return;
+            }
+            self.step = 0;
+        }
+    }
+
+    /// The current step
+    fn tone(&self) -> Option<Tone> {
+        self.pattern.tone(self.step, self.config.locator_period_ms, self.config.payload_id)
+    }
+}
@@ -0,0 +1,103 @@
+//! Pattern timing, played with a millisecond clock
+
+use psat_buzzer::pattern::{Pattern, MORSE_UNIT_MS, RESONANCE_HZ};
+use psat_buzzer::player::{Config, Player};
+
+/// Plays a pattern from `start_ms` until `end_ms`, updating every millisecond, and returns each frequency change
+fn record(player: &mut Player, pattern: Pattern, start_ms: u32, end_ms: u32) -> Vec<(u32, u16)> {
+    let mut changes = vec![(start_ms, player.play(pattern, start_ms))];
+    for now_ms in start_ms + 1..end_ms {
+        if let Some(frequency_hz) = player.update(now_ms) {
+            changes.push((now_ms, frequency_hz));
+        }
+    }
+    changes
+}
+
+#[test]
+fn pattern_ids() {
+    let patterns = [
+        Pattern::Off,
+        Pattern::BootOk,
+        Pattern::ArmedWarning,
+        Pattern::Locator,
+        Pattern::PayloadId,
+        Pattern::ErrorCode(1),
+        Pattern::ErrorCode(15),
+    ];
+    for pattern in patterns {
+        assert_eq!(Pattern::try_from(pattern.id()), Ok(pattern));
+    }
+    assert_eq!(Pattern::ErrorCode(3).id(), 0x13);
+    for id in [5, 0x10, 0x20, 0xFF] {
+        assert_eq!(Pattern::try_from(id), Err("Unknown buzzer pattern"));
+    }
+}
+
+#[test]
+fn boot_ok_plays_once() {
+    let mut player = Player::new(Config::default());
+    assert_eq!(record(&mut player, Pattern::BootOk, 100, 1000), [(100, 1047), (200, 1319), (300, 1568), (500, 0)]);
+    assert_eq!(player.pattern(), Pattern::Off);
+}
+
+#[test]
+fn error_code_repeats_three_times() {
+    let mut player = Player::new(Config::default());
+    let changes = record(&mut player, Pattern::ErrorCode(2), 0, 10_000);
+    // Two 400 ms beeps and a second's pause, three times
+    let once = [(0, 880), (400, 0), (600, 880), (1000, 0)];
+    let expected: Vec<_> =
+        (0..3).flat_map(|repeat| once.map(|(time, frequency)| (time + 2000 * repeat, frequency))).collect();
+    assert_eq!(changes, expected);
+    assert_eq!(player.pattern(), Pattern::Off);
+}
+
+#[test]
+fn armed_warning_and_locator_repeat_until_changed() {
+    let mut player = Player::new(Config { locator_period_ms: 5000, ..Config::default() });
+    let changes = record(&mut player, Pattern::ArmedWarning, 0, 3000);
+    assert_eq!(
+        changes,
+        [(0, RESONANCE_HZ), (100, 0), (1000, RESONANCE_HZ), (1100, 0), (2000, RESONANCE_HZ), (2100, 0)]
+    );
+    assert_eq!(player.pattern(), Pattern::ArmedWarning);
+
+    // The locator chirp replaces it, every 5 seconds
+    let changes = record(&mut player, Pattern::Locator, 3000, 13_000);
+    let chirp = |start| [(start, 3000), (start + 50, 3500), (start + 100, RESONANCE_HZ), (start + 150, 0)];
+    assert_eq!(changes, [chirp(3000), chirp(8000)].concat());
+
+    // Turned off over the uplink
+    assert_eq!(player.play(Pattern::Off, 13_000), 0);
+    assert_eq!(player.update(20_000), None);
+}
+
+#[test]
+fn payload_id_in_morse() {
+    let mut player = Player::new(Config { payload_id: "A e", ..Config::default() });
+    let changes = record(&mut player, Pattern::PayloadId, 0, 5000);
+    let unit = MORSE_UNIT_MS;
+    // Dot, dash, a word gap, and a dot followed by a word gap
+    let expected = [(0, 2000), (unit, 0), (2 * unit, 2000), (5 * unit, 0), (12 * unit, 2000), (13 * unit, 0)];
+    assert_eq!(changes, expected);
+    assert_eq!(player.update(20 * unit), None);
+    assert_eq!(player.pattern(), Pattern::Off, "the word gap ends it");
+
+    // Nothing to send
+    let mut player = Player::new(Config { payload_id: "#", ..Config::default() });
+    player.play(Pattern::PayloadId, 0);
+    assert_eq!(player.update(1000), None);
+    assert_eq!(player.pattern(), Pattern::Off);
+}
+
+#[test]
+fn late_updates_keep_time() {
+    let mut player = Player::new(Config::default());
+    player.play(Pattern::ArmedWarning, 0);
+    // Updates held off for a few seconds, e.g. by a long radio transfer, skip ahead
+    assert_eq!(player.update(5050), None);
+    assert_eq!(player.frequency_hz(), RESONANCE_HZ);
+    assert_eq!(player.update(5100), Some(0));
+    assert_eq!(player.update(6000), Some(RESONANCE_HZ));
+}
@@ -0,0 +1,144 @@
+//! The MCU side: sends one command at a time and waits for the response, retrying on timeouts
+//!
+//! The client is blocking, as both links to the camera are driven by the CPU. It has no clock of its own; the
+//! [`Link`] waits for bytes with a timeout.
+
+use crate::frame::{CameraError, Command, Request, Response, Status, RESPONSE_LEN};
+use crate::reader::Reader;
+
+/// A half-duplex byte link to the camera
+pub trait Link {
+    /// Sends a request frame, and returns once it has been sent
+    fn send(&mut self, frame: &[u8]) -> Result<(), &'static str>;
+    /// Waits up to `timeout_ms` for the next byte, and returns `None` if none has arrived in time
+    fn receive(&mut self, timeout_ms: u32) -> Option<u8>;
+}
+
+/// Timeouts and retries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// How long the camera may take to answer a command
+    pub response_timeout_ms: u32,
+    /// How long the camera may take to answer a capture, which includes saving the picture
+    pub capture_timeout_ms: u32,
+    /// The longest gap between two bytes of a response
+    pub byte_timeout_ms: u32,
+    /// The total amount of transmissions per command
+    pub max_attempts: u8,
+}
+impl Default for Config {
+    /// A UXGA JPEG takes about a second to capture and save
+    fn default() -> Self {
+        Self { response_timeout_ms: 250, capture_timeout_ms: 3000, byte_timeout_ms: 20, max_attempts: 3 }
+    }
+}
+
+/// Why a command has failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The camera did not answer any attempt
+    NoResponse,
+    /// The camera did not execute the command
+    Rejected(CameraError),
+    /// The request could not be sent
+    Link(&'static str),
+}
+
+/// Bytes that are skipped while waiting for a response, e.g. log text, before the attempt is given up. Without a
+/// limit, a camera that keeps printing would never time out.
+const MAX_BYTES_PER_ATTEMPT: u16 = 512;
+
+/// Sends commands to the camera and matches the responses
+#[derive(Debug)]
+pub struct Client<L: Link> {
+    /// The link to the camera
+    link: L,
+    /// Timeouts and retries
+    config: Config,
+    /// The sequence number of the last request
+    sequence: u8,
+    /// Collects the response
+    reader: Reader<RESPONSE_LEN>,
+}
+impl<L: Link> Client<L> {
+    /// Creates a client on the given link
+    pub const fn new(link: L, config: Config) -> Self {
+        Self { link, config, sequence: 0, reader: Reader::responses() }
+    }
+
+    /// Takes a picture, and returns the status with the number of the new picture
+    pub fn capture(&mut self) -> Result<Status, Error> {
+        self.execute(Command::Capture)
+    }
+
+    /// Starts taking `count` pictures, or pictures until the burst is stopped for `0`, every `interval_ms`
+    pub fn start_burst(&mut self, interval_ms: u16, count: u8) -> Result<Status, Error> {
+        self.execute(Command::StartBurst { interval_ms, count })
+    }
+
+    /// Stops taking pictures
+    pub fn stop_burst(&mut self) -> Result<Status, Error> {
+        self.execute(Command::StopBurst)
+    }
+
+    /// Reports the status and the image count
+    pub fn status(&mut self) -> Result<Status, Error> {
+        self.execute(Command::Status)
+    }
+
+    /// Sends a command and waits for the response, retrying until the attempts are used up
+    ///
+    /// # Errors
+    /// Responses to other requests, e.g. a late answer to an earlier command, are ignored. A rejection is not retried,
+    /// as the camera would reject the command again.
+    pub fn execute(&mut self, command: Command) -> Result<Status, Error> {
+        self.sequence = self.sequence.wrapping_add(1);
+        let request = Request::new(self.sequence, command);
+        let frame = request.encode();
+        let timeout_ms = match command {
+            Command::Capture => self.config.capture_timeout_ms,
+            _ => self.config.response_timeout_ms,
+        };
+
+        for _ in 0..self.config.max_attempts {
+            self.link.send(&frame).map_err(Error::Link)?;
+            if let Some(response) = self.wait(&request, timeout_ms) {
+                return response.result.map(|()| response.status).map_err(Error::Rejected);
+            }
+        }
+        Err(Error::NoResponse)
+    }
+
+    /// The link
+    pub fn link(&self) -> &L {
+        &self.link
+    }
+
+    /// The link, e.g. to reconfigure it
+    pub fn link_mut(&mut self) -> &mut L {
+        &mut self.link
+    }
+
+    /// Gives back the link
+    pub fn release(self) -> L {
+        self.link
+    }
+
+    /// Reads until the response to the request arrives, or the camera falls silent
+    fn wait(&mut self, request: &Request, timeout_ms: u32) -> Option<Response> {
+        self.reader.clear();
+        for _ in 0..MAX_BYTES_PER_ATTEMPT {
+            // Once a frame has started, the rest of it follows without a pause
+            let timeout_ms = if self.reader.is_reading() { self.config.byte_timeout_ms } else { timeout_ms };
+            let byte = self.link.receive(timeout_ms)?;
+            let Some(frame) = self.reader.push(byte) else { continue };
+            match Response::decode(&frame) {
+                Ok(response) if response.sequence == request.sequence && response.id == request.id => {
+                    return Some(response)
+                }
+                _ => continue,
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,234 @@
+//! Request and response frames
+//!
+//! # Layout
+//! Frames have a fixed length, so they can be read without a length field. All multi-byte fields are little-endian.
+//!
+//! A request (MCU to camera) is `magic 0xE5 | sequence | command ID | argument (3) | CRC (2)`.
+//!
+//! A response (camera to MCU) is `magic 0xE6 | sequence | command ID | result | flags | images (2) | CRC (2)`, where
+//! result `0` means the command has been executed and any other value is a [`CameraError`]. Every response carries
+//! the camera's [`Status`], so a command also reports the image count.
+//!
+//! The CRC is a CRC-16/CCITT-FALSE over all preceding bytes.
+
+use psat_telemetry::crc::crc16;
+
+/// The first byte of every request frame
+pub const REQUEST_MAGIC: u8 = 0xE5;
+/// The first byte of every response frame
+pub const RESPONSE_MAGIC: u8 = 0xE6;
+/// The encoded length of a request frame
+pub const REQUEST_LEN: usize = 8;
+/// The encoded length of a response frame
+pub const RESPONSE_LEN: usize = 9;
+
+/// A command for the camera
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Take a picture and save it to the SD card
+    Capture,
+    /// Take pictures at a fixed interval
+    StartBurst {
+        /// The time from one picture to the next
+        interval_ms: u16,
+        /// The amount of pictures, or `0` to take pictures until the burst is stopped
+        count: u8,
+    },
+    /// Stop taking pictures
+    StopBurst,
+    /// Report the status and the image count
+    Status,
+}
+impl Command {
+    /// The command ID and argument
+    pub const fn encode(&self) -> (u8, [u8; 3]) {
+        match *self {
+            Self::Capture => (0x01, [0; 3]),
+            Self::StartBurst { interval_ms, count } => {
+                let [i0, i1] = interval_ms.to_le_bytes();
+                (0x02, [i0, i1, count])
+            }
+            Self::StopBurst => (0x03, [0; 3]),
+            Self::Status => (0x04, [0; 3]),
+        }
+    }
+
+    /// Decodes a command ID and argument
+    pub const fn decode(id: u8, argument: [u8; 3]) -> Result<Self, CameraError> {
+        match (id, argument) {
+            (0x01, [0, 0, 0]) => Ok(Self::Capture),
+            // A zero interval would take pictures as fast as the SD card allows, which starves the camera's other tasks
+            (0x02, [0, 0, _]) => Err(CameraError::InvalidArgument),
+            (0x02, [i0, i1, count]) => Ok(Self::StartBurst { interval_ms: u16::from_le_bytes([i0, i1]), count }),
+            (0x03, [0, 0, 0]) => Ok(Self::StopBurst),
+            (0x04, [0, 0, 0]) => Ok(Self::Status),
+            (0x01..=0x04, _) => Err(CameraError::InvalidArgument),
+            _ => Err(CameraError::UnknownCommand),
+        }
+    }
+}
+
+/// Why the camera has not executed a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CameraError {
+    /// The command ID is unknown
+    UnknownCommand = 1,
+    /// The argument is invalid for the command
+    InvalidArgument = 2,
+    /// A burst is under way, so a single picture cannot be taken
+    Busy = 3,
+    /// The camera did not deliver a picture
+    CaptureFailed = 4,
+    /// The picture could not be saved, e.g. because the SD card is missing or full
+    StorageFailed = 5,
+}
+impl TryFrom<u8> for CameraError {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::UnknownCommand),
+            2 => Ok(Self::InvalidArgument),
+            3 => Ok(Self::Busy),
+            4 => Ok(Self::CaptureFailed),
+            5 => Ok(Self::StorageFailed),
+            _ => Err("Invalid camera error"),
+        }
+    }
+}
+
+/// The state of the camera, as reported in every response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Status {
+    /// The image sensor has been initialised
+    pub camera_ready: bool,
+    /// The SD card is mounted
+    pub storage_ready: bool,
+    /// A burst is under way
+    pub bursting: bool,
+    /// The number of the last saved picture, which counts up across restarts of the camera
+    pub images: u16,
+}
+impl Status {
+    /// The flags byte
+    const fn flags(&self) -> u8 {
+        (self.camera_ready as u8) | (self.storage_ready as u8) << 1 | (self.bursting as u8) << 2
+    }
+}
+
+/// A request frame
+///
+/// # Note
+/// The command is kept raw, so the camera can reject unknown commands instead of dropping them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Request {
+    /// The sequence number, which is echoed in the response. Retries keep the sequence number, so the camera can
+    /// repeat its response instead of executing the command twice.
+    pub sequence: u8,
+    /// The command ID
+    pub id: u8,
+    /// The command argument
+    pub argument: [u8; 3],
+}
+impl Request {
+    /// Creates a request for the given command
+    pub const fn new(sequence: u8, command: Command) -> Self {
+        let (id, argument) = command.encode();
+        Self { sequence, id, argument }
+    }
+
+    /// The command
+    pub const fn command(&self) -> Result<Command, CameraError> {
+        Command::decode(self.id, self.argument)
+    }
+
+    /// Encodes the request
+    pub fn encode(&self) -> [u8; REQUEST_LEN] {
+        let [a0, a1, a2] = self.argument;
+        let mut frame = [REQUEST_MAGIC, self.sequence, self.id, a0, a1, a2, 0, 0];
+        seal(&mut frame);
+        frame
+    }
+
+    /// Decodes and validates a request
+    pub fn decode(frame: &[u8]) -> Result<Self, &'static str> {
+        let [_, sequence, id, a0, a1, a2] = *open::<REQUEST_LEN>(frame, REQUEST_MAGIC)? else {
+            return Err("Invalid frame length");
+        };
+        Ok(Self { sequence, id, argument: [a0, a1, a2] })
+    }
+}
+
+/// A response frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Response {
+    /// The sequence number of the request
+    pub sequence: u8,
+    /// The command ID of the request
+    pub id: u8,
+    /// The result; `Ok` if the command has been executed
+    pub result: Result<(), CameraError>,
+    /// The status after the command
+    pub status: Status,
+}
+impl Response {
+    /// Encodes the response
+    pub fn encode(&self) -> [u8; RESPONSE_LEN] {
+        let result = match self.result {
+            Ok(()) => 0,
+            Err(error) => error as u8,
+        };
+        let [i0, i1] = self.status.images.to_le_bytes();
+        let mut frame = [RESPONSE_MAGIC, self.sequence, self.id, result, self.status.flags(), i0, i1, 0, 0];
+        seal(&mut frame);
+        frame
+    }
+
+    /// Decodes and validates a response
+    pub fn decode(frame: &[u8]) -> Result<Self, &'static str> {
+        let [_, sequence, id, result, flags, i0, i1] = *open::<RESPONSE_LEN>(frame, RESPONSE_MAGIC)? else {
+            return Err("Invalid frame length");
+        };
+        let result = match result {
+            0 => Ok(()),
+            error => Err(CameraError::try_from(error)?),
+        };
+        let status = Status {
+            camera_ready: flags & 0x01 != 0,
+            storage_ready: flags & 0x02 != 0,
+            bursting: flags & 0x04 != 0,
+            images: u16::from_le_bytes([i0, i1]),
+        };
+        Ok(Self { sequence, id, result, status })
+    }
+}
+
+/// Whether a frame ends in a valid CRC
+pub(crate) fn is_sealed(frame: &[u8]) -> bool {
+    frame.split_last_chunk::<2>().is_some_and(|(body, crc)| crc16(body) == u16::from_le_bytes(*crc))
+}
+
+/// Writes the CRC into the last two bytes of a frame
+fn seal(frame: &mut [u8]) {
+    if let Some((body, crc)) = frame.split_last_chunk_mut::<2>() {
+        *crc = crc16(body).to_le_bytes();
+    }
+}
+
+/// Validates magic, length and CRC of an `N`-byte frame, and returns its body
+fn open<const N: usize>(frame: &[u8], magic: u8) -> Result<&[u8], &'static str> {
+    let [first, ..] = frame else {
+        return Err("Truncated frame");
+    };
+    let true = *first == magic else {
+        return Err("Invalid magic byte");
+    };
+    let true = frame.len() == N else {
+        return Err("Invalid frame length");
+    };
+    let true = is_sealed(frame) else {
+        return Err("Checksum mismatch");
+    };
+    frame.split_last_chunk::<2>().map(|(body, _)| body).ok_or("Invalid frame length")
+}
@@ -0,0 +1,74 @@
+//! Finds frames in a byte stream
+//!
+//! The link to the camera is shared with log text, as the camera prints its log on its UART. The reader therefore skips
+//! everything up to a magic byte, and only returns frames with a valid CRC. If a frame turns out to be invalid, reading
+//! restarts at the next magic byte within it, so a magic byte in log text cannot hide a frame that follows.
+
+use crate::frame::{is_sealed, REQUEST_LEN, REQUEST_MAGIC, RESPONSE_LEN, RESPONSE_MAGIC};
+
+/// Collects `N`-byte frames that start with a magic byte
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reader<const N: usize> {
+    /// The first byte of every frame
+    magic: u8,
+    /// The bytes read so far
+    buffer: [u8; N],
+    /// The amount of bytes read so far
+    len: usize,
+}
+impl Reader<REQUEST_LEN> {
+    /// A reader for request frames, as used by the camera
+    pub const fn requests() -> Self {
+        Self::new(REQUEST_MAGIC)
+    }
+}
+impl Reader<RESPONSE_LEN> {
+    /// A reader for response frames, as used by the MCU
+    pub const fn responses() -> Self {
+        Self::new(RESPONSE_MAGIC)
+    }
+}
+impl<const N: usize> Reader<N> {
+    /// Creates a reader for frames that start with the given magic byte
+    pub const fn new(magic: u8) -> Self {
+        Self { magic, buffer: [0; N], len: 0 }
+    }
+
+    /// Adds a byte, and returns the frame if it completes a valid one
+    pub fn push(&mut self, byte: u8) -> Option<[u8; N]> {
+        if self.len == 0 && byte != self.magic {
+            return None;
+        }
+        let slot = self.buffer.get_mut(self.len)?;
+        *slot = byte;
+        self.len = self.len.saturating_add(1);
+        if self.len < N {
+            return None;
+        }
+
+        if is_sealed(&self.buffer) {
+            self.len = 0;
+            return Some(self.buffer);
+        }
+        // Start again at the next magic byte after the one that has turned out not to start a frame
+        match self.buffer.iter().skip(1).position(|&byte| byte == self.magic) {
+            Some(offset) => {
+                let start = offset.saturating_add(1);
+                self.buffer.copy_within(start.., 0);
+                self.len = N.saturating_sub(start);
+            }
+            None => self.len = 0,
+        }
+        None
+    }
+
+    /// Whether a frame has been started
+    pub const fn is_reading(&self) -> bool {
+        self.len > 0
+    }
+
+    /// Drops a partly read frame
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
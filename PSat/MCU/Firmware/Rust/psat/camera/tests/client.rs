@@ -0,0 +1,229 @@
+//! Commands against a simulated camera on a link with a millisecond clock
+
+use psat_camera::client::{Client, Config, Error, Link};
+use psat_camera::frame::{CameraError, Command, Request, Response, Status, REQUEST_LEN};
+use psat_camera::reader::Reader;
+use std::collections::VecDeque;
+
+/// The time a byte takes on the link
+const BYTE_MS: u32 = 1;
+/// The time the camera takes to capture and save a picture
+const CAPTURE_MS: u32 = 800;
+
+/// A simulated ESP32-CAM, running the camera side of the protocol
+struct Camera {
+    /// The time on the link
+    now_ms: u32,
+    /// Whether the camera is connected at all
+    connected: bool,
+    /// Whether the camera prints its log on the link
+    logging: bool,
+    /// The status
+    status: Status,
+    /// Collects requests
+    reader: Reader<REQUEST_LEN>,
+    /// The last request and its response
+    last: Option<(Request, Response)>,
+    /// Bytes on their way to the MCU, with the time they arrive
+    outgoing: VecDeque<(u32, u8)>,
+    /// The indices of responses that are lost on the way
+    lost: Vec<usize>,
+    /// The indices of responses that arrive with a flipped bit
+    corrupted: Vec<usize>,
+    /// The amount of responses so far
+    responses: usize,
+    /// The commands the camera has executed
+    executed: Vec<Command>,
+}
+impl Camera {
+    /// A camera that is up and has the given picture count
+    fn new(images: u16) -> Self {
+        Self {
+            now_ms: 0,
+            connected: true,
+            logging: false,
+            status: Status { camera_ready: true, storage_ready: true, bursting: false, images },
+            reader: Reader::requests(),
+            last: None,
+            outgoing: VecDeque::new(),
+            lost: Vec::new(),
+            corrupted: Vec::new(),
+            responses: 0,
+            executed: Vec::new(),
+        }
+    }
+
+    /// Executes a request, and returns the response and how long it took
+    fn execute(&mut self, request: &Request) -> (Response, u32) {
+        let result = request.command().and_then(|command| {
+            match command {
+                Command::Capture if self.status.bursting => return Err(CameraError::Busy),
+                Command::Capture => self.status.images += 1,
+                Command::StartBurst { .. } => self.status.bursting = true,
+                Command::StopBurst => self.status.bursting = false,
+                Command::Status => (),
+            }
+            self.executed.push(command);
+            Ok(())
+        });
+        let delay_ms = if result.is_ok() && request.command() == Ok(Command::Capture) { CAPTURE_MS } else { 5 };
+        (Response { sequence: request.sequence, id: request.id, result, status: self.status }, delay_ms)
+    }
+
+    /// Queues bytes for the MCU, one after another from the given time
+    fn transmit(&mut self, start_ms: u32, bytes: &[u8]) {
+        let start_ms = self.outgoing.back().map_or(start_ms, |&(at, _)| at.max(start_ms));
+        for (i, &byte) in bytes.iter().enumerate() {
+            self.outgoing.push_back((start_ms + (i as u32 + 1) * BYTE_MS, byte));
+        }
+    }
+}
+impl Link for Camera {
+    fn send(&mut self, frame: &[u8]) -> Result<(), &'static str> {
+        self.now_ms += frame.len() as u32 * BYTE_MS;
+        if !self.connected {
+            return Ok(());
+        }
+        for &byte in frame {
+            let Some(frame) = self.reader.push(byte) else { continue };
+            let request = Request::decode(&frame).expect("valid request");
+
+            // A repeated request gets the same response, without executing the command again
+            let (response, delay_ms) = match self.last {
+                Some((last, response)) if last == request => (response, 5),
+                _ => self.execute(&request),
+            };
+            self.last = Some((request, response));
+
+            if self.logging && request.command() == Ok(Command::Capture) {
+                let log = format!("Picture file name: /picture{}.jpg\n", self.status.images);
+                self.transmit(self.now_ms, log.as_bytes());
+            }
+            let mut encoded = response.encode();
+            if self.corrupted.contains(&self.responses) {
+                encoded[4] ^= 0x01;
+            }
+            if !self.lost.contains(&self.responses) {
+                self.transmit(self.now_ms + delay_ms, &encoded);
+            }
+            self.responses += 1;
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self, timeout_ms: u32) -> Option<u8> {
+        match self.outgoing.front() {
+            Some(&(at, byte)) if at <= self.now_ms + timeout_ms => {
+                self.outgoing.pop_front();
+                self.now_ms = self.now_ms.max(at);
+                Some(byte)
+            }
+            _ => {
+                self.now_ms += timeout_ms;
+                None
+            }
+        }
+    }
+}
+
+#[test]
+fn capture_and_status() {
+    let mut camera = Client::new(Camera::new(44), Config::default());
+    let status = camera.status().expect("status");
+    assert_eq!(status, Status { camera_ready: true, storage_ready: true, bursting: false, images: 44 });
+
+    assert_eq!(camera.capture().map(|status| status.images), Ok(45));
+    assert_eq!(camera.capture().map(|status| status.images), Ok(46));
+    let camera = camera.release();
+    assert_eq!(camera.executed, [Command::Status, Command::Capture, Command::Capture]);
+    assert_eq!(camera.responses, 3);
+    assert!(camera.now_ms < 2 * CAPTURE_MS + 100, "took {} ms", camera.now_ms);
+}
+
+#[test]
+fn burst_and_rejections() {
+    let mut camera = Client::new(Camera::new(0), Config::default());
+    assert_eq!(camera.start_burst(500, 0).map(|status| status.bursting), Ok(true));
+    // A rejected command is not retried
+    assert_eq!(camera.capture(), Err(Error::Rejected(CameraError::Busy)));
+    assert_eq!(camera.link().responses, 2);
+
+    assert_eq!(camera.stop_burst().map(|status| status.bursting), Ok(false));
+    assert_eq!(camera.start_burst(0, 10), Err(Error::Rejected(CameraError::InvalidArgument)));
+    assert_eq!(camera.capture().map(|status| status.images), Ok(1));
+}
+
+#[test]
+fn log_text_is_skipped() {
+    let mut camera = Camera::new(6);
+    camera.logging = true;
+    let mut camera = Client::new(camera, Config::default());
+    assert_eq!(camera.capture().map(|status| status.images), Ok(7));
+    assert_eq!(camera.capture().map(|status| status.images), Ok(8));
+    assert_eq!(camera.link().responses, 2);
+}
+
+#[test]
+fn lost_response_is_repeated_without_a_second_picture() {
+    let mut camera = Camera::new(0);
+    camera.lost = vec![0];
+    let mut camera = Client::new(camera, Config::default());
+    assert_eq!(camera.capture().map(|status| status.images), Ok(1));
+
+    let camera = camera.release();
+    assert_eq!(camera.executed, [Command::Capture]);
+    assert_eq!(camera.responses, 2);
+    assert!(camera.now_ms > Config::default().capture_timeout_ms, "waited for the first attempt");
+}
+
+#[test]
+fn corrupted_response_is_retried() {
+    let mut camera = Camera::new(0);
+    camera.corrupted = vec![0];
+    let mut camera = Client::new(camera, Config::default());
+    assert!(camera.status().is_ok());
+    assert_eq!(camera.link().responses, 2);
+}
+
+#[test]
+fn no_camera_times_out() {
+    let mut camera = Camera::new(0);
+    camera.connected = false;
+    let config = Config::default();
+    let mut camera = Client::new(camera, config);
+    assert_eq!(camera.status(), Err(Error::NoResponse));
+
+    let attempts = u32::from(config.max_attempts);
+    let send_ms = REQUEST_LEN as u32 * BYTE_MS;
+    assert_eq!(camera.link().now_ms, attempts * (send_ms + config.response_timeout_ms));
+}
+
+#[test]
+fn frames() {
+    let request = Request::new(7, Command::StartBurst { interval_ms: 1000, count: 20 });
+    let encoded = request.encode();
+    assert_eq!(encoded[..6], [0xE5, 7, 0x02, 0xE8, 0x03, 20]);
+    assert_eq!(Request::decode(&encoded), Ok(request));
+    assert_eq!(request.command(), Ok(Command::StartBurst { interval_ms: 1000, count: 20 }));
+    assert_eq!(Request { sequence: 1, id: 0x09, argument: [0; 3] }.command(), Err(CameraError::UnknownCommand));
+    assert_eq!(Request { sequence: 1, id: 0x01, argument: [1, 0, 0] }.command(), Err(CameraError::InvalidArgument));
+
+    let status = Status { camera_ready: true, storage_ready: false, bursting: true, images: 300 };
+    let response = Response { sequence: 7, id: 0x02, result: Err(CameraError::StorageFailed), status };
+    let mut encoded = response.encode();
+    assert_eq!(Response::decode(&encoded), Ok(response));
+    encoded[5] ^= 0x80;
+    assert_eq!(Response::decode(&encoded), Err("Checksum mismatch"));
+    assert_eq!(Response::decode(&encoded[..8]), Err("Invalid frame length"));
+}
+
+#[test]
+fn reader_resynchronises() {
+    let response = Response { sequence: 3, id: 0x04, result: Ok(()), status: Status::default() }.encode();
+    let mut reader = Reader::responses();
+    // Log text with a stray magic byte, then a truncated frame, then the frame
+    let stream = [b"ok \xE6\xE6 ".as_slice(), &response[..5], &response].concat();
+    let frames: Vec<_> = stream.iter().filter_map(|&byte| reader.push(byte)).collect();
+    assert_eq!(frames, [response]);
+    assert!(!reader.is_reading());
+}
@@ -0,0 +1,134 @@
+//! Message authentication with SipHash-2-4
+//!
+//! SipHash is a keyed hash designed for short messages. It needs no tables and only 64-bit additions, rotations and
+//! XORs, so it fits the MSP430's flash budget where HMAC-SHA256 would not.
+
+/// The length of a MAC
+pub const MAC_LEN: usize = 8;
+
+/// A 128-bit pre-shared key
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Key(pub [u8; 16]);
+impl Key {
+    /// Parses a key from 32 hex digits, e.g. from a build-time environment variable
+    pub const fn from_hex(hex: &str) -> Option<Self> {
+        /// Decodes a single hex digit
+        const fn digit(char: u8) -> Option<u8> {
+            match char {
+                b'0'..=b'9' => Some(char.wrapping_sub(b'0')),
+                b'a'..=b'f' => Some(char.wrapping_sub(b'a').wrapping_add(10)),
+                b'A'..=b'F' => Some(char.wrapping_sub(b'A').wrapping_add(10)),
+                _ => None,
+            }
+        }
+
+        // Accumulate the digits big-endian, as they are written
+        let mut key: u128 = 0;
+        let mut rest = hex.as_bytes();
+        let mut digits = 0_usize;
+        while let [char, tail @ ..] = rest {
+            let Some(value) = digit(*char) else {
+                return None;
+            };
+            key = key.wrapping_shl(4) | value as u128;
+            (rest, digits) = (tail, digits.wrapping_add(1));
+        }
+
+        match digits {
+            32 => Some(Self(key.to_be_bytes())),
+            _ => None,
+        }
+    }
+
+    /// Computes the MAC of a message
+    pub fn mac(&self, message: &[u8]) -> [u8; MAC_LEN] {
+        siphash24(self, message).to_le_bytes()
+    }
+
+    /// Verifies the MAC of a message
+    ///
+    /// # Note
+    /// The comparison does not exit early, so its timing does not reveal how many bytes of the MAC were correct.
+    pub fn verify(&self, message: &[u8], mac: &[u8; MAC_LEN]) -> bool {
+        let expected = self.mac(message);
+        let difference = expected.iter().zip(mac).fold(0, |difference, (a, b)| difference | (a ^ b));
+        difference == 0
+    }
+}
+impl core::fmt::Debug for Key {
+    /// Keeps the key out of logs
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("Key(..)")
+    }
+}
+
+/// The SipHash state
+struct State([u64; 4]);
+impl State {
+    /// A single SipRound
+    fn round(&mut self) {
+        let [v0, v1, v2, v3] = &mut self.0;
+        *v0 = v0.wrapping_add(*v1);
+        *v1 = v1.rotate_left(13) ^ *v0;
+        *v0 = v0.rotate_left(32);
+        *v2 = v2.wrapping_add(*v3);
+        *v3 = v3.rotate_left(16) ^ *v2;
+        *v0 = v0.wrapping_add(*v3);
+        *v3 = v3.rotate_left(21) ^ *v0;
+        *v2 = v2.wrapping_add(*v1);
+        *v1 = v1.rotate_left(17) ^ *v2;
+        *v2 = v2.rotate_left(32);
+    }
+
+    /// Absorbs a message word with two compression rounds
+    fn compress(&mut self, word: u64) {
+        let [_, _, _, v3] = &mut self.0;
+        *v3 ^= word;
+        self.round();
+        self.round();
+        let [v0, ..] = &mut self.0;
+        *v0 ^= word;
+    }
+}
+
+/// Computes SipHash-2-4
+pub fn siphash24(key: &Key, message: &[u8]) -> u64 {
+    let [k0, k1] = [key.0.first_chunk::<8>(), key.0.last_chunk::<8>()].map(|half| match half {
+        Some(half) => u64::from_le_bytes(*half),
+        None => 0,
+    });
+    let mut state = State([
+        k0 ^ 0x736f_6d65_7073_6575,
+        k1 ^ 0x646f_7261_6e64_6f6d,
+        k0 ^ 0x6c79_6765_6e65_7261,
+        k1 ^ 0x7465_6462_7974_6573,
+    ]);
+
+    // Absorb the full words
+    let mut chunks = message.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut word = [0; 8];
+        word.copy_from_slice(chunk);
+        state.compress(u64::from_le_bytes(word));
+    }
+
+    // The last word holds the remaining bytes and the message length modulo 256
+    let mut last = [0; 8];
+    let remainder = chunks.remainder();
+    if let Some(slot) = last.get_mut(..remainder.len()) {
+        slot.copy_from_slice(remainder);
+    }
+    if let Some(length) = last.last_mut() {
+        *length = message.len() as u8;
+    }
+    state.compress(u64::from_le_bytes(last));
+
+    // Finalize
+    let [_, _, v2, _] = &mut state.0;
+    *v2 ^= 0xff;
+    for _ in 0..4 {
+        state.round();
+    }
+    let [v0, v1, v2, v3] = state.0;
+    v0 ^ v1 ^ v2 ^ v3
+}
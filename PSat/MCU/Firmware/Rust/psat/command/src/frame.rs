@@ -0,0 +1,209 @@
+//! Command and response frames
+//!
+//! # Layout (version 2)
+//! All multi-byte fields are little-endian.
+//!
+//! A command (uplink) is `magic 0xC3 | version | counter (4) | command ID | argument | MAC (8)`, where the MAC is a
+//! keyed [SipHash-2-4](crate::auth) over all preceding bytes. The counter must increase with every new command, so
+//! recorded commands cannot be replayed.
+//!
+//! A response (downlink) is `magic 0xC4 | version | counter (4) | status | CRC (2)`, where status `0` is an ACK and any
+//! other value is a NACK with the given [`NackReason`]. The CRC is a CRC-16/CCITT-FALSE over all preceding bytes.
+//! Responses are not authenticated, as they carry no secrets and trigger no actions.
+
+use crate::auth::{Key, MAC_LEN};
+use psat_telemetry::crc::crc16;
+
+/// The first byte of every command frame
+pub const COMMAND_MAGIC: u8 = 0xC3;
+/// The first byte of every response frame
+pub const RESPONSE_MAGIC: u8 = 0xC4;
+/// The frame format version
+pub const VERSION: u8 = 2;
+/// The encoded length of a command frame
+pub const COMMAND_LEN: usize = 16;
+/// The encoded length of a response frame
+pub const RESPONSE_LEN: usize = 9;
+
+/// A command that can be sent to the payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Arm the payload for launch
+    Arm,
+    /// Disarm the payload
+    Disarm,
+    /// Trigger the camera servo
+    TriggerServo,
+    /// Play a buzzer pattern; pattern `0` turns the buzzer off
+    Buzzer {
+        /// The pattern number
+        pattern: u8,
+    },
+    /// Get a fresh GPS fix and send it down
+    RequestGpsFix,
+}
+impl Command {
+    /// The command ID and argument
+    pub const fn encode(&self) -> (u8, u8) {
+        match *self {
+            Self::Arm => (0x01, 0),
+            Self::Disarm => (0x02, 0),
+            Self::TriggerServo => (0x03, 0),
+            Self::Buzzer { pattern } => (0x04, pattern),
+            Self::RequestGpsFix => (0x05, 0),
+        }
+    }
+
+    /// Decodes a command ID and argument
+    pub const fn decode(id: u8, argument: u8) -> Result<Self, NackReason> {
+        match (id, argument) {
+            (0x01, 0) => Ok(Self::Arm),
+            (0x02, 0) => Ok(Self::Disarm),
+            (0x03, 0) => Ok(Self::TriggerServo),
+            (0x04, pattern) => Ok(Self::Buzzer { pattern }),
+            (0x05, 0) => Ok(Self::RequestGpsFix),
+            (0x01..=0x05, _) => Err(NackReason::InvalidArgument),
+            _ => Err(NackReason::UnknownCommand),
+        }
+    }
+}
+
+/// Why a command has been rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum NackReason {
+    /// The command ID is unknown
+    UnknownCommand = 1,
+    /// The argument is invalid for the command
+    InvalidArgument = 2,
+    /// The command is not allowed in the current state, e.g. triggering the servo while disarmed
+    InvalidState = 3,
+    /// The command is not supported by this firmware
+    Unsupported = 4,
+    /// The command has been executed but failed
+    Failed = 5,
+    /// The counter is not newer than the last accepted command
+    Replayed = 6,
+}
+impl TryFrom<u8> for NackReason {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::UnknownCommand),
+            2 => Ok(Self::InvalidArgument),
+            3 => Ok(Self::InvalidState),
+            4 => Ok(Self::Unsupported),
+            5 => Ok(Self::Failed),
+            6 => Ok(Self::Replayed),
+            _ => Err("Invalid NACK reason"),
+        }
+    }
+}
+
+/// A command frame
+///
+/// # Note
+/// The command is kept raw, so the payload can NACK unknown commands instead of dropping them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Request {
+    /// The replay protection counter, which is echoed in the response
+    pub counter: u32,
+    /// The command ID
+    pub id: u8,
+    /// The command argument
+    pub argument: u8,
+}
+impl Request {
+    /// Creates a request for the given command
+    pub const fn new(counter: u32, command: Command) -> Self {
+        let (id, argument) = command.encode();
+        Self { counter, id, argument }
+    }
+
+    /// The command
+    pub const fn command(&self) -> Result<Command, NackReason> {
+        Command::decode(self.id, self.argument)
+    }
+
+    /// Encodes and authenticates the request
+    pub fn encode(&self, key: &Key) -> [u8; COMMAND_LEN] {
+        let [c0, c1, c2, c3] = self.counter.to_le_bytes();
+        let mut frame = [COMMAND_MAGIC, VERSION, c0, c1, c2, c3, self.id, self.argument, 0, 0, 0, 0, 0, 0, 0, 0];
+        if let Some((body, mac)) = frame.split_last_chunk_mut::<MAC_LEN>() {
+            *mac = key.mac(body);
+        }
+        frame
+    }
+
+    /// Decodes a request and verifies its MAC
+    pub fn decode(frame: &[u8], key: &Key) -> Result<Self, &'static str> {
+        let (body, mac) = open::<COMMAND_LEN, MAC_LEN>(frame, COMMAND_MAGIC)?;
+        let true = key.verify(body, mac) else {
+            return Err("MAC mismatch");
+        };
+
+        let [_, _, c0, c1, c2, c3, id, argument] = *body else {
+            return Err("Invalid frame length");
+        };
+        Ok(Self { counter: u32::from_le_bytes([c0, c1, c2, c3]), id, argument })
+    }
+}
+
+/// A response frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Response {
+    /// The counter of the request
+    pub counter: u32,
+    /// The result; `Ok` for an ACK or the reason for a NACK
+    pub result: Result<(), NackReason>,
+}
+impl Response {
+    /// Encodes the response
+    pub fn encode(&self) -> [u8; RESPONSE_LEN] {
+        let [c0, c1, c2, c3] = self.counter.to_le_bytes();
+        let status = match self.result {
+            Ok(()) => 0,
+            Err(reason) => reason as u8,
+        };
+        let mut frame = [RESPONSE_MAGIC, VERSION, c0, c1, c2, c3, status, 0, 0];
+        if let Some((body, crc)) = frame.split_last_chunk_mut::<2>() {
+            *crc = crc16(body).to_le_bytes();
+        }
+        frame
+    }
+
+    /// Decodes and validates a response
+    pub fn decode(frame: &[u8]) -> Result<Self, &'static str> {
+        let (body, crc) = open::<RESPONSE_LEN, 2>(frame, RESPONSE_MAGIC)?;
+        let true = crc16(body) == u16::from_le_bytes(*crc) else {
+            return Err("Checksum mismatch");
+        };
+
+        let [_, _, c0, c1, c2, c3, status] = *body else {
+            return Err("Invalid frame length");
+        };
+        let result = match status {
+            0 => Ok(()),
+            reason => Err(NackReason::try_from(reason)?),
+        };
+        Ok(Self { counter: u32::from_le_bytes([c0, c1, c2, c3]), result })
+    }
+}
+
+/// Validates magic, version and length of a frame, and splits it into the body and the `T`-byte trailer
+fn open<const N: usize, const T: usize>(frame: &[u8], magic: u8) -> Result<(&[u8], &[u8; T]), &'static str> {
+    let [first, version, ..] = frame else {
+        return Err("Truncated frame");
+    };
+    let true = *first == magic else {
+        return Err("Invalid magic byte");
+    };
+    let VERSION = *version else {
+        return Err("Unsupported frame version");
+    };
+    let true = frame.len() == N else {
+        return Err("Invalid frame length");
+    };
+    frame.split_last_chunk::<T>().ok_or("Invalid frame length")
+}
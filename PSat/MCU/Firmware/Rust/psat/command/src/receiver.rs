@@ -0,0 +1,84 @@
+//! The payload side: authenticates and executes commands, and suppresses duplicates and replays
+
+use crate::auth::Key;
+use crate::frame::{Command, NackReason, Request, Response};
+
+/// Non-volatile storage for the counter of the last accepted command, e.g. in FRAM
+pub trait CounterStore {
+    /// Loads the counter, or `0` if none has been stored yet
+    fn load(&mut self) -> u32;
+    /// Stores the counter
+    fn store(&mut self, counter: u32) -> Result<(), &'static str>;
+}
+
+/// Executes received commands and answers them
+///
+/// # Replays and duplicates
+/// A command is only executed if its counter is newer than the last accepted one, and the counter is stored before the
+/// command is executed, so a reset cannot reopen a replay window.
+///
+/// If a response gets lost, the ground station repeats the request with the same counter. The receiver then repeats
+/// the response of the last request instead of executing the command a second time. Older requests are NACKed as
+/// [`NackReason::Replayed`].
+///
+/// # Storage
+/// The counter storage is passed in with every call rather than owned, so the firmware can keep using its FRAM for
+/// other values in between.
+#[derive(Debug, Clone)]
+pub struct Receiver {
+    /// The pre-shared key
+    key: Key,
+    /// The counter of the last accepted command
+    counter: u32,
+    /// The last request and its response
+    last: Option<(Request, Response)>,
+}
+impl Receiver {
+    /// Creates a new receiver and loads the last accepted counter
+    pub fn new<S: CounterStore>(key: Key, store: &mut S) -> Self {
+        let counter = store.load();
+        Self { key, counter, last: None }
+    }
+
+    /// The counter of the last accepted command
+    pub const fn counter(&self) -> u32 {
+        self.counter
+    }
+
+    /// Handles a received frame and returns the response to send
+    ///
+    /// # Errors
+    /// Frames that are not valid command frames or fail authentication are not answered and return an error, as they
+    /// may be noise, traffic from other devices or forgeries.
+    pub fn handle<S, F>(&mut self, frame: &[u8], store: &mut S, execute: F) -> Result<Response, &'static str>
+    where
+        S: CounterStore,
+        F: FnOnce(Command) -> Result<(), NackReason>,
+    {
+        let request = Request::decode(frame, &self.key)?;
+        if let Some((last, response)) = self.last {
+            if last == request {
+                // A retransmission, so the response got lost
+                return Ok(response);
+            }
+        }
+
+        let result = match request.counter > self.counter {
+            true => self.accept(request.counter, store).and_then(|()| request.command()).and_then(execute),
+            false => Err(NackReason::Replayed),
+        };
+        let response = Response { counter: request.counter, result };
+        if result != Err(NackReason::Replayed) {
+            self.last = Some((request, response));
+        }
+        Ok(response)
+    }
+
+    /// Stores a new counter
+    fn accept<S: CounterStore>(&mut self, counter: u32, store: &mut S) -> Result<(), NackReason> {
+        // Without a stored counter the command could be replayed after a reset, so do not execute it
+        store.store(counter).map_err(|_| NackReason::Failed)?;
+        self.counter = counter;
+        Ok(())
+    }
+}
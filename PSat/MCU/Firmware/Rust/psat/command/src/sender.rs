@@ -0,0 +1,150 @@
+//! The ground side: sends one command at a time and retries with exponential backoff until it is answered
+
+use crate::auth::Key;
+use crate::frame::{Command, NackReason, Request, Response, COMMAND_LEN};
+
+/// The retry policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The total amount of transmissions per command
+    pub max_attempts: u8,
+    /// The time to wait for a response after the first transmission, in milliseconds
+    pub initial_backoff_ms: u32,
+    /// The upper bound for the backoff, in milliseconds
+    pub max_backoff_ms: u32,
+}
+impl RetryPolicy {
+    /// The backoff after the given attempt, starting at `1`, which doubles with every attempt
+    pub fn backoff_ms(&self, attempt: u8) -> u32 {
+        let doublings = u32::from(attempt.saturating_sub(1)).min(31);
+        self.initial_backoff_ms.saturating_mul(1 << doublings).min(self.max_backoff_ms)
+    }
+}
+impl Default for RetryPolicy {
+    /// Five attempts, starting at the telemetry interval of 5 seconds
+    fn default() -> Self {
+        Self { max_attempts: 5, initial_backoff_ms: 5_000, max_backoff_ms: 60_000 }
+    }
+}
+
+/// What the sender wants to do next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Poll {
+    /// No command is pending
+    Idle,
+    /// Waiting for a response or for the backoff to expire
+    Wait,
+    /// Transmit the given frame now
+    Transmit([u8; COMMAND_LEN]),
+    /// All attempts have been used up without a response; the command has been dropped
+    Failed(Command),
+}
+
+/// The outcome of a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The payload executed the command
+    Acked(Command),
+    /// The payload rejected the command
+    Nacked(Command, NackReason),
+}
+
+/// A command that is waiting for a response
+#[derive(Debug, Clone, Copy)]
+struct Pending {
+    /// The command
+    command: Command,
+    /// The request
+    request: Request,
+    /// The amount of transmissions so far
+    attempts: u8,
+    /// When the next transmission is due
+    due_ms: u64,
+}
+
+/// Sends commands and matches responses
+///
+/// # Timing
+/// The sender has no clock of its own; all methods take the current time in milliseconds from an arbitrary epoch.
+#[derive(Debug, Clone)]
+pub struct Sender {
+    /// The retry policy
+    policy: RetryPolicy,
+    /// The pre-shared key
+    key: Key,
+    /// The counter of the last sent command
+    counter: u32,
+    /// The pending command
+    pending: Option<Pending>,
+}
+impl Sender {
+    /// Creates a new sender that continues after the given counter
+    ///
+    /// # Note
+    /// The payload rejects counters that are not newer than the last one it accepted, so the counter must be persisted
+    /// across ground station restarts, see [`Self::counter`].
+    pub const fn new(policy: RetryPolicy, key: Key, counter: u32) -> Self {
+        Self { policy, key, counter, pending: None }
+    }
+
+    /// The counter of the last sent command
+    pub const fn counter(&self) -> u32 {
+        self.counter
+    }
+
+    /// Queues a command for transmission with the next [`Self::poll`]
+    pub fn send(&mut self, command: Command, now_ms: u64) -> Result<(), &'static str> {
+        let None = self.pending else {
+            return Err("Another command is still pending");
+        };
+        let Some(counter) = self.counter.checked_add(1) else {
+            return Err("The command counter is exhausted");
+        };
+
+        self.counter = counter;
+        let request = Request::new(counter, command);
+        self.pending = Some(Pending { command, request, attempts: 0, due_ms: now_ms });
+        Ok(())
+    }
+
+    /// Whether a command is waiting for a response
+    pub const fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Advances the retry timer
+    pub fn poll(&mut self, now_ms: u64) -> Poll {
+        let Some(pending) = &mut self.pending else {
+            return Poll::Idle;
+        };
+        if now_ms < pending.due_ms {
+            return Poll::Wait;
+        }
+
+        // Give up once the last attempt has timed out
+        if pending.attempts >= self.policy.max_attempts {
+            let command = pending.command;
+            self.pending = None;
+            return Poll::Failed(command);
+        }
+
+        pending.attempts = pending.attempts.saturating_add(1);
+        pending.due_ms = now_ms.saturating_add(u64::from(self.policy.backoff_ms(pending.attempts)));
+        Poll::Transmit(pending.request.encode(&self.key))
+    }
+
+    /// Handles a received frame, and returns the outcome if it answers the pending command
+    ///
+    /// # Note
+    /// Invalid frames and stale responses, e.g. to an earlier attempt of an already answered command, are ignored.
+    pub fn receive(&mut self, frame: &[u8]) -> Option<Outcome> {
+        let response = Response::decode(frame).ok()?;
+        let pending = self.pending.filter(|pending| pending.request.counter == response.counter)?;
+
+        self.pending = None;
+        match response.result {
+            Ok(()) => Some(Outcome::Acked(pending.command)),
+            Err(reason) => Some(Outcome::Nacked(pending.command, reason)),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! SipHash-2-4 test vectors
+//!
+//! The reference vectors are from the SipHash paper and its reference implementation, which use the key `00 01 .. 0f`
+//! and the messages `[]`, `[00]`, `[00 01]` and so on.
+
+use psat_command::auth::{siphash24, Key};
+
+/// The reference key
+const KEY: Key = Key([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+
+/// The message `00 01 .. (len - 1)`
+fn message(len: u8) -> Vec<u8> {
+    (0..len).collect()
+}
+
+#[test]
+fn reference_vectors() {
+    let vectors = [
+        (0, 0x726f_db47_dd0e_0e31),
+        (1, 0x74f8_39c5_93dc_67fd),
+        (7, 0xab02_00f5_8b01_d137),
+        (8, 0x93f5_f579_9a93_2462),
+        (15, 0xa129_ca61_49be_45e5),
+        (63, 0x958a_324c_eb06_4572),
+    ];
+    for (len, expected) in vectors {
+        assert_eq!(siphash24(&KEY, &message(len)), expected, "message length {len}");
+    }
+}
+
+#[test]
+fn mac_and_verify() {
+    let message = message(15);
+    let mac = KEY.mac(&message);
+    assert_eq!(mac, 0xa129_ca61_49be_45e5_u64.to_le_bytes());
+    assert!(KEY.verify(&message, &mac));
+
+    // Every single bit flip in the MAC or message is detected
+    for bit in 0..64 {
+        let mut forged = mac;
+        forged[bit / 8] ^= 1 << (bit % 8);
+        assert!(!KEY.verify(&message, &forged));
+    }
+    for bit in 0..message.len() * 8 {
+        let mut forged = message.clone();
+        forged[bit / 8] ^= 1 << (bit % 8);
+        assert!(!KEY.verify(&forged, &mac));
+    }
+
+    // Another key gives another MAC
+    let other = Key([0xFF; 16]);
+    assert!(!other.verify(&message, &mac));
+}
+
+#[test]
+fn parses_hex_keys() {
+    assert_eq!(Key::from_hex("000102030405060708090a0b0c0d0e0f"), Some(KEY));
+    assert_eq!(Key::from_hex("000102030405060708090A0B0C0D0E0F"), Some(KEY));
+    assert_eq!(Key::from_hex("000102030405060708090a0b0c0d0e"), None);
+    assert_eq!(Key::from_hex("000102030405060708090a0b0c0d0e0f00"), None);
+    assert_eq!(Key::from_hex("000102030405060708090a0b0c0d0e0"), None);
+    assert_eq!(Key::from_hex("000102030405060708090a0b0c0d0e0g"), None);
+    assert_eq!(format!("{KEY:?}"), "Key(..)");
+}
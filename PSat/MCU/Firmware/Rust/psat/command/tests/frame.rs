@@ -0,0 +1,88 @@
+//! Command and response frame tests
+
+use psat_command::auth::Key;
+use psat_command::frame::{Command, NackReason, Request, Response, COMMAND_LEN, RESPONSE_LEN, VERSION};
+use psat_telemetry::crc::crc16;
+
+/// The SipHash reference key
+const KEY: Key = Key([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+
+/// All commands
+const COMMANDS: [Command; 5] =
+    [Command::Arm, Command::Disarm, Command::TriggerServo, Command::Buzzer { pattern: 3 }, Command::RequestGpsFix];
+
+#[test]
+fn known_frames() {
+    // The MACs have been computed with an independent SipHash implementation
+    let request = Request::new(0x0102_0304, Command::Buzzer { pattern: 7 }).encode(&KEY);
+    let expected = [0xC3, 0x02, 0x04, 0x03, 0x02, 0x01, 0x04, 0x07, 0x04, 0x5C, 0xC8, 0xD4, 0x58, 0x20, 0x15, 0x51];
+    assert_eq!(request, expected);
+
+    let key = Key::from_hex("2b7e151628aed2a6abf7158809cf4f3c").expect("valid key");
+    let request = Request::new(u32::MAX, Command::Arm).encode(&key);
+    let expected = [0xC3, 0x02, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0x00, 0x6B, 0x95, 0xDE, 0x62, 0xB2, 0xC5, 0x50, 0x08];
+    assert_eq!(request, expected);
+
+    let response = Response { counter: 0x0102_0304, result: Err(NackReason::InvalidState) }.encode();
+    assert_eq!(response[..7], [0xC4, 0x02, 0x04, 0x03, 0x02, 0x01, 0x03]);
+    assert_eq!(response[7..], crc16(&response[..7]).to_le_bytes());
+}
+
+#[test]
+fn round_trip() {
+    for (counter, command) in (1..).zip(COMMANDS) {
+        let request = Request::new(counter, command);
+        let decoded = Request::decode(&request.encode(&KEY), &KEY).expect("valid request");
+        assert_eq!(decoded, request);
+        assert_eq!(decoded.command(), Ok(command));
+    }
+
+    let results = [Ok(()), Err(NackReason::UnknownCommand), Err(NackReason::Failed), Err(NackReason::Replayed)];
+    for result in results {
+        let response = Response { counter: u32::MAX, result };
+        assert_eq!(Response::decode(&response.encode()), Ok(response));
+    }
+}
+
+#[test]
+fn unknown_commands_and_arguments() {
+    assert_eq!(Command::decode(0x06, 0), Err(NackReason::UnknownCommand));
+    assert_eq!(Command::decode(0x00, 0), Err(NackReason::UnknownCommand));
+    assert_eq!(Command::decode(0x01, 1), Err(NackReason::InvalidArgument));
+}
+
+#[test]
+fn rejects_invalid_frames() {
+    let frame = Request::new(1, Command::Arm).encode(&KEY);
+
+    // Responses are not requests and vice versa
+    let response = Response { counter: 1, result: Ok(()) }.encode();
+    assert_eq!(Request::decode(&response, &KEY), Err("Invalid magic byte"));
+    assert_eq!(Response::decode(&frame), Err("Invalid magic byte"));
+
+    let mut future_version = frame;
+    future_version[1] = VERSION + 1;
+    assert_eq!(Request::decode(&future_version, &KEY), Err("Unsupported frame version"));
+
+    assert_eq!(Request::decode(&frame[..1], &KEY), Err("Truncated frame"));
+    assert_eq!(Request::decode(&frame[..COMMAND_LEN - 1], &KEY), Err("Invalid frame length"));
+    assert_eq!(Response::decode(&response[..RESPONSE_LEN - 1]), Err("Invalid frame length"));
+
+    let mut corrupted = response;
+    corrupted[6] ^= 0x02;
+    assert_eq!(Response::decode(&corrupted), Err("Checksum mismatch"));
+}
+
+#[test]
+fn rejects_forged_commands() {
+    let frame = Request::new(1, Command::Disarm).encode(&KEY);
+
+    // A changed command, a changed counter or a wrong key all fail authentication
+    let mut forged = frame;
+    forged[6] = 0x03;
+    assert_eq!(Request::decode(&forged, &KEY), Err("MAC mismatch"));
+    let mut forged = frame;
+    forged[2] = 2;
+    assert_eq!(Request::decode(&forged, &KEY), Err("MAC mismatch"));
+    assert_eq!(Request::decode(&frame, &Key([0; 16])), Err("MAC mismatch"));
+}
@@ -0,0 +1,250 @@
+//! End-to-end tests over a simulated lossy link
+
+use psat_command::auth::Key;
+use psat_command::frame::{Command, NackReason, Request};
+use psat_command::receiver::{CounterStore, Receiver};
+use psat_command::sender::{Outcome, Poll, RetryPolicy, Sender};
+
+/// A fast retry policy for the tests
+const POLICY: RetryPolicy = RetryPolicy { max_attempts: 4, initial_backoff_ms: 100, max_backoff_ms: 300 };
+/// The pre-shared key
+const KEY: Key = Key([0x5A; 16]);
+
+/// A counter store in RAM that can be made to fail
+#[derive(Default)]
+struct Fram {
+    /// The stored counter
+    counter: u32,
+    /// Whether writes fail
+    broken: bool,
+}
+impl CounterStore for Fram {
+    fn load(&mut self) -> u32 {
+        self.counter
+    }
+    fn store(&mut self, counter: u32) -> Result<(), &'static str> {
+        if self.broken {
+            return Err("Write failed");
+        }
+        self.counter = counter;
+        Ok(())
+    }
+}
+
+/// A simulated link where the uplink and downlink drop the packets with the given indices
+struct Link {
+    /// The ground station
+    sender: Sender,
+    /// The payload
+    receiver: Receiver,
+    /// The payload's counter storage
+    fram: Fram,
+    /// The indices of uplink packets to drop
+    drop_uplink: Vec<usize>,
+    /// The indices of downlink packets to drop
+    drop_downlink: Vec<usize>,
+    /// The amount of uplink packets so far
+    uplink: usize,
+    /// The amount of downlink packets so far
+    downlink: usize,
+    /// The commands that have been executed by the payload
+    executed: Vec<Command>,
+}
+impl Link {
+    /// Creates a new link
+    fn new(drop_uplink: &[usize], drop_downlink: &[usize]) -> Self {
+        Self {
+            sender: Sender::new(POLICY, KEY, 0x8000),
+            receiver: Receiver::new(KEY, &mut Fram::default()),
+            fram: Fram::default(),
+            drop_uplink: drop_uplink.to_vec(),
+            drop_downlink: drop_downlink.to_vec(),
+            uplink: 0,
+            downlink: 0,
+            executed: Vec::new(),
+        }
+    }
+
+    /// Runs the sender in 10 ms steps until the command has been answered or has failed, and returns the outcome and
+    /// the times of all transmissions
+    fn run(&mut self, command: Command, execute: fn(Command) -> Result<(), NackReason>) -> (Poll, Vec<u64>) {
+        self.sender.send(command, 0).expect("no pending command");
+        let mut transmissions = Vec::new();
+        for now in (0..10_000).step_by(10) {
+            let request = match self.sender.poll(now) {
+                Poll::Transmit(request) => request,
+                Poll::Wait => continue,
+                done => return (done, transmissions),
+            };
+            transmissions.push(now);
+
+            self.uplink += 1;
+            if self.drop_uplink.contains(&(self.uplink - 1)) {
+                continue;
+            }
+            let executed = &mut self.executed;
+            let response = self
+                .receiver
+                .handle(&request, &mut self.fram, |command| {
+                    executed.push(command);
+                    execute(command)
+                })
+                .expect("valid request");
+
+            self.downlink += 1;
+            if self.drop_downlink.contains(&(self.downlink - 1)) {
+                continue;
+            }
+            if let Some(outcome) = self.sender.receive(&response.encode()) {
+                let done = match outcome {
+                    Outcome::Acked(_) => Poll::Idle,
+                    Outcome::Nacked(command, _) => Poll::Failed(command),
+                };
+                assert!(!self.sender.is_pending());
+                return (done, transmissions);
+            }
+        }
+        panic!("The sender did not finish");
+    }
+}
+
+#[test]
+fn acked_on_first_attempt() {
+    let mut link = Link::new(&[], &[]);
+    let (outcome, transmissions) = link.run(Command::Arm, |_| Ok(()));
+    assert_eq!(outcome, Poll::Idle);
+    assert_eq!(transmissions, [0]);
+    assert_eq!(link.executed, [Command::Arm]);
+}
+
+#[test]
+fn retries_with_backoff() {
+    // The first two requests get lost
+    let mut link = Link::new(&[0, 1], &[]);
+    let (outcome, transmissions) = link.run(Command::Arm, |_| Ok(()));
+    assert_eq!(outcome, Poll::Idle);
+    assert_eq!(transmissions, [0, 100, 300]);
+    assert_eq!(link.executed, [Command::Arm]);
+}
+
+#[test]
+fn lost_ack_does_not_execute_twice() {
+    // The first two ACKs get lost, so the payload sees the same request three times
+    let mut link = Link::new(&[], &[0, 1]);
+    let (outcome, transmissions) = link.run(Command::TriggerServo, |_| Ok(()));
+    assert_eq!(outcome, Poll::Idle);
+    assert_eq!(transmissions.len(), 3);
+    assert_eq!(link.executed, [Command::TriggerServo]);
+
+    // The next command uses a new counter and is executed again
+    let (outcome, _) = link.run(Command::TriggerServo, |_| Ok(()));
+    assert_eq!(outcome, Poll::Idle);
+    assert_eq!(link.executed, [Command::TriggerServo, Command::TriggerServo]);
+}
+
+#[test]
+fn fails_after_all_attempts() {
+    let mut link = Link::new(&[0, 1, 2, 3], &[]);
+    let (outcome, transmissions) = link.run(Command::Disarm, |_| Ok(()));
+    assert_eq!(outcome, Poll::Failed(Command::Disarm));
+    // The backoff is capped at 300 ms, and the last attempt gets a full backoff before giving up
+    assert_eq!(transmissions, [0, 100, 300, 600]);
+    assert!(link.executed.is_empty());
+    assert_eq!(link.sender.poll(10_000), Poll::Idle);
+}
+
+#[test]
+fn nacks() {
+    let mut link = Link::new(&[], &[]);
+    link.sender.send(Command::TriggerServo, 0).expect("no pending command");
+    let Poll::Transmit(request) = link.sender.poll(0) else {
+        panic!("Expected a transmission");
+    };
+    let response =
+        link.receiver.handle(&request, &mut link.fram, |_| Err(NackReason::InvalidState)).expect("valid request");
+    assert_eq!(
+        link.sender.receive(&response.encode()),
+        Some(Outcome::Nacked(Command::TriggerServo, NackReason::InvalidState))
+    );
+
+    // Unknown commands are NACKed without being executed
+    let unknown = Request { counter: 0x9000, id: 0x7F, argument: 0 }.encode(&KEY);
+    let response = link
+        .receiver
+        .handle(&unknown, &mut link.fram, |_| panic!("Executed an unknown command"))
+        .expect("valid request");
+    assert_eq!(response.result, Err(NackReason::UnknownCommand));
+}
+
+#[test]
+fn ignores_noise_and_stale_responses() {
+    let mut sender = Sender::new(POLICY, KEY, 1);
+    let mut fram = Fram::default();
+    let mut receiver = Receiver::new(KEY, &mut fram);
+    assert_eq!(receiver.handle(b"$GPGGA,noise", &mut fram, |_| Ok(())), Err("Invalid magic byte"));
+
+    sender.send(Command::Arm, 0).expect("no pending command");
+    assert_eq!(sender.send(Command::Disarm, 0), Err("Another command is still pending"));
+    let Poll::Transmit(request) = sender.poll(0) else {
+        panic!("Expected a transmission");
+    };
+
+    // A response to another counter does not complete the command
+    let stale = Request { counter: 1, ..Request::decode(&request, &KEY).expect("valid request") }.encode(&KEY);
+    let stale = receiver.handle(&stale, &mut fram, |_| Ok(())).expect("valid request");
+    assert_eq!(sender.receive(&stale.encode()), None);
+    assert!(sender.is_pending());
+}
+
+#[test]
+fn rejects_replays_across_resets() {
+    let mut fram = Fram::default();
+    let mut receiver = Receiver::new(KEY, &mut fram);
+    let first = Request::new(10, Command::Arm).encode(&KEY);
+    let second = Request::new(11, Command::Disarm).encode(&KEY);
+    let mut executed = Vec::new();
+    let mut execute = |command| {
+        executed.push(command);
+        Ok(())
+    };
+
+    assert_eq!(receiver.handle(&first, &mut fram, &mut execute).map(|r| r.result), Ok(Ok(())));
+    assert_eq!(receiver.handle(&second, &mut fram, &mut execute).map(|r| r.result), Ok(Ok(())));
+    assert_eq!(fram.counter, 11);
+
+    // A recorded older command is not executed again
+    assert_eq!(receiver.handle(&first, &mut fram, &mut execute).map(|r| r.result), Ok(Err(NackReason::Replayed)));
+
+    // After a reset, the counter is restored from FRAM, so even the last command is rejected
+    let mut receiver = Receiver::new(KEY, &mut fram);
+    assert_eq!(receiver.counter(), 11);
+    assert_eq!(receiver.handle(&second, &mut fram, &mut execute).map(|r| r.result), Ok(Err(NackReason::Replayed)));
+    assert_eq!(executed, [Command::Arm, Command::Disarm]);
+}
+
+#[test]
+fn does_not_execute_without_stored_counter() {
+    let mut fram = Fram { broken: true, ..Default::default() };
+    let mut receiver = Receiver::new(KEY, &mut fram);
+    let request = Request::new(1, Command::TriggerServo).encode(&KEY);
+    let response =
+        receiver.handle(&request, &mut fram, |_| panic!("Executed without a stored counter")).expect("valid request");
+    assert_eq!(response.result, Err(NackReason::Failed));
+    assert_eq!(receiver.counter(), 0);
+}
+
+#[test]
+fn counter_exhaustion() {
+    let mut sender = Sender::new(POLICY, KEY, u32::MAX - 1);
+    let mut fram = Fram::default();
+    let mut receiver = Receiver::new(KEY, &mut fram);
+    sender.send(Command::Arm, 0).expect("no pending command");
+    assert_eq!(sender.counter(), u32::MAX);
+    let Poll::Transmit(request) = sender.poll(0) else {
+        panic!("Expected a transmission");
+    };
+    let response = receiver.handle(&request, &mut fram, |_| Ok(())).expect("valid request");
+    assert_eq!(sender.receive(&response.encode()), Some(Outcome::Acked(Command::Arm)));
+
+    assert_eq!(sender.send(Command::Disarm, 0), Err("The command counter is exhausted"));
+}
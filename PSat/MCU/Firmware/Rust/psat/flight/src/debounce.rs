@@ -0,0 +1,24 @@
+//! Time-based debouncing
+
+/// Reports a condition only once it has held continuously for a while
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct Debounce {
+    /// When the condition became true, or `None` while it is false
+    since_ms: Option<u32>,
+}
+impl Debounce {
+    /// Feeds the current value of the condition, and returns whether it has held for at least `duration_ms`
+    pub fn update(&mut self, condition: bool, now_ms: u32, duration_ms: u32) -> bool {
+        if !condition {
+            self.since_ms = None;
+            return false;
+        }
+        let since_ms = *self.since_ms.get_or_insert(now_ms);
+        now_ms.wrapping_sub(since_ms) >= duration_ms
+    }
+
+    /// Forgets the condition, e.g. after a state change
+    pub fn reset(&mut self) {
+        self.since_ms = None;
+    }
+}
@@ -0,0 +1,156 @@
+//! Ejection detection from the photoresistor
+//!
+//! Inside the capsule the light level sits at a dark baseline, which depends on the part, the temperature and how
+//! well the capsule is sealed, so it is calibrated from the first readings and then tracks slow drift while it stays
+//! dark. Light is seen once the level rises a fixed amount above the baseline, and is lost once it falls back below a
+//! lower level, so noise near the threshold does not make it flicker. Ejection is reported once light has been seen for
+//! a while.
+
+use crate::debounce::Debounce;
+
+/// Thresholds, in the units of the readings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// How many readings are averaged for the initial baseline
+    pub calibration_readings: u16,
+    /// The rise above the baseline at which light is seen
+    pub light_rise: u16,
+    /// The rise above the baseline below which light is lost, which must not exceed `light_rise`
+    pub dark_rise: u16,
+    /// How quickly the baseline follows dark readings, as a shift: each reading moves it by 1/2^n of the difference
+    pub baseline_shift: u8,
+    /// How long light must be seen to report ejection
+    pub ejection_debounce_ms: u32,
+}
+impl Default for Config {
+    /// For 12-bit readings. A tenth of the full scale is well clear of the noise, but well within the rise in daylight.
+    fn default() -> Self {
+        Self { calibration_readings: 16, light_rise: 400, dark_rise: 200, baseline_shift: 6, ejection_debounce_ms: 200 }
+    }
+}
+
+/// The dark baseline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Baseline {
+    /// Averaging the first readings
+    Calibrating {
+        /// The sum of the readings so far
+        sum: u32,
+        /// How many there were
+        readings: u16,
+    },
+    /// Tracking dark readings
+    Tracking {
+        /// The baseline, with 8 fractional bits so slow drift is not rounded away
+        level_x256: u32,
+    },
+}
+
+/// Detects ejection from a stream of light levels
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LightDetector {
+    /// The thresholds
+    config: Config,
+    /// The dark baseline
+    baseline: Baseline,
+    /// Whether light is seen
+    light: bool,
+    /// How long light has been seen
+    ejection: Debounce,
+    /// Whether ejection has been reported
+    ejected: bool,
+    /// The latest reading
+    level: u16,
+}
+impl LightDetector {
+    /// A detector that starts calibrating with the next reading
+    #[must_use]
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            baseline: Baseline::Calibrating { sum: 0, readings: 0 },
+            light: false,
+            ejection: Debounce::default(),
+            ejected: false,
+            level: 0,
+        }
+    }
+
+    /// Forgets the baseline and any ejection, e.g. once the payload is sealed in the capsule
+    pub fn recalibrate(&mut self) {
+        *self = Self::new(self.config);
+    }
+
+    /// Feeds a reading taken at `now_ms`, and returns `true` the first time ejection is detected
+    pub fn update(&mut self, level: u16, now_ms: u32) -> bool {
+        self.level = level;
+        let Some(baseline) = self.baseline() else {
+            self.calibrate(level);
+            return false;
+        };
+        let rise = level.saturating_sub(baseline);
+        self.light = if self.light { rise >= self.config.dark_rise } else { rise >= self.config.light_rise };
+        if !self.light && !self.ejected {
+            self.track(level);
+        }
+        let ejected = self.ejection.update(self.light, now_ms, self.config.ejection_debounce_ms);
+        if ejected && !self.ejected {
+            self.ejected = true;
+            return true;
+        }
+        false
+    }
+
+    /// Whether light is seen, or `None` while calibrating
+    #[must_use]
+    pub fn light(&self) -> Option<bool> {
+        self.baseline().map(|_| self.light)
+    }
+
+    /// Whether ejection has been detected since the last calibration
+    #[must_use]
+    pub const fn ejected(&self) -> bool {
+        self.ejected
+    }
+
+    /// The dark baseline, or `None` while calibrating
+    #[must_use]
+    pub fn baseline(&self) -> Option<u16> {
+        match self.baseline {
+            Baseline::Calibrating { .. } => None,
+            Baseline::Tracking { level_x256 } => u16::try_from(level_x256 >> 8).ok(),
+        }
+    }
+
+    /// The latest reading
+    #[must_use]
+    pub const fn level(&self) -> u16 {
+        self.level
+    }
+
+    /// Adds a reading to the initial average
+    fn calibrate(&mut self, level: u16) {
+        let Baseline::Calibrating { sum, readings } = self.baseline else { return };
+        let sum = sum.saturating_add(u32::from(level));
+        let readings = readings.saturating_add(1);
+        self.baseline = if readings >= self.config.calibration_readings {
+            Baseline::Tracking { level_x256: sum.checked_div(u32::from(readings)).unwrap_or(0) << 8 }
+        } else {
+            Baseline::Calibrating { sum, readings }
+        };
+    }
+
+    /// Moves the baseline towards a dark reading
+    fn track(&mut self, level: u16) {
+        let Baseline::Tracking { level_x256 } = self.baseline else { return };
+        let target = u32::from(level) << 8;
+        let shift = self.config.baseline_shift;
+        self.baseline = Baseline::Tracking {
+            level_x256: if target >= level_x256 {
+                level_x256.saturating_add(target.saturating_sub(level_x256).checked_shr(u32::from(shift)).unwrap_or(0))
+            } else {
+                level_x256.saturating_sub(level_x256.saturating_sub(target).checked_shr(u32::from(shift)).unwrap_or(0))
+            },
+        };
+    }
+}
@@ -0,0 +1,415 @@
+//! The mission phases and the transitions between them
+//!
+//! # Phases
+//! The payload waits on the pad in [`PreLaunch`](FlightState::PreLaunch) until it is armed over the uplink. Once
+//! [`Armed`](FlightState::Armed), the boost is detected from the acceleration, or from the GPS altitude if the
+//! accelerometer has failed. During [`Ascent`](FlightState::Ascent) the payload sits in the dark capsule until it is
+//! ejected; ejection is detected from the light sensor, or from the GPS altitude passing apogee. After a fixed
+//! [`Ejection`](FlightState::Ejection) phase for deploying the payload, it is in [`Descent`](FlightState::Descent)
+//! until it stops moving, and enters [`Recovery`](FlightState::Recovery) a while after it has
+//! [`Landed`](FlightState::Landed).
+//!
+//! # False triggers
+//! - Every sensor condition must hold for a while, so shocks, flickering light and single GPS glitches are ignored.
+//! - The phases only advance in order, and only an armed payload can launch.
+//! - Ejection is not detected in the first seconds of the ascent, when light may leak into the capsule.
+//! - The coast after the boost reads as free fall, so free fall is not used to detect ejection.
+//! - The accelerometer alone cannot tell a steady parachute descent from lying on the ground, so landing without a GPS
+//!   fix needs a much longer quiet period.
+//! - Timeouts advance the ascent and descent if the sensors never trigger, so the payload always ends up beaconing.
+
+use crate::debounce::Debounce;
+use psat_nav::kinematics::{Sample, Velocity};
+use psat_telemetry::frame::FlightState;
+
+/// The encoded length of a [`Snapshot`]
+pub const SNAPSHOT_LEN: usize = 11;
+
+/// GPS conditions are ignored once the last fix is older than this
+const FIX_STALE_MS: u32 = 5000;
+/// Standard gravity in milli-g, as measured at rest
+const GRAVITY_MG: u16 = 1000;
+
+/// Detection thresholds and timeouts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Config {
+    /// The acceleration that indicates the boost
+    pub launch_acceleration_mg: u16,
+    /// How long the boost acceleration must last
+    pub launch_debounce_ms: u32,
+    /// The climb above the pad that indicates a launch without the accelerometer
+    pub launch_climb_m: f32,
+    /// How long the climb must last
+    pub launch_climb_debounce_ms: u32,
+    /// How long after launch ejection is first detected
+    pub min_ascent_ms: u32,
+    /// How long the light must last to indicate ejection
+    pub ejection_light_debounce_ms: u32,
+    /// The drop below the highest altitude that indicates apogee
+    pub apogee_drop_m: f32,
+    /// How long the drop must last
+    pub apogee_debounce_ms: u32,
+    /// How long after launch ejection is assumed if it has not been detected
+    pub ascent_timeout_ms: u32,
+    /// How long the ejection phase lasts
+    pub ejection_ms: u32,
+    /// The highest vertical speed on the ground, allowing for GPS noise
+    pub landed_vertical_speed_m_s: f32,
+    /// How far the acceleration may differ from 1 g on the ground
+    pub landed_acceleration_tolerance_mg: u16,
+    /// How long the payload must be still to have landed
+    pub landed_debounce_ms: u32,
+    /// How long the payload must be still to have landed without a GPS fix
+    pub landed_without_fix_debounce_ms: u32,
+    /// How long after ejection landing is assumed if it has not been detected
+    pub descent_timeout_ms: u32,
+    /// How long after landing recovery starts
+    pub recovery_delay_ms: u32,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            launch_acceleration_mg: 3000,
+            launch_debounce_ms: 250,
+            launch_climb_m: 50.0,
+            launch_climb_debounce_ms: 2000,
+            min_ascent_ms: 5000,
+            ejection_light_debounce_ms: 200,
+            apogee_drop_m: 20.0,
+            apogee_debounce_ms: 1500,
+            ascent_timeout_ms: 2 * 60 * 1000,
+            ejection_ms: 5000,
+            landed_vertical_speed_m_s: 2.0,
+            landed_acceleration_tolerance_mg: 150,
+            landed_debounce_ms: 10_000,
+            landed_without_fix_debounce_ms: 60_000,
+            descent_timeout_ms: 30 * 60 * 1000,
+            recovery_delay_ms: 2 * 60 * 1000,
+        }
+    }
+}
+
+/// The sensor readings at one point in time
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Inputs {
+    /// The time in milliseconds since an arbitrary epoch, which may wrap around
+    pub now_ms: u32,
+    /// The magnitude of a new acceleration sample, or `None` if there is none, e.g. between batches from the FIFO or
+    /// because the accelerometer has failed
+    pub acceleration_mg: Option<u16>,
+    /// Whether the light sensor sees light in a new reading, or `None` if there is none
+    pub light: Option<bool>,
+    /// A new GPS fix, if one has arrived since the last update
+    pub fix: Option<Sample>,
+}
+
+/// The state that survives a reset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Snapshot {
+    /// The mission phase
+    pub state: FlightState,
+    /// The pad altitude in metres
+    pub ground_altitude_m: Option<f32>,
+    /// The highest altitude since launch in metres
+    pub max_altitude_m: Option<f32>,
+    /// The highest acceleration since launch in milli-g
+    pub peak_acceleration_mg: u16,
+}
+impl Snapshot {
+    /// Encodes the snapshot, with altitudes in decimetres
+    pub fn to_bytes(&self) -> [u8; SNAPSHOT_LEN] {
+        let [ground, max] = [self.ground_altitude_m, self.max_altitude_m].map(encode_altitude);
+        let peak = self.peak_acceleration_mg.to_le_bytes();
+        let mut bytes = [0; SNAPSHOT_LEN];
+        for (byte, value) in bytes.iter_mut().zip([self.state as u8].iter().chain(&ground).chain(&max).chain(&peak)) {
+            *byte = *value;
+        }
+        bytes
+    }
+
+    /// Decodes a snapshot
+    ///
+    /// # Errors
+    /// Fails if the state is invalid.
+    pub fn from_bytes(bytes: [u8; SNAPSHOT_LEN]) -> Result<Self, &'static str> {
+        let [state, g0, g1, g2, g3, m0, m1, m2, m3, p0, p1] = bytes;
+        Ok(Self {
+            state: FlightState::try_from(state)?,
+            ground_altitude_m: decode_altitude([g0, g1, g2, g3]),
+            max_altitude_m: decode_altitude([m0, m1, m2, m3]),
+            peak_acceleration_mg: u16::from_le_bytes([p0, p1]),
+        })
+    }
+}
+
+/// Encodes an optional altitude in decimetres, with `i32::MIN` for `None`
+fn encode_altitude(altitude_m: Option<f32>) -> [u8; 4] {
+    altitude_m.map_or(i32::MIN, |altitude_m| (altitude_m * 10.0) as i32).to_le_bytes()
+}
+
+/// Decodes an optional altitude
+fn decode_altitude(bytes: [u8; 4]) -> Option<f32> {
+    match i32::from_le_bytes(bytes) {
+        i32::MIN => None,
+        altitude_dm => Some(altitude_dm as f32 / 10.0),
+    }
+}
+
+/// The flight state machine
+#[derive(Debug, Clone)]
+pub struct FlightStateMachine {
+    /// The thresholds and timeouts
+    config: Config,
+    /// The mission phase
+    state: FlightState,
+    /// When the phase was entered
+    entered_ms: u32,
+    /// The pad altitude
+    ground_altitude_m: Option<f32>,
+    /// The highest altitude since launch
+    max_altitude_m: Option<f32>,
+    /// The highest acceleration since launch
+    peak_acceleration_mg: u16,
+    /// The last fix
+    last_fix: Option<Sample>,
+    /// The vertical speed between the last two fixes
+    vertical_speed_m_s: Option<f32>,
+    /// Whether the last fix is well above the pad
+    climbing: bool,
+    /// Whether the last fix is well below the highest altitude
+    past_apogee: bool,
+    /// The boost acceleration
+    boost: Debounce,
+    /// The climb above the pad
+    climb: Debounce,
+    /// The light after ejection
+    light: Debounce,
+    /// The descent from apogee
+    apogee: Debounce,
+    /// Lying still with a GPS fix
+    still: Debounce,
+    /// Lying still without a GPS fix
+    still_without_fix: Debounce,
+}
+impl FlightStateMachine {
+    /// Creates a state machine on the pad
+    pub fn new(config: Config, now_ms: u32) -> Self {
+        Self::restore(
+            config,
+            Snapshot {
+                state: FlightState::PreLaunch,
+                ground_altitude_m: None,
+                max_altitude_m: None,
+                peak_acceleration_mg: 0,
+            },
+            now_ms,
+        )
+    }
+
+    /// Resumes after a reset, restarting the timeout of the current phase
+    pub fn restore(config: Config, snapshot: Snapshot, now_ms: u32) -> Self {
+        Self {
+            config,
+            state: snapshot.state,
+            entered_ms: now_ms,
+            ground_altitude_m: snapshot.ground_altitude_m,
+            max_altitude_m: snapshot.max_altitude_m,
+            peak_acceleration_mg: snapshot.peak_acceleration_mg,
+            last_fix: None,
+            vertical_speed_m_s: None,
+            climbing: false,
+            past_apogee: false,
+            boost: Debounce::default(),
+            climb: Debounce::default(),
+            light: Debounce::default(),
+            apogee: Debounce::default(),
+            still: Debounce::default(),
+            still_without_fix: Debounce::default(),
+        }
+    }
+
+    /// The state to persist
+    pub const fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            state: self.state,
+            ground_altitude_m: self.ground_altitude_m,
+            max_altitude_m: self.max_altitude_m,
+            peak_acceleration_mg: self.peak_acceleration_mg,
+        }
+    }
+
+    /// The mission phase
+    pub const fn state(&self) -> FlightState {
+        self.state
+    }
+
+    /// The pad altitude in metres
+    pub const fn ground_altitude_m(&self) -> Option<f32> {
+        self.ground_altitude_m
+    }
+
+    /// The highest altitude since launch in metres
+    pub const fn max_altitude_m(&self) -> Option<f32> {
+        self.max_altitude_m
+    }
+
+    /// The highest acceleration since launch in milli-g, e.g. from the boost or the landing impact
+    pub const fn peak_acceleration_mg(&self) -> u16 {
+        self.peak_acceleration_mg
+    }
+
+    /// The vertical speed between the last two fixes
+    pub const fn vertical_speed_m_s(&self) -> Option<f32> {
+        self.vertical_speed_m_s
+    }
+
+    /// Arms the payload for launch
+    ///
+    /// # Errors
+    /// Fails once the payload has launched.
+    pub fn arm(&mut self, now_ms: u32) -> Result<(), &'static str> {
+        let (FlightState::PreLaunch | FlightState::Armed) = self.state else {
+            return Err("Already launched");
+        };
+        self.enter(FlightState::Armed, now_ms);
+        Ok(())
+    }
+
+    /// Disarms the payload
+    ///
+    /// # Errors
+    /// Fails once the payload has launched.
+    pub fn disarm(&mut self, now_ms: u32) -> Result<(), &'static str> {
+        let (FlightState::PreLaunch | FlightState::Armed) = self.state else {
+            return Err("Already launched");
+        };
+        self.enter(FlightState::PreLaunch, now_ms);
+        Ok(())
+    }
+
+    /// Feeds the sensor readings, and returns the new phase if it changed
+    pub fn update(&mut self, inputs: &Inputs) -> Option<FlightState> {
+        if let Some(fix) = inputs.fix {
+            self.add_fix(fix);
+        }
+
+        let config = self.config;
+        let elapsed_ms = inputs.now_ms.wrapping_sub(self.entered_ms);
+        let next = match self.state {
+            FlightState::PreLaunch | FlightState::Recovery => None,
+            FlightState::Armed => self.launched(inputs).then_some(FlightState::Ascent),
+            FlightState::Ascent => (elapsed_ms >= config.ascent_timeout_ms
+                || (elapsed_ms >= config.min_ascent_ms && self.ejected(inputs)))
+            .then_some(FlightState::Ejection),
+            FlightState::Ejection => (elapsed_ms >= config.ejection_ms).then_some(FlightState::Descent),
+            FlightState::Descent => {
+                (elapsed_ms >= config.descent_timeout_ms || self.landed(inputs)).then_some(FlightState::Landed)
+            }
+            FlightState::Landed => (elapsed_ms >= config.recovery_delay_ms).then_some(FlightState::Recovery),
+        };
+        if let Some(next) = next {
+            self.enter(next, inputs.now_ms);
+        }
+        if let (FlightState::Ascent | FlightState::Ejection | FlightState::Descent, Some(acceleration_mg)) =
+            (self.state, inputs.acceleration_mg)
+        {
+            self.peak_acceleration_mg = self.peak_acceleration_mg.max(acceleration_mg);
+        }
+        next
+    }
+
+    /// Enters a phase
+    fn enter(&mut self, state: FlightState, now_ms: u32) {
+        if state == FlightState::Ascent {
+            // Track apogee from the pad
+            self.max_altitude_m = self.last_fix.map(|fix| fix.position.altitude_m()).or(self.ground_altitude_m);
+            self.peak_acceleration_mg = 0;
+        }
+        self.state = state;
+        self.entered_ms = now_ms;
+        for debounce in [
+            &mut self.boost,
+            &mut self.climb,
+            &mut self.light,
+            &mut self.apogee,
+            &mut self.still,
+            &mut self.still_without_fix,
+        ] {
+            debounce.reset();
+        }
+    }
+
+    /// Updates the altitude conditions with a new fix
+    fn add_fix(&mut self, fix: Sample) {
+        let altitude_m = fix.position.altitude_m();
+        self.vertical_speed_m_s =
+            self.last_fix.and_then(|last| Velocity::between(&last, &fix)).map(|velocity| velocity.vertical_speed_m_s);
+        self.last_fix = Some(fix);
+
+        self.climbing = self.ground_altitude_m.is_some_and(|ground| altitude_m - ground > self.config.launch_climb_m);
+        match self.state {
+            // Follow the pad altitude, except while it looks like a climb, so a launch or a glitch does not move it
+            FlightState::PreLaunch | FlightState::Armed if !self.climbing => self.ground_altitude_m = Some(altitude_m),
+            FlightState::Ascent | FlightState::Ejection | FlightState::Descent => {
+                let max_altitude_m = self.max_altitude_m.map_or(altitude_m, |max| max.max(altitude_m));
+                self.max_altitude_m = Some(max_altitude_m);
+                self.past_apogee = max_altitude_m - altitude_m > self.config.apogee_drop_m;
+            }
+            _ => (),
+        }
+    }
+
+    /// Whether the last fix is recent enough to use
+    fn has_fresh_fix(&self, now_ms: u32) -> bool {
+        self.last_fix.is_some_and(|fix| now_ms.wrapping_sub(fix.time_ms) <= FIX_STALE_MS)
+    }
+
+    /// Whether the boost or the climb has been detected
+    fn launched(&mut self, inputs: &Inputs) -> bool {
+        let config = self.config;
+        let fresh = self.has_fresh_fix(inputs.now_ms);
+        // Update both, so neither debounce misses a sample
+        let boost = inputs.acceleration_mg.is_some_and(|acceleration| {
+            self.boost.update(acceleration >= config.launch_acceleration_mg, inputs.now_ms, config.launch_debounce_ms)
+        });
+        let climb = self.climb.update(self.climbing && fresh, inputs.now_ms, config.launch_climb_debounce_ms);
+        boost || climb
+    }
+
+    /// Whether ejection or apogee has been detected
+    fn ejected(&mut self, inputs: &Inputs) -> bool {
+        let config = self.config;
+        let fresh = self.has_fresh_fix(inputs.now_ms);
+        let light = inputs
+            .light
+            .is_some_and(|light| self.light.update(light, inputs.now_ms, config.ejection_light_debounce_ms));
+        let apogee = self.apogee.update(self.past_apogee && fresh, inputs.now_ms, config.apogee_debounce_ms);
+        light || apogee
+    }
+
+    /// Whether the payload has been still for long enough to have landed
+    fn landed(&mut self, inputs: &Inputs) -> bool {
+        let config = self.config;
+        let resting = inputs
+            .acceleration_mg
+            .map(|acceleration| acceleration.abs_diff(GRAVITY_MG) <= config.landed_acceleration_tolerance_mg);
+        let stationary = match (self.has_fresh_fix(inputs.now_ms), self.vertical_speed_m_s) {
+            (true, Some(speed)) => Some(speed.abs() <= config.landed_vertical_speed_m_s),
+            _ => None,
+        };
+
+        let still = match stationary {
+            Some(stationary) => stationary && resting.unwrap_or(true),
+            None => false,
+        };
+        let still = self.still.update(still, inputs.now_ms, config.landed_debounce_ms);
+        let still_without_fix = resting.is_some_and(|resting| {
+            self.still_without_fix.update(
+                stationary.is_none() && resting,
+                inputs.now_ms,
+                config.landed_without_fix_debounce_ms,
+            )
+        });
+        still || still_without_fix
+    }
+}
@@ -0,0 +1,71 @@
+//! Ejection detection from synthetic light levels, one reading every 50 ms
+
+use psat_flight::light::{Config, LightDetector};
+
+/// Feeds readings from `start_ms`, and returns the times at which ejection was reported
+fn feed(detector: &mut LightDetector, start_ms: u32, levels: impl IntoIterator<Item = u16>) -> Vec<u32> {
+    let mut ejections = Vec::new();
+    for (index, level) in levels.into_iter().enumerate() {
+        let now_ms = start_ms + 50 * index as u32;
+        if detector.update(level, now_ms) {
+            ejections.push(now_ms);
+        }
+    }
+    ejections
+}
+
+#[test]
+fn calibrates_then_detects_ejection() {
+    let mut detector = LightDetector::new(Config::default());
+    assert_eq!(detector.light(), None);
+    // Noisy darkness inside the capsule
+    assert_eq!(feed(&mut detector, 0, (0..16).map(|index| 290 + index % 3 * 10)), []);
+    assert_eq!(detector.baseline(), Some(299));
+    assert_eq!(detector.light(), Some(false));
+
+    // Light just below the threshold is ignored, then daylight is seen for 200 ms
+    assert_eq!(feed(&mut detector, 1000, [650, 690, 2500, 2600, 2400, 2550, 2700, 2600]), [1300]);
+    let baseline = detector.baseline();
+    assert!(detector.ejected());
+    assert_eq!(detector.level(), 2600);
+
+    // Reported only once, and the baseline does not follow the light
+    assert_eq!(feed(&mut detector, 2000, [300; 20].into_iter().chain([3000; 20])), []);
+    assert_eq!(detector.baseline(), baseline);
+}
+
+#[test]
+fn hysteresis_and_flicker() {
+    let config = Config::default();
+    let mut detector = LightDetector::new(config);
+    feed(&mut detector, 0, [1000; 16]);
+
+    // Light leaks around the threshold while tumbling: once seen, it stays seen until it drops below the lower level
+    let levels = [1400, 1250, 1300, 1210];
+    assert_eq!(feed(&mut detector, 1000, levels), []);
+    assert_eq!(detector.light(), Some(true));
+    assert_eq!(feed(&mut detector, 1200, [1199]), []);
+    assert_eq!(detector.light(), Some(false));
+
+    // Flickers shorter than the debounce do not count
+    let flicker = [1500, 1500, 1500, 1000].repeat(5);
+    assert_eq!(feed(&mut detector, 2000, flicker), []);
+    assert!(!detector.ejected());
+}
+
+#[test]
+fn tracks_slow_drift() {
+    let mut detector = LightDetector::new(Config::default());
+    feed(&mut detector, 0, [500; 16]);
+    // The capsule warms up on the pad, and the dark level creeps up by 600 over a few minutes
+    let drift = (0..=3000).map(|index| 500 + index / 5);
+    assert_eq!(feed(&mut detector, 1000, drift), []);
+    let baseline = detector.baseline().expect("calibrated");
+    assert!((1050..=1100).contains(&baseline), "baseline {baseline}");
+
+    // Starting over, e.g. when armed, forgets the old baseline
+    detector.recalibrate();
+    assert_eq!(detector.baseline(), None);
+    feed(&mut detector, 0, [2000; 16]);
+    assert_eq!(detector.baseline(), Some(2000));
+}
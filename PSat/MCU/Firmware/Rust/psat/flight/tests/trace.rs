@@ -0,0 +1,233 @@
+//! Replays recorded sensor traces through the flight state machine
+//!
+//! Each trace has a row every 200 ms with the acceleration and light readings, and a GPS altitude every second. Empty
+//! cells are missing readings, e.g. from a failed sensor. The `event` column holds uplink commands.
+
+use psat_flight::machine::{Config, FlightStateMachine, Inputs, Snapshot};
+use psat_nav::kinematics::Sample;
+use psat_telemetry::frame::{FlightState, Position};
+
+/// A nominal flight: a pad bump, the boost, ejection at 50 s, a parachute descent and touchdown at about 310 s
+const NOMINAL: &str = include_str!("traces/nominal.csv");
+/// The same flight with a failed accelerometer and light sensor
+const GPS_ONLY: &str = include_str!("traces/gps_only.csv");
+/// An armed payload on the pad that is bumped, sees light and a GPS glitch, and is disarmed
+const FALSE_TRIGGERS: &str = include_str!("traces/false_triggers.csv");
+
+/// A trace row
+struct Row {
+    /// The inputs
+    inputs: Inputs,
+    /// The uplink command, if any
+    event: String,
+}
+
+/// Parses a trace
+fn parse(trace: &str) -> Vec<Row> {
+    trace
+        .lines()
+        .skip(1)
+        .map(|line| {
+            let [time, event, acceleration, light, altitude] =
+                line.split(',').collect::<Vec<_>>().try_into().expect("five columns");
+            let now_ms = time.parse().expect("valid time");
+            let fix = altitude.parse::<f32>().ok().map(|altitude_m| Sample {
+                position: Position::from_degrees(-27.4698, 153.0251, altitude_m),
+                time_ms: now_ms,
+            });
+            let inputs = Inputs {
+                now_ms,
+                acceleration_mg: acceleration.parse().ok(),
+                light: light.parse::<u8>().ok().map(|light| light != 0),
+                fix,
+            };
+            Row { inputs, event: event.to_string() }
+        })
+        .collect()
+}
+
+/// Replays rows, and returns the transitions with their times
+fn replay(machine: &mut FlightStateMachine, rows: &[Row]) -> Vec<(u32, FlightState)> {
+    let mut transitions = Vec::new();
+    for row in rows {
+        let now_ms = row.inputs.now_ms;
+        match row.event.as_str() {
+            "arm" => machine.arm(now_ms).expect("armed on the pad"),
+            "disarm" => machine.disarm(now_ms).expect("disarmed on the pad"),
+            _ => (),
+        }
+        if !row.event.is_empty() {
+            transitions.push((now_ms, machine.state()));
+        }
+        if let Some(state) = machine.update(&row.inputs) {
+            transitions.push((now_ms, state));
+        }
+    }
+    transitions
+}
+
+/// Asserts the landing and recovery times, which depend on GPS noise
+fn assert_landing(transitions: &[(u32, FlightState)], landed_ms: std::ops::Range<u32>) {
+    let [.., (landed, FlightState::Landed), (recovery, FlightState::Recovery)] = transitions else {
+        panic!("did not land: {transitions:?}");
+    };
+    assert!(landed_ms.contains(landed), "landed at {landed} ms");
+    assert_eq!(recovery - landed, Config::default().recovery_delay_ms);
+}
+
+#[test]
+fn nominal_flight() {
+    let mut machine = FlightStateMachine::new(Config::default(), 0);
+    let transitions = replay(&mut machine, &parse(NOMINAL));
+    assert_eq!(
+        transitions[..4],
+        [
+            (5000, FlightState::Armed),
+            // The pad bump at 12 s is too short, the boost starts at 30 s
+            (30_400, FlightState::Ascent),
+            // The light flickers while tumbling, but is on for long enough
+            (50_200, FlightState::Ejection),
+            (55_200, FlightState::Descent),
+        ]
+    );
+    // Touchdown at about 310 s, plus 10 s of lying still
+    assert_landing(&transitions[4..], 318_000..324_000);
+    assert_eq!(transitions.len(), 6);
+
+    let max_altitude_m = machine.max_altitude_m().expect("apogee");
+    assert!((2580.0..2600.0).contains(&max_altitude_m), "apogee at {max_altitude_m} m");
+    assert!((machine.ground_altitude_m().expect("pad") - 27.5).abs() < 1.0);
+    // The ejection shock, not the pad bump
+    assert_eq!(machine.peak_acceleration_mg(), 15_000);
+}
+
+#[test]
+fn gps_only_flight() {
+    let mut machine = FlightStateMachine::new(Config::default(), 0);
+    let transitions = replay(&mut machine, &parse(GPS_ONLY));
+    let [(5000, FlightState::Armed), (ascent, FlightState::Ascent), (ejection, FlightState::Ejection), (descent, FlightState::Descent), ..] =
+        transitions[..]
+    else {
+        panic!("unexpected transitions: {transitions:?}");
+    };
+    // The payload is 50 m above the pad at 32 s, which must last 2 s
+    assert_eq!(ascent, 34_000);
+    // Apogee is at about 54 s, and the altitude is 20 m lower 2 s later
+    assert!((56_000..60_000).contains(&ejection), "ejected at {ejection} ms");
+    assert_eq!(descent, ejection + 5000);
+    assert_landing(&transitions[4..], 318_000..324_000);
+    assert_eq!(machine.peak_acceleration_mg(), 0);
+}
+
+#[test]
+fn ignores_false_triggers() {
+    let mut machine = FlightStateMachine::new(Config::default(), 0);
+    let transitions = replay(&mut machine, &parse(FALSE_TRIGGERS));
+    assert_eq!(transitions, [(2000, FlightState::Armed), (110_000, FlightState::PreLaunch)]);
+}
+
+#[test]
+fn resumes_after_reset() {
+    let rows = parse(NOMINAL);
+    let (before, after) = rows.split_at(rows.len() / 2);
+    let mut machine = FlightStateMachine::new(Config::default(), 0);
+    replay(&mut machine, before);
+    assert_eq!(machine.state(), FlightState::Descent);
+
+    // The snapshot goes through FRAM as bytes
+    let snapshot = Snapshot::from_bytes(machine.snapshot().to_bytes()).expect("valid snapshot");
+    assert_eq!(snapshot.state, FlightState::Descent);
+    let mut machine = FlightStateMachine::restore(Config::default(), snapshot, after[0].inputs.now_ms);
+    assert!(machine.arm(after[0].inputs.now_ms).is_err(), "cannot re-arm after launch");
+    assert_landing(&replay(&mut machine, after), 318_000..324_000);
+}
+
+#[test]
+fn times_out_without_sensors() {
+    let config = Config::default();
+    let mut machine = FlightStateMachine::new(config, 0);
+    machine.arm(0).expect("armed");
+    // A launch detected by the accelerometer, after which every sensor fails
+    for now_ms in [0, 300] {
+        machine.update(&Inputs { now_ms, acceleration_mg: Some(9000), ..Inputs::default() });
+    }
+    assert_eq!(machine.state(), FlightState::Ascent);
+
+    let mut transitions = Vec::new();
+    for now_ms in (300..=60 * 60 * 1000).step_by(1000) {
+        if let Some(state) = machine.update(&Inputs { now_ms, ..Inputs::default() }) {
+            transitions.push((now_ms - 300, state));
+        }
+    }
+    let ejection = config.ascent_timeout_ms;
+    let descent = ejection + config.ejection_ms;
+    let landed = descent + config.descent_timeout_ms;
+    assert_eq!(
+        transitions,
+        [
+            (ejection, FlightState::Ejection),
+            (descent, FlightState::Descent),
+            (landed, FlightState::Landed),
+            (landed + config.recovery_delay_ms, FlightState::Recovery),
+        ]
+    );
+}
+
+#[test]
+fn lands_on_accelerometer_without_fix() {
+    let config = Config::default();
+    let snapshot = Snapshot {
+        state: FlightState::Descent,
+        ground_altitude_m: Some(27.5),
+        max_altitude_m: None,
+        peak_acceleration_mg: 8000,
+    };
+    let mut machine = FlightStateMachine::restore(config, snapshot, 0);
+    // A steady parachute descent reads as 1 g for a while, which is not enough without a fix
+    for now_ms in (0..config.landed_without_fix_debounce_ms).step_by(200) {
+        assert_eq!(machine.update(&Inputs { now_ms, acceleration_mg: Some(1010), ..Inputs::default() }), None);
+    }
+    let now_ms = config.landed_without_fix_debounce_ms;
+    assert_eq!(
+        machine.update(&Inputs { now_ms, acceleration_mg: Some(990), ..Inputs::default() }),
+        Some(FlightState::Landed)
+    );
+    assert_eq!(machine.peak_acceleration_mg(), 8000, "restored from the snapshot");
+}
+
+#[test]
+fn interleaved_batches() {
+    // Acceleration samples from the FIFO arrive in batches, between updates with only a fix or nothing at all
+    let mut machine = FlightStateMachine::new(Config::default(), 0);
+    machine.arm(0).expect("armed");
+    let mut transitions = Vec::new();
+    for now_ms in (1000..1500).step_by(10) {
+        let acceleration_mg = (now_ms % 100 == 0).then_some(6000);
+        if let Some(state) = machine.update(&Inputs { now_ms, acceleration_mg, ..Inputs::default() }) {
+            transitions.push((now_ms, state));
+        }
+    }
+    assert_eq!(transitions, [(1300, FlightState::Ascent)]);
+
+    // The landing impact is recorded
+    let snapshot = Snapshot { state: FlightState::Descent, ..machine.snapshot() };
+    let mut machine = FlightStateMachine::restore(Config::default(), snapshot, 2000);
+    for (now_ms, acceleration_mg) in [(2000, Some(1200)), (2010, None), (2020, Some(12_500)), (2030, Some(900))] {
+        machine.update(&Inputs { now_ms, acceleration_mg, ..Inputs::default() });
+    }
+    assert_eq!(machine.peak_acceleration_mg(), 12_500);
+}
+
+#[test]
+fn snapshot_encoding() {
+    let snapshot = Snapshot {
+        state: FlightState::Ascent,
+        ground_altitude_m: Some(-3.5),
+        max_altitude_m: None,
+        peak_acceleration_mg: 8200,
+    };
+    let bytes = snapshot.to_bytes();
+    assert_eq!(bytes, [2, 0xDD, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x80, 0x08, 0x20]);
+    assert_eq!(Snapshot::from_bytes(bytes), Ok(snapshot));
+    assert_eq!(Snapshot::from_bytes([7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]), Err("Invalid flight state"));
+}
@@ -0,0 +1,275 @@
+//! Exports flight tracks as GPX 1.1, KML and GeoJSON
+//!
+//! # Flight events
+//! Launch, apogee and landing are detected from the altitude profile and marked as waypoints. Altitudes are compared
+//! with a margin of [`EVENT_MARGIN_M`] to ignore GPS altitude noise while on the ground.
+
+use crate::output::json_string;
+use std::fmt::{self, Display, Formatter};
+
+/// The altitude change in metres that separates flight from GPS altitude noise
+pub const EVENT_MARGIN_M: f64 = 20.0;
+/// The amount of fixes within the margin around the final altitude that are needed to report a landing
+pub const LANDED_FIXES: usize = 3;
+
+/// A UTC date and time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    /// The year
+    pub year: i32,
+    /// The month, from `1` to `12`
+    pub month: u8,
+    /// The day of the month, from `1` to `31`
+    pub day: u8,
+    /// The milliseconds since midnight
+    pub millis_of_day: u32,
+}
+impl DateTime {
+    /// The milliseconds per day
+    const MILLIS_PER_DAY: u64 = 86_400_000;
+
+    /// Creates a date and time from the milliseconds since the Unix epoch
+    #[allow(clippy::arithmetic_side_effects, reason = "all intermediate values are bounded by the input range")]
+    pub fn from_unix_millis(millis: u64) -> Self {
+        let days = millis / Self::MILLIS_PER_DAY;
+        let millis_of_day = (millis % Self::MILLIS_PER_DAY) as u32;
+
+        // Convert the days since the epoch to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+        let days = days as i64 + 719_468;
+        let era = days / 146_097;
+        let day_of_era = days - era * 146_097;
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u8;
+        let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u8;
+        let year = (year_of_era + era * 400 + i64::from(month <= 2)) as i32;
+        Self { year, month, day, millis_of_day }
+    }
+
+    /// Parses a `YYYY-MM-DD` date at midnight
+    pub fn parse_date(date: &str) -> Option<Self> {
+        let mut parts = date.splitn(3, '-');
+        let year = parts.next()?.parse().ok()?;
+        let month @ 1..=12 = parts.next()?.parse().ok()? else {
+            return None;
+        };
+        let day @ 1..=31 = parts.next()?.parse().ok()? else {
+            return None;
+        };
+        Some(Self { year, month, day, millis_of_day: 0 })
+    }
+
+    /// The same date at the given time of day in seconds
+    ///
+    /// # Note
+    /// GPS fixes only carry the time of day, so tracks across midnight UTC will jump back by a day.
+    pub fn with_time_of_day(self, seconds: f64) -> Self {
+        let millis_of_day = (seconds * 1000.0).round().clamp(0.0, (Self::MILLIS_PER_DAY - 1) as f64) as u32;
+        Self { millis_of_day, ..self }
+    }
+}
+impl Display for DateTime {
+    /// Formats the date and time as ISO 8601, e.g. `2024-05-04T01:23:45.600Z`
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (seconds, millis) = (self.millis_of_day / 1000, self.millis_of_day % 1000);
+        let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+        write!(f, "{:04}-{:02}-{:02}T{hours:02}:{minutes:02}:{seconds:02}", self.year, self.month, self.day)?;
+        match millis {
+            0 => write!(f, "Z"),
+            millis => write!(f, ".{millis:03}Z"),
+        }
+    }
+}
+
+/// A single position fix
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackPoint {
+    /// The time of the fix, if known
+    pub time: Option<DateTime>,
+    /// The latitude in decimal degrees, positive to the north
+    pub latitude: f64,
+    /// The longitude in decimal degrees, positive to the east
+    pub longitude: f64,
+    /// The altitude above mean sea level in metres
+    pub altitude_m: f64,
+}
+
+/// The indices of the flight events within a track
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Events {
+    /// The last point on the ground before the climb
+    pub launch: Option<usize>,
+    /// The highest point
+    pub apogee: Option<usize>,
+    /// The first point back on the ground
+    pub landing: Option<usize>,
+}
+impl Events {
+    /// The events as names and point indices, in flight order
+    pub fn named(&self) -> impl Iterator<Item = (&'static str, usize)> {
+        let events = [("Launch", self.launch), ("Apogee", self.apogee), ("Landing", self.landing)];
+        events.into_iter().filter_map(|(name, index)| Some((name, index?)))
+    }
+}
+
+/// A flight track
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Track {
+    /// The track name
+    pub name: String,
+    /// The position fixes in chronological order
+    pub points: Vec<TrackPoint>,
+}
+impl Track {
+    /// Creates an empty track
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), points: Vec::new() }
+    }
+
+    /// Detects launch, apogee and landing
+    ///
+    /// # Note
+    /// Events are only reported if the track actually climbed by more than [`EVENT_MARGIN_M`], and landing is only
+    /// reported if the track came back down by more than that and then stayed within the margin for at least
+    /// [`LANDED_FIXES`] fixes until the end.
+    pub fn events(&self) -> Events {
+        let altitudes: Vec<f64> = self.points.iter().map(|point| point.altitude_m).collect();
+        let (Some(&start), Some(&end)) = (altitudes.first(), altitudes.last()) else {
+            return Events::default();
+        };
+
+        // Launch is the last point before the altitude leaves the margin around the start altitude
+        let Some(climb) = altitudes.iter().position(|altitude| altitude - start > EVENT_MARGIN_M) else {
+            return Events::default();
+        };
+        let launch = climb.saturating_sub(1);
+
+        // Apogee is the first highest point
+        let apogee =
+            altitudes.iter().enumerate().fold(launch, |highest, (index, altitude)| match altitudes.get(highest) {
+                Some(highest_altitude) if altitude > highest_altitude => index,
+                _ => highest,
+            });
+
+        // Landing is the first point after apogee from which the track stays within the margin around its end
+        let apogee_altitude = altitudes.get(apogee).copied().unwrap_or(start);
+        let landing = altitudes.iter().rposition(|altitude| (altitude - end).abs() > EVENT_MARGIN_M);
+        let landing = landing.map(|index| index.saturating_add(1)).filter(|&landing| {
+            let landed_fixes = altitudes.len().saturating_sub(landing);
+            apogee_altitude - end > EVENT_MARGIN_M && landed_fixes >= LANDED_FIXES
+        });
+        Events { launch: Some(launch), apogee: Some(apogee), landing }
+    }
+
+    /// Exports the track as GPX 1.1, with a waypoint per flight event
+    pub fn to_gpx(&self) -> String {
+        let mut gpx = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        gpx += "<gpx version=\"1.1\" creator=\"psat-ground-station\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n";
+        for (name, index) in self.events().named() {
+            let Some(point) = self.points.get(index) else {
+                continue;
+            };
+            gpx += &format!("  <wpt lat=\"{:.7}\" lon=\"{:.7}\">\n", point.latitude, point.longitude);
+            gpx += &Self::gpx_point_details(point, "    ");
+            gpx += &format!("    <name>{name}</name>\n  </wpt>\n");
+        }
+
+        gpx += &format!("  <trk>\n    <name>{}</name>\n    <trkseg>\n", xml_escape(&self.name));
+        for point in &self.points {
+            gpx += &format!("      <trkpt lat=\"{:.7}\" lon=\"{:.7}\">\n", point.latitude, point.longitude);
+            gpx += &Self::gpx_point_details(point, "        ");
+            gpx += "      </trkpt>\n";
+        }
+        gpx += "    </trkseg>\n  </trk>\n</gpx>\n";
+        gpx
+    }
+
+    /// The `ele` and `time` elements of a GPX point
+    fn gpx_point_details(point: &TrackPoint, indent: &str) -> String {
+        let mut details = format!("{indent}<ele>{:.1}</ele>\n", point.altitude_m);
+        if let Some(time) = point.time {
+            details += &format!("{indent}<time>{time}</time>\n");
+        }
+        details
+    }
+
+    /// Exports the track as KML, extruded to the ground, with a placemark per flight event
+    pub fn to_kml(&self) -> String {
+        let mut kml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        kml += "<kml xmlns=\"http://www.opengis.net/kml/2.2\">\n  <Document>\n";
+        kml += &format!("    <name>{}</name>\n", xml_escape(&self.name));
+        kml += "    <Style id=\"track\">\n      <LineStyle><color>ff0000ff</color><width>3</width></LineStyle>\n";
+        kml += "      <PolyStyle><color>7f0000ff</color></PolyStyle>\n    </Style>\n";
+        for (name, index) in self.events().named() {
+            let Some(point) = self.points.get(index) else {
+                continue;
+            };
+            kml += &format!("    <Placemark>\n      <name>{name}</name>\n");
+            if let Some(time) = point.time {
+                kml += &format!("      <TimeStamp><when>{time}</when></TimeStamp>\n");
+            }
+            kml += "      <Point>\n        <altitudeMode>absolute</altitudeMode>\n";
+            kml += &format!("        <coordinates>{}</coordinates>\n", Self::kml_coordinate(point));
+            kml += "      </Point>\n    </Placemark>\n";
+        }
+
+        kml += &format!("    <Placemark>\n      <name>{}</name>\n", xml_escape(&self.name));
+        kml += "      <styleUrl>#track</styleUrl>\n      <LineString>\n        <extrude>1</extrude>\n";
+        kml += "        <tessellate>1</tessellate>\n        <altitudeMode>absolute</altitudeMode>\n";
+        kml += "        <coordinates>\n";
+        for point in &self.points {
+            kml += &format!("          {}\n", Self::kml_coordinate(point));
+        }
+        kml += "        </coordinates>\n      </LineString>\n    </Placemark>\n  </Document>\n</kml>\n";
+        kml
+    }
+
+    /// A KML `lon,lat,alt` tuple
+    fn kml_coordinate(point: &TrackPoint) -> String {
+        format!("{:.7},{:.7},{:.1}", point.longitude, point.latitude, point.altitude_m)
+    }
+
+    /// Exports the track as a GeoJSON `FeatureCollection` with a `LineString` and a `Point` per flight event
+    ///
+    /// # Note
+    /// The point times are stored in a `times` property of the `LineString`, with `null` for unknown times.
+    pub fn to_geojson(&self) -> String {
+        let coordinates: Vec<String> = self.points.iter().map(Self::geojson_coordinate).collect();
+        let times: Vec<String> = self
+            .points
+            .iter()
+            .map(|point| point.time.map(|time| format!("\"{time}\"")).unwrap_or_else(|| "null".to_string()))
+            .collect();
+
+        let mut features = vec![format!(
+            "{{\"type\":\"Feature\",\"properties\":{{\"name\":{},\"times\":[{}]}},\
+            \"geometry\":{{\"type\":\"LineString\",\"coordinates\":[{}]}}}}",
+            json_string(&self.name),
+            times.join(","),
+            coordinates.join(",")
+        )];
+        for (name, index) in self.events().named() {
+            let Some(point) = self.points.get(index) else {
+                continue;
+            };
+            let time = point.time.map(|time| format!(",\"time\":\"{time}\"")).unwrap_or_default();
+            features.push(format!(
+                "{{\"type\":\"Feature\",\"properties\":{{\"name\":\"{name}\"{time}}},\
+                \"geometry\":{{\"type\":\"Point\",\"coordinates\":{}}}}}",
+                Self::geojson_coordinate(point)
+            ));
+        }
+        format!("{{\"type\":\"FeatureCollection\",\"features\":[\n{}\n]}}\n", features.join(",\n"))
+    }
+
+    /// A GeoJSON `[lon,lat,alt]` position
+    fn geojson_coordinate(point: &TrackPoint) -> String {
+        format!("[{:.7},{:.7},{:.1}]", point.longitude, point.latitude, point.altitude_m)
+    }
+}
+
+/// Escapes text for XML
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,89 @@
+//! Parses the lines the Beacon receiver prints over its debug UART
+//!
+//! # Format
+//! Received packets are printed as `[HH:MM:SS] 'text', Strength: -80, RSSI: -101, SNR: 9, Gain: G1`. Binary payloads
+//! are printed as `hex:a501...` instead of a quoted text. The link statistics are omitted if the modem could not be
+//! read.
+
+/// The link statistics of a received packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkStats {
+    /// The packet signal strength in dBm
+    pub strength_dbm: i16,
+    /// The RSSI in dBm
+    pub rssi_dbm: i16,
+    /// The packet SNR in dB
+    pub snr_db: i8,
+    /// The LNA gain step, from `1` (maximum gain) to `6`
+    pub gain: u8,
+}
+
+/// A packet as printed by the receiver
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet {
+    /// The receiver's time since boot as `HH:MM:SS`
+    pub time: String,
+    /// The raw payload
+    pub payload: Vec<u8>,
+    /// The link statistics, if available
+    pub stats: Option<LinkStats>,
+}
+
+/// A line printed by the receiver
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Line {
+    /// A received packet
+    Packet(Packet),
+    /// Any other output, e.g. boot messages or errors
+    Other(String),
+}
+impl Line {
+    /// Parses a line; trailing whitespace is ignored
+    pub fn parse(line: &str) -> Self {
+        match Self::parse_packet(line.trim_end()) {
+            Some(packet) => Self::Packet(packet),
+            None => Self::Other(line.trim_end().to_string()),
+        }
+    }
+
+    /// Parses a packet line
+    fn parse_packet(line: &str) -> Option<Packet> {
+        let (time, rest) = line.strip_prefix('[')?.split_once("] ")?;
+
+        // Split off the link statistics; quoted text may contain commas itself, so search from the end
+        let (payload, stats) = match rest.rfind(", Strength: ") {
+            Some(index) => {
+                let (payload, stats) = rest.split_at(index);
+                (payload, Some(Self::parse_stats(stats.strip_prefix(", ")?)?))
+            }
+            None => (rest, None),
+        };
+
+        let payload = if let Some(hex) = payload.strip_prefix("hex:") {
+            decode_hex(hex)?
+        } else {
+            payload.strip_prefix('\'')?.strip_suffix('\'')?.as_bytes().to_vec()
+        };
+        Some(Packet { time: time.to_string(), payload, stats })
+    }
+
+    /// Parses `Strength: -80, RSSI: -101, SNR: 9, Gain: G1`
+    fn parse_stats(stats: &str) -> Option<LinkStats> {
+        let mut fields = stats.split(", ");
+        let mut field = |name: &str| fields.next()?.strip_prefix(name)?.strip_prefix(": ");
+        let strength_dbm = field("Strength")?.parse().ok()?;
+        let rssi_dbm = field("RSSI")?.parse().ok()?;
+        let snr_db = field("SNR")?.parse().ok()?;
+        let gain = field("Gain")?.strip_prefix('G')?.parse().ok()?;
+        Some(LinkStats { strength_dbm, rssi_dbm, snr_db, gain })
+    }
+}
+
+/// Decodes a lowercase or uppercase hex string
+pub fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    let true = (hex.len().is_multiple_of(2) && hex.bytes().all(|digit| digit.is_ascii_hexdigit())) else {
+        return None;
+    };
+    let digits = hex.as_bytes().chunks_exact(2);
+    digits.map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()).collect()
+}
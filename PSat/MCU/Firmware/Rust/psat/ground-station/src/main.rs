@@ -0,0 +1,357 @@
+//! Decodes the Beacon receiver's serial output, shows received packets live and writes CSV/JSON Lines logs, or sends an
+//! uplink command through the receiver
+
+use psat_command::auth::Key;
+use psat_command::frame::Command;
+use psat_command::sender::Outcome;
+use psat_ground_station::export::{DateTime, Track};
+use psat_ground_station::line::Line;
+use psat_ground_station::output::{CsvLog, JsonlLog};
+use psat_ground_station::record::Record;
+use psat_ground_station::uplink::{self, Step, Uplink};
+use psat_nav::geodesy::Range;
+use psat_telemetry::frame::Position;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, Instant, SystemTime};
+
+/// The baud rate of the receiver's debug UART, `DEBUG_SERIAL_BAUD` in the firmware
+const DEFAULT_BAUD: u32 = 115_200;
+
+/// The usage message
+const USAGE: &str = "\
+Usage: ground-station (--serial <PORT> [--baud <BAUD>] | --log <FILE>) [--csv <FILE>] [--jsonl <FILE>]
+                      [--gpx <FILE>] [--kml <FILE>] [--geojson <FILE>] [--date <YYYY-MM-DD>]
+                      [--station <LAT,LON>] [--verbose]
+
+  --serial <PORT>  Read from the receiver's serial port, e.g. /dev/ttyUSB0
+  --baud <BAUD>    The serial baud rate [default: 115200]
+  --log <FILE>     Read a recorded log instead; use `-` for stdin
+  --csv <FILE>     Append decoded packets to a CSV file
+  --jsonl <FILE>   Append decoded packets to a JSON Lines file
+  --gpx <FILE>     Export the flight track as GPX 1.1
+  --kml <FILE>     Export the flight track as KML
+  --geojson <FILE> Export the flight track as GeoJSON
+  --date <DATE>    The UTC flight date for GPS timestamps [default: today for --serial, none for --log]
+  --station <POS>  The ground station position in decimal degrees, e.g. -27.4698,153.0251, to show the payload's
+                   distance and bearing from it [default: the first received position]
+  --verbose        Also show lines that are not packets
+
+       ground-station send --serial <PORT> [--baud <BAUD>] --counter <FILE> <COMMAND>
+
+  Sends an uplink command through the receiver, and waits until the payload answers it. The key is read as 32 hex digits
+  from PSAT_COMMAND_KEY, as for the firmware.
+
+  <COMMAND>        arm, disarm, trigger-servo, gps-fix or buzzer <PATTERN>
+  --counter <FILE> Stores the counter of the last sent command, which must be kept for the next commands";
+
+/// Where to read from
+enum Source {
+    /// A serial port with the given baud rate
+    Serial(String, u32),
+    /// A recorded log, or stdin
+    Log(String),
+}
+
+/// The command line arguments
+struct Args {
+    /// Where to read from
+    source: Source,
+    /// The CSV log path
+    csv: Option<String>,
+    /// The JSON Lines log path
+    jsonl: Option<String>,
+    /// The track export paths
+    exports: Exports,
+    /// The UTC flight date
+    date: Option<DateTime>,
+    /// The ground station position
+    station: Option<Position>,
+    /// Whether to show lines that are not packets
+    verbose: bool,
+}
+
+/// The track export paths
+#[derive(Default)]
+struct Exports {
+    /// The GPX path
+    gpx: Option<String>,
+    /// The KML path
+    kml: Option<String>,
+    /// The GeoJSON path
+    geojson: Option<String>,
+}
+impl Exports {
+    /// Whether any export is enabled
+    fn any(&self) -> bool {
+        self.gpx.is_some() || self.kml.is_some() || self.geojson.is_some()
+    }
+
+    /// Writes the track to all export files
+    fn write(&self, track: &Track) -> Result<(), String> {
+        let exports = [
+            (&self.gpx, Track::to_gpx as fn(&Track) -> String),
+            (&self.kml, Track::to_kml),
+            (&self.geojson, Track::to_geojson),
+        ];
+        for (path, export) in exports {
+            if let Some(path) = path {
+                std::fs::write(path, export(track)).map_err(|e| format!("Failed to write {path}: {e}"))?;
+            }
+        }
+        Ok(())
+    }
+}
+impl Args {
+    /// Parses the command line arguments
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let (mut serial, mut baud, mut log, mut csv, mut jsonl, mut verbose) =
+            (None, DEFAULT_BAUD, None, None, None, false);
+        let (mut exports, mut date, mut station) = (Exports::default(), None, None);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("Missing value for {arg}"));
+            match arg.as_str() {
+                "--serial" => serial = Some(value()?),
+                "--baud" => baud = value()?.parse().map_err(|_| "Invalid baud rate")?,
+                "--log" => log = Some(value()?),
+                "--csv" => csv = Some(value()?),
+                "--jsonl" => jsonl = Some(value()?),
+                "--gpx" => exports.gpx = Some(value()?),
+                "--kml" => exports.kml = Some(value()?),
+                "--geojson" => exports.geojson = Some(value()?),
+                "--date" => date = Some(DateTime::parse_date(&value()?).ok_or("Invalid date")?),
+                "--station" => station = Some(parse_station(&value()?).ok_or("Invalid station position")?),
+                "--verbose" => verbose = true,
+                _ => return Err(format!("Unknown argument {arg}")),
+            }
+        }
+
+        let source = match (serial, log) {
+            (Some(port), None) => {
+                // Live fixes are from today
+                date = date.or_else(|| Some(now()?.with_time_of_day(0.0)));
+                Source::Serial(port, baud)
+            }
+            (None, Some(path)) => Source::Log(path),
+            _ => return Err("Exactly one of --serial and --log is required".to_string()),
+        };
+        Ok(Self { source, csv, jsonl, exports, date, station, verbose })
+    }
+}
+
+/// The command line arguments of the `send` subcommand
+struct SendArgs {
+    /// The receiver's serial port
+    port: String,
+    /// The serial baud rate
+    baud: u32,
+    /// The file that stores the command counter
+    counter: PathBuf,
+    /// The command
+    command: Command,
+    /// The pre-shared key
+    key: Key,
+}
+impl SendArgs {
+    /// Parses the command line arguments after `send`
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let (mut port, mut baud, mut counter, mut command) = (None, DEFAULT_BAUD, None, Vec::new());
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("Missing value for {arg}"));
+            match arg.as_str() {
+                "--serial" => port = Some(value()?),
+                "--baud" => baud = value()?.parse().map_err(|_| "Invalid baud rate")?,
+                "--counter" => counter = Some(PathBuf::from(value()?)),
+                _ if arg.starts_with("--") => return Err(format!("Unknown argument {arg}")),
+                _ => command.push(arg),
+            }
+        }
+
+        let command = match command.as_slice() {
+            [name] => uplink::parse_command(name, None)?,
+            [name, argument] => uplink::parse_command(name, Some(argument))?,
+            _ => return Err("Exactly one command is required".to_string()),
+        };
+        let key = std::env::var("PSAT_COMMAND_KEY").map_err(|_| "PSAT_COMMAND_KEY is not set")?;
+        let key = Key::from_hex(key.trim()).ok_or("PSAT_COMMAND_KEY must be 32 hex digits")?;
+        let port = port.ok_or("--serial is required")?;
+        let counter = counter.ok_or("--counter is required")?;
+        Ok(Self { port, baud, counter, command, key })
+    }
+}
+
+/// Parses a `LAT,LON` position in decimal degrees
+fn parse_station(value: &str) -> Option<Position> {
+    let (latitude, longitude) = value.split_once(',')?;
+    let (latitude, longitude) = (latitude.trim().parse::<f64>().ok()?, longitude.trim().parse::<f64>().ok()?);
+    if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+        return None;
+    }
+    let latitude_e7 = (latitude * 1e7).round() as i32;
+    Some(Position { latitude_e7, longitude_e7: (longitude * 1e7).round() as i32, altitude_dm: 0 })
+}
+
+/// The current UTC time
+fn now() -> Option<DateTime> {
+    let since_epoch = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).ok()?;
+    Some(DateTime::from_unix_millis(since_epoch.as_millis() as u64))
+}
+
+/// Opens the input stream
+fn open(source: &Source) -> Result<Box<dyn Read>, String> {
+    match source {
+        Source::Serial(port, baud) => {
+            // Use a short timeout so a quiet link does not look like an error
+            let port = serialport::new(port, *baud)
+                .timeout(Duration::from_secs(1))
+                .open()
+                .map_err(|e| format!("Failed to open {port}: {e}"))?;
+            Ok(Box::new(port))
+        }
+        Source::Log(path) if path == "-" => Ok(Box::new(io::stdin())),
+        Source::Log(path) => Ok(Box::new(File::open(path).map_err(|e| format!("Failed to open {path}: {e}"))?)),
+    }
+}
+
+/// Creates or appends to a log file
+fn create(path: &str) -> Result<(BufWriter<File>, bool), String> {
+    let file =
+        File::options().create(true).append(true).open(path).map_err(|e| format!("Failed to open {path}: {e}"))?;
+    let is_empty = file.metadata().map(|metadata| metadata.len() == 0).unwrap_or(true);
+    Ok((BufWriter::new(file), is_empty))
+}
+
+/// Reads and decodes lines until the input ends
+fn run(args: Args) -> Result<(), String> {
+    let mut csv = match &args.csv {
+        // Only write the header into new files
+        Some(path) => match create(path)? {
+            (file, true) => Some(CsvLog::new(file).map_err(|e| format!("Failed to write {path}: {e}"))?),
+            (file, false) => Some(CsvLog::append(file)),
+        },
+        None => None,
+    };
+    let mut jsonl = match &args.jsonl {
+        Some(path) => Some(JsonlLog::new(create(path)?.0)),
+        None => None,
+    };
+
+    let mut track = Track::new("PSat flight");
+    // Without a known station position, ranges are from the launch point
+    let (mut origin, origin_name) = match args.station {
+        Some(station) => (Some(station), "station"),
+        None => (None, "launch"),
+    };
+    let live = matches!(args.source, Source::Serial(..));
+
+    let mut input = BufReader::new(open(&args.source)?);
+    if let Source::Serial(port, baud) = &args.source {
+        eprintln!("Listening on {port} at {baud} baud");
+    }
+
+    // Lines may be split across read timeouts, so only clear the buffer once a line is complete
+    let mut buf = Vec::new();
+    loop {
+        match input.read_until(b'\n', &mut buf) {
+            Ok(0) if buf.is_empty() => return args.exports.write(&track),
+            Ok(_) => (),
+            Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::Interrupted) => continue,
+            Err(e) => return Err(format!("Failed to read input: {e}")),
+        }
+
+        // The UART may produce garbage at boot, so decode lossily
+        let line = String::from_utf8_lossy(&buf).into_owned();
+        buf.clear();
+        match Line::parse(&line) {
+            Line::Packet(packet) => {
+                let record = Record::from(packet);
+                let range = record.position().map(|position| {
+                    let origin = *origin.get_or_insert(position);
+                    format!(" | {} from {origin_name}", Range::between(&origin, &position))
+                });
+                println!("{record}{}", range.unwrap_or_default());
+                if let Some(csv) = &mut csv {
+                    csv.write(&record).map_err(|e| format!("Failed to write CSV log: {e}"))?;
+                }
+                if let Some(jsonl) = &mut jsonl {
+                    jsonl.write(&record).map_err(|e| format!("Failed to write JSONL log: {e}"))?;
+                }
+
+                // Keep the exports up to date while live, so they can be opened during the flight
+                let received = if live { now() } else { None };
+                if let Some(point) = record.track_point(args.date, received) {
+                    track.points.push(point);
+                    if live && args.exports.any() {
+                        args.exports.write(&track)?;
+                    }
+                }
+            }
+            Line::Other(other) if args.verbose && !other.is_empty() => println!("> {other}"),
+            Line::Other(_) => (),
+        }
+    }
+}
+
+/// Sends a command, and shows the received packets until it has been answered
+fn send(args: SendArgs) -> Result<(), String> {
+    let start = Instant::now();
+    let now_ms = || start.elapsed().as_millis() as u64;
+    let mut uplink = Uplink::send(args.command, args.key, &args.counter, now_ms())?;
+
+    let mut port = serialport::new(&args.port, args.baud)
+        .timeout(Duration::from_secs(1))
+        .open()
+        .map_err(|e| format!("Failed to open {}: {e}", args.port))?;
+    let mut input = BufReader::new(port.try_clone().map_err(|e| format!("Failed to open {}: {e}", args.port))?);
+    eprintln!("Sending {:?} #{} on {} at {} baud", args.command, uplink.counter(), args.port, args.baud);
+
+    let mut buf = Vec::new();
+    loop {
+        match input.read_until(b'\n', &mut buf) {
+            Ok(0) => return Err("The receiver's serial port was closed".to_string()),
+            Ok(_) => (),
+            Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::Interrupted) => continue,
+            Err(e) => return Err(format!("Failed to read input: {e}")),
+        }
+
+        let line = String::from_utf8_lossy(&buf).into_owned();
+        buf.clear();
+        let Line::Packet(packet) = Line::parse(&line) else {
+            continue;
+        };
+        let record = Record::from(packet);
+        println!("{record}");
+        match uplink.receive(&record.payload, now_ms()) {
+            Step::Wait => (),
+            Step::Transmit(line) => {
+                port.write_all(line.as_bytes()).map_err(|e| format!("Failed to write to {}: {e}", args.port))?;
+                eprintln!("Transmitting {:?} #{}", args.command, uplink.counter());
+            }
+            Step::Done(Outcome::Acked(command)) => {
+                println!("{command:?} acknowledged");
+                return Ok(());
+            }
+            Step::Done(Outcome::Nacked(command, reason)) => return Err(format!("{command:?} rejected: {reason:?}")),
+            Step::Failed(command) => return Err(format!("{command:?} was not answered")),
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1).peekable();
+    let result = if args.next_if(|arg| arg == "send").is_some() {
+        SendArgs::parse(args).map_err(|e| format!("{e}\n\n{USAGE}")).and_then(send)
+    } else {
+        Args::parse(args).map_err(|e| format!("{e}\n\n{USAGE}")).and_then(run)
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! CSV and JSON Lines logs
+
+use crate::payload::Payload;
+use crate::record::Record;
+use std::io::{self, Write};
+
+/// The CSV columns
+const CSV_HEADER: &str = "time,kind,sequence,uptime_s,latitude,longitude,altitude_m,satellites,battery_mv,light_level,\
+    accel_x_mg,accel_y_mg,accel_z_mg,flight_state,strength_dbm,rssi_dbm,snr_db,gain,text";
+
+/// The fields of a record in column order, with `None` for empty cells
+///
+/// # Note
+/// Numeric values are pre-formatted, while the text is kept separate as it needs quoting.
+fn fields(record: &Record) -> (Vec<(&'static str, Option<String>)>, Option<String>) {
+    /// Formats an optional value
+    fn some<T: ToString>(value: T) -> Option<String> {
+        Some(value.to_string())
+    }
+
+    let position = record.payload.position();
+    let telemetry = match &record.payload {
+        Payload::Telemetry(telemetry) => Some(telemetry),
+        _ => None,
+    };
+    let text = match &record.payload {
+        Payload::Text(text) => Some(text.clone()),
+        Payload::Invalid { error, .. } => Some(error.to_string()),
+        Payload::Response(response) => match response.result {
+            Ok(()) => Some("ACK".to_string()),
+            Err(reason) => Some(format!("NACK {reason:?}")),
+        },
+        Payload::Telemetry(_) | Payload::Gga(_) => None,
+    };
+    let satellites = match &record.payload {
+        Payload::Telemetry(telemetry) => some(telemetry.satellites),
+        Payload::Gga(fix) => some(fix.satellites),
+        Payload::Response(_) | Payload::Text(_) | Payload::Invalid { .. } => None,
+    };
+    let sequence = match &record.payload {
+        Payload::Telemetry(telemetry) => some(telemetry.sequence),
+        Payload::Response(response) => some(response.counter),
+        Payload::Gga(_) | Payload::Text(_) | Payload::Invalid { .. } => None,
+    };
+
+    let fields = vec![
+        ("sequence", sequence),
+        ("uptime_s", telemetry.and_then(|t| some(t.uptime_s))),
+        ("latitude", position.map(|(latitude, _, _)| format!("{latitude:.7}"))),
+        ("longitude", position.map(|(_, longitude, _)| format!("{longitude:.7}"))),
+        ("altitude_m", position.map(|(_, _, altitude)| format!("{altitude:.1}"))),
+        ("satellites", satellites),
+        ("battery_mv", telemetry.and_then(|t| some(t.battery_mv))),
+        ("light_level", telemetry.and_then(|t| some(t.light_level))),
+        ("accel_x_mg", telemetry.and_then(|t| some(t.acceleration.x_mg))),
+        ("accel_y_mg", telemetry.and_then(|t| some(t.acceleration.y_mg))),
+        ("accel_z_mg", telemetry.and_then(|t| some(t.acceleration.z_mg))),
+        ("flight_state", telemetry.map(|t| format!("{:?}", t.flight_state))),
+        ("strength_dbm", record.stats.and_then(|s| some(s.strength_dbm))),
+        ("rssi_dbm", record.stats.and_then(|s| some(s.rssi_dbm))),
+        ("snr_db", record.stats.and_then(|s| some(s.snr_db))),
+        ("gain", record.stats.and_then(|s| some(s.gain))),
+    ];
+    (fields, text)
+}
+
+/// A CSV log with a header row
+pub struct CsvLog<W: Write> {
+    /// The underlying writer
+    writer: W,
+}
+impl<W: Write> CsvLog<W> {
+    /// Creates a new log and writes the header row
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writeln!(writer, "{CSV_HEADER}")?;
+        Ok(Self { writer })
+    }
+
+    /// Continues an existing log without writing another header row
+    pub const fn append(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Appends a record and flushes the log, so it survives the ground station being killed
+    pub fn write(&mut self, record: &Record) -> io::Result<()> {
+        let (fields, text) = fields(record);
+        let mut row = vec![csv_escape(&record.time), record.payload.kind().to_string()];
+        row.extend(fields.into_iter().map(|(_, value)| value.unwrap_or_default()));
+        row.push(text.as_deref().map(csv_escape).unwrap_or_default());
+        writeln!(self.writer, "{}", row.join(","))?;
+        self.writer.flush()
+    }
+}
+
+/// A JSON Lines log with one object per record; empty fields are omitted
+pub struct JsonlLog<W: Write> {
+    /// The underlying writer
+    writer: W,
+}
+impl<W: Write> JsonlLog<W> {
+    /// Creates a new log
+    pub const fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Appends a record and flushes the log, so it survives the ground station being killed
+    pub fn write(&mut self, record: &Record) -> io::Result<()> {
+        let (fields, text) = fields(record);
+        let mut members = vec![
+            format!("\"time\":{}", json_string(&record.time)),
+            format!("\"kind\":{}", json_string(record.payload.kind())),
+        ];
+        for (name, value) in fields {
+            let Some(value) = value else {
+                continue;
+            };
+            // Flight states are names, all other fields are numbers
+            match name {
+                "flight_state" => members.push(format!("\"{name}\":{}", json_string(&value))),
+                _ => members.push(format!("\"{name}\":{value}")),
+            }
+        }
+        if let Some(text) = text {
+            members.push(format!("\"text\":{}", json_string(&text)));
+        }
+        writeln!(self.writer, "{{{}}}", members.join(","))?;
+        self.writer.flush()
+    }
+}
+
+/// Quotes a CSV cell if necessary
+fn csv_escape(value: &str) -> String {
+    match value.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
+
+/// Encodes a JSON string
+pub(crate) fn json_string(value: &str) -> String {
+    let mut encoded = String::from("\"");
+    for char in value.chars() {
+        match char {
+            '"' => encoded.push_str("\\\""),
+            '\\' => encoded.push_str("\\\\"),
+            '\n' => encoded.push_str("\\n"),
+            '\r' => encoded.push_str("\\r"),
+            '\t' => encoded.push_str("\\t"),
+            char if char.is_control() => encoded.push_str(&format!("\\u{:04x}", char as u32)),
+            char => encoded.push(char),
+        }
+    }
+    encoded.push('"');
+    encoded
+}
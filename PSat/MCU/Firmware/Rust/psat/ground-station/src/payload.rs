@@ -0,0 +1,154 @@
+//! Decodes packet payloads
+
+use psat_command::frame::{Response, RESPONSE_MAGIC};
+use psat_telemetry::frame::{Telemetry, MAGIC};
+
+/// A position fix from a raw NMEA GGA sentence, as sent by `Gps::get_raw_gga_packet`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GgaFix {
+    /// The UTC time of day in seconds, if reported
+    pub time_of_day_s: Option<f64>,
+    /// The latitude in decimal degrees, positive to the north
+    pub latitude: f64,
+    /// The longitude in decimal degrees, positive to the east
+    pub longitude: f64,
+    /// The altitude above mean sea level in metres
+    pub altitude_m: f64,
+    /// The amount of satellites in use
+    pub satellites: u8,
+}
+impl GgaFix {
+    /// Parses a GGA sentence; returns `None` if it is malformed or has no fix
+    ///
+    /// # Note
+    /// The checksum is validated if present.
+    pub fn parse(sentence: &str) -> Option<Self> {
+        let sentence = sentence.trim_end().strip_prefix('$')?;
+        let body = match sentence.split_once('*') {
+            Some((body, checksum)) => {
+                let checksum = u8::from_str_radix(checksum, 16).ok()?;
+                let true = body.bytes().fold(0, |acc, byte| acc ^ byte) == checksum else {
+                    return None;
+                };
+                body
+            }
+            None => sentence,
+        };
+
+        // `GPGGA,time,lat,N,lon,E,quality,satellites,hdop,altitude,M,...`
+        let fields: Vec<&str> = body.split(',').collect();
+        let [talker, time, lat, ns, lon, ew, quality, satellites, _hdop, altitude, ..] = fields.as_slice() else {
+            return None;
+        };
+        let true = (talker.len() == 5 && talker.ends_with("GGA")) else {
+            return None;
+        };
+        let ("1" | "2") = *quality else {
+            return None;
+        };
+
+        let latitude = Self::degrees(lat, 2)?;
+        let longitude = Self::degrees(lon, 3)?;
+        let latitude = match *ns {
+            "N" => latitude,
+            "S" => -latitude,
+            _ => return None,
+        };
+        let longitude = match *ew {
+            "E" => longitude,
+            "W" => -longitude,
+            _ => return None,
+        };
+        Some(Self {
+            time_of_day_s: Self::time_of_day(time),
+            latitude,
+            longitude,
+            altitude_m: altitude.parse().ok()?,
+            satellites: satellites.parse().ok()?,
+        })
+    }
+
+    /// Converts NMEA `hhmmss.sss` to seconds since midnight
+    fn time_of_day(value: &str) -> Option<f64> {
+        let hours: f64 = value.get(..2)?.parse().ok()?;
+        let minutes: f64 = value.get(2..4)?.parse().ok()?;
+        let seconds: f64 = value.get(4..)?.parse().ok()?;
+        Some(hours * 3600.0 + minutes * 60.0 + seconds)
+    }
+
+    /// Converts NMEA `(d)ddmm.mmmm` to decimal degrees
+    fn degrees(value: &str, degree_digits: usize) -> Option<f64> {
+        let (degrees, minutes) = (value.get(..degree_digits)?, value.get(degree_digits..)?);
+        let degrees: f64 = degrees.parse().ok()?;
+        let minutes: f64 = minutes.parse().ok()?;
+        Some(degrees + minutes / 60.0)
+    }
+}
+
+/// A decoded payload
+#[derive(Debug, Clone, PartialEq)]
+pub enum Payload {
+    /// A telemetry frame
+    Telemetry(Telemetry),
+    /// A raw NMEA GGA sentence with a fix
+    Gga(GgaFix),
+    /// A response to an uplink command
+    Response(Response),
+    /// Any other text, e.g. the range test's timestamps
+    Text(String),
+    /// Binary data that is not a valid telemetry frame
+    Invalid {
+        /// The raw data
+        data: Vec<u8>,
+        /// Why the data could not be decoded
+        error: &'static str,
+    },
+}
+impl Payload {
+    /// Decodes a raw payload
+    pub fn decode(data: &[u8]) -> Self {
+        if let [MAGIC, ..] = data {
+            return match Telemetry::decode(data) {
+                Ok(telemetry) => Self::Telemetry(telemetry),
+                Err(error) => Self::Invalid { data: data.to_vec(), error },
+            };
+        }
+        if let [RESPONSE_MAGIC, ..] = data {
+            return match Response::decode(data) {
+                Ok(response) => Self::Response(response),
+                Err(error) => Self::Invalid { data: data.to_vec(), error },
+            };
+        }
+
+        let text = String::from_utf8_lossy(data).into_owned();
+        match GgaFix::parse(&text) {
+            Some(fix) => Self::Gga(fix),
+            None => Self::Text(text),
+        }
+    }
+
+    /// A short name of the payload kind
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            Self::Telemetry(_) => "telemetry",
+            Self::Gga(_) => "gga",
+            Self::Response(_) => "response",
+            Self::Text(_) => "text",
+            Self::Invalid { .. } => "invalid",
+        }
+    }
+
+    /// The position as latitude, longitude and altitude, if the payload carries one
+    pub fn position(&self) -> Option<(f64, f64, f64)> {
+        match self {
+            Self::Telemetry(telemetry) => {
+                let position = telemetry.position?;
+                let latitude = f64::from(position.latitude_e7) / 1e7;
+                let longitude = f64::from(position.longitude_e7) / 1e7;
+                Some((latitude, longitude, f64::from(position.altitude_dm) / 10.0))
+            }
+            Self::Gga(fix) => Some((fix.latitude, fix.longitude, fix.altitude_m)),
+            Self::Response(_) | Self::Text(_) | Self::Invalid { .. } => None,
+        }
+    }
+}
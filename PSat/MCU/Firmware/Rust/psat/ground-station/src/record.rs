@@ -0,0 +1,76 @@
+//! A received and decoded packet
+
+use crate::export::{DateTime, TrackPoint};
+use crate::line::{LinkStats, Packet};
+use crate::payload::Payload;
+use psat_telemetry::frame::Position;
+use std::fmt::{self, Display, Formatter};
+
+/// A received and decoded packet
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    /// The receiver's time since boot as `HH:MM:SS`
+    pub time: String,
+    /// The decoded payload
+    pub payload: Payload,
+    /// The link statistics, if available
+    pub stats: Option<LinkStats>,
+}
+impl Record {
+    /// The record as a track point, if it carries a position
+    ///
+    /// # Timestamps
+    /// GGA sentences only carry the UTC time of day, which is combined with the flight `date`. Telemetry frames carry
+    /// no GPS time, so they are stamped with the `received` time instead.
+    pub fn track_point(&self, date: Option<DateTime>, received: Option<DateTime>) -> Option<TrackPoint> {
+        let (latitude, longitude, altitude_m) = self.payload.position()?;
+        let time = match &self.payload {
+            Payload::Gga(fix) => date.zip(fix.time_of_day_s).map(|(date, time)| date.with_time_of_day(time)),
+            _ => received,
+        };
+        Some(TrackPoint { time, latitude, longitude, altitude_m })
+    }
+
+    /// The record's position in telemetry units, e.g. for the range from the ground station
+    pub fn position(&self) -> Option<Position> {
+        let (latitude, longitude, altitude_m) = self.payload.position()?;
+        Some(Position {
+            latitude_e7: (latitude * 1e7).round() as i32,
+            longitude_e7: (longitude * 1e7).round() as i32,
+            altitude_dm: (altitude_m * 10.0).round() as i32,
+        })
+    }
+}
+impl From<Packet> for Record {
+    fn from(packet: Packet) -> Self {
+        Self { time: packet.time, payload: Payload::decode(&packet.payload), stats: packet.stats }
+    }
+}
+impl Display for Record {
+    /// A one-line summary for the live display
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}]", self.time)?;
+        match &self.payload {
+            Payload::Telemetry(telemetry) => write!(f, " #{} {:?}", telemetry.sequence, telemetry.flight_state)?,
+            Payload::Gga(_) => write!(f, " GGA")?,
+            Payload::Response(response) => match response.result {
+                Ok(()) => write!(f, " ACK #{}", response.counter)?,
+                Err(reason) => write!(f, " NACK #{} {reason:?}", response.counter)?,
+            },
+            Payload::Text(text) => write!(f, " '{text}'")?,
+            Payload::Invalid { error, .. } => write!(f, " invalid frame: {error}")?,
+        }
+        match self.payload.position() {
+            Some((latitude, longitude, altitude)) => write!(f, " at {latitude:.6}, {longitude:.6}, {altitude:.1} m")?,
+            None if matches!(self.payload, Payload::Telemetry(_)) => write!(f, " without fix")?,
+            None => (),
+        }
+        if let Payload::Telemetry(telemetry) = &self.payload {
+            write!(f, ", {} sats, {} mV", telemetry.satellites, telemetry.battery_mv)?;
+        }
+        if let Some(stats) = &self.stats {
+            write!(f, " | RSSI {} dBm, SNR {} dB", stats.rssi_dbm, stats.snr_db)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+//! Sends an uplink command through the Beacon receiver
+//!
+//! # Format
+//! The Beacon transmits every line of the form `hex:c301...` it receives on its serial port as a command frame. The payload
+//! only listens right after it has sent a telemetry frame, so a due frame is written as soon as a telemetry frame has been
+//! received.
+
+use crate::payload::Payload;
+use psat_command::auth::Key;
+use psat_command::frame::Command;
+use psat_command::sender::{Outcome, Poll, RetryPolicy, Sender};
+use std::fmt::Write;
+use std::path::Path;
+
+/// What to do after a received payload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    /// Keep listening
+    Wait,
+    /// Write the given line to the Beacon
+    Transmit(String),
+    /// The command has been answered
+    Done(Outcome),
+    /// All attempts have been used up without a response
+    Failed(Command),
+}
+
+/// A command on its way to the payload
+#[derive(Debug)]
+pub struct Uplink {
+    /// The sender
+    sender: Sender,
+}
+impl Uplink {
+    /// Queues a command, continuing after the counter in the given file, which is created if it does not exist
+    ///
+    /// # Note
+    /// The new counter is stored before the command is transmitted, so it is not reused if the ground station stops.
+    pub fn send(command: Command, key: Key, counter_path: &Path, now_ms: u64) -> Result<Self, String> {
+        let counter = match std::fs::read_to_string(counter_path) {
+            Ok(counter) => {
+                counter.trim().parse().map_err(|_| format!("Invalid counter in {}", counter_path.display()))?
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(format!("Failed to read {}: {e}", counter_path.display())),
+        };
+
+        let mut sender = Sender::new(RetryPolicy::default(), key, counter);
+        sender.send(command, now_ms)?;
+        std::fs::write(counter_path, format!("{}\n", sender.counter()))
+            .map_err(|e| format!("Failed to write {}: {e}", counter_path.display()))?;
+        Ok(Self { sender })
+    }
+
+    /// The counter of the command
+    pub const fn counter(&self) -> u32 {
+        self.sender.counter()
+    }
+
+    /// Handles a received payload
+    pub fn receive(&mut self, payload: &Payload, now_ms: u64) -> Step {
+        match payload {
+            // The payload's RX window is open now
+            Payload::Telemetry(_) => match self.sender.poll(now_ms) {
+                Poll::Transmit(frame) => Step::Transmit(frame_line(&frame)),
+                Poll::Failed(command) => Step::Failed(command),
+                Poll::Idle | Poll::Wait => Step::Wait,
+            },
+            Payload::Response(response) => match self.sender.receive(&response.encode()) {
+                Some(outcome) => Step::Done(outcome),
+                None => Step::Wait,
+            },
+            Payload::Gga(_) | Payload::Text(_) | Payload::Invalid { .. } => Step::Wait,
+        }
+    }
+}
+
+/// Parses a command name, e.g. `arm` or `buzzer`; the buzzer takes the pattern number as argument
+pub fn parse_command(name: &str, argument: Option<&str>) -> Result<Command, String> {
+    let command = match (name, argument) {
+        ("arm", None) => Command::Arm,
+        ("disarm", None) => Command::Disarm,
+        ("trigger-servo", None) => Command::TriggerServo,
+        ("gps-fix", None) => Command::RequestGpsFix,
+        ("buzzer", Some(pattern)) => {
+            Command::Buzzer { pattern: pattern.parse().map_err(|_| "Invalid buzzer pattern")? }
+        }
+        ("buzzer", None) => return Err("Missing buzzer pattern".to_string()),
+        ("arm" | "disarm" | "trigger-servo" | "gps-fix", Some(argument)) => {
+            return Err(format!("Unexpected argument {argument} for {name}"))
+        }
+        _ => return Err(format!("Unknown command {name}")),
+    };
+    Ok(command)
+}
+
+/// The line that makes the Beacon transmit a frame
+pub fn frame_line(frame: &[u8]) -> String {
+    let mut line = String::from("hex:");
+    for byte in frame {
+        let _ = write!(line, "{byte:02x}");
+    }
+    line.push('\n');
+    line
+}
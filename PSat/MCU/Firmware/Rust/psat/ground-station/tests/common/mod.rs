@@ -0,0 +1,36 @@
+//! Shared helpers for the ground station tests
+
+#![allow(dead_code, reason = "not every test uses every helper")]
+
+use std::path::PathBuf;
+
+/// The recorded receiver log
+pub const FLIGHT_LOG: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/logs/flight.log");
+/// The expected CSV log for the recorded receiver log
+pub const FLIGHT_CSV: &str = include_str!("../logs/flight.csv");
+/// The expected JSON Lines log for the recorded receiver log
+pub const FLIGHT_JSONL: &str = include_str!("../logs/flight.jsonl");
+
+/// The ground station binary
+pub const GROUND_STATION: &str = env!("CARGO_BIN_EXE_ground-station");
+
+/// A unique, empty path in the temp directory that is removed on drop
+pub struct TempPath(pub PathBuf);
+impl TempPath {
+    /// Creates a new path with the given name
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("psat-ground-station-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        Self(path)
+    }
+
+    /// The file contents
+    pub fn read(&self) -> String {
+        std::fs::read_to_string(&self.0).expect("failed to read output file")
+    }
+}
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
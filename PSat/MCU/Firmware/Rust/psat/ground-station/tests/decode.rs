@@ -0,0 +1,70 @@
+//! Line parsing and payload decoding tests
+
+use psat_command::frame::{NackReason, Response};
+use psat_ground_station::line::{decode_hex, Line, LinkStats, Packet};
+use psat_ground_station::payload::{GgaFix, Payload};
+use psat_telemetry::frame::{FlightState, Position, Telemetry};
+
+#[test]
+fn parses_text_packet() {
+    let line = Line::parse("[00:01:02] 'Hi, there', Strength: -61, RSSI: -72, SNR: -3, Gain: G2\r\n");
+    let stats = LinkStats { strength_dbm: -61, rssi_dbm: -72, snr_db: -3, gain: 2 };
+    let packet = Packet { time: "00:01:02".to_string(), payload: b"Hi, there".to_vec(), stats: Some(stats) };
+    assert_eq!(line, Line::Packet(packet));
+}
+
+#[test]
+fn parses_hex_packet_without_stats() {
+    let line = Line::parse("[00:01:02] hex:a5C3ff");
+    let packet = Packet { time: "00:01:02".to_string(), payload: vec![0xA5, 0xC3, 0xFF], stats: None };
+    assert_eq!(line, Line::Packet(packet));
+}
+
+#[test]
+fn passes_through_other_lines() {
+    for line in ["Hello world!", "[00:00:05] Packet incoming: 39 bytes, CR 4/5", "[00:00:05] hex:a5c", ""] {
+        assert_eq!(Line::parse(line), Line::Other(line.to_string()));
+    }
+    assert_eq!(decode_hex("+f"), None);
+}
+
+#[test]
+fn decodes_telemetry() {
+    let telemetry = Telemetry {
+        sequence: 7,
+        position: Some(Position::from_degrees(-27.4698, 153.0251, 100.0)),
+        flight_state: FlightState::Descent,
+        ..Default::default()
+    };
+    let payload = Payload::decode(&telemetry.encode());
+    assert_eq!(payload, Payload::Telemetry(telemetry));
+    let (latitude, longitude, altitude) = payload.position().expect("missing position");
+    assert!((latitude - -27.4698).abs() < 1e-5 && (longitude - 153.0251).abs() < 1e-5 && altitude == 100.0);
+}
+
+#[test]
+fn decodes_gga() {
+    let payload = Payload::decode(b"$GPGGA,012345.000,2728.1880,S,15301.5060,W,1,07,1.10,45.3,M,39.0,M,,*56\r\n");
+    let Payload::Gga(GgaFix { time_of_day_s, latitude, longitude, altitude_m, satellites }) = payload else {
+        panic!("not a GGA fix: {payload:?}");
+    };
+    assert!((latitude - -27.4698).abs() < 1e-9 && (longitude - -153.0251).abs() < 1e-9);
+    assert_eq!((time_of_day_s, altitude_m, satellites), (Some(5025.0), 45.3, 7));
+
+    // Bad checksum and no fix
+    let bad_checksum = b"$GPGGA,012345.000,2728.1880,S,15301.5060,W,1,07,1.10,45.3,M,39.0,M,,*57";
+    assert!(matches!(Payload::decode(bad_checksum), Payload::Text(_)));
+    assert_eq!(GgaFix::parse("$GPGGA,012345.000,,,,,0,00,99.99,,M,,M,,*57"), None);
+}
+
+#[test]
+fn decodes_command_responses() {
+    let response = Response { counter: 42, result: Err(NackReason::InvalidState) };
+    let payload = Payload::decode(&response.encode());
+    assert_eq!(payload, Payload::Response(response));
+    assert_eq!(payload.kind(), "response");
+
+    let mut corrupted = response.encode();
+    corrupted[6] ^= 0x01;
+    assert!(matches!(Payload::decode(&corrupted), Payload::Invalid { error: "Checksum mismatch", .. }));
+}
@@ -0,0 +1,114 @@
+//! Track export and flight event detection tests
+
+use psat_ground_station::export::{DateTime, Events, Track, TrackPoint};
+
+/// A short flight: on the pad, climb, apogee, descent and landing with some GPS altitude noise
+fn flight() -> Track {
+    let date = DateTime::parse_date("2024-05-04").expect("invalid date");
+    let altitudes = [30.0, 32.5, 29.0, 31.0, 180.0, 640.0, 910.5, 905.0, 700.0, 420.0, 160.0, 45.0, 38.0, 41.5, 39.0];
+    let points = altitudes
+        .iter()
+        .enumerate()
+        .map(|(index, &altitude_m)| TrackPoint {
+            time: Some(date.with_time_of_day(5025.0 + index as f64 * 1.5)),
+            latitude: -27.4698 + index as f64 * 0.0001,
+            longitude: 153.0251 - index as f64 * 0.0002,
+            altitude_m,
+        })
+        .collect();
+    Track { name: "Test <flight> & co".to_string(), points }
+}
+
+#[test]
+fn date_time() {
+    let time = DateTime::from_unix_millis(1_714_785_825_600);
+    assert_eq!(time.to_string(), "2024-05-04T01:23:45.600Z");
+    assert_eq!(DateTime::from_unix_millis(951_782_400_000).to_string(), "2000-02-29T00:00:00Z");
+    assert_eq!(DateTime::parse_date("2024-05-04").map(|date| date.with_time_of_day(5025.6)), Some(time));
+    assert_eq!(DateTime::parse_date("2024-13-04"), None);
+}
+
+#[test]
+fn detects_flight_events() {
+    let events = flight().events();
+    assert_eq!(events, Events { launch: Some(3), apogee: Some(6), landing: Some(11) });
+    assert_eq!(events.named().collect::<Vec<_>>(), [("Launch", 3), ("Apogee", 6), ("Landing", 11)]);
+}
+
+#[test]
+fn ignores_ground_noise_and_incomplete_flights() {
+    let mut track = flight();
+    track.points.truncate(4);
+    assert_eq!(track.events(), Events::default());
+
+    // Still in the air
+    let mut track = flight();
+    track.points.truncate(9);
+    assert_eq!(track.events(), Events { launch: Some(3), apogee: Some(6), landing: None });
+
+    assert_eq!(Track::new("Empty").events(), Events::default());
+}
+
+#[test]
+fn gpx() {
+    let gpx = flight().to_gpx();
+    assert!(gpx.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<gpx version=\"1.1\""));
+    assert!(gpx.contains("xmlns=\"http://www.topografix.com/GPX/1/1\""));
+    assert_eq!(gpx.matches("<trkpt ").count(), 15);
+    assert!(gpx.contains(concat!(
+        "  <wpt lat=\"-27.4692000\" lon=\"153.0239000\">\n",
+        "    <ele>910.5</ele>\n",
+        "    <time>2024-05-04T01:23:54Z</time>\n",
+        "    <name>Apogee</name>\n",
+        "  </wpt>\n"
+    )));
+    assert!(gpx.contains("<name>Test &lt;flight&gt; &amp; co</name>"));
+
+    // Waypoints must precede the track
+    let waypoints = ["Launch", "Apogee", "Landing"].map(|name| gpx.find(&format!("<name>{name}</name>")));
+    assert!(waypoints.iter().all(|index| index < &gpx.find("<trk>")));
+}
+
+#[test]
+fn kml() {
+    let kml = flight().to_kml();
+    assert!(kml.contains("<kml xmlns=\"http://www.opengis.net/kml/2.2\">"));
+    assert!(kml.contains("<extrude>1</extrude>"));
+    assert!(kml.contains("<altitudeMode>absolute</altitudeMode>"));
+    assert!(kml.contains("          153.0251000,-27.4698000,30.0\n"));
+    assert!(kml.contains(concat!(
+        "      <name>Landing</name>\n",
+        "      <TimeStamp><when>2024-05-04T01:24:01.500Z</when></TimeStamp>\n"
+    )));
+}
+
+#[test]
+fn geojson() {
+    let geojson: serde_json::Value = serde_json::from_str(&flight().to_geojson()).expect("invalid GeoJSON");
+    let features = geojson["features"].as_array().expect("missing features");
+    assert_eq!(geojson["type"], "FeatureCollection");
+    assert_eq!(features.len(), 4);
+
+    let line = &features[0];
+    assert_eq!(line["geometry"]["type"], "LineString");
+    assert_eq!(line["geometry"]["coordinates"].as_array().map(Vec::len), Some(15));
+    assert_eq!(line["geometry"]["coordinates"][0], serde_json::json!([153.0251, -27.4698, 30.0]));
+    assert_eq!(line["properties"]["name"], "Test <flight> & co");
+    assert_eq!(line["properties"]["times"][1], "2024-05-04T01:23:46.500Z");
+
+    let names: Vec<_> = features[1..].iter().map(|feature| feature["properties"]["name"].clone()).collect();
+    assert_eq!(names, ["Launch", "Apogee", "Landing"]);
+    assert_eq!(
+        features[2]["geometry"],
+        serde_json::json!({ "type": "Point", "coordinates": [153.0239, -27.4692, 910.5] })
+    );
+}
+
+#[test]
+fn geojson_without_times() {
+    let mut track = flight();
+    track.points.iter_mut().for_each(|point| point.time = None);
+    let geojson: serde_json::Value = serde_json::from_str(&track.to_geojson()).expect("invalid GeoJSON");
+    assert_eq!(geojson["features"][0]["properties"]["times"][0], serde_json::Value::Null);
+    assert!(!track.to_gpx().contains("<time>"));
+}
@@ -0,0 +1,103 @@
+//! Decodes a recorded receiver log with the ground station binary
+
+mod common;
+
+use common::{TempPath, FLIGHT_CSV, FLIGHT_JSONL, FLIGHT_LOG, GROUND_STATION};
+use std::process::Command;
+
+#[test]
+fn decodes_recorded_log() {
+    let (csv, jsonl) = (TempPath::new("log.csv"), TempPath::new("log.jsonl"));
+    let output = Command::new(GROUND_STATION)
+        .args(["--log", FLIGHT_LOG, "--csv"])
+        .arg(&csv.0)
+        .arg("--jsonl")
+        .arg(&jsonl.0)
+        .output()
+        .expect("failed to run ground station");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    assert_eq!(csv.read(), FLIGHT_CSV);
+    assert_eq!(jsonl.read(), FLIGHT_JSONL);
+
+    // The live display shows every packet with its position and link quality
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.lines().count(), 7);
+    assert!(
+        stdout.contains("[00:00:20] #2 Ascent at -27.465000, 153.030000, 1234.5 m, 8 sats, 4010 mV | RSSI -106 dBm")
+    );
+    // Ranges are from the first position when the station position is unknown
+    assert!(stdout.contains("SNR -3 dB | 720 m at 042° from launch"));
+}
+
+#[test]
+fn shows_range_from_station() {
+    let output = Command::new(GROUND_STATION)
+        .args(["--log", FLIGHT_LOG, "--station", "-27.47,153.02"])
+        .output()
+        .expect("failed to run ground station");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("#1 Armed at -27.469800, 153.025100, 27.5 m, 6 sats, 4098 mV | RSSI -75 dBm, SNR 9 dB | 504 m at 087° from station"));
+    assert!(stdout.contains("SNR -3 dB | 1.1 km at 061° from station"));
+}
+
+#[test]
+fn appends_to_existing_csv() {
+    let csv = TempPath::new("append.csv");
+    for _ in 0..2 {
+        let status = Command::new(GROUND_STATION)
+            .args(["--log", FLIGHT_LOG, "--csv"])
+            .arg(&csv.0)
+            .output()
+            .expect("failed to run ground station")
+            .status;
+        assert!(status.success());
+    }
+
+    // The header is only written once
+    let rows = FLIGHT_CSV.lines().skip(1).collect::<Vec<_>>().join("\n");
+    assert_eq!(csv.read(), format!("{FLIGHT_CSV}{rows}\n"));
+}
+
+#[test]
+fn rejects_invalid_arguments() {
+    for args in [
+        &[][..],
+        &["--log", FLIGHT_LOG, "--serial", "/dev/null"],
+        &["--log"],
+        &["--frobnicate"],
+        &["--log", FLIGHT_LOG, "--station", "91,0"],
+    ] {
+        let output = Command::new(GROUND_STATION).args(args).output().expect("failed to run ground station");
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Usage: ground-station"));
+    }
+}
+
+#[test]
+fn exports_track_from_recorded_log() {
+    let (gpx, kml, geojson) = (TempPath::new("track.gpx"), TempPath::new("track.kml"), TempPath::new("track.geojson"));
+    let status = Command::new(GROUND_STATION)
+        .args(["--log", FLIGHT_LOG, "--date", "2024-05-04", "--gpx"])
+        .arg(&gpx.0)
+        .arg("--kml")
+        .arg(&kml.0)
+        .arg("--geojson")
+        .arg(&geojson.0)
+        .output()
+        .expect("failed to run ground station")
+        .status;
+    assert!(status.success());
+
+    // Two telemetry frames and the GGA sentence carry a position; only the GGA sentence has a GPS time
+    let gpx = gpx.read();
+    assert_eq!(gpx.matches("<trkpt ").count(), 3);
+    assert_eq!(gpx.matches("<time>2024-05-04T01:23:45Z</time>").count(), 2);
+    assert!(gpx.contains("<name>Launch</name>") && gpx.contains("<name>Apogee</name>"));
+    assert_eq!(kml.read().matches("<Placemark>").count(), 3);
+
+    let geojson: serde_json::Value = serde_json::from_str(&geojson.read()).expect("invalid GeoJSON");
+    let coordinates = &geojson["features"][0]["geometry"]["coordinates"];
+    assert_eq!(coordinates[2], serde_json::json!([153.03, -27.465, 1234.5]));
+}
@@ -0,0 +1,95 @@
+//! Talks to the ground station through a pseudo-terminal, as if it was the receiver's serial port
+
+#![cfg(unix)]
+
+mod common;
+
+use common::{TempPath, FLIGHT_CSV, FLIGHT_LOG, GROUND_STATION};
+use psat_command::auth::Key;
+use psat_command::frame::{Request, Response};
+use psat_ground_station::line::decode_hex;
+use psat_telemetry::frame::Telemetry;
+use serialport::{SerialPort, TTYPort};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn decodes_serial_stream() {
+    let (mut receiver, ground_station_end) = TTYPort::pair().expect("failed to create pseudo-terminal");
+    let port = ground_station_end.name().expect("pseudo-terminal has no name");
+    let csv = TempPath::new("pty.csv");
+
+    let mut child = Command::new(GROUND_STATION)
+        .args(["--serial", &port, "--csv"])
+        .arg(&csv.0)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run ground station");
+
+    // Wait until the port is open and configured, so no data is lost
+    let mut stderr = BufReader::new(child.stderr.take().expect("missing stderr"));
+    let mut ready = String::new();
+    stderr.read_line(&mut ready).expect("failed to read ground station output");
+    assert!(ready.starts_with("Listening on"), "{ready}");
+
+    // Send the log in small chunks with pauses, so lines get split across reads and read timeouts
+    let log = std::fs::read(FLIGHT_LOG).expect("failed to read log");
+    for chunk in log.chunks(17) {
+        receiver.write_all(chunk).expect("failed to write to pseudo-terminal");
+        thread::sleep(Duration::from_millis(5));
+    }
+    thread::sleep(Duration::from_millis(1_500));
+
+    // Wait until all packets have been logged
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while std::fs::read_to_string(&csv.0).unwrap_or_default() != FLIGHT_CSV && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(50));
+    }
+    child.kill().expect("failed to stop ground station");
+    child.wait().expect("failed to wait for ground station");
+    assert_eq!(csv.read(), FLIGHT_CSV);
+}
+
+#[test]
+fn sends_command_through_receiver() {
+    let (mut receiver, ground_station_end) = TTYPort::pair().expect("failed to create pseudo-terminal");
+    let port = ground_station_end.name().expect("pseudo-terminal has no name");
+    let counter = TempPath::new("pty-counter");
+    let key = Key([0x5A; 16]);
+
+    let mut child = Command::new(GROUND_STATION)
+        .args(["send", "--serial", &port, "--counter"])
+        .arg(&counter.0)
+        .args(["buzzer", "3"])
+        .env("PSAT_COMMAND_KEY", "5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run ground station");
+    let mut stderr = BufReader::new(child.stderr.take().expect("missing stderr"));
+    let mut ready = String::new();
+    stderr.read_line(&mut ready).expect("failed to read ground station output");
+    assert!(ready.starts_with("Sending Buzzer { pattern: 3 } #1"), "{ready}");
+
+    // Act as the Beacon: print a telemetry frame, and answer the command frame written back for the payload
+    let mut beacon = BufReader::new(receiver.try_clone_native().expect("failed to clone pseudo-terminal"));
+    let telemetry = Telemetry::default().encode().iter().map(|byte| format!("{byte:02x}")).collect::<String>();
+    writeln!(receiver, "[00:00:05] hex:{telemetry}, Strength: -60, RSSI: -70, SNR: 9, Gain: G1")
+        .expect("failed to write to pseudo-terminal");
+    let mut line = String::new();
+    beacon.read_line(&mut line).expect("failed to read command frame");
+    let hex = line.trim_end().strip_prefix("hex:").expect("not a frame line");
+    let request = Request::decode(&decode_hex(hex).expect("invalid hex"), &key).expect("invalid request");
+    assert_eq!((request.counter, request.command()), (1, Ok(psat_command::frame::Command::Buzzer { pattern: 3 })));
+
+    let response = Response { counter: 1, result: Ok(()) }.encode();
+    let response = response.iter().map(|byte| format!("{byte:02x}")).collect::<String>();
+    writeln!(receiver, "[00:00:06] hex:{response}").expect("failed to write to pseudo-terminal");
+
+    let status = child.wait().expect("failed to wait for ground station");
+    assert!(status.success());
+    assert_eq!(counter.read(), "1\n");
+}
@@ -0,0 +1,98 @@
+//! Uplink command tests, with the test as the Beacon and the payload
+
+mod common;
+
+use common::TempPath;
+use psat_command::auth::Key;
+use psat_command::frame::{Command, NackReason, Request, Response};
+use psat_command::sender::Outcome;
+use psat_ground_station::line::decode_hex;
+use psat_ground_station::payload::Payload;
+use psat_ground_station::uplink::{frame_line, parse_command, Step, Uplink};
+use psat_telemetry::frame::Telemetry;
+
+/// The pre-shared key
+const KEY: Key = Key([0x5A; 16]);
+
+/// The payload's request in a line written to the Beacon
+fn request(line: &str) -> Request {
+    let hex = line.strip_prefix("hex:").and_then(|hex| hex.strip_suffix('\n')).expect("not a frame line");
+    Request::decode(&decode_hex(hex).expect("invalid hex"), &KEY).expect("invalid request")
+}
+
+#[test]
+fn parses_commands() {
+    assert_eq!(parse_command("arm", None), Ok(Command::Arm));
+    assert_eq!(parse_command("disarm", None), Ok(Command::Disarm));
+    assert_eq!(parse_command("trigger-servo", None), Ok(Command::TriggerServo));
+    assert_eq!(parse_command("gps-fix", None), Ok(Command::RequestGpsFix));
+    assert_eq!(parse_command("buzzer", Some("3")), Ok(Command::Buzzer { pattern: 3 }));
+
+    assert!(parse_command("buzzer", None).is_err());
+    assert!(parse_command("buzzer", Some("loud")).is_err());
+    assert!(parse_command("arm", Some("now")).is_err());
+    assert!(parse_command("launch", None).is_err());
+}
+
+#[test]
+fn frame_line_is_lowercase_hex() {
+    assert_eq!(frame_line(&[0xC3, 0x0A, 0xFF]), "hex:c30aff\n");
+}
+
+#[test]
+fn transmits_after_telemetry_until_answered() {
+    let counter = TempPath::new("uplink-counter");
+    std::fs::write(&counter.0, "41\n").expect("failed to write counter");
+    let mut uplink = Uplink::send(Command::Arm, KEY, &counter.0, 0).expect("failed to send");
+    assert_eq!(counter.read(), "42\n", "the counter is stored before the first transmission");
+
+    // Only a telemetry frame opens the payload's RX window
+    assert_eq!(uplink.receive(&Payload::Text("00:00:01".to_string()), 0), Step::Wait);
+    let Step::Transmit(line) = uplink.receive(&Payload::Telemetry(Telemetry::default()), 0) else {
+        panic!("no transmission after telemetry");
+    };
+    let request = request(&line);
+    assert_eq!((request.counter, request.command()), (42, Ok(Command::Arm)));
+    assert_eq!(uplink.receive(&Payload::Telemetry(Telemetry::default()), 1000), Step::Wait, "backing off");
+
+    // Responses to other commands are ignored
+    let stale = Response { counter: 41, result: Ok(()) };
+    assert_eq!(uplink.receive(&Payload::Response(stale), 2000), Step::Wait);
+    let response = Response { counter: 42, result: Ok(()) };
+    assert_eq!(uplink.receive(&Payload::Response(response), 2000), Step::Done(Outcome::Acked(Command::Arm)));
+}
+
+#[test]
+fn reports_nack_and_lost_commands() {
+    let counter = TempPath::new("uplink-nack-counter");
+    let mut uplink = Uplink::send(Command::TriggerServo, KEY, &counter.0, 0).expect("failed to send");
+    assert_eq!(counter.read(), "1\n", "a missing counter file starts at 0");
+    let response = Response { counter: 1, result: Err(NackReason::InvalidState) };
+    assert_eq!(
+        uplink.receive(&Payload::Response(response), 0),
+        Step::Done(Outcome::Nacked(Command::TriggerServo, NackReason::InvalidState))
+    );
+
+    // Every telemetry frame after the backoff is another attempt, until they are used up
+    let mut uplink = Uplink::send(Command::Disarm, KEY, &counter.0, 0).expect("failed to send");
+    let mut steps = Vec::new();
+    for second in 0..300 {
+        match uplink.receive(&Payload::Telemetry(Telemetry::default()), second * 1000) {
+            Step::Wait => (),
+            Step::Transmit(line) => steps.push(request(&line).counter),
+            step => {
+                assert_eq!(step, Step::Failed(Command::Disarm));
+                break;
+            }
+        }
+    }
+    assert_eq!(steps, [2; 5], "five attempts with the same counter");
+}
+
+#[test]
+fn rejects_invalid_counter_file() {
+    let counter = TempPath::new("uplink-invalid-counter");
+    std::fs::write(&counter.0, "many").expect("failed to write counter");
+    assert!(Uplink::send(Command::Arm, KEY, &counter.0, 0).is_err());
+    assert_eq!(counter.read(), "many", "the counter file is left alone");
+}
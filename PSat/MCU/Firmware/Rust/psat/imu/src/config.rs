@@ -0,0 +1,155 @@
+//! Output data rates, full-scale ranges and FIFO modes
+
+/// An output data rate, shared by the accelerometer, the gyroscope and the FIFO
+///
+/// Rates are ordered, so a FIFO rate can be compared against the sensor rates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum DataRate {
+    /// The sensor is off
+    PowerDown = 0,
+    /// 12.5 Hz
+    Hz12_5 = 1,
+    /// 26 Hz
+    Hz26 = 2,
+    /// 52 Hz
+    Hz52 = 3,
+    /// 104 Hz
+    Hz104 = 4,
+    /// 208 Hz
+    Hz208 = 5,
+    /// 416 Hz
+    Hz416 = 6,
+    /// 833 Hz
+    Hz833 = 7,
+    /// 1.66 kHz
+    Hz1660 = 8,
+    /// 3.33 kHz
+    Hz3330 = 9,
+    /// 6.66 kHz
+    Hz6660 = 10,
+}
+
+/// The accelerometer full-scale range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccelScale {
+    /// ±2 g
+    G2,
+    /// ±4 g
+    G4,
+    /// ±8 g
+    G8,
+    /// ±16 g
+    G16,
+}
+impl AccelScale {
+    /// The `FS_XL` bits of `CTRL1_XL`
+    pub(crate) const fn bits(&self) -> u8 {
+        match self {
+            Self::G2 => 0b0000,
+            Self::G16 => 0b0100,
+            Self::G4 => 0b1000,
+            Self::G8 => 0b1100,
+        }
+    }
+
+    /// The sensitivity in micro-g per LSB
+    pub const fn ug_per_lsb(&self) -> i32 {
+        match self {
+            Self::G2 => 61,
+            Self::G4 => 122,
+            Self::G8 => 244,
+            Self::G16 => 488,
+        }
+    }
+}
+
+/// The gyroscope full-scale range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GyroScale {
+    /// ±125 degrees per second
+    Dps125,
+    /// ±250 degrees per second
+    Dps250,
+    /// ±500 degrees per second
+    Dps500,
+    /// ±1000 degrees per second
+    Dps1000,
+    /// ±2000 degrees per second
+    Dps2000,
+}
+impl GyroScale {
+    /// The `FS_G` and `FS_125` bits of `CTRL2_G`
+    pub(crate) const fn bits(&self) -> u8 {
+        match self {
+            Self::Dps125 => 0b0010,
+            Self::Dps250 => 0b0000,
+            Self::Dps500 => 0b0100,
+            Self::Dps1000 => 0b1000,
+            Self::Dps2000 => 0b1100,
+        }
+    }
+
+    /// The sensitivity in micro-degrees per second per LSB
+    pub const fn udps_per_lsb(&self) -> i64 {
+        match self {
+            Self::Dps125 => 4375,
+            Self::Dps250 => 8750,
+            Self::Dps500 => 17_500,
+            Self::Dps1000 => 35_000,
+            Self::Dps2000 => 70_000,
+        }
+    }
+}
+
+/// How the hardware FIFO buffers samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fifo {
+    /// The FIFO is off, and only the latest sample can be read
+    Bypass,
+    /// The FIFO keeps the newest samples, overwriting the oldest when it is full
+    Continuous {
+        /// The rate at which samples are stored, at most the rate of each enabled sensor
+        rate: DataRate,
+        /// The number of samples at which the watermark flag is set
+        watermark: u16,
+    },
+}
+
+/// The sensor configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// The accelerometer output data rate
+    pub accel_rate: DataRate,
+    /// The accelerometer range
+    pub accel_scale: AccelScale,
+    /// The gyroscope output data rate, or [`DataRate::PowerDown`] to save power
+    pub gyro_rate: DataRate,
+    /// The gyroscope range
+    pub gyro_scale: GyroScale,
+    /// The FIFO mode
+    pub fifo: Fifo,
+}
+impl Config {
+    /// The number of 16-bit words per sample in the FIFO: the gyroscope axes, if it is on, followed by the
+    /// accelerometer axes
+    pub fn fifo_words_per_sample(&self) -> u16 {
+        if self.gyro_rate == DataRate::PowerDown {
+            3
+        } else {
+            6
+        }
+    }
+}
+impl Default for Config {
+    /// Both sensors at 104 Hz, with ranges wide enough for the boost and a tumbling payload, and the FIFO off
+    fn default() -> Self {
+        Self {
+            accel_rate: DataRate::Hz104,
+            accel_scale: AccelScale::G16,
+            gyro_rate: DataRate::Hz104,
+            gyro_scale: GyroScale::Dps2000,
+            fifo: Fifo::Bypass,
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! The embedded motion detectors, which raise the INT1 pin so the MCU does not have to poll
+//!
+//! The wake-up detector responds to changes in acceleration, such as the start of the boost or an impact, rather than
+//! to a steady acceleration. The free-fall detector responds to all axes being close to zero for a while. Tilt and
+//! significant motion run on the embedded functions engine, and need the accelerometer at 26 Hz or more.
+
+use crate::config::AccelScale;
+
+/// The free-fall threshold, which has only a few settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FreeFallThreshold {
+    /// 156 mg
+    Mg156 = 0,
+    /// 219 mg
+    Mg219 = 1,
+    /// 250 mg
+    Mg250 = 2,
+    /// 312 mg
+    Mg312 = 3,
+    /// 344 mg
+    Mg344 = 4,
+    /// 406 mg
+    Mg406 = 5,
+    /// 469 mg
+    Mg469 = 6,
+    /// 500 mg
+    Mg500 = 7,
+}
+
+/// The wake-up detector settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WakeUp {
+    /// The change in acceleration that triggers it, in steps of 1/64 of the accelerometer's full scale
+    pub threshold_mg: u16,
+    /// How many samples the change must last, at most [`WakeUp::MAX_DURATION`]
+    pub duration_samples: u8,
+}
+impl WakeUp {
+    /// The longest duration
+    pub const MAX_DURATION: u8 = 3;
+
+    /// The `WK_THS` bits for a full scale, rounded to the nearest step
+    pub(crate) fn threshold_bits(&self, scale: AccelScale) -> Result<u8, &'static str> {
+        // 1/64 of the full scale is 512 LSB
+        let step_mg = scale.ug_per_lsb().saturating_mul(512) / 1000;
+        let steps = i32::from(self.threshold_mg).saturating_add(step_mg / 2).checked_div(step_mg);
+        match steps.and_then(|steps| u8::try_from(steps).ok()) {
+            Some(steps @ 1..=0x3F) => Ok(steps),
+            _ => Err("Wake-up threshold out of range for the full scale"),
+        }
+    }
+}
+
+/// The free-fall detector settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FreeFall {
+    /// The acceleration below which the payload is falling
+    pub threshold: FreeFallThreshold,
+    /// How many samples the fall must last, at most [`FreeFall::MAX_DURATION`]
+    pub duration_samples: u8,
+}
+impl FreeFall {
+    /// The longest duration
+    pub const MAX_DURATION: u8 = 0x3F;
+}
+
+/// Which detectors raise INT1, and their settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Detectors {
+    /// The wake-up detector, for high-g events
+    pub wake_up: Option<WakeUp>,
+    /// The free-fall detector
+    pub free_fall: Option<FreeFall>,
+    /// The tilt detector, for a change in orientation of more than 35°
+    pub tilt: bool,
+    /// The significant motion detector, for a change in location
+    pub significant_motion: bool,
+}
+impl Detectors {
+    /// Whether the embedded functions engine is needed
+    pub(crate) const fn uses_embedded_functions(&self) -> bool {
+        self.tilt || self.significant_motion
+    }
+}
+
+/// The detectors that have fired since they were last read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Events {
+    /// A high-g event
+    pub wake_up: bool,
+    /// Free fall
+    pub free_fall: bool,
+    /// A change in orientation
+    pub tilt: bool,
+    /// Significant motion
+    pub significant_motion: bool,
+}
+impl Events {
+    /// Whether any detector has fired
+    pub const fn any(&self) -> bool {
+        self.wake_up || self.free_fall || self.tilt || self.significant_motion
+    }
+}
@@ -0,0 +1,458 @@
+//! The LSM6DSM driver
+//!
+//! # I2C transfers
+//! Registers are read with a separate write of the register address and a read, rather than with `WriteRead`, since the
+//! MSP430 HAL's `write_read` does the read first. The LSM6DSM keeps its register pointer across the stop condition, and
+//! auto-increments it during burst reads.
+
+use crate::config::{Config, DataRate, Fifo};
+use crate::detect::{Detectors, Events, FreeFall, WakeUp};
+use embedded_hal::blocking::i2c::{Read, Write};
+
+/// The I2C address with SA0 tied low
+pub const ADDRESS_SA0_LOW: u8 = 0x6A;
+/// The I2C address with SA0 tied high
+pub const ADDRESS_SA0_HIGH: u8 = 0x6B;
+
+/// The `WHO_AM_I` value of the LSM6DSM
+pub const WHO_AM_I: u8 = 0x6A;
+
+/// The capacity of the hardware FIFO in 16-bit words
+pub const FIFO_WORDS: u16 = 2048;
+
+/// How many times the software reset bit is polled before giving up. A reset takes about 50 µs.
+const RESET_POLLS: u8 = 100;
+
+/// Register addresses
+mod reg {
+    pub const FIFO_CTRL1: u8 = 0x06;
+    pub const FIFO_CTRL5: u8 = 0x0A;
+    pub const INT1_CTRL: u8 = 0x0D;
+    pub const WHO_AM_I: u8 = 0x0F;
+    pub const CTRL1_XL: u8 = 0x10;
+    pub const CTRL3_C: u8 = 0x12;
+    pub const CTRL10_C: u8 = 0x19;
+    pub const WAKE_UP_SRC: u8 = 0x1B;
+    pub const OUT_TEMP_L: u8 = 0x20;
+    pub const FIFO_STATUS1: u8 = 0x3A;
+    pub const FIFO_DATA_OUT_L: u8 = 0x3E;
+    pub const FUNC_SRC1: u8 = 0x53;
+    pub const TAP_CFG: u8 = 0x58;
+    pub const WAKE_UP_THS: u8 = 0x5B;
+}
+
+/// `CTRL3_C` bits
+mod ctrl3 {
+    /// Block data update, so the low and high bytes of a reading always belong together
+    pub const BDU: u8 = 1 << 6;
+    /// Register address auto-increment during burst transfers
+    pub const IF_INC: u8 = 1 << 2;
+    /// Software reset, cleared by the sensor when done
+    pub const SW_RESET: u8 = 1 << 0;
+}
+
+/// Embedded function bits, in `CTRL10_C` to enable them and `INT1_CTRL`, `MD1_CFG` and the source registers to route and
+/// report them
+mod func {
+    /// `TAP_CFG`: enables the wake-up and free-fall detectors
+    pub const INTERRUPTS_ENABLE: u8 = 1 << 7;
+    /// `TAP_CFG`: latches the interrupts until the source register is read
+    pub const LIR: u8 = 1 << 0;
+    /// `CTRL10_C`: the pedometer, which significant motion builds on
+    pub const PEDO_EN: u8 = 1 << 4;
+    /// `CTRL10_C`: tilt detection
+    pub const TILT_EN: u8 = 1 << 3;
+    /// `CTRL10_C`: the embedded functions engine
+    pub const FUNC_EN: u8 = 1 << 2;
+    /// `CTRL10_C`: significant motion detection
+    pub const SIGN_MOTION_EN: u8 = 1 << 0;
+    /// `INT1_CTRL`: significant motion on INT1
+    pub const INT1_SIGN_MOT: u8 = 1 << 6;
+    /// `MD1_CFG`: wake-up on INT1
+    pub const INT1_WU: u8 = 1 << 5;
+    /// `MD1_CFG`: free fall on INT1
+    pub const INT1_FF: u8 = 1 << 4;
+    /// `MD1_CFG`: tilt on INT1
+    pub const INT1_TILT: u8 = 1 << 1;
+    /// `WAKE_UP_SRC`: free fall detected
+    pub const FF_IA: u8 = 1 << 5;
+    /// `WAKE_UP_SRC`: wake-up detected
+    pub const WU_IA: u8 = 1 << 3;
+    /// `FUNC_SRC1`: significant motion detected
+    pub const SIGN_MOTION_IA: u8 = 1 << 6;
+    /// `FUNC_SRC1`: tilt detected
+    pub const TILT_IA: u8 = 1 << 5;
+}
+
+/// The `FIFO_MODE` bits of `FIFO_CTRL5` for continuous mode
+const FIFO_MODE_CONTINUOUS: u8 = 0b110;
+/// The `DEC_FIFO_*` value for storing every sample of a sensor
+const FIFO_NO_DECIMATION: u8 = 0b001;
+
+/// A reading of both sensors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Sample {
+    /// The acceleration in milli-g along the X, Y and Z axes
+    pub acceleration_mg: [i16; 3],
+    /// The angular rate in milli-degrees per second around the X, Y and Z axes, or `None` if the gyroscope is off
+    pub angular_rate_mdps: Option<[i32; 3]>,
+    /// The die temperature in hundredths of a degree Celsius, or `None` for samples from the FIFO
+    pub temperature_cdeg: Option<i16>,
+}
+impl Sample {
+    /// The magnitude of the acceleration in milli-g, which is about 1000 at rest whatever the orientation
+    pub fn acceleration_magnitude_mg(&self) -> u16 {
+        let sum_of_squares = self.acceleration_mg.iter().fold(0_u32, |sum, &axis| {
+            let axis = u32::from(axis.unsigned_abs());
+            sum.saturating_add(axis.saturating_mul(axis))
+        });
+        u16::try_from(isqrt(sum_of_squares)).unwrap_or(u16::MAX)
+    }
+}
+
+/// The integer square root, rounded down
+fn isqrt(mut n: u32) -> u32 {
+    let mut root = 0_u32;
+    let mut bit = 1_u32 << 30;
+    while bit > n {
+        bit >>= 2;
+    }
+    while bit != 0 {
+        let candidate = root.saturating_add(bit);
+        if n >= candidate {
+            n = n.saturating_sub(candidate);
+            root = (root >> 1).saturating_add(bit);
+        } else {
+            root >>= 1;
+        }
+        bit >>= 2;
+    }
+    root
+}
+
+/// The FIFO fill level and flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FifoStatus {
+    /// The number of unread 16-bit words
+    pub unread_words: u16,
+    /// Whether the watermark has been reached
+    pub watermark: bool,
+    /// Whether samples have been overwritten since the last read
+    pub overrun: bool,
+    /// Whether the FIFO is empty
+    pub empty: bool,
+    /// The index within a sample of the next word to be read
+    pub pattern: u16,
+}
+
+/// An LSM6DSM accelerometer and gyroscope on an I2C bus
+#[derive(Debug)]
+pub struct Lsm6dsm<I2C> {
+    /// The bus
+    i2c: I2C,
+    /// The I2C address
+    address: u8,
+    /// The configuration last written to the sensor
+    config: Config,
+    /// The detectors last written to the sensor
+    detectors: Detectors,
+    /// How many times the FIFO has overrun
+    fifo_overruns: u16,
+}
+impl<I2C, E> Lsm6dsm<I2C>
+where
+    I2C: Write<Error = E> + Read<Error = E>,
+{
+    /// Wraps the bus without talking to the sensor. Call [`init`](Self::init) before reading.
+    pub fn new(i2c: I2C, address: u8) -> Self {
+        Self { i2c, address, config: Config::default(), detectors: Detectors::default(), fifo_overruns: 0 }
+    }
+
+    /// Checks that the sensor is an LSM6DSM, resets it and applies the configuration
+    ///
+    /// # Errors
+    /// Fails if the sensor does not respond, is not an LSM6DSM, does not finish its reset, or the configuration is
+    /// invalid.
+    pub fn init(&mut self, config: Config) -> Result<(), &'static str> {
+        if self.who_am_i()? != WHO_AM_I {
+            return Err("Not an LSM6DSM");
+        }
+        self.write_registers(reg::CTRL3_C, &[ctrl3::IF_INC | ctrl3::SW_RESET])?;
+        let mut resetting = true;
+        for _ in 0..RESET_POLLS {
+            resetting = self.read_register(reg::CTRL3_C)? & ctrl3::SW_RESET != 0;
+            if !resetting {
+                break;
+            }
+        }
+        if resetting {
+            return Err("IMU reset timed out");
+        }
+        self.write_registers(reg::CTRL3_C, &[ctrl3::BDU | ctrl3::IF_INC])?;
+        self.configure(config)
+    }
+
+    /// Changes the data rates, ranges and FIFO mode, and re-applies the detectors for the new range. Changing the FIFO
+    /// mode discards its contents.
+    ///
+    /// # Errors
+    /// Fails if the transfer fails, if the FIFO is on while the accelerometer is off or is faster than an enabled
+    /// sensor, or if the detectors do not suit the new configuration.
+    pub fn configure(&mut self, config: Config) -> Result<(), &'static str> {
+        Self::check_detectors(&config, &self.detectors)?;
+        if let Fifo::Continuous { rate, .. } = config.fifo {
+            let gyro_too_slow = config.gyro_rate != DataRate::PowerDown && rate > config.gyro_rate;
+            if rate == DataRate::PowerDown || rate > config.accel_rate || gyro_too_slow {
+                return Err("FIFO rate must not exceed the sensor rates");
+            }
+        }
+        let ctrl1_xl = (config.accel_rate as u8) << 4 | config.accel_scale.bits();
+        let ctrl2_g = (config.gyro_rate as u8) << 4 | config.gyro_scale.bits();
+        self.write_registers(reg::CTRL1_XL, &[ctrl1_xl, ctrl2_g])?;
+
+        // Bypass mode empties the FIFO, so it restarts aligned to the first word of a sample
+        self.write_registers(reg::FIFO_CTRL5, &[0])?;
+        if let Fifo::Continuous { rate, watermark } = config.fifo {
+            let words_per_sample = config.fifo_words_per_sample();
+            let [threshold_low, threshold_high] =
+                watermark.saturating_mul(words_per_sample).min(FIFO_WORDS.saturating_sub(1)).to_le_bytes();
+            let gyro_decimation = if words_per_sample == 6 { FIFO_NO_DECIMATION } else { 0 };
+            let fifo_ctrl3 = gyro_decimation << 3 | FIFO_NO_DECIMATION;
+            let fifo_ctrl5 = (rate as u8) << 3 | FIFO_MODE_CONTINUOUS;
+            self.write_registers(reg::FIFO_CTRL1, &[threshold_low, threshold_high, fifo_ctrl3, 0, fifo_ctrl5])?;
+        }
+        self.config = config;
+        self.set_detectors(self.detectors)
+    }
+
+    /// Configures the embedded motion detectors and routes them to INT1, which stays high until
+    /// [`read_events`](Self::read_events) is called
+    ///
+    /// # Errors
+    /// Fails if the transfer fails, if a threshold or duration is out of range, or if the embedded functions are used
+    /// with the accelerometer below 26 Hz.
+    pub fn set_detectors(&mut self, detectors: Detectors) -> Result<(), &'static str> {
+        Self::check_detectors(&self.config, &detectors)?;
+        let wake_up_ths = match detectors.wake_up {
+            Some(wake_up) => wake_up.threshold_bits(self.config.accel_scale)?,
+            None => 0,
+        };
+        let wake_dur = detectors.wake_up.map_or(0, |wake_up| wake_up.duration_samples);
+        let (ff_ths, ff_dur) =
+            detectors.free_fall.map_or((0, 0), |free_fall| (free_fall.threshold as u8, free_fall.duration_samples));
+        // The sixth bit of the free-fall duration lives in WAKE_UP_DUR
+        let wake_up_dur = (ff_dur & 0x20) << 2 | wake_dur << 5;
+        let free_fall = (ff_dur & 0x1F) << 3 | ff_ths;
+        let md1_cfg = if detectors.wake_up.is_some() { func::INT1_WU } else { 0 }
+            | if detectors.free_fall.is_some() { func::INT1_FF } else { 0 }
+            | if detectors.tilt { func::INT1_TILT } else { 0 };
+        let int1_ctrl = if detectors.significant_motion { func::INT1_SIGN_MOT } else { 0 };
+        let ctrl10_c = if detectors.uses_embedded_functions() { func::FUNC_EN } else { 0 }
+            | if detectors.tilt { func::TILT_EN } else { 0 }
+            | if detectors.significant_motion { func::PEDO_EN | func::SIGN_MOTION_EN } else { 0 };
+
+        let basic = detectors.wake_up.is_some() || detectors.free_fall.is_some();
+        let tap_cfg = if basic { func::INTERRUPTS_ENABLE | func::LIR } else { 0 };
+        self.write_registers(reg::TAP_CFG, &[tap_cfg])?;
+        self.write_registers(reg::WAKE_UP_THS, &[wake_up_ths, wake_up_dur, free_fall, md1_cfg])?;
+        self.write_registers(reg::INT1_CTRL, &[int1_ctrl])?;
+        self.write_registers(reg::CTRL10_C, &[ctrl10_c])?;
+        self.detectors = detectors;
+        Ok(())
+    }
+
+    /// Reads which detectors have fired, which also releases INT1
+    ///
+    /// # Errors
+    /// Fails if the sensor does not respond.
+    pub fn read_events(&mut self) -> Result<Events, &'static str> {
+        let wake_up_src = self.read_register(reg::WAKE_UP_SRC)?;
+        let func_src1 = self.read_register(reg::FUNC_SRC1)?;
+        Ok(Events {
+            wake_up: wake_up_src & func::WU_IA != 0,
+            free_fall: wake_up_src & func::FF_IA != 0,
+            tilt: func_src1 & func::TILT_IA != 0,
+            significant_motion: func_src1 & func::SIGN_MOTION_IA != 0,
+        })
+    }
+
+    /// The detectors last applied
+    pub const fn detectors(&self) -> &Detectors {
+        &self.detectors
+    }
+
+    /// Checks that the detectors suit a configuration
+    fn check_detectors(config: &Config, detectors: &Detectors) -> Result<(), &'static str> {
+        if let Some(wake_up) = detectors.wake_up {
+            wake_up.threshold_bits(config.accel_scale)?;
+            if wake_up.duration_samples > WakeUp::MAX_DURATION {
+                return Err("Wake-up duration out of range");
+            }
+        }
+        if detectors.free_fall.is_some_and(|free_fall| free_fall.duration_samples > FreeFall::MAX_DURATION) {
+            return Err("Free-fall duration out of range");
+        }
+        if detectors.uses_embedded_functions() && config.accel_rate < DataRate::Hz26 {
+            return Err("Tilt and significant motion need the accelerometer at 26 Hz or more");
+        }
+        Ok(())
+    }
+
+    /// The configuration last applied
+    pub const fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Reads the `WHO_AM_I` register, which is [`WHO_AM_I`] for an LSM6DSM
+    ///
+    /// # Errors
+    /// Fails if the sensor does not respond.
+    pub fn who_am_i(&mut self) -> Result<u8, &'static str> {
+        self.read_register(reg::WHO_AM_I)
+    }
+
+    /// Reads the latest temperature, angular rate and acceleration in one burst
+    ///
+    /// # Errors
+    /// Fails if the sensor does not respond.
+    pub fn read(&mut self) -> Result<Sample, &'static str> {
+        let mut bytes = [0; 14];
+        self.read_registers(reg::OUT_TEMP_L, &mut bytes)?;
+        let [t0, t1, gx0, gx1, gy0, gy1, gz0, gz1, ax0, ax1, ay0, ay1, az0, az1] = bytes;
+        let temperature = i32::from(i16::from_le_bytes([t0, t1]));
+        // 256 LSB per degree, centred on 25 °C
+        let temperature_cdeg = 2500_i32.saturating_add(temperature.saturating_mul(100) >> 8);
+        Ok(Sample {
+            acceleration_mg: self.acceleration_mg([[ax0, ax1], [ay0, ay1], [az0, az1]]),
+            angular_rate_mdps: self.angular_rate_mdps([[gx0, gx1], [gy0, gy1], [gz0, gz1]]),
+            temperature_cdeg: i16::try_from(temperature_cdeg).ok(),
+        })
+    }
+
+    /// Reads the FIFO fill level and flags
+    ///
+    /// # Errors
+    /// Fails if the sensor does not respond.
+    pub fn fifo_status(&mut self) -> Result<FifoStatus, &'static str> {
+        let mut bytes = [0; 4];
+        self.read_registers(reg::FIFO_STATUS1, &mut bytes)?;
+        let [status1, status2, status3, status4] = bytes;
+        Ok(FifoStatus {
+            unread_words: u16::from_le_bytes([status1, status2 & 0b111]),
+            watermark: status2 & 1 << 7 != 0,
+            overrun: status2 & 1 << 6 != 0,
+            empty: status2 & 1 << 4 != 0,
+            pattern: u16::from_le_bytes([status3, status4 & 0b11]),
+        })
+    }
+
+    /// Reads as many whole samples from the FIFO as fit in `samples`, oldest first, and returns how many were read
+    ///
+    /// A partly read sample, e.g. after an overrun, is skipped. Overruns are counted in
+    /// [`fifo_overruns`](Self::fifo_overruns).
+    ///
+    /// # Errors
+    /// Fails if the transfer fails, or if the FIFO is off.
+    pub fn drain_fifo(&mut self, samples: &mut [Sample]) -> Result<usize, &'static str> {
+        let Fifo::Continuous { .. } = self.config.fifo else {
+            return Err("IMU FIFO is off");
+        };
+        let status = self.fifo_status()?;
+        if status.overrun {
+            self.fifo_overruns = self.fifo_overruns.saturating_add(1);
+        }
+        let words_per_sample = self.config.fifo_words_per_sample();
+        let mut unread_words = status.unread_words;
+
+        // Skip to the start of the next sample
+        let misaligned = words_per_sample.saturating_sub(status.pattern).checked_rem(words_per_sample).unwrap_or(0);
+        for _ in 0..misaligned.min(unread_words) {
+            self.read_registers(reg::FIFO_DATA_OUT_L, &mut [0; 2])?;
+        }
+        unread_words = unread_words.saturating_sub(misaligned);
+
+        let available = usize::from(unread_words.checked_div(words_per_sample).unwrap_or(0));
+        for sample in samples.iter_mut().take(available) {
+            // The address wraps from FIFO_DATA_OUT_H back to FIFO_DATA_OUT_L, so a burst reads consecutive words
+            let mut words = [[0; 2]; 6];
+            let words = words.get_mut(..usize::from(words_per_sample)).ok_or("IMU FIFO sample too long")?;
+            self.read_registers(reg::FIFO_DATA_OUT_L, words.as_flattened_mut())?;
+            *sample = match *words {
+                [gx, gy, gz, ax, ay, az] => Sample {
+                    acceleration_mg: self.acceleration_mg([ax, ay, az]),
+                    angular_rate_mdps: self.angular_rate_mdps([gx, gy, gz]),
+                    temperature_cdeg: None,
+                },
+                [ax, ay, az] => Sample {
+                    acceleration_mg: self.acceleration_mg([ax, ay, az]),
+                    angular_rate_mdps: None,
+                    temperature_cdeg: None,
+                },
+                _ => return Err("IMU FIFO sample has an invalid length"),
+            };
+        }
+        Ok(available.min(samples.len()))
+    }
+
+    /// How many times the FIFO has overrun, i.e. was not drained fast enough
+    pub const fn fifo_overruns(&self) -> u16 {
+        self.fifo_overruns
+    }
+
+    /// Releases the bus
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    /// Converts raw accelerometer axes
+    fn acceleration_mg(&self, axes: [[u8; 2]; 3]) -> [i16; 3] {
+        let ug_per_lsb = self.config.accel_scale.ug_per_lsb();
+        axes.map(|axis| {
+            let mg = i32::from(i16::from_le_bytes(axis)).saturating_mul(ug_per_lsb) / 1000;
+            i16::try_from(mg).unwrap_or(if mg < 0 { i16::MIN } else { i16::MAX })
+        })
+    }
+
+    /// Converts raw gyroscope axes, or returns `None` if the gyroscope is off
+    fn angular_rate_mdps(&self, axes: [[u8; 2]; 3]) -> Option<[i32; 3]> {
+        if self.config.gyro_rate == DataRate::PowerDown {
+            return None;
+        }
+        let udps_per_lsb = self.config.gyro_scale.udps_per_lsb();
+        Some(axes.map(|axis| {
+            let mdps = i64::from(i16::from_le_bytes(axis)).saturating_mul(udps_per_lsb) / 1000;
+            // At most 2000 dps, which fits
+            i32::try_from(mdps).unwrap_or_default()
+        }))
+    }
+
+    /// Reads one register
+    fn read_register(&mut self, register: u8) -> Result<u8, &'static str> {
+        let mut value = [0];
+        self.read_registers(register, &mut value)?;
+        let [value] = value;
+        Ok(value)
+    }
+
+    /// Reads consecutive registers
+    fn read_registers(&mut self, first: u8, values: &mut [u8]) -> Result<(), &'static str> {
+        if values.is_empty() {
+            return Ok(());
+        }
+        self.i2c.write(self.address, &[first]).map_err(|_| "IMU I2C write failed")?;
+        self.i2c.read(self.address, values).map_err(|_| "IMU I2C read failed")
+    }
+
+    /// Writes consecutive registers
+    fn write_registers(&mut self, first: u8, values: &[u8]) -> Result<(), &'static str> {
+        let mut buffer = [0; 8];
+        let Some(bytes) = buffer.get_mut(..values.len().saturating_add(1)) else {
+            return Err("IMU write too long");
+        };
+        if let [register, rest @ ..] = bytes {
+            *register = first;
+            rest.copy_from_slice(values);
+        }
+        self.i2c.write(self.address, bytes).map_err(|_| "IMU I2C write failed")
+    }
+}
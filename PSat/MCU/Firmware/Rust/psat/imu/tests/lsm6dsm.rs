@@ -0,0 +1,250 @@
+//! Driver tests against a simulated LSM6DSM
+
+use embedded_hal::blocking::i2c::{Read, Write};
+use psat_imu::config::{AccelScale, Config, DataRate, Fifo, GyroScale};
+use psat_imu::detect::{Detectors, Events, FreeFall, FreeFallThreshold, WakeUp};
+use psat_imu::lsm6dsm::{Lsm6dsm, Sample, ADDRESS_SA0_LOW};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// The simulated sensor's register file, with a FIFO behind `FIFO_DATA_OUT`
+struct Simulated {
+    /// The registers
+    registers: [u8; 0x80],
+    /// The register pointer
+    pointer: u8,
+    /// Every write, with the address and the bytes
+    writes: Vec<Vec<u8>>,
+    /// The FIFO words
+    fifo: VecDeque<i16>,
+    /// The words per FIFO sample
+    words_per_sample: u16,
+    /// The index within a sample of the next FIFO word
+    pattern: u16,
+}
+impl Simulated {
+    /// A freshly powered sensor
+    fn new() -> Self {
+        let mut registers = [0; 0x80];
+        registers[0x0F] = 0x6A;
+        Self { registers, pointer: 0, writes: Vec::new(), fifo: VecDeque::new(), words_per_sample: 6, pattern: 0 }
+    }
+
+    /// The value of the next register in a burst read
+    fn read_next(&mut self) -> u8 {
+        let value = match self.pointer {
+            0x3A => self.fifo.len() as u8,
+            0x3B => (self.fifo.len() >> 8) as u8 | if self.fifo.is_empty() { 1 << 4 } else { 0 } | self.registers[0x3B],
+            0x3C => self.pattern as u8,
+            0x3E => self.fifo.front().copied().unwrap_or_default().to_le_bytes()[0],
+            0x3F => {
+                self.pattern = (self.pattern + 1) % self.words_per_sample;
+                self.fifo.pop_front().unwrap_or_default().to_le_bytes()[1]
+            }
+            register => self.registers[usize::from(register)],
+        };
+        // The FIFO output wraps around
+        self.pointer = if self.pointer == 0x3F { 0x3E } else { self.pointer + 1 };
+        value
+    }
+
+    /// Writes raw little-endian words into consecutive registers
+    fn set_words(&mut self, first: usize, words: &[i16]) {
+        for (index, word) in words.iter().enumerate() {
+            self.registers[first + 2 * index..][..2].copy_from_slice(&word.to_le_bytes());
+        }
+    }
+}
+
+/// The I2C bus to the simulated sensor, shared with the test
+#[derive(Clone)]
+struct Bus(Rc<RefCell<Simulated>>);
+impl Bus {
+    /// A bus to a freshly powered sensor
+    fn new() -> Self {
+        Self(Rc::new(RefCell::new(Simulated::new())))
+    }
+}
+impl Write for Bus {
+    type Error = ();
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), ()> {
+        if address != ADDRESS_SA0_LOW {
+            return Err(());
+        }
+        let mut sensor = self.0.borrow_mut();
+        sensor.writes.push(bytes.to_vec());
+        let (&register, values) = bytes.split_first().ok_or(())?;
+        sensor.pointer = register;
+        for &value in values {
+            let pointer = usize::from(sensor.pointer);
+            // The software reset finishes immediately
+            sensor.registers[pointer] = value & !1;
+            sensor.pointer += 1;
+        }
+        // Bypass mode empties the FIFO
+        if bytes == [0x0A, 0] {
+            sensor.fifo.clear();
+            sensor.pattern = 0;
+        }
+        Ok(())
+    }
+}
+impl Read for Bus {
+    type Error = ();
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), ()> {
+        if address != ADDRESS_SA0_LOW {
+            return Err(());
+        }
+        let mut sensor = self.0.borrow_mut();
+        for byte in buffer {
+            *byte = sensor.read_next();
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn init_checks_and_configures() {
+    let bus = Bus::new();
+    let mut imu = Lsm6dsm::new(bus.clone(), ADDRESS_SA0_LOW);
+    let config = Config { fifo: Fifo::Continuous { rate: DataRate::Hz52, watermark: 10 }, ..Config::default() };
+    imu.init(config).expect("initialised");
+    assert_eq!(imu.config(), &config);
+    assert_eq!(
+        bus.0.borrow().writes,
+        [
+            vec![0x0F],
+            vec![0x12, 0b0000_0101],
+            vec![0x12],
+            vec![0x12, 0b0100_0100],
+            vec![0x10, 0x44, 0x4C],
+            vec![0x0A, 0x00],
+            vec![0x06, 60, 0, 0b0000_1001, 0, 0b0001_1110],
+            // Every detector is off
+            vec![0x58, 0],
+            vec![0x5B, 0, 0, 0, 0],
+            vec![0x0D, 0],
+            vec![0x19, 0],
+        ]
+    );
+
+    let bus = Bus::new();
+    bus.0.borrow_mut().registers[0x0F] = 0x69;
+    assert_eq!(Lsm6dsm::new(bus, ADDRESS_SA0_LOW).init(Config::default()), Err("Not an LSM6DSM"));
+    assert_eq!(Lsm6dsm::new(Bus::new(), 0x6B).init(Config::default()), Err("IMU I2C write failed"));
+
+    // The FIFO cannot run faster than the sensors
+    let mut imu = Lsm6dsm::new(Bus::new(), ADDRESS_SA0_LOW);
+    let config = Config { fifo: Fifo::Continuous { rate: DataRate::Hz208, watermark: 10 }, ..Config::default() };
+    assert_eq!(imu.init(config), Err("FIFO rate must not exceed the sensor rates"));
+}
+
+#[test]
+fn burst_read_scales() {
+    let bus = Bus::new();
+    let mut imu = Lsm6dsm::new(bus.clone(), ADDRESS_SA0_LOW);
+    imu.init(Config { accel_scale: AccelScale::G4, gyro_scale: GyroScale::Dps250, ..Config::default() })
+        .expect("initialised");
+    // 30 °C, (1, -2, 250) dps and (0, 500, -1000) mg
+    bus.0.borrow_mut().set_words(0x20, &[1280, 114, -229, 28_571, 0, 4098, -8197]);
+
+    let sample = imu.read().expect("read");
+    assert_eq!(sample.temperature_cdeg, Some(3000));
+    assert_eq!(sample.angular_rate_mdps, Some([997, -2003, 249_996]));
+    assert_eq!(sample.acceleration_mg, [0, 499, -1000]);
+    assert_eq!(sample.acceleration_magnitude_mg(), 1117);
+
+    // Full scale at ±16 g, with the gyroscope off
+    imu.configure(Config { gyro_rate: DataRate::PowerDown, ..Config::default() }).expect("configured");
+    bus.0.borrow_mut().set_words(0x28, &[i16::MIN, i16::MAX, i16::MAX]);
+    let sample = imu.read().expect("read");
+    assert_eq!(sample.acceleration_mg, [-15_990, 15_990, 15_990]);
+    assert_eq!(sample.angular_rate_mdps, None);
+    assert_eq!(sample.acceleration_magnitude_mg(), 27_695);
+}
+
+#[test]
+fn drains_fifo_in_whole_samples() {
+    let bus = Bus::new();
+    let mut imu = Lsm6dsm::new(bus.clone(), ADDRESS_SA0_LOW);
+    let mut samples = [Sample::default(); 8];
+    assert_eq!(imu.drain_fifo(&mut samples), Err("IMU FIFO is off"));
+    let config = Config {
+        accel_scale: AccelScale::G2,
+        gyro_scale: GyroScale::Dps125,
+        fifo: Fifo::Continuous { rate: DataRate::Hz104, watermark: 3 },
+        ..Config::default()
+    };
+    imu.init(config).expect("initialised");
+    {
+        // The end of a sample that was partly read before, three whole samples, and half of another
+        let mut sensor = bus.0.borrow_mut();
+        sensor.pattern = 4;
+        sensor.fifo.extend([7, 7]);
+        for index in 1..=3 {
+            sensor.fifo.extend([0, 0, 100 * index, 0, 0, 2048 * index]);
+        }
+        sensor.fifo.extend([1, 2, 3]);
+    }
+
+    let mut two = [Sample::default(); 2];
+    assert_eq!(imu.drain_fifo(&mut two), Ok(2));
+    assert_eq!(two.map(|sample| sample.acceleration_mg[2]), [124, 249]);
+    assert_eq!(two.map(|sample| sample.angular_rate_mdps.map(|rate| rate[2])), [Some(437), Some(875)]);
+    assert_eq!(two[0].temperature_cdeg, None);
+
+    // The rest is read on the next call, leaving the partial sample
+    assert_eq!(imu.drain_fifo(&mut samples), Ok(1));
+    assert_eq!(samples[0].acceleration_mg, [0, 0, 374]);
+    assert_eq!(imu.fifo_status().map(|status| (status.unread_words, status.pattern)), Ok((3, 0)));
+    assert_eq!(imu.fifo_overruns(), 0);
+
+    // Overruns are counted
+    bus.0.borrow_mut().registers[0x3B] = 1 << 6;
+    imu.drain_fifo(&mut samples).expect("drained");
+    assert_eq!(imu.fifo_overruns(), 1);
+}
+
+#[test]
+fn detectors_raise_int1() {
+    let bus = Bus::new();
+    let mut imu = Lsm6dsm::new(bus.clone(), ADDRESS_SA0_LOW);
+    imu.init(Config::default()).expect("initialised");
+    bus.0.borrow_mut().writes.clear();
+
+    let detectors = Detectors {
+        wake_up: Some(WakeUp { threshold_mg: 3000, duration_samples: 2 }),
+        free_fall: Some(FreeFall { threshold: FreeFallThreshold::Mg312, duration_samples: 40 }),
+        tilt: true,
+        significant_motion: false,
+    };
+    imu.set_detectors(detectors).expect("configured");
+    assert_eq!(
+        bus.0.borrow().writes,
+        [
+            vec![0x58, 0b1000_0001],
+            // 3 g in 250 mg steps at ±16 g, and the sixth bit of the free-fall duration in WAKE_UP_DUR
+            vec![0x5B, 12, 0b1100_0000, 0b0100_0011, 0b0011_0010],
+            vec![0x0D, 0],
+            vec![0x19, 0b0000_1100],
+        ]
+    );
+
+    // A narrower range cannot represent the threshold, and the detectors stay as they were
+    let narrow = Config { accel_scale: AccelScale::G2, ..Config::default() };
+    assert_eq!(imu.configure(narrow), Err("Wake-up threshold out of range for the full scale"));
+    assert_eq!(imu.config(), &Config::default());
+    let too_high = Detectors { wake_up: Some(WakeUp { threshold_mg: 16_000, duration_samples: 0 }), ..detectors };
+    assert_eq!(imu.set_detectors(too_high), Err("Wake-up threshold out of range for the full scale"));
+    let slow = Config { accel_rate: DataRate::Hz12_5, gyro_rate: DataRate::PowerDown, ..Config::default() };
+    assert_eq!(imu.configure(slow), Err("Tilt and significant motion need the accelerometer at 26 Hz or more"));
+    assert_eq!(imu.detectors(), &detectors);
+
+    assert_eq!(imu.read_events(), Ok(Events::default()));
+    bus.0.borrow_mut().registers[0x1B] = 0b0010_1001;
+    bus.0.borrow_mut().registers[0x53] = 0b0010_0000;
+    let events = imu.read_events().expect("read");
+    assert_eq!(events, Events { wake_up: true, free_fall: true, tilt: true, significant_motion: false });
+    assert!(events.any());
+}
@@ -0,0 +1,125 @@
+//! Distances, bearings and local coordinates between positions
+//!
+//! The earth is modelled as a sphere, which is accurate to about 0.5 % and plenty for tracking a payload. Angle
+//! differences are taken in 1e-7 degrees before converting to `f32`, so nearby positions keep their full precision.
+
+use core::f32::consts::PI;
+use core::fmt::{self, Display, Formatter};
+use psat_telemetry::frame::Position;
+
+/// The mean earth radius in metres
+pub const EARTH_RADIUS_M: f32 = 6_371_008.8;
+
+/// Radians per 1e-7 degrees
+const RADIANS_PER_E7: f32 = PI / 180.0 / 1e7;
+
+/// The latitude in radians
+fn latitude_rad(position: &Position) -> f32 {
+    position.latitude_e7 as f32 * RADIANS_PER_E7
+}
+
+/// The latitude and longitude differences in radians, with the longitude difference across the antimeridian
+fn deltas_rad(from: &Position, to: &Position) -> (f32, f32) {
+    const FULL_CIRCLE_E7: i64 = 3_600_000_000;
+    const HALF_CIRCLE_E7: i64 = 1_800_000_000;
+
+    let latitude_e7 = i64::from(to.latitude_e7).saturating_sub(i64::from(from.latitude_e7));
+    let mut longitude_e7 = i64::from(to.longitude_e7).saturating_sub(i64::from(from.longitude_e7));
+    if longitude_e7 > HALF_CIRCLE_E7 {
+        longitude_e7 = longitude_e7.saturating_sub(FULL_CIRCLE_E7);
+    } else if longitude_e7 < -HALF_CIRCLE_E7 {
+        longitude_e7 = longitude_e7.saturating_add(FULL_CIRCLE_E7);
+    }
+    (latitude_e7 as f32 * RADIANS_PER_E7, longitude_e7 as f32 * RADIANS_PER_E7)
+}
+
+/// The great-circle distance in metres, ignoring the altitudes
+pub fn distance_m(from: &Position, to: &Position) -> f32 {
+    let (latitude, longitude) = deltas_rad(from, to);
+    // Haversine, which stays accurate for small distances
+    let (sin_latitude, sin_longitude) = (libm::sinf(latitude / 2.0), libm::sinf(longitude / 2.0));
+    let a = sin_latitude * sin_latitude
+        + libm::cosf(latitude_rad(from)) * libm::cosf(latitude_rad(to)) * sin_longitude * sin_longitude;
+    2.0 * EARTH_RADIUS_M * libm::atan2f(libm::sqrtf(a), libm::sqrtf((1.0 - a).max(0.0)))
+}
+
+/// The initial great-circle bearing in degrees clockwise from true north, from `0` up to `360`
+///
+/// # Note
+/// The bearing between identical positions is `0`.
+pub fn bearing_deg(from: &Position, to: &Position) -> f32 {
+    let (latitude, longitude) = deltas_rad(from, to);
+    let (from_latitude, to_latitude) = (latitude_rad(from), latitude_rad(to));
+    let sin_half_longitude = libm::sinf(longitude / 2.0);
+    let east = libm::sinf(longitude) * libm::cosf(to_latitude);
+    // `cos φ1 sin φ2 - sin φ1 cos φ2 cos Δλ`, rearranged to avoid cancellation between nearby positions
+    let north = libm::sinf(latitude)
+        + 2.0 * libm::sinf(from_latitude) * libm::cosf(to_latitude) * sin_half_longitude * sin_half_longitude;
+    let bearing = libm::atan2f(east, north).to_degrees();
+    if bearing < 0.0 {
+        bearing + 360.0
+    } else {
+        bearing
+    }
+}
+
+/// A distance and bearing, e.g. from the ground station to the payload
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range {
+    /// The great-circle distance in metres
+    pub distance_m: f32,
+    /// The initial bearing in degrees clockwise from true north
+    pub bearing_deg: f32,
+}
+impl Range {
+    /// The range from one position to another
+    pub fn between(from: &Position, to: &Position) -> Self {
+        Self { distance_m: distance_m(from, to), bearing_deg: bearing_deg(from, to) }
+    }
+}
+impl Display for Range {
+    /// Formats as e.g. `850 m at 312°` or `1.2 km at 047°`
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // Round before wrapping, so 359.7° shows as 000°
+        let bearing = (libm::roundf(self.bearing_deg) as u16) % 360;
+        if self.distance_m < 1000.0 {
+            write!(f, "{:.0} m at {bearing:03}°", self.distance_m)
+        } else {
+            write!(f, "{:.1} km at {bearing:03}°", self.distance_m / 1000.0)
+        }
+    }
+}
+
+/// A position in a local east, north, up frame in metres
+///
+/// # Accuracy
+/// The frame is a local flat-earth approximation around the origin, e.g. the launch point. Within 50 km of the origin
+/// the horizontal error stays below about 0.1 %. Up is the altitude difference, so it follows the curvature of the earth
+/// instead of the tangent plane.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Enu {
+    /// Metres to the east
+    pub east_m: f32,
+    /// Metres to the north
+    pub north_m: f32,
+    /// Metres up
+    pub up_m: f32,
+}
+impl Enu {
+    /// The position relative to the origin
+    pub fn between(origin: &Position, position: &Position) -> Self {
+        let (latitude, longitude) = deltas_rad(origin, position);
+        // Scale the longitude at the mean latitude, which keeps the error symmetric around the origin
+        let mean_latitude = latitude_rad(origin) + latitude / 2.0;
+        Self {
+            east_m: EARTH_RADIUS_M * longitude * libm::cosf(mean_latitude),
+            north_m: EARTH_RADIUS_M * latitude,
+            up_m: position.altitude_m() - origin.altitude_m(),
+        }
+    }
+
+    /// The horizontal distance from the origin
+    pub fn horizontal_m(&self) -> f32 {
+        libm::hypotf(self.east_m, self.north_m)
+    }
+}
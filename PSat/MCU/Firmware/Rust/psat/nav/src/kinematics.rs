@@ -0,0 +1,41 @@
+//! Velocities from consecutive fixes
+
+use crate::geodesy::{bearing_deg, distance_m};
+use psat_telemetry::frame::Position;
+
+/// A position at a point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    /// The position
+    pub position: Position,
+    /// The time in milliseconds since an arbitrary epoch, which may wrap around
+    pub time_ms: u32,
+}
+
+/// The average velocity between two samples
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Velocity {
+    /// The horizontal speed in metres per second
+    pub ground_speed_m_s: f32,
+    /// The vertical speed in metres per second, positive upwards
+    pub vertical_speed_m_s: f32,
+    /// The course over ground in degrees clockwise from true north
+    pub course_deg: f32,
+}
+impl Velocity {
+    /// The average velocity from one sample to a later one
+    ///
+    /// Returns `None` if both samples have the same time.
+    pub fn between(from: &Sample, to: &Sample) -> Option<Self> {
+        let elapsed_ms = to.time_ms.wrapping_sub(from.time_ms);
+        let 1.. = elapsed_ms else {
+            return None;
+        };
+        let elapsed_s = elapsed_ms as f32 / 1000.0;
+        Some(Self {
+            ground_speed_m_s: distance_m(&from.position, &to.position) / elapsed_s,
+            vertical_speed_m_s: (to.position.altitude_m() - from.position.altitude_m()) / elapsed_s,
+            course_deg: bearing_deg(&from.position, &to.position),
+        })
+    }
+}
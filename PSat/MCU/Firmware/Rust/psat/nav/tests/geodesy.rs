@@ -0,0 +1,87 @@
+//! Geodesy and kinematics tests against double-precision reference values
+
+use psat_nav::geodesy::{bearing_deg, distance_m, Enu, Range};
+use psat_nav::kinematics::{Sample, Velocity};
+use psat_telemetry::frame::Position;
+
+/// A position from decimal degrees, rounded exactly
+fn position(latitude: f64, longitude: f64, altitude_m: f32) -> Position {
+    Position {
+        latitude_e7: (latitude * 1e7).round() as i32,
+        longitude_e7: (longitude * 1e7).round() as i32,
+        altitude_dm: (altitude_m * 10.0) as i32,
+    }
+}
+
+/// Asserts that a value is within a tolerance
+fn assert_near(actual: f32, expected: f32, tolerance: f32) {
+    assert!((actual - expected).abs() <= tolerance, "{actual} is not within {tolerance} of {expected}");
+}
+
+/// Brisbane, the launch point for the tests
+fn launch() -> Position {
+    position(-27.4698, 153.0251, 27.5)
+}
+
+#[test]
+fn long_distances_and_bearings() {
+    let (london, new_york) = (position(51.5007, -0.1246, 0.0), position(40.6892, -74.0445, 0.0));
+    assert_near(distance_m(&london, &new_york), 5_574_848.2, 50.0);
+    assert_near(bearing_deg(&london, &new_york), 288.337, 0.01);
+
+    let sydney = position(-33.8688, 151.2093, 0.0);
+    assert_near(distance_m(&launch(), &sydney), 732_379.8, 10.0);
+    assert_near(bearing_deg(&launch(), &sydney), 193.260, 0.01);
+
+    // Across the antimeridian
+    let (west, east) = (position(10.0, 179.9, 0.0), position(10.0, -179.9, 0.0));
+    assert_near(distance_m(&west, &east), 21_901.2, 1.0);
+    assert_near(bearing_deg(&west, &east), 89.983, 0.01);
+    assert_near(bearing_deg(&east, &west), 270.017, 0.01);
+}
+
+#[test]
+fn short_distances_keep_precision() {
+    let north = position(-27.4698 + 0.000009, 153.0251, 27.5);
+    assert_near(distance_m(&launch(), &north), 1.0008, 0.005);
+    assert_near(bearing_deg(&launch(), &north), 0.0, 0.01);
+    assert_eq!((distance_m(&launch(), &launch()), bearing_deg(&launch(), &launch())), (0.0, 0.0));
+
+    let payload = position(-27.46, 153.035, 1200.0);
+    assert_near(distance_m(&launch(), &payload), 1463.40, 0.5);
+    assert_near(bearing_deg(&launch(), &payload), 41.874, 0.05);
+}
+
+#[test]
+fn formats_ranges() {
+    let range = Range::between(&launch(), &position(-27.46, 153.035, 1200.0));
+    assert_eq!(range.to_string(), "1.5 km at 042°");
+    assert_eq!(Range { distance_m: 850.4, bearing_deg: 312.2 }.to_string(), "850 m at 312°");
+    assert_eq!(Range { distance_m: 1200.0, bearing_deg: 47.0 }.to_string(), "1.2 km at 047°");
+    assert_eq!(Range { distance_m: 5.0, bearing_deg: 359.7 }.to_string(), "5 m at 000°");
+}
+
+#[test]
+fn local_frame() {
+    let enu = Enu::between(&launch(), &position(-27.46, 153.035, 1200.0));
+    // The reference distance and bearing, split into components
+    let (distance, bearing) = (1463.40_f32, 41.874_f32.to_radians());
+    assert_near(enu.east_m, distance * bearing.sin(), 1.0);
+    assert_near(enu.north_m, distance * bearing.cos(), 1.0);
+    assert_near(enu.up_m, 1172.5, 0.01);
+    assert_near(enu.horizontal_m(), distance, 1.0);
+    assert_eq!(Enu::between(&launch(), &launch()), Enu::default());
+}
+
+#[test]
+fn velocities() {
+    // Climbing at 5 m/s while drifting north-east
+    let from = Sample { position: launch(), time_ms: u32::MAX - 500 };
+    let to = Sample { position: position(-27.46, 153.035, 127.5), time_ms: 19_499 };
+    let velocity = Velocity::between(&from, &to).expect("time passed");
+    assert_near(velocity.vertical_speed_m_s, 5.0, 0.001);
+    assert_near(velocity.ground_speed_m_s, 1463.40 / 20.0, 0.05);
+    assert_near(velocity.course_deg, 41.874, 0.05);
+
+    assert_eq!(Velocity::between(&from, &from), None);
+}
@@ -0,0 +1,266 @@
+//! Field splitting and number parsing
+//!
+//! Numbers are parsed by hand instead of with `str::parse::<f32>`, which pulls several kilobytes of code into the
+//! MSP430 image. All fields may be empty, and numbers may have any amount of decimals.
+
+use crate::sentence::{Coordinates, Date, Time};
+
+/// The maximum amount of significant digits that are kept; further decimals are truncated
+const MAX_DIGITS: u8 = 12;
+
+/// A decimal number as an integer mantissa and the amount of decimals
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Decimal {
+    /// The digits without the decimal point
+    pub mantissa: i64,
+    /// The amount of digits after the decimal point
+    pub decimals: u8,
+}
+impl Decimal {
+    /// Parses `[-]digits[.digits]`
+    pub fn parse(field: &[u8]) -> Result<Self, &'static str> {
+        let (negative, digits) = match field {
+            [b'-', rest @ ..] => (true, rest),
+            digits => (false, digits),
+        };
+
+        let (mut mantissa, mut decimals, mut significant, mut seen_point, mut seen_digit) =
+            (0_i64, 0_u8, 0_u8, false, false);
+        for &char in digits {
+            match char {
+                b'.' if !seen_point => seen_point = true,
+                b'0'..=b'9' => {
+                    seen_digit = true;
+                    // Truncate excess decimals, but reject integers that are too large
+                    if significant >= MAX_DIGITS {
+                        let true = seen_point else {
+                            return Err("Number out of range");
+                        };
+                        continue;
+                    }
+                    // Leading zeros do not count towards the precision
+                    if mantissa != 0 || char != b'0' {
+                        significant = significant.saturating_add(1);
+                    }
+                    mantissa = mantissa.saturating_mul(10).saturating_add(i64::from(char.wrapping_sub(b'0')));
+                    if seen_point {
+                        decimals = decimals.saturating_add(1);
+                    }
+                }
+                _ => return Err("Invalid number"),
+            }
+        }
+
+        let true = seen_digit else {
+            return Err("Invalid number");
+        };
+        Ok(Self { mantissa: if negative { mantissa.saturating_neg() } else { mantissa }, decimals })
+    }
+
+    /// The number rounded towards zero to the given amount of decimals
+    pub fn scaled(&self, decimals: u8) -> i64 {
+        match decimals.checked_sub(self.decimals) {
+            Some(more) => self.mantissa.saturating_mul(pow10(more)),
+            None => self.mantissa.checked_div(pow10(self.decimals.saturating_sub(decimals))).unwrap_or_default(),
+        }
+    }
+
+    /// The number as a float
+    pub fn to_f32(self) -> f32 {
+        self.mantissa as f32 / pow10(self.decimals) as f32
+    }
+}
+
+/// `10^exponent`, saturating
+fn pow10(exponent: u8) -> i64 {
+    10_i64.saturating_pow(u32::from(exponent))
+}
+
+/// An iterator over the fields of a sentence
+///
+/// # Note
+/// Older NMEA versions omit trailing fields, e.g. the mode indicator, so fields after the end read as empty.
+#[derive(Debug, Clone)]
+pub(crate) struct Fields<'a> {
+    /// The remaining fields
+    rest: Option<&'a [u8]>,
+}
+impl<'a> Fields<'a> {
+    /// Creates a new iterator
+    pub const fn new(fields: &'a [u8]) -> Self {
+        Self { rest: Some(fields) }
+    }
+
+    /// The next field, or an empty field after the end
+    pub fn next_raw(&mut self) -> &'a [u8] {
+        let Some(rest) = self.rest else {
+            return &[];
+        };
+        match rest.iter().position(|&byte| byte == b',') {
+            Some(comma) => {
+                self.rest = rest.get(comma.saturating_add(1)..);
+                rest.get(..comma).unwrap_or_default()
+            }
+            None => {
+                self.rest = None;
+                rest
+            }
+        }
+    }
+
+    /// Skips the next field
+    pub fn skip(&mut self) {
+        self.next_raw();
+    }
+
+    /// The next field as a single character
+    pub fn next_char(&mut self) -> Result<Option<u8>, &'static str> {
+        match self.next_raw() {
+            [] => Ok(None),
+            [char] => Ok(Some(*char)),
+            _ => Err("Invalid character field"),
+        }
+    }
+
+    /// The next field as a decimal number
+    pub fn next_decimal(&mut self) -> Result<Option<Decimal>, &'static str> {
+        match self.next_raw() {
+            [] => Ok(None),
+            field => Decimal::parse(field).map(Some),
+        }
+    }
+
+    /// The next field as a float
+    pub fn next_f32(&mut self) -> Result<Option<f32>, &'static str> {
+        Ok(self.next_decimal()?.map(Decimal::to_f32))
+    }
+
+    /// The next field as an integer, which must not have decimals
+    pub fn next_int<T: TryFrom<i64>>(&mut self) -> Result<Option<T>, &'static str> {
+        let Some(decimal) = self.next_decimal()? else {
+            return Ok(None);
+        };
+        let 0 = decimal.decimals else {
+            return Err("Expected an integer");
+        };
+        T::try_from(decimal.mantissa).map(Some).map_err(|_| "Number out of range")
+    }
+
+    /// The next field as a UTC time `hhmmss[.sss]`
+    pub fn next_time(&mut self) -> Result<Option<Time>, &'static str> {
+        let field = self.next_raw();
+        let [h0, h1, m0, m1, s0, s1, fraction @ ..] = field else {
+            return match field {
+                [] => Ok(None),
+                _ => Err("Invalid time"),
+            };
+        };
+
+        let millis = match fraction {
+            [] => 0,
+            [b'.', ..] => Decimal::parse(fraction)?.scaled(3),
+            _ => return Err("Invalid time"),
+        };
+        let time = Time {
+            hours: two_digits(*h0, *h1)?,
+            minutes: two_digits(*m0, *m1)?,
+            seconds: two_digits(*s0, *s1)?,
+            millis: u16::try_from(millis).map_err(|_| "Invalid time")?,
+        };
+        // Allow a leap second
+        let true = (time.hours < 24 && time.minutes < 60 && time.seconds <= 60 && time.millis < 1000) else {
+            return Err("Invalid time");
+        };
+        Ok(Some(time))
+    }
+
+    /// The next field as a date `ddmmyy`
+    pub fn next_date(&mut self) -> Result<Option<Date>, &'static str> {
+        let field = self.next_raw();
+        let [d0, d1, m0, m1, y0, y1] = field else {
+            return match field {
+                [] => Ok(None),
+                _ => Err("Invalid date"),
+            };
+        };
+        let date = Date {
+            day: two_digits(*d0, *d1)?,
+            month: two_digits(*m0, *m1)?,
+            year: u16::from(two_digits(*y0, *y1)?).saturating_add(2000),
+        };
+        let true = ((1..=31).contains(&date.day) && (1..=12).contains(&date.month)) else {
+            return Err("Invalid date");
+        };
+        Ok(Some(date))
+    }
+
+    /// The next two fields as a latitude `ddmm.mmmm` and `N` or `S`, and the two after as a longitude `dddmm.mmmm` and
+    /// `E` or `W`
+    pub fn next_coordinates(&mut self) -> Result<Option<Coordinates>, &'static str> {
+        let latitude = angle(self.next_raw(), self.next_char()?, b'N', b'S', 90)?;
+        let longitude = angle(self.next_raw(), self.next_char()?, b'E', b'W', 180)?;
+        match (latitude, longitude) {
+            (Some(latitude_e7), Some(longitude_e7)) => Ok(Some(Coordinates { latitude_e7, longitude_e7 })),
+            (None, None) => Ok(None),
+            _ => Err("Incomplete position"),
+        }
+    }
+
+    /// The next two fields as a signed value, e.g. a magnetic variation and `E` or `W`
+    pub fn next_signed_f32(&mut self, positive: u8, negative: u8) -> Result<Option<f32>, &'static str> {
+        let value = self.next_f32()?;
+        match (value, self.next_char()?) {
+            (Some(value), Some(hemisphere)) if hemisphere == positive => Ok(Some(value)),
+            (Some(value), Some(hemisphere)) if hemisphere == negative => Ok(Some(-value)),
+            (None, None) => Ok(None),
+            _ => Err("Invalid direction"),
+        }
+    }
+}
+
+/// Parses a two-digit number
+fn two_digits(tens: u8, ones: u8) -> Result<u8, &'static str> {
+    match (tens, ones) {
+        (b'0'..=b'9', b'0'..=b'9') => {
+            Ok(tens.wrapping_sub(b'0').wrapping_mul(10).wrapping_add(ones.wrapping_sub(b'0')))
+        }
+        _ => Err("Invalid digits"),
+    }
+}
+
+/// Parses an angle `[d]ddmm.mmmm` with its hemisphere into 1e-7 degrees
+fn angle(
+    field: &[u8],
+    hemisphere: Option<u8>,
+    positive: u8,
+    negative: u8,
+    max: i64,
+) -> Result<Option<i32>, &'static str> {
+    let (field, hemisphere) = match (field, hemisphere) {
+        ([], None) => return Ok(None),
+        ([], Some(_)) | (_, None) => return Err("Incomplete position"),
+        (field, Some(hemisphere)) => (field, hemisphere),
+    };
+
+    // Minutes are kept with 6 decimals, which is about 2 mm
+    let decimal = Decimal::parse(field)?;
+    let true = decimal.mantissa >= 0 else {
+        return Err("Invalid position");
+    };
+    let value = decimal.scaled(6);
+    let (degrees, minutes_e6) = (value / 100_000_000, value % 100_000_000);
+    let true = minutes_e6 < 60_000_000 else {
+        return Err("Invalid position");
+    };
+
+    let e7 = degrees.saturating_mul(10_000_000).saturating_add(minutes_e6.saturating_mul(10) / 60);
+    let true = e7 <= max.saturating_mul(10_000_000) else {
+        return Err("Invalid position");
+    };
+    let e7 = i32::try_from(e7).map_err(|_| "Invalid position")?;
+    match hemisphere {
+        hemisphere if hemisphere == positive => Ok(Some(e7)),
+        hemisphere if hemisphere == negative => Ok(Some(e7.saturating_neg())),
+        _ => Err("Invalid hemisphere"),
+    }
+}
@@ -0,0 +1,122 @@
+//! Sentence framing and checksum validation
+
+/// The maximum sentence length, including `$` and the checksum but excluding `\r\n`
+///
+/// # Note
+/// The NMEA 0183 limit is 82 characters including `\r\n`; some MTK messages exceed it slightly, so allow a few more.
+pub const MAX_LEN: usize = 96;
+
+/// A sentence with a valid checksum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Raw<'a> {
+    /// The address field, e.g. `GPGGA` or `PMTK001`
+    pub address: &'a [u8],
+    /// The comma-separated data fields after the address, without the leading comma
+    pub fields: &'a [u8],
+}
+impl<'a> Raw<'a> {
+    /// Validates a single sentence
+    ///
+    /// # Format
+    /// A sentence is `$<address>,<fields>*<checksum>`, where the checksum is the XOR of all bytes between `$` and `*` as
+    /// two hex digits. Trailing `\r` and `\n` are ignored.
+    pub fn parse(line: &'a [u8]) -> Result<Self, &'static str> {
+        let line = line.trim_ascii_end();
+        let [b'$', rest @ ..] = line else {
+            return Err("Missing start delimiter");
+        };
+        let Some((body, [b'*', high, low])) = rest.split_last_chunk::<3>() else {
+            return Err("Missing checksum");
+        };
+        let (Some(high), Some(low)) = (hex_digit(*high), hex_digit(*low)) else {
+            return Err("Invalid checksum digits");
+        };
+        let true = checksum(body) == (high << 4) | low else {
+            return Err("Checksum mismatch");
+        };
+
+        // The address may be followed by fields, or stand alone
+        let (address, fields) = match body.iter().position(|&byte| byte == b',') {
+            Some(comma) => {
+                (body.get(..comma).unwrap_or_default(), body.get(comma.saturating_add(1)..).unwrap_or_default())
+            }
+            None => (body, &[][..]),
+        };
+        let true = (!address.is_empty() && address.iter().all(u8::is_ascii_alphanumeric)) else {
+            return Err("Invalid address");
+        };
+        Ok(Self { address, fields })
+    }
+}
+
+/// Computes the checksum over the bytes between `$` and `*`
+pub fn checksum(body: &[u8]) -> u8 {
+    body.iter().fold(0, |checksum, byte| checksum ^ byte)
+}
+
+/// Decodes an upper or lower case hex digit
+const fn hex_digit(char: u8) -> Option<u8> {
+    match char {
+        b'0'..=b'9' => Some(char.wrapping_sub(b'0')),
+        b'a'..=b'f' => Some(char.wrapping_sub(b'a').wrapping_add(10)),
+        b'A'..=b'F' => Some(char.wrapping_sub(b'A').wrapping_add(10)),
+        _ => None,
+    }
+}
+
+/// A streaming sentence framer
+///
+/// # Resynchronisation
+/// Bytes before a `$` are discarded, and a `$` always starts a new sentence, so a sentence that was cut short by a lost
+/// byte or a buffer overrun only costs that sentence.
+#[derive(Debug, Clone)]
+pub struct Parser {
+    /// The sentence buffer
+    buf: [u8; MAX_LEN],
+    /// The amount of buffered bytes, or `None` while waiting for a `$`
+    len: Option<usize>,
+}
+impl Parser {
+    /// Creates a new parser
+    pub const fn new() -> Self {
+        Self { buf: [0; MAX_LEN], len: None }
+    }
+
+    /// Feeds a byte, and returns the sentence once it is complete
+    ///
+    /// # Errors
+    /// Sentences that are too long or fail validation are returned as errors, so the caller can count them.
+    pub fn push(&mut self, byte: u8) -> Option<Result<Raw<'_>, &'static str>> {
+        match (byte, self.len) {
+            (b'$', _) => {
+                if let Some(first) = self.buf.first_mut() {
+                    *first = b'$';
+                }
+                self.len = Some(1);
+                None
+            }
+            (_, None) => None,
+            (b'\r' | b'\n', Some(len)) => {
+                self.len = None;
+                let line = self.buf.get(..len).unwrap_or_default();
+                Some(Raw::parse(line))
+            }
+            (byte, Some(len)) => match self.buf.get_mut(len) {
+                Some(slot) => {
+                    *slot = byte;
+                    self.len = Some(len.saturating_add(1));
+                    None
+                }
+                None => {
+                    self.len = None;
+                    Some(Err("Sentence too long"))
+                }
+            },
+        }
+    }
+}
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,327 @@
+//! PMTK commands for the MediaTek chipset in the CD-PA1616D
+//!
+//! Commands are encoded into a [`Packet`] that can be written to the UART as is. Most commands are answered with a
+//! `$PMTK001` [`Ack`]; restarts are answered with a `$PMTK010` startup message instead.
+
+use crate::field::Fields;
+use crate::parser::{checksum, Raw};
+
+/// The maximum length of an encoded packet, including `\r\n`
+pub const PACKET_LEN: usize = 64;
+
+/// The slowest fix interval the module supports, in milliseconds
+pub const MAX_FIX_INTERVAL_MS: u16 = 10_000;
+/// The fastest fix interval the module supports, in milliseconds
+///
+/// # Note
+/// Faster than 1 Hz needs more than 9600 baud to fit the default output.
+pub const MIN_FIX_INTERVAL_MS: u16 = 100;
+
+/// The highest output divider for a sentence in [`OutputRates`]
+pub const MAX_OUTPUT_RATE: u8 = 5;
+
+/// A UART baud rate supported by the module
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BaudRate {
+    /// 4800 baud
+    B4800,
+    /// 9600 baud, the factory default
+    B9600,
+    /// 14400 baud
+    B14400,
+    /// 19200 baud
+    B19200,
+    /// 38400 baud
+    B38400,
+    /// 57600 baud
+    B57600,
+    /// 115200 baud
+    B115200,
+}
+impl BaudRate {
+    /// The rate in bits per second
+    pub const fn bits_per_second(&self) -> u32 {
+        match self {
+            Self::B4800 => 4800,
+            Self::B9600 => 9600,
+            Self::B14400 => 14_400,
+            Self::B19200 => 19_200,
+            Self::B38400 => 38_400,
+            Self::B57600 => 57_600,
+            Self::B115200 => 115_200,
+        }
+    }
+}
+
+/// The navigation mode, which sets the dynamic model and altitude limits of the position filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum NavigationMode {
+    /// General purpose, for altitudes below 10 km
+    Normal = 0,
+    /// Running and walking, for speeds below 5 m/s
+    Fitness = 1,
+    /// High dynamics, for altitudes below 10 km
+    Aviation = 2,
+    /// Low dynamics, for altitudes up to 80 km
+    ///
+    /// # Note
+    /// The other modes lose lock above 18 km, so this is the mode to fly in.
+    Balloon = 3,
+}
+
+/// How often each sentence is output, in fixes
+///
+/// `0` disables a sentence, `1` outputs it with every fix and `n` outputs it every `n` fixes, up to
+/// [`MAX_OUTPUT_RATE`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OutputRates {
+    /// Geographic position
+    pub gll: u8,
+    /// Recommended minimum data
+    pub rmc: u8,
+    /// Course and speed over ground
+    pub vtg: u8,
+    /// Fix data
+    pub gga: u8,
+    /// Dilution of precision and active satellites
+    pub gsa: u8,
+    /// Satellites in view
+    pub gsv: u8,
+    /// Time and date
+    pub zda: u8,
+}
+impl OutputRates {
+    /// The output the module starts with: RMC, VTG, GGA and GSA with every fix, and GSV every 5 fixes
+    pub const FACTORY: Self = Self { gll: 0, rmc: 1, vtg: 1, gga: 1, gsa: 1, gsv: 5, zda: 0 };
+
+    /// The rates in the order of the `PMTK314` fields, where unused fields are reserved
+    const fn fields(&self) -> [u8; 19] {
+        [self.gll, self.rmc, self.vtg, self.gga, self.gsa, self.gsv, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, self.zda, 0]
+    }
+}
+
+/// A command for the module
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Does nothing except being acknowledged, to check that the module is alive
+    Test,
+    /// Restarts using all available data
+    HotStart,
+    /// Restarts without the ephemeris, keeping the almanac, time and last position
+    WarmStart,
+    /// Restarts without any navigation data
+    ColdStart,
+    /// A cold start that also resets the configuration to the factory defaults
+    FullColdStart,
+    /// Stops navigating until any byte is received
+    Standby,
+    /// Stops everything except the RTC and backup RAM, until the module is power cycled
+    ///
+    /// # Note
+    /// The navigation data only survives if `VBACKUP` stays powered, which allows a hot start afterwards.
+    Backup,
+    /// Sets the UART baud rate, effective immediately and without an acknowledgement
+    SetBaudRate(BaudRate),
+    /// Sets the fix interval in milliseconds, between [`MIN_FIX_INTERVAL_MS`] and [`MAX_FIX_INTERVAL_MS`]
+    SetFixInterval(u16),
+    /// Sets which sentences are output
+    SetOutput(OutputRates),
+    /// Sets the navigation mode
+    SetNavigationMode(NavigationMode),
+}
+impl Command {
+    /// The packet type number, as echoed in the [`Ack`]
+    pub const fn id(&self) -> u16 {
+        match self {
+            Self::Test => 0,
+            Self::HotStart => 101,
+            Self::WarmStart => 102,
+            Self::ColdStart => 103,
+            Self::FullColdStart => 104,
+            Self::Standby => 161,
+            Self::Backup => 225,
+            Self::SetBaudRate(_) => 251,
+            Self::SetFixInterval(_) => 220,
+            Self::SetOutput(_) => 314,
+            Self::SetNavigationMode(_) => 886,
+        }
+    }
+
+    /// Whether the module answers with an [`Ack`]
+    ///
+    /// Restarts answer with [`Response::Startup`] instead, and a new baud rate is in effect before an acknowledgement
+    /// could be sent.
+    pub const fn is_acknowledged(&self) -> bool {
+        !matches!(self, Self::HotStart | Self::WarmStart | Self::ColdStart | Self::FullColdStart | Self::SetBaudRate(_))
+    }
+
+    /// Encodes the command, including the checksum and `\r\n`
+    ///
+    /// # Errors
+    /// Fails if a fix interval or an output rate is out of range.
+    pub fn encode(&self) -> Result<Packet, &'static str> {
+        let mut packet = Packet::new(self.id());
+        match self {
+            Self::Test | Self::HotStart | Self::WarmStart | Self::ColdStart | Self::FullColdStart => (),
+            Self::Standby => packet.push_field(0),
+            Self::Backup => packet.push_field(4),
+            Self::SetBaudRate(baud) => packet.push_field(baud.bits_per_second()),
+            Self::SetFixInterval(millis) => {
+                let true = (MIN_FIX_INTERVAL_MS..=MAX_FIX_INTERVAL_MS).contains(millis) else {
+                    return Err("Fix interval out of range");
+                };
+                packet.push_field(u32::from(*millis));
+            }
+            Self::SetOutput(rates) => {
+                let fields = rates.fields();
+                let true = fields.iter().all(|&rate| rate <= MAX_OUTPUT_RATE) else {
+                    return Err("Output rate out of range");
+                };
+                for rate in fields {
+                    packet.push_field(u32::from(rate));
+                }
+            }
+            Self::SetNavigationMode(mode) => packet.push_field(u32::from(*mode as u8)),
+        }
+        packet.finish();
+        Ok(packet)
+    }
+}
+
+/// An encoded command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet {
+    /// The packet, starting with `$`
+    buf: [u8; PACKET_LEN],
+    /// The amount of bytes used
+    len: usize,
+}
+impl Packet {
+    /// Starts a packet with the `PMTK` address and the packet type
+    fn new(id: u16) -> Self {
+        let mut packet = Self { buf: [0; PACKET_LEN], len: 0 };
+        packet.push(b"$PMTK");
+        // The packet type is always three digits
+        packet.push_number(u32::from(id), 3);
+        packet
+    }
+
+    /// The packet, ready to be written to the UART
+    pub fn as_bytes(&self) -> &[u8] {
+        self.buf.get(..self.len).unwrap_or_default()
+    }
+
+    /// Appends a comma and a number
+    fn push_field(&mut self, value: u32) {
+        self.push(b",");
+        self.push_number(value, 1);
+    }
+
+    /// Appends the checksum and `\r\n`
+    fn finish(&mut self) {
+        const HEX: &[u8; 16] = b"0123456789ABCDEF";
+        let checksum = checksum(self.as_bytes().get(1..).unwrap_or_default());
+        let (high, low) = (usize::from(checksum >> 4), usize::from(checksum & 0xF));
+        self.push(&[b'*', HEX.get(high).copied().unwrap_or_default(), HEX.get(low).copied().unwrap_or_default()]);
+        self.push(b"\r\n");
+    }
+
+    /// Appends a number with at least `width` digits
+    fn push_number(&mut self, value: u32, width: usize) {
+        // u32::MAX has 10 digits
+        let mut digits = [b'0'; 10];
+        let (mut value, mut count) = (value, 0_usize);
+        for digit in digits.iter_mut().rev() {
+            #[allow(clippy::arithmetic_side_effects, reason = "The divisor is a non-zero constant")]
+            let (quotient, remainder) = (value / 10, value % 10);
+            *digit = b'0'.wrapping_add(remainder as u8);
+            value = quotient;
+            count = count.saturating_add(1);
+            if value == 0 && count >= width {
+                break;
+            }
+        }
+        self.push(digits.get(digits.len().saturating_sub(count)..).unwrap_or_default());
+    }
+
+    /// Appends bytes, which always fit as all packets are shorter than the buffer
+    fn push(&mut self, bytes: &[u8]) {
+        let end = self.len.saturating_add(bytes.len());
+        if let Some(slot) = self.buf.get_mut(self.len..end) {
+            slot.copy_from_slice(bytes);
+            self.len = end;
+        }
+    }
+}
+
+/// The outcome of a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum AckResult {
+    /// The packet was not understood
+    InvalidCommand = 0,
+    /// The packet is valid, but the command is not supported
+    Unsupported = 1,
+    /// The command is supported, but failed
+    Failed = 2,
+    /// The command succeeded
+    Succeeded = 3,
+}
+impl TryFrom<u8> for AckResult {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::InvalidCommand),
+            1 => Ok(Self::Unsupported),
+            2 => Ok(Self::Failed),
+            3 => Ok(Self::Succeeded),
+            _ => Err("Invalid acknowledgement flag"),
+        }
+    }
+}
+
+/// A `$PMTK001` acknowledgement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ack {
+    /// The packet type of the acknowledged command, see [`Command::id`]
+    pub command: u16,
+    /// The outcome
+    pub result: AckResult,
+}
+impl Ack {
+    /// Whether this acknowledges the given command
+    pub const fn is_for(&self, command: &Command) -> bool {
+        self.command == command.id()
+    }
+}
+
+/// A message from the module about a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Response {
+    /// A `$PMTK001` acknowledgement
+    Ack(Ack),
+    /// `$PMTK010,001`, sent once the module has (re)started
+    Startup,
+}
+impl TryFrom<Raw<'_>> for Response {
+    type Error = &'static str;
+
+    fn try_from(raw: Raw<'_>) -> Result<Self, Self::Error> {
+        let mut fields = Fields::new(raw.fields);
+        match raw.address {
+            b"PMTK001" => {
+                let (Some(command), Some(result)) = (fields.next_int::<u16>()?, fields.next_int::<u8>()?) else {
+                    return Err("Incomplete acknowledgement");
+                };
+                Ok(Self::Ack(Ack { command, result: AckResult::try_from(result)? }))
+            }
+            b"PMTK010" => match fields.next_int::<u16>()? {
+                Some(1) => Ok(Self::Startup),
+                _ => Err("Unsupported system message"),
+            },
+            _ => Err("Unsupported sentence"),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! A lock-free single-producer, single-consumer byte queue, to move received bytes from the UART interrupt to the main
+//! loop
+//!
+//! The MSP430 has no atomic read-modify-write instructions, but aligned loads and stores are atomic, which is all a
+//! single-producer, single-consumer queue needs. Every slot is an atomic byte, so the queue needs no `unsafe` code.
+
+use portable_atomic::{AtomicU16, AtomicU8, AtomicUsize, Ordering};
+
+/// A byte queue with `N - 1` usable slots
+///
+/// # Contract
+/// Only one context may push (e.g. the interrupt handler) and only one context may pop (e.g. the main loop). Pushing
+/// from two contexts at once does not cause undefined behaviour, but may lose or garble bytes.
+#[derive(Debug)]
+pub struct RingBuffer<const N: usize> {
+    /// The slots
+    buf: [AtomicU8; N],
+    /// The next slot to write, only written by the producer
+    head: AtomicUsize,
+    /// The next slot to read, only written by the consumer
+    tail: AtomicUsize,
+    /// The amount of bytes dropped because the queue was full, saturating, only written by the producer
+    dropped: AtomicU16,
+}
+impl<const N: usize> RingBuffer<N> {
+    /// Creates an empty queue
+    pub const fn new() -> Self {
+        Self {
+            buf: [const { AtomicU8::new(0) }; N],
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            dropped: AtomicU16::new(0),
+        }
+    }
+
+    /// The index after the given one
+    const fn next(index: usize) -> usize {
+        match index.checked_add(1) {
+            Some(next) if next < N => next,
+            _ => 0,
+        }
+    }
+
+    /// Appends a byte, or drops it if the queue is full
+    ///
+    /// # Note
+    /// Returns whether the byte has been queued.
+    pub fn push(&self, byte: u8) -> bool {
+        let head = self.head.load(Ordering::Relaxed);
+        let next = Self::next(head);
+        let (Some(slot), false) = (self.buf.get(head), next == self.tail.load(Ordering::Acquire)) else {
+            let dropped = self.dropped.load(Ordering::Relaxed);
+            self.dropped.store(dropped.saturating_add(1), Ordering::Relaxed);
+            return false;
+        };
+
+        // Publish the byte before the new head
+        slot.store(byte, Ordering::Relaxed);
+        self.head.store(next, Ordering::Release);
+        true
+    }
+
+    /// Takes the oldest byte
+    pub fn pop(&self) -> Option<u8> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        if tail == self.head.load(Ordering::Acquire) {
+            return None;
+        }
+
+        // Read the byte before releasing the slot
+        let byte = self.buf.get(tail)?.load(Ordering::Relaxed);
+        self.tail.store(Self::next(tail), Ordering::Release);
+        Some(byte)
+    }
+
+    /// The amount of queued bytes
+    pub fn len(&self) -> usize {
+        let (head, tail) = (self.head.load(Ordering::Acquire), self.tail.load(Ordering::Acquire));
+        match head.checked_sub(tail) {
+            Some(len) => len,
+            None => N.saturating_sub(tail).saturating_add(head),
+        }
+    }
+
+    /// Whether no bytes are queued
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The amount of bytes that have been dropped because the queue was full
+    pub fn dropped(&self) -> u16 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+impl<const N: usize> Default for RingBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
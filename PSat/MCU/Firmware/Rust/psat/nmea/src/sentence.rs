@@ -0,0 +1,447 @@
+//! Decoded sentences
+
+use crate::field::Fields;
+use crate::parser::Raw;
+
+/// The maximum amount of satellites in a GSV sentence
+pub const GSV_SATELLITES: usize = 4;
+/// The maximum amount of satellites in a GSA sentence
+pub const GSA_SATELLITES: usize = 12;
+
+/// A UTC time of day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Time {
+    /// The hours
+    pub hours: u8,
+    /// The minutes
+    pub minutes: u8,
+    /// The seconds, which may be `60` during a leap second
+    pub seconds: u8,
+    /// The milliseconds
+    pub millis: u16,
+}
+impl Time {
+    /// The milliseconds since midnight
+    pub fn millis_of_day(&self) -> u32 {
+        #[allow(clippy::arithmetic_side_effects, reason = "The fields are small, so this can never overflow")]
+        let millis = ((u32::from(self.hours) * 60 + u32::from(self.minutes)) * 60 + u32::from(self.seconds)) * 1000
+            + u32::from(self.millis);
+        millis
+    }
+}
+
+/// A UTC date
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Date {
+    /// The year, assuming the 21st century
+    pub year: u16,
+    /// The month, starting at `1`
+    pub month: u8,
+    /// The day of the month, starting at `1`
+    pub day: u8,
+}
+
+/// A position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Coordinates {
+    /// The latitude in 1e-7 degrees, positive to the north
+    pub latitude_e7: i32,
+    /// The longitude in 1e-7 degrees, positive to the east
+    pub longitude_e7: i32,
+}
+impl Coordinates {
+    /// The latitude in decimal degrees
+    pub fn latitude(&self) -> f32 {
+        self.latitude_e7 as f32 / 1e7
+    }
+    /// The longitude in decimal degrees
+    pub fn longitude(&self) -> f32 {
+        self.longitude_e7 as f32 / 1e7
+    }
+}
+
+/// The talker, i.e. the satellite system a sentence is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Talker {
+    /// `GP`: GPS only
+    Gps,
+    /// `GL`: GLONASS only
+    Glonass,
+    /// `GA`: Galileo only
+    Galileo,
+    /// `GB` or `BD`: BeiDou only
+    BeiDou,
+    /// `GN`: a combined solution from several systems
+    Gnss,
+}
+impl TryFrom<[u8; 2]> for Talker {
+    type Error = &'static str;
+
+    fn try_from(value: [u8; 2]) -> Result<Self, Self::Error> {
+        match &value {
+            b"GP" => Ok(Self::Gps),
+            b"GL" => Ok(Self::Glonass),
+            b"GA" => Ok(Self::Galileo),
+            b"GB" | b"BD" => Ok(Self::BeiDou),
+            b"GN" => Ok(Self::Gnss),
+            _ => Err("Unsupported talker"),
+        }
+    }
+}
+
+/// The GGA fix quality
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FixQuality {
+    /// No fix
+    Invalid = 0,
+    /// A standalone GPS fix
+    Gps = 1,
+    /// A differential GPS fix, e.g. with SBAS
+    Differential = 2,
+    /// A precise positioning service fix
+    Pps = 3,
+    /// A real time kinematic fix with fixed integers
+    Rtk = 4,
+    /// A real time kinematic fix with float integers
+    FloatRtk = 5,
+    /// A dead reckoning estimate
+    Estimated = 6,
+    /// A manually entered position
+    Manual = 7,
+    /// A simulated position
+    Simulation = 8,
+}
+impl FixQuality {
+    /// Whether the position is a measurement, i.e. not invalid, manual or simulated
+    ///
+    /// # Note
+    /// Dead reckoning estimates count, as the MTK chipsets briefly report them when the signal is lost.
+    pub const fn is_valid(&self) -> bool {
+        !matches!(self, Self::Invalid | Self::Manual | Self::Simulation)
+    }
+}
+impl TryFrom<u8> for FixQuality {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Invalid),
+            1 => Ok(Self::Gps),
+            2 => Ok(Self::Differential),
+            3 => Ok(Self::Pps),
+            4 => Ok(Self::Rtk),
+            5 => Ok(Self::FloatRtk),
+            6 => Ok(Self::Estimated),
+            7 => Ok(Self::Manual),
+            8 => Ok(Self::Simulation),
+            _ => Err("Invalid fix quality"),
+        }
+    }
+}
+
+/// The mode indicator of NMEA 2.3 and later
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// An autonomous fix
+    Autonomous,
+    /// A differential fix
+    Differential,
+    /// A dead reckoning estimate
+    Estimated,
+    /// A manually entered position
+    Manual,
+    /// A simulated position
+    Simulated,
+    /// No valid data
+    NotValid,
+}
+impl TryFrom<u8> for Mode {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            b'A' => Ok(Self::Autonomous),
+            b'D' => Ok(Self::Differential),
+            b'E' => Ok(Self::Estimated),
+            b'M' => Ok(Self::Manual),
+            b'S' => Ok(Self::Simulated),
+            b'N' => Ok(Self::NotValid),
+            _ => Err("Invalid mode"),
+        }
+    }
+}
+
+/// The GSA fix type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixType {
+    /// No fix
+    None,
+    /// A 2D fix without altitude
+    Fix2d,
+    /// A 3D fix
+    Fix3d,
+}
+
+/// GGA: the fix data
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gga {
+    /// The UTC time of the fix
+    pub time: Option<Time>,
+    /// The position
+    pub position: Option<Coordinates>,
+    /// The fix quality
+    pub quality: FixQuality,
+    /// The amount of satellites in use
+    pub satellites: Option<u8>,
+    /// The horizontal dilution of precision
+    pub hdop: Option<f32>,
+    /// The altitude above mean sea level in metres
+    pub altitude_m: Option<f32>,
+    /// The height of the geoid above the WGS84 ellipsoid in metres
+    pub geoid_separation_m: Option<f32>,
+}
+
+/// RMC: the recommended minimum data
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rmc {
+    /// The UTC time of the fix
+    pub time: Option<Time>,
+    /// Whether the data is valid (`A`) rather than a warning (`V`)
+    pub valid: bool,
+    /// The position
+    pub position: Option<Coordinates>,
+    /// The speed over ground in knots
+    pub speed_knots: Option<f32>,
+    /// The course over ground in degrees from true north
+    pub course_deg: Option<f32>,
+    /// The UTC date of the fix
+    pub date: Option<Date>,
+    /// The magnetic variation in degrees, positive to the east
+    pub magnetic_variation_deg: Option<f32>,
+    /// The mode
+    pub mode: Option<Mode>,
+}
+
+/// GSA: the satellites in use and the dilution of precision
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gsa {
+    /// Whether the receiver switches between 2D and 3D automatically (`A`) rather than manually (`M`)
+    pub automatic: bool,
+    /// The fix type
+    pub fix: FixType,
+    /// The PRNs of the satellites in use
+    pub satellites: [Option<u8>; GSA_SATELLITES],
+    /// The position dilution of precision
+    pub pdop: Option<f32>,
+    /// The horizontal dilution of precision
+    pub hdop: Option<f32>,
+    /// The vertical dilution of precision
+    pub vdop: Option<f32>,
+}
+
+/// A satellite in view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Satellite {
+    /// The PRN
+    pub prn: u8,
+    /// The elevation in degrees
+    pub elevation_deg: Option<u8>,
+    /// The azimuth in degrees from true north
+    pub azimuth_deg: Option<u16>,
+    /// The signal to noise ratio in dB-Hz, or `None` if the satellite is not tracked
+    pub snr_db: Option<u8>,
+}
+
+/// GSV: the satellites in view, spread over several sentences
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gsv {
+    /// The amount of sentences in this group
+    pub total: u8,
+    /// The number of this sentence, starting at `1`
+    pub number: u8,
+    /// The total amount of satellites in view
+    pub in_view: u8,
+    /// Up to four satellites
+    pub satellites: [Option<Satellite>; GSV_SATELLITES],
+}
+
+/// VTG: the course and speed over ground
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vtg {
+    /// The course over ground in degrees from true north
+    pub course_true_deg: Option<f32>,
+    /// The course over ground in degrees from magnetic north
+    pub course_magnetic_deg: Option<f32>,
+    /// The speed over ground in knots
+    pub speed_knots: Option<f32>,
+    /// The speed over ground in kilometres per hour
+    pub speed_kmh: Option<f32>,
+    /// The mode
+    pub mode: Option<Mode>,
+}
+
+/// GLL: the geographic position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gll {
+    /// The position
+    pub position: Option<Coordinates>,
+    /// The UTC time of the fix
+    pub time: Option<Time>,
+    /// Whether the data is valid (`A`) rather than a warning (`V`)
+    pub valid: bool,
+    /// The mode
+    pub mode: Option<Mode>,
+}
+
+/// The message of a sentence
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Message {
+    /// GGA: the fix data
+    Gga(Gga),
+    /// RMC: the recommended minimum data
+    Rmc(Rmc),
+    /// GSA: the satellites in use and the dilution of precision
+    Gsa(Gsa),
+    /// GSV: the satellites in view
+    Gsv(Gsv),
+    /// VTG: the course and speed over ground
+    Vtg(Vtg),
+    /// GLL: the geographic position
+    Gll(Gll),
+}
+
+/// A decoded sentence
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sentence {
+    /// The talker
+    pub talker: Talker,
+    /// The message
+    pub message: Message,
+}
+impl Sentence {
+    /// Validates and decodes a single sentence
+    pub fn parse(line: &[u8]) -> Result<Self, &'static str> {
+        Self::try_from(Raw::parse(line)?)
+    }
+}
+impl TryFrom<Raw<'_>> for Sentence {
+    type Error = &'static str;
+
+    fn try_from(raw: Raw<'_>) -> Result<Self, Self::Error> {
+        let [t0, t1, k0, k1, k2] = *raw.address else {
+            return Err("Unsupported sentence");
+        };
+        let talker = Talker::try_from([t0, t1])?;
+        let mut fields = Fields::new(raw.fields);
+        let message = match &[k0, k1, k2] {
+            b"GGA" => Message::Gga(gga(&mut fields)?),
+            b"RMC" => Message::Rmc(rmc(&mut fields)?),
+            b"GSA" => Message::Gsa(gsa(&mut fields)?),
+            b"GSV" => Message::Gsv(gsv(&mut fields)?),
+            b"VTG" => Message::Vtg(vtg(&mut fields)?),
+            b"GLL" => Message::Gll(gll(&mut fields)?),
+            _ => return Err("Unsupported sentence"),
+        };
+        Ok(Self { talker, message })
+    }
+}
+
+/// Decodes a status field, `A` for valid and `V` for a warning
+fn status(fields: &mut Fields) -> Result<bool, &'static str> {
+    match fields.next_char()? {
+        Some(b'A') => Ok(true),
+        Some(b'V') | None => Ok(false),
+        Some(_) => Err("Invalid status"),
+    }
+}
+
+/// Decodes a mode field
+fn mode(fields: &mut Fields) -> Result<Option<Mode>, &'static str> {
+    fields.next_char()?.map(Mode::try_from).transpose()
+}
+
+/// Decodes the GGA fields
+fn gga(fields: &mut Fields) -> Result<Gga, &'static str> {
+    let time = fields.next_time()?;
+    let position = fields.next_coordinates()?;
+    let quality = FixQuality::try_from(fields.next_int()?.unwrap_or(0))?;
+    let satellites = fields.next_int()?;
+    let hdop = fields.next_f32()?;
+    let altitude_m = fields.next_f32()?;
+    fields.skip(); // Altitude unit, always metres
+    let geoid_separation_m = fields.next_f32()?;
+    Ok(Gga { time, position, quality, satellites, hdop, altitude_m, geoid_separation_m })
+}
+
+/// Decodes the RMC fields
+fn rmc(fields: &mut Fields) -> Result<Rmc, &'static str> {
+    let time = fields.next_time()?;
+    let valid = status(fields)?;
+    let position = fields.next_coordinates()?;
+    let speed_knots = fields.next_f32()?;
+    let course_deg = fields.next_f32()?;
+    let date = fields.next_date()?;
+    let magnetic_variation_deg = fields.next_signed_f32(b'E', b'W')?;
+    let mode = mode(fields)?;
+    Ok(Rmc { time, valid, position, speed_knots, course_deg, date, magnetic_variation_deg, mode })
+}
+
+/// Decodes the GSA fields
+fn gsa(fields: &mut Fields) -> Result<Gsa, &'static str> {
+    let automatic = match fields.next_char()? {
+        Some(b'A') => true,
+        Some(b'M') | None => false,
+        Some(_) => return Err("Invalid selection mode"),
+    };
+    let fix = match fields.next_int::<u8>()? {
+        Some(1) | None => FixType::None,
+        Some(2) => FixType::Fix2d,
+        Some(3) => FixType::Fix3d,
+        Some(_) => return Err("Invalid fix type"),
+    };
+    let mut satellites = [None; GSA_SATELLITES];
+    for satellite in &mut satellites {
+        *satellite = fields.next_int()?;
+    }
+    let (pdop, hdop, vdop) = (fields.next_f32()?, fields.next_f32()?, fields.next_f32()?);
+    Ok(Gsa { automatic, fix, satellites, pdop, hdop, vdop })
+}
+
+/// Decodes the GSV fields
+fn gsv(fields: &mut Fields) -> Result<Gsv, &'static str> {
+    let (Some(total), Some(number), Some(in_view)) = (fields.next_int()?, fields.next_int()?, fields.next_int()?)
+    else {
+        return Err("Missing GSV header");
+    };
+    let mut satellites = [None; GSV_SATELLITES];
+    for satellite in &mut satellites {
+        let prn = fields.next_int()?;
+        let (elevation_deg, azimuth_deg, snr_db) = (fields.next_int()?, fields.next_int()?, fields.next_int()?);
+        *satellite = prn.map(|prn| Satellite { prn, elevation_deg, azimuth_deg, snr_db });
+    }
+    Ok(Gsv { total, number, in_view, satellites })
+}
+
+/// Decodes the VTG fields
+fn vtg(fields: &mut Fields) -> Result<Vtg, &'static str> {
+    let course_true_deg = fields.next_f32()?;
+    fields.skip(); // `T`
+    let course_magnetic_deg = fields.next_f32()?;
+    fields.skip(); // `M`
+    let speed_knots = fields.next_f32()?;
+    fields.skip(); // `N`
+    let speed_kmh = fields.next_f32()?;
+    fields.skip(); // `K`
+    let mode = mode(fields)?;
+    Ok(Vtg { course_true_deg, course_magnetic_deg, speed_knots, speed_kmh, mode })
+}
+
+/// Decodes the GLL fields
+fn gll(fields: &mut Fields) -> Result<Gll, &'static str> {
+    let position = fields.next_coordinates()?;
+    let time = fields.next_time()?;
+    let valid = status(fields)?;
+    let mode = mode(fields)?;
+    Ok(Gll { position, time, valid, mode })
+}
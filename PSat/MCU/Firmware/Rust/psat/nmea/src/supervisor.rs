@@ -0,0 +1,249 @@
+//! GPS power and time-to-first-fix supervision
+//!
+//! The [`Supervisor`] decides when the module should be powered, based on the fixes it reports and the time. It
+//! measures the time to first fix (TTFF) of every start, power cycles the module if it does not get a fix in time, and
+//! can duty cycle it to save power, e.g. while waiting for recovery after landing.
+//!
+//! All times are in milliseconds since an arbitrary epoch, and may wrap around.
+
+/// How long after the last fix the ephemeris is still good enough for a hot start
+pub const EPHEMERIS_LIFETIME_MS: u32 = 2 * 60 * 60 * 1000;
+
+/// Supervision settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// How long to wait for a fix after powering on before giving up
+    pub fix_timeout_ms: u32,
+    /// How long the module is kept off when power cycling, so it resets completely
+    pub power_cycle_off_ms: u32,
+    /// Whether `VBACKUP` stays powered while the module is off, which keeps the navigation data for warm and hot starts
+    pub backup_powered: bool,
+}
+impl Default for Config {
+    /// A cold start takes about 35 seconds in the open, so allow for a poor view of the sky
+    fn default() -> Self {
+        Self { fix_timeout_ms: 5 * 60 * 1000, power_cycle_off_ms: 2000, backup_powered: false }
+    }
+}
+
+/// The kind of start, depending on the navigation data the module still has
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartKind {
+    /// Without any navigation data
+    Cold,
+    /// With the time, almanac and last position, but an outdated ephemeris
+    Warm,
+    /// With a current ephemeris
+    Hot,
+}
+
+/// Something that happened, for logging
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// The module was powered on
+    PoweredOn(StartKind),
+    /// The first fix after a start
+    Fixed {
+        /// The kind of start
+        start: StartKind,
+        /// The time to first fix
+        ttff_ms: u32,
+    },
+    /// The fix was lost
+    Lost,
+    /// There was no fix in time, so the module was powered off
+    TimedOut,
+    /// The module was powered off until the next duty cycle
+    Sleeping,
+}
+
+/// The power state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Powered off for a while
+    Off {
+        /// When the module was powered off
+        since_ms: u32,
+        /// How long it stays off
+        duration_ms: u32,
+    },
+    /// Powered on and waiting for a fix
+    Acquiring {
+        /// When the start began
+        since_ms: u32,
+    },
+    /// Powered on with a fix
+    Tracking,
+}
+
+/// Decides when the GPS is powered, and measures its starts
+#[derive(Debug, Clone)]
+pub struct Supervisor {
+    /// The settings
+    config: Config,
+    /// The power state
+    state: State,
+    /// The kind of the current or last start
+    start: StartKind,
+    /// When the last fix was received
+    last_fix_ms: Option<u32>,
+    /// The TTFF of the last start that got a fix
+    ttff_ms: Option<u32>,
+    /// The amount of satellites in use
+    satellites: u8,
+    /// The amount of power cycles after timeouts, saturating
+    power_cycles: u16,
+    /// The time the module is off between fixes while duty cycling
+    duty_cycle_ms: Option<u32>,
+}
+impl Supervisor {
+    /// Creates a supervisor for a module that was just powered on without any navigation data
+    pub const fn new(config: Config, now_ms: u32) -> Self {
+        Self {
+            config,
+            state: State::Acquiring { since_ms: now_ms },
+            start: StartKind::Cold,
+            last_fix_ms: None,
+            ttff_ms: None,
+            satellites: 0,
+            power_cycles: 0,
+            duty_cycle_ms: None,
+        }
+    }
+
+    /// Whether the module should be powered
+    pub const fn is_powered(&self) -> bool {
+        !matches!(self.state, State::Off { .. })
+    }
+
+    /// Whether the module has a fix
+    pub const fn has_fix(&self) -> bool {
+        matches!(self.state, State::Tracking)
+    }
+
+    /// The kind of the current or last start
+    pub const fn start_kind(&self) -> StartKind {
+        self.start
+    }
+
+    /// The time to first fix of the last start that got a fix
+    pub const fn ttff_ms(&self) -> Option<u32> {
+        self.ttff_ms
+    }
+
+    /// The amount of satellites in use, or `0` while the module is off
+    pub const fn satellites(&self) -> u8 {
+        self.satellites
+    }
+
+    /// The amount of power cycles after timeouts
+    pub const fn power_cycles(&self) -> u16 {
+        self.power_cycles
+    }
+
+    /// Whether the module is duty cycled
+    pub const fn is_duty_cycled(&self) -> bool {
+        self.duty_cycle_ms.is_some()
+    }
+
+    /// Feeds the fix status of a GGA sentence
+    pub fn update(&mut self, now_ms: u32, fix: bool, satellites: u8) -> Option<Event> {
+        if !self.is_powered() {
+            return None;
+        }
+        self.satellites = satellites;
+        match (self.state, fix) {
+            (State::Acquiring { since_ms }, true) => {
+                let ttff_ms = now_ms.wrapping_sub(since_ms);
+                self.ttff_ms = Some(ttff_ms);
+                self.last_fix_ms = Some(now_ms);
+                self.state = State::Tracking;
+                // One fix is all we wanted this cycle
+                if let Some(duration_ms) = self.duty_cycle_ms {
+                    self.power_off(now_ms, duration_ms);
+                }
+                Some(Event::Fixed { start: self.start, ttff_ms })
+            }
+            (State::Tracking, true) => {
+                self.last_fix_ms = Some(now_ms);
+                None
+            }
+            (State::Tracking, false) => {
+                // The module keeps its ephemeris while it is powered
+                self.state = State::Acquiring { since_ms: now_ms };
+                self.start = StartKind::Hot;
+                Some(Event::Lost)
+            }
+            (State::Acquiring { .. } | State::Off { .. }, false) | (State::Off { .. }, true) => None,
+        }
+    }
+
+    /// Checks the timeouts, and returns what happened
+    pub fn poll(&mut self, now_ms: u32) -> Option<Event> {
+        match self.state {
+            State::Acquiring { since_ms } if now_ms.wrapping_sub(since_ms) >= self.config.fix_timeout_ms => {
+                // Try again next cycle when duty cycling, otherwise reset the module as it may be stuck
+                let duration_ms = match self.duty_cycle_ms {
+                    Some(duration_ms) => duration_ms,
+                    None => {
+                        self.power_cycles = self.power_cycles.saturating_add(1);
+                        self.config.power_cycle_off_ms
+                    }
+                };
+                self.power_off(now_ms, duration_ms);
+                Some(Event::TimedOut)
+            }
+            State::Off { since_ms, duration_ms } if now_ms.wrapping_sub(since_ms) >= duration_ms => {
+                self.start = self.next_start(now_ms);
+                self.state = State::Acquiring { since_ms: now_ms };
+                Some(Event::PoweredOn(self.start))
+            }
+            State::Acquiring { .. } | State::Off { .. } | State::Tracking => None,
+        }
+    }
+
+    /// Starts or stops duty cycling, with the module off for `off_ms` between fixes
+    ///
+    /// A module that already has a fix is powered off right away.
+    pub fn set_duty_cycle(&mut self, now_ms: u32, off_ms: Option<u32>) -> Option<Event> {
+        if self.duty_cycle_ms == off_ms {
+            return None;
+        }
+        self.duty_cycle_ms = off_ms;
+        match (self.state, off_ms) {
+            (State::Tracking, Some(duration_ms)) => {
+                self.power_off(now_ms, duration_ms);
+                Some(Event::Sleeping)
+            }
+            // Power on at once when continuous tracking is wanted again
+            (State::Off { .. }, None) => self.wake(now_ms),
+            _ => None,
+        }
+    }
+
+    /// Powers the module on at once for a fix, e.g. when the ground station asks for one between duty cycles
+    ///
+    /// A duty cycled module is powered off again after the fix, as usual.
+    pub fn wake(&mut self, now_ms: u32) -> Option<Event> {
+        let State::Off { since_ms, .. } = self.state else {
+            return None;
+        };
+        self.state = State::Off { since_ms, duration_ms: 0 };
+        self.poll(now_ms)
+    }
+
+    /// Powers the module off for a while
+    fn power_off(&mut self, now_ms: u32, duration_ms: u32) {
+        self.state = State::Off { since_ms: now_ms, duration_ms };
+        self.satellites = 0;
+    }
+
+    /// The kind of start after powering on
+    fn next_start(&self, now_ms: u32) -> StartKind {
+        match (self.config.backup_powered, self.last_fix_ms) {
+            (false, _) | (true, None) => StartKind::Cold,
+            (true, Some(last_fix_ms)) if now_ms.wrapping_sub(last_fix_ms) < EPHEMERIS_LIFETIME_MS => StartKind::Hot,
+            (true, Some(_)) => StartKind::Warm,
+        }
+    }
+}
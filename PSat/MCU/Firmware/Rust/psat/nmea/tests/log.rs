@@ -0,0 +1,120 @@
+//! Streams recorded CD-PA1616D output through the parser
+//!
+//! The logs follow the module's MTK output format: `cd-pa1616d-gps.log` holds the GPS-only example output from the
+//! datasheet, and `cd-pa1616d-gnss.log` a cold start to a 3D fix with the default GPS + GLONASS output.
+
+use psat_nmea::parser::Parser;
+use psat_nmea::sentence::{Coordinates, FixQuality, FixType, Message, Sentence, Talker};
+
+/// The GPS-only log
+const GPS_LOG: &[u8] = include_bytes!("logs/cd-pa1616d-gps.log");
+/// The GPS + GLONASS log
+const GNSS_LOG: &[u8] = include_bytes!("logs/cd-pa1616d-gnss.log");
+
+/// Streams a log byte by byte and returns the decoded sentences and the errors
+fn stream(log: &[u8]) -> (Vec<Sentence>, Vec<&'static str>) {
+    let mut parser = Parser::new();
+    let (mut sentences, mut errors) = (Vec::new(), Vec::new());
+    for &byte in log {
+        match parser.push(byte).map(|raw| raw.and_then(Sentence::try_from)) {
+            Some(Ok(sentence)) => sentences.push(sentence),
+            Some(Err(error)) => errors.push(error),
+            None => (),
+        }
+    }
+    (sentences, errors)
+}
+
+/// The short name of a message
+fn kind(message: &Message) -> &'static str {
+    match message {
+        Message::Gga(_) => "GGA",
+        Message::Rmc(_) => "RMC",
+        Message::Gsa(_) => "GSA",
+        Message::Gsv(_) => "GSV",
+        Message::Vtg(_) => "VTG",
+        Message::Gll(_) => "GLL",
+    }
+}
+
+#[test]
+fn gps_log() {
+    let (sentences, errors) = stream(GPS_LOG);
+    assert_eq!(errors, [] as [&str; 0]);
+    let kinds: Vec<_> = sentences.iter().map(|sentence| kind(&sentence.message)).collect();
+    assert_eq!(kinds, ["GGA", "GSA", "GSV", "GSV", "GSV", "RMC", "VTG", "GLL"]);
+    assert!(sentences.iter().all(|sentence| sentence.talker == Talker::Gps));
+
+    // All satellites in view are reported across the GSV group
+    let in_view: usize = sentences
+        .iter()
+        .filter_map(|sentence| match sentence.message {
+            Message::Gsv(gsv) => Some(gsv.satellites.iter().flatten().count()),
+            _ => None,
+        })
+        .sum();
+    assert_eq!(in_view, 9);
+}
+
+#[test]
+fn gnss_cold_start_log() {
+    let (sentences, errors) = stream(GNSS_LOG);
+
+    // The boot messages are proprietary and skipped
+    assert_eq!(errors, ["Unsupported sentence"; 2]);
+
+    let ggas: Vec<_> = sentences
+        .iter()
+        .filter_map(|sentence| match sentence.message {
+            Message::Gga(gga) => Some(gga),
+            _ => None,
+        })
+        .collect();
+    let qualities: Vec<_> = ggas.iter().map(|gga| gga.quality).collect();
+    assert_eq!(
+        qualities,
+        [
+            FixQuality::Invalid,
+            FixQuality::Invalid,
+            FixQuality::Invalid,
+            FixQuality::Gps,
+            FixQuality::Gps,
+            FixQuality::Gps
+        ]
+    );
+    assert!(ggas.iter().all(|gga| gga.position.is_some() == gga.quality.is_valid()));
+
+    // The last fix has an extra digit of precision
+    let last = ggas.last().expect("no GGA sentences");
+    assert_eq!(last.position, Some(Coordinates { latitude_e7: -274_697_908, longitude_e7: 1_530_251_041 }));
+    assert_eq!(
+        (last.satellites, last.hdop, last.altitude_m, last.geoid_separation_m),
+        (Some(7), Some(1.12), Some(45.0), Some(38.2))
+    );
+
+    // GPS and GLONASS each report their own GSA sentence under the GN talker
+    let fixes: Vec<_> = sentences
+        .iter()
+        .filter_map(|sentence| match sentence.message {
+            Message::Gsa(gsa) => Some(gsa.fix),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(fixes.iter().filter(|&&fix| fix == FixType::Fix3d).count(), 6);
+    assert!(sentences.iter().any(|sentence| sentence.talker == Talker::Glonass));
+}
+
+#[test]
+fn resynchronises_after_noise() {
+    // Boot garbage, a sentence cut short by a lost line ending, a bit error and an overlong line
+    let mut log = b"\xff\x00garbage$GNVTG,0.00,T,,M,0.00,N,0.00".to_vec();
+    log.extend_from_slice(b"$GNGSA,A,1,,,,,,,,,,,,,,,*01\r\n");
+    log.extend_from_slice(b"$GP");
+    log.extend_from_slice(&[b'A'; 120]);
+    log.extend_from_slice(b"\r\n");
+    log.extend_from_slice(GPS_LOG);
+
+    let (sentences, errors) = stream(&log);
+    assert_eq!(errors, ["Checksum mismatch", "Sentence too long"]);
+    assert_eq!(sentences.len(), 8);
+}
@@ -0,0 +1,82 @@
+//! PMTK command encoding and response decoding tests, checked against the packets in the MTK command reference
+
+use psat_nmea::parser::{Parser, Raw};
+use psat_nmea::pmtk::{Ack, AckResult, BaudRate, Command, NavigationMode, OutputRates, Response};
+
+/// Encodes a command as a string
+fn encode(command: Command) -> String {
+    let packet = command.encode().expect("valid command");
+    String::from_utf8(packet.as_bytes().to_vec()).expect("ASCII packet")
+}
+
+/// Decodes a response
+fn response(line: &str) -> Result<Response, &'static str> {
+    Response::try_from(Raw::parse(line.as_bytes())?)
+}
+
+#[test]
+fn encodes_commands() {
+    let cases = [
+        (Command::Test, "$PMTK000*32\r\n"),
+        (Command::HotStart, "$PMTK101*32\r\n"),
+        (Command::ColdStart, "$PMTK103*30\r\n"),
+        (Command::FullColdStart, "$PMTK104*37\r\n"),
+        (Command::Standby, "$PMTK161,0*28\r\n"),
+        (Command::Backup, "$PMTK225,4*2F\r\n"),
+        (Command::SetBaudRate(BaudRate::B38400), "$PMTK251,38400*27\r\n"),
+        (Command::SetFixInterval(1000), "$PMTK220,1000*1F\r\n"),
+        (Command::SetNavigationMode(NavigationMode::Balloon), "$PMTK886,3*2B\r\n"),
+        (Command::SetOutput(OutputRates::FACTORY), "$PMTK314,0,1,1,1,1,5,0,0,0,0,0,0,0,0,0,0,0,0,0*2D\r\n"),
+        (
+            Command::SetOutput(OutputRates { gga: 1, ..OutputRates::default() }),
+            "$PMTK314,0,0,0,1,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0*29\r\n",
+        ),
+    ];
+    for (command, expected) in cases {
+        assert_eq!(encode(command), expected, "{command:?}");
+        // Our own parser must accept what we send
+        assert!(Raw::parse(expected.as_bytes()).is_ok());
+    }
+}
+
+#[test]
+fn rejects_out_of_range_arguments() {
+    assert_eq!(Command::SetFixInterval(99).encode(), Err("Fix interval out of range"));
+    assert_eq!(Command::SetFixInterval(10_001).encode(), Err("Fix interval out of range"));
+    assert!(Command::SetFixInterval(100).encode().is_ok());
+    assert_eq!(
+        Command::SetOutput(OutputRates { gsv: 6, ..OutputRates::FACTORY }).encode(),
+        Err("Output rate out of range")
+    );
+}
+
+#[test]
+fn decodes_responses() {
+    let ack = Ack { command: 886, result: AckResult::Succeeded };
+    assert_eq!(response("$PMTK001,886,3*36"), Ok(Response::Ack(ack)));
+    assert!(ack.is_for(&Command::SetNavigationMode(NavigationMode::Normal)));
+    assert!(!ack.is_for(&Command::Standby));
+
+    assert_eq!(response("$PMTK001,314,1*34"), Ok(Response::Ack(Ack { command: 314, result: AckResult::Unsupported })));
+    assert_eq!(response("$PMTK010,001*2E"), Ok(Response::Startup));
+    assert_eq!(response("$GPGGA,,,,,,0,,,,,,,,*66"), Err("Unsupported sentence"));
+}
+
+#[test]
+fn finds_ack_in_stream() {
+    let stream = b"$GPGSA,A,1,,,,,,,,,,,,,,,*1E\r\n$PMTK001,220,3*30\r\n$GPGSA,A,1,,,,,,,,,,,,,,,*1E\r\n";
+    let mut parser = Parser::new();
+    let responses: Vec<_> = stream
+        .iter()
+        .filter_map(|&byte| parser.push(byte).and_then(|raw| Response::try_from(raw.ok()?).ok()))
+        .collect();
+    assert_eq!(responses, [Response::Ack(Ack { command: 220, result: AckResult::Succeeded })]);
+}
+
+#[test]
+fn acknowledgement_expectations() {
+    assert!(Command::SetNavigationMode(NavigationMode::Balloon).is_acknowledged());
+    assert!(Command::Standby.is_acknowledged());
+    assert!(!Command::ColdStart.is_acknowledged());
+    assert!(!Command::SetBaudRate(BaudRate::B115200).is_acknowledged());
+}
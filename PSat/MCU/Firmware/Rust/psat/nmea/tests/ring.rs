@@ -0,0 +1,66 @@
+//! Ring buffer tests
+
+use psat_nmea::parser::Parser;
+use psat_nmea::ring::RingBuffer;
+use psat_nmea::sentence::Sentence;
+use std::sync::Arc;
+use std::thread;
+
+#[test]
+fn fifo_order_and_wrap_around() {
+    let ring = RingBuffer::<4>::new();
+    assert!(ring.is_empty());
+    for round in 0..10_u8 {
+        assert!(ring.push(round) && ring.push(round.wrapping_add(100)));
+        assert_eq!(ring.len(), 2);
+        assert_eq!((ring.pop(), ring.pop(), ring.pop()), (Some(round), Some(round.wrapping_add(100)), None));
+    }
+    assert_eq!(ring.dropped(), 0);
+}
+
+#[test]
+fn drops_bytes_when_full() {
+    let ring = RingBuffer::<4>::new();
+    assert!(ring.push(1) && ring.push(2) && ring.push(3));
+    assert!(!ring.push(4));
+    assert_eq!((ring.len(), ring.dropped()), (3, 1));
+
+    // The queued bytes are kept, and there is room again after a pop
+    assert_eq!(ring.pop(), Some(1));
+    assert!(ring.push(5));
+    assert_eq!([ring.pop(), ring.pop(), ring.pop()], [Some(2), Some(3), Some(5)]);
+}
+
+#[test]
+fn concurrent_producer_and_consumer() {
+    // A sentence stream pushed from another thread, like the UART interrupt, arrives intact
+    const LOG: &[u8] = include_bytes!("logs/cd-pa1616d-gps.log");
+    const REPEATS: usize = 200;
+    let ring = Arc::new(RingBuffer::<64>::new());
+
+    let producer = {
+        let ring = Arc::clone(&ring);
+        thread::spawn(move || {
+            for &byte in LOG.iter().cycle().take(LOG.len() * REPEATS) {
+                while !ring.push(byte) {
+                    thread::yield_now();
+                }
+            }
+        })
+    };
+
+    let (mut parser, mut sentences, mut received) = (Parser::new(), 0, 0);
+    while received < LOG.len() * REPEATS {
+        let Some(byte) = ring.pop() else {
+            thread::yield_now();
+            continue;
+        };
+        received += 1;
+        if let Some(sentence) = parser.push(byte) {
+            sentence.and_then(Sentence::try_from).expect("corrupted sentence");
+            sentences += 1;
+        }
+    }
+    producer.join().expect("producer panicked");
+    assert_eq!(sentences, 8 * REPEATS);
+}
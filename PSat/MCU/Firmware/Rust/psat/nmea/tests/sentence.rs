@@ -0,0 +1,181 @@
+//! Sentence decoding tests with the example output from the CD-PA1616D datasheet
+
+use psat_nmea::parser::Raw;
+use psat_nmea::sentence::{
+    Coordinates, Date, FixQuality, FixType, Gga, Gll, Message, Mode, Rmc, Satellite, Sentence, Talker, Time, Vtg,
+};
+
+/// The datasheet position, 23°07.1256'N 120°16.4438'E
+const POSITION: Coordinates = Coordinates { latitude_e7: 231_187_600, longitude_e7: 1_202_740_633 };
+/// The datasheet time
+const TIME: Time = Time { hours: 6, minutes: 49, seconds: 51, millis: 0 };
+
+/// Parses a sentence and returns its message
+fn message(line: &str) -> Message {
+    let sentence = Sentence::parse(line.as_bytes()).expect("valid sentence");
+    assert_eq!(sentence.talker, Talker::Gps);
+    sentence.message
+}
+
+#[test]
+fn gga() {
+    let gga = message("$GPGGA,064951.000,2307.1256,N,12016.4438,E,1,8,0.95,39.9,M,17.8,M,,*63\r\n");
+    let expected = Gga {
+        time: Some(TIME),
+        position: Some(POSITION),
+        quality: FixQuality::Gps,
+        satellites: Some(8),
+        hdop: Some(0.95),
+        altitude_m: Some(39.9),
+        geoid_separation_m: Some(17.8),
+    };
+    assert_eq!(gga, Message::Gga(expected));
+}
+
+#[test]
+fn rmc() {
+    let rmc = message("$GPRMC,064951.000,A,2307.1256,N,12016.4438,E,0.03,165.48,260406,3.05,W,A*2C");
+    let expected = Rmc {
+        time: Some(TIME),
+        valid: true,
+        position: Some(POSITION),
+        speed_knots: Some(0.03),
+        course_deg: Some(165.48),
+        date: Some(Date { year: 2006, month: 4, day: 26 }),
+        magnetic_variation_deg: Some(-3.05),
+        mode: Some(Mode::Autonomous),
+    };
+    assert_eq!(rmc, Message::Rmc(expected));
+}
+
+#[test]
+fn gsa_gsv_vtg_gll() {
+    let Message::Gsa(gsa) = message("$GPGSA,A,3,29,21,26,15,18,09,06,10,,,,,2.32,0.95,2.11*00") else {
+        panic!("not a GSA sentence");
+    };
+    let satellites = [29, 21, 26, 15, 18, 9, 6, 10].map(Some);
+    assert_eq!(
+        (gsa.automatic, gsa.fix, &gsa.satellites[..8], &gsa.satellites[8..]),
+        (true, FixType::Fix3d, &satellites[..], &[None; 4][..])
+    );
+    assert_eq!((gsa.pdop, gsa.hdop, gsa.vdop), (Some(2.32), Some(0.95), Some(2.11)));
+
+    let Message::Gsv(gsv) = message("$GPGSV,3,3,09,07,,,26*73") else {
+        panic!("not a GSV sentence");
+    };
+    assert_eq!((gsv.total, gsv.number, gsv.in_view), (3, 3, 9));
+    let satellite = Satellite { prn: 7, elevation_deg: None, azimuth_deg: None, snr_db: Some(26) };
+    assert_eq!(gsv.satellites, [Some(satellite), None, None, None]);
+
+    let vtg = message("$GPVTG,165.48,T,,M,0.03,N,0.06,K,A*36");
+    let expected = Vtg {
+        course_true_deg: Some(165.48),
+        course_magnetic_deg: None,
+        speed_knots: Some(0.03),
+        speed_kmh: Some(0.06),
+        mode: Some(Mode::Autonomous),
+    };
+    assert_eq!(vtg, Message::Vtg(expected));
+
+    let gll = message("$GPGLL,2307.1256,N,12016.4438,E,064951.000,A,A*5F");
+    assert_eq!(
+        gll,
+        Message::Gll(Gll { position: Some(POSITION), time: Some(TIME), valid: true, mode: Some(Mode::Autonomous) })
+    );
+}
+
+#[test]
+fn empty_fields_without_fix() {
+    let Ok(Sentence { talker: Talker::Gnss, message: Message::Gga(gga) }) =
+        Sentence::parse(b"$GNGGA,235947.800,,,,,0,0,,,M,,M,,*50")
+    else {
+        panic!("not a GGA sentence");
+    };
+    assert_eq!(gga.time, Some(Time { hours: 23, minutes: 59, seconds: 47, millis: 800 }));
+    assert_eq!(
+        (gga.position, gga.quality, gga.satellites, gga.hdop, gga.altitude_m),
+        (None, FixQuality::Invalid, Some(0), None, None)
+    );
+
+    // NMEA 2.2 sentences end before the mode field
+    let Ok(Sentence { message: Message::Rmc(rmc), .. }) = Sentence::parse(b"$GPRMC,,V,,,,,,,,,*31") else {
+        panic!("not an RMC sentence");
+    };
+    assert_eq!((rmc.time, rmc.valid, rmc.position, rmc.date, rmc.mode), (None, false, None, None, None));
+}
+
+#[test]
+fn all_fix_qualities() {
+    let qualities = [
+        (0, FixQuality::Invalid, false),
+        (1, FixQuality::Gps, true),
+        (2, FixQuality::Differential, true),
+        (3, FixQuality::Pps, true),
+        (4, FixQuality::Rtk, true),
+        (5, FixQuality::FloatRtk, true),
+        (6, FixQuality::Estimated, true),
+        (7, FixQuality::Manual, false),
+        (8, FixQuality::Simulation, false),
+    ];
+    for (digit, expected, valid) in qualities {
+        let body = format!("GPGGA,064951.000,3352.128,S,15112.558,W,{digit},4,1.8,12.0,M,22.1,M,,");
+        let checksum = body.bytes().fold(0, |checksum, byte| checksum ^ byte);
+        let Ok(Sentence { message: Message::Gga(gga), .. }) =
+            Sentence::parse(format!("${body}*{checksum:02X}").as_bytes())
+        else {
+            panic!("not a GGA sentence");
+        };
+        assert_eq!((gga.quality, gga.quality.is_valid()), (expected, valid));
+        assert_eq!(gga.position, Some(Coordinates { latitude_e7: -338_688_000, longitude_e7: -1_512_093_000 }));
+    }
+}
+
+#[test]
+fn variable_precision() {
+    /// Decodes the position of a GLL sentence
+    fn position(latitude: &str, longitude: &str) -> Option<Coordinates> {
+        let body = format!("GNGLL,{latitude},S,{longitude},W,,V,N");
+        let checksum = body.bytes().fold(0, |checksum, byte| checksum ^ byte);
+        match Sentence::parse(format!("${body}*{checksum:02X}").as_bytes()).expect("valid sentence").message {
+            Message::Gll(gll) => gll.position,
+            message => panic!("not a GLL sentence: {message:?}"),
+        }
+    }
+
+    let expected = Coordinates { latitude_e7: -274_698_000, longitude_e7: -1_530_251_000 };
+    assert_eq!(position("2728.188", "15301.506"), Some(expected));
+    assert_eq!(position("2728.1880", "15301.5060"), Some(expected));
+    assert_eq!(position("2728.188000", "15301.50600000"), Some(expected));
+    // A single digit of minutes is about 1.85 km
+    assert_eq!(
+        position("2728", "15301").map(|p| (p.latitude_e7, p.longitude_e7)),
+        Some((-274_666_666, -1_530_166_666))
+    );
+    assert!((Coordinates::latitude(&expected) - -27.4698).abs() < 1e-5);
+}
+
+#[test]
+fn rejects_invalid_sentences() {
+    let errors = [
+        ("GPGGA,064951.000*12", "Missing start delimiter"),
+        ("$GPGGA,064951.000", "Missing checksum"),
+        ("$GPGGA,064951.000*G1", "Invalid checksum digits"),
+        ("$GPGGA,064951.000,2307.1256,N,12016.4438,E,1,8,0.95,39.9,M,17.8,M,,*62", "Checksum mismatch"),
+        ("$PMTK011,MTKGPS*08", "Unsupported sentence"),
+        ("$GPTXT,01,01,02,ANTSTATUS=OPEN*2B", "Unsupported sentence"),
+        ("$QZGGA,,,,,,0,,,,,,,,*7A", "Unsupported talker"),
+        ("$GPGGA,256951.000,,,,,0,,,,,,,,*74", "Invalid time"),
+        ("$GPGGA,064951.000,2307.1256,N,,,1,,,,,,,,*10", "Incomplete position"),
+        ("$GPGGA,064951.000,9307.1256,N,12016.4438,E,1,,,,,,,,*4F", "Invalid position"),
+        ("$GPGGA,064951.000,2307.1256,X,12016.4438,E,1,,,,,,,,*52", "Invalid hemisphere"),
+        ("$GPGGA,064951.000,,,,,9,,,,,,,,*7E", "Invalid fix quality"),
+        ("$GPGGA,064951.000,,,,,1,8.5,,,,,,,*55", "Expected an integer"),
+    ];
+    for (line, error) in errors {
+        assert_eq!(Sentence::parse(line.as_bytes()).map(|_| ()), Err(error), "{line}");
+    }
+
+    // Proprietary sentences are still valid raw sentences
+    let raw = Raw::parse(b"$PMTK001,220,3*30\r\n").expect("valid sentence");
+    assert_eq!((raw.address, raw.fields), (&b"PMTK001"[..], &b"220,3"[..]));
+}
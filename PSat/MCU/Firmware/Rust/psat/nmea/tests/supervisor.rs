@@ -0,0 +1,101 @@
+//! GPS supervision tests over simulated starts
+
+use psat_nmea::supervisor::{Config, Event, StartKind, Supervisor, EPHEMERIS_LIFETIME_MS};
+
+/// A short timeout to keep the numbers readable
+const CONFIG: Config = Config { fix_timeout_ms: 60_000, power_cycle_off_ms: 2000, backup_powered: true };
+
+#[test]
+fn measures_cold_start() {
+    let mut gps = Supervisor::new(CONFIG, 1000);
+    assert!(gps.is_powered());
+    assert_eq!(gps.update(20_000, false, 3), None);
+    assert_eq!(gps.poll(20_000), None);
+    assert_eq!(gps.update(36_000, true, 6), Some(Event::Fixed { start: StartKind::Cold, ttff_ms: 35_000 }));
+    assert!(gps.has_fix());
+    assert_eq!((gps.ttff_ms(), gps.satellites()), (Some(35_000), 6));
+
+    // A lost fix restarts the clock, but with a current ephemeris
+    assert_eq!(gps.update(40_000, false, 2), Some(Event::Lost));
+    assert_eq!(gps.update(41_500, true, 5), Some(Event::Fixed { start: StartKind::Hot, ttff_ms: 1500 }));
+}
+
+#[test]
+fn power_cycles_after_timeout() {
+    let mut gps = Supervisor::new(CONFIG, 0);
+    assert_eq!(gps.poll(59_999), None);
+    assert_eq!(gps.poll(60_000), Some(Event::TimedOut));
+    assert!(!gps.is_powered());
+    assert_eq!((gps.power_cycles(), gps.satellites()), (1, 0));
+    // Fixes that were still in the receive buffer are ignored
+    assert_eq!(gps.update(60_500, true, 4), None);
+
+    assert_eq!(gps.poll(61_999), None);
+    assert_eq!(gps.poll(62_000), Some(Event::PoweredOn(StartKind::Cold)));
+    assert!(gps.is_powered());
+    assert_eq!(gps.update(100_000, true, 7), Some(Event::Fixed { start: StartKind::Cold, ttff_ms: 38_000 }));
+}
+
+#[test]
+fn duty_cycles_after_landing() {
+    const OFF_MS: u32 = 10 * 60 * 1000;
+    let mut gps = Supervisor::new(CONFIG, 0);
+    gps.update(30_000, true, 8);
+    assert_eq!(gps.set_duty_cycle(40_000, Some(OFF_MS)), Some(Event::Sleeping));
+    assert_eq!(gps.set_duty_cycle(40_000, Some(OFF_MS)), None);
+    assert!(!gps.is_powered() && gps.is_duty_cycled());
+
+    // A hot start, as the backup domain kept the ephemeris
+    assert_eq!(gps.poll(40_000 + OFF_MS), Some(Event::PoweredOn(StartKind::Hot)));
+    assert_eq!(gps.update(42_000 + OFF_MS, true, 8), Some(Event::Fixed { start: StartKind::Hot, ttff_ms: 2000 }));
+    assert!(!gps.is_powered(), "powered off after a single fix");
+
+    // Without a fix, the module sleeps until the next cycle instead of being power cycled
+    let on_ms = 42_000 + 2 * OFF_MS;
+    assert_eq!(gps.poll(on_ms), Some(Event::PoweredOn(StartKind::Hot)));
+    assert_eq!(gps.poll(on_ms + CONFIG.fix_timeout_ms), Some(Event::TimedOut));
+    assert_eq!(gps.power_cycles(), 0);
+
+    // Stopping duty cycling powers on right away
+    assert_eq!(gps.set_duty_cycle(on_ms + 70_000, None), Some(Event::PoweredOn(StartKind::Hot)));
+    assert!(gps.is_powered());
+}
+
+#[test]
+fn wakes_for_a_fix_between_duty_cycles() {
+    const OFF_MS: u32 = 10 * 60 * 1000;
+    let mut gps = Supervisor::new(CONFIG, 0);
+    assert_eq!(gps.wake(1000), None, "already powered");
+    gps.update(30_000, true, 8);
+    gps.set_duty_cycle(40_000, Some(OFF_MS));
+
+    assert_eq!(gps.wake(100_000), Some(Event::PoweredOn(StartKind::Hot)));
+    assert!(gps.is_powered());
+    assert_eq!(gps.update(101_000, true, 7), Some(Event::Fixed { start: StartKind::Hot, ttff_ms: 1000 }));
+    assert!(!gps.is_powered() && gps.is_duty_cycled(), "still duty cycled after the fix");
+    assert_eq!(gps.poll(101_000 + OFF_MS), Some(Event::PoweredOn(StartKind::Hot)));
+}
+
+#[test]
+fn start_kind_depends_on_backup_and_age() {
+    let mut gps = Supervisor::new(CONFIG, 0);
+    gps.update(30_000, true, 8);
+    gps.set_duty_cycle(30_000, Some(EPHEMERIS_LIFETIME_MS));
+    assert_eq!(gps.poll(30_000 + EPHEMERIS_LIFETIME_MS), Some(Event::PoweredOn(StartKind::Warm)));
+
+    let mut gps = Supervisor::new(Config { backup_powered: false, ..CONFIG }, 0);
+    gps.update(30_000, true, 8);
+    gps.set_duty_cycle(30_000, Some(1000));
+    assert_eq!(gps.poll(31_000), Some(Event::PoweredOn(StartKind::Cold)));
+}
+
+#[test]
+fn survives_clock_wraparound() {
+    let start = u32::MAX - 10_000;
+    let mut gps = Supervisor::new(CONFIG, start);
+    assert_eq!(gps.poll(start.wrapping_add(59_999)), None);
+    assert_eq!(
+        gps.update(start.wrapping_add(20_000), true, 5),
+        Some(Event::Fixed { start: StartKind::Cold, ttff_ms: 20_000 })
+    );
+}
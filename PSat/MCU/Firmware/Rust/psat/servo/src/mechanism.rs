@@ -0,0 +1,169 @@
+//! The camera trigger and shutter reload mechanism
+//!
+//! Each action moves one servo out, holds it there for a moment, and brings it back to rest, after which the servo is
+//! turned off. The two servos share one current sense line, which is only ever fed to the servo that is moving. A jam
+//! in either direction stops the action, and leaves the servo off until the next action.
+
+use crate::servo::{Servo, Status};
+use embedded_hal::PwmPin;
+
+/// An action of the mechanism
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Presses the camera's shutter button with the trigger servo
+    TriggerShutter,
+    /// Winds the shutter on for the next picture with the shutter servo
+    Reload,
+}
+
+/// How an action ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The servo went out and back
+    Done(Action),
+    /// The servo stalled and was cut off
+    Jammed(Action),
+}
+
+/// The servo angles and hold times of the actions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// The trigger servo's rest angle, clear of the button
+    pub trigger_rest_deg: u16,
+    /// The trigger servo's angle with the button pressed
+    pub trigger_pressed_deg: u16,
+    /// How long the button is held
+    pub press_ms: u32,
+    /// The shutter servo's rest angle
+    pub shutter_rest_deg: u16,
+    /// The shutter servo's angle with the shutter wound on
+    pub shutter_wound_deg: u16,
+    /// How long the shutter servo waits at the end of its travel, so the winding catches
+    pub wind_ms: u32,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            trigger_rest_deg: 0,
+            trigger_pressed_deg: 45,
+            press_ms: 300,
+            shutter_rest_deg: 0,
+            shutter_wound_deg: 120,
+            wind_ms: 200,
+        }
+    }
+}
+
+/// Where an action is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    /// No action
+    Idle,
+    /// Moving out
+    Out(Action),
+    /// Holding out until the time
+    Hold(Action, u32),
+    /// Moving back to rest
+    Back(Action),
+}
+
+/// The trigger and shutter servos
+#[derive(Debug)]
+pub struct Mechanism<T, S> {
+    /// The angles and hold times
+    config: Config,
+    /// The servo that presses the shutter button
+    trigger: Servo<T>,
+    /// The servo that winds the shutter on
+    shutter: Servo<S>,
+    /// Where the current action is
+    phase: Phase,
+}
+impl<T: PwmPin<Duty = u16>, S: PwmPin<Duty = u16>> Mechanism<T, S> {
+    /// The mechanism at rest. The servos are assumed to be at their rest angles.
+    pub const fn new(config: Config, trigger: Servo<T>, shutter: Servo<S>) -> Self {
+        Self { config, trigger, shutter, phase: Phase::Idle }
+    }
+
+    /// Starts an action
+    ///
+    /// # Errors
+    /// Fails if another action is under way.
+    pub fn start(&mut self, action: Action, now_ms: u32) -> Result<(), &'static str> {
+        if self.phase != Phase::Idle {
+            return Err("Servo action already under way");
+        }
+        let angle_deg = match action {
+            Action::TriggerShutter => self.config.trigger_pressed_deg,
+            Action::Reload => self.config.shutter_wound_deg,
+        };
+        self.move_to(action, angle_deg, now_ms);
+        self.phase = Phase::Out(action);
+        Ok(())
+    }
+
+    /// Moves the action on, with the servo current if it was measured, and returns how it ended once it has
+    pub fn update(&mut self, now_ms: u32, current_ma: Option<u16>) -> Option<Outcome> {
+        let (Phase::Out(action) | Phase::Hold(action, _) | Phase::Back(action)) = self.phase else { return None };
+        let status = match action {
+            Action::TriggerShutter => self.trigger.update(now_ms, current_ma),
+            Action::Reload => self.shutter.update(now_ms, current_ma),
+        };
+        if status == Status::Stalled {
+            self.phase = Phase::Idle;
+            return Some(Outcome::Jammed(action));
+        }
+        match self.phase {
+            Phase::Out(action) if status == Status::Holding => {
+                let hold_ms = match action {
+                    Action::TriggerShutter => self.config.press_ms,
+                    Action::Reload => self.config.wind_ms,
+                };
+                self.phase = Phase::Hold(action, now_ms.wrapping_add(hold_ms));
+            }
+            // Compared as a difference, so a wrap of the millisecond clock is harmless
+            Phase::Hold(action, until_ms) if now_ms.wrapping_sub(until_ms) < u32::MAX / 2 => {
+                let angle_deg = match action {
+                    Action::TriggerShutter => self.config.trigger_rest_deg,
+                    Action::Reload => self.config.shutter_rest_deg,
+                };
+                self.move_to(action, angle_deg, now_ms);
+                self.phase = Phase::Back(action);
+            }
+            Phase::Back(action) if status == Status::Holding => {
+                match action {
+                    Action::TriggerShutter => self.trigger.power_off(),
+                    Action::Reload => self.shutter.power_off(),
+                }
+                self.phase = Phase::Idle;
+                return Some(Outcome::Done(action));
+            }
+            _ => (),
+        }
+        None
+    }
+
+    /// Whether an action is under way
+    #[must_use]
+    pub fn is_busy(&self) -> bool {
+        self.phase != Phase::Idle
+    }
+
+    /// The servo that presses the shutter button
+    pub const fn trigger(&self) -> &Servo<T> {
+        &self.trigger
+    }
+
+    /// The servo that winds the shutter on
+    pub const fn shutter(&self) -> &Servo<S> {
+        &self.shutter
+    }
+
+    /// Starts moving the servo of an action
+    fn move_to(&mut self, action: Action, angle_deg: u16, now_ms: u32) {
+        match action {
+            Action::TriggerShutter => self.trigger.move_to(angle_deg, now_ms),
+            Action::Reload => self.shutter.move_to(angle_deg, now_ms),
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! A hobby servo on a 50 Hz PWM output, moved smoothly and cut off when it stalls
+//!
+//! The servo has no position feedback, so its position is where it has been told to go. A servo that cannot get
+//! there, e.g. because the mechanism is jammed, draws its stall current until it is cut off, which is what the current
+//! sense detects.
+
+use embedded_hal::PwmPin;
+
+/// The PWM period
+pub const PERIOD_US: u32 = 20_000;
+
+/// The pulse widths of a servo at either end of its travel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Calibration {
+    /// The pulse width at 0°
+    pub min_pulse_us: u16,
+    /// The pulse width at the end of the travel
+    pub max_pulse_us: u16,
+    /// The travel
+    pub range_deg: u16,
+}
+impl Calibration {
+    /// The pulse width for an angle, which is clamped to the travel
+    #[must_use]
+    pub fn pulse_us(&self, angle_mdeg: u32) -> u16 {
+        let range_mdeg = u32::from(self.range_deg).saturating_mul(1000);
+        let span_us = i64::from(self.max_pulse_us).saturating_sub(i64::from(self.min_pulse_us));
+        let offset_us = span_us
+            .saturating_mul(i64::from(angle_mdeg.min(range_mdeg)))
+            .checked_div(i64::from(range_mdeg))
+            .unwrap_or(0);
+        u16::try_from(i64::from(self.min_pulse_us).saturating_add(offset_us)).unwrap_or(self.min_pulse_us)
+    }
+}
+impl Default for Calibration {
+    /// The nominal 180° servo. Most servos need calibrating against their end stops.
+    fn default() -> Self {
+        Self { min_pulse_us: 500, max_pulse_us: 2500, range_deg: 180 }
+    }
+}
+
+/// Motion and stall detection settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// How fast the servo is moved towards its target
+    pub speed_deg_per_s: u16,
+    /// The current above which the servo is stalling
+    pub stall_current_ma: u16,
+    /// How long the current must stay above the limit, so the inrush at the start of a move is ignored
+    pub stall_debounce_ms: u32,
+}
+impl Default for Config {
+    /// A third of the speed of a typical servo, well below its stall current
+    fn default() -> Self {
+        Self { speed_deg_per_s: 180, stall_current_ma: 600, stall_debounce_ms: 250 }
+    }
+}
+
+/// A linear current sense amplifier, whose output is `(current - offset) * gain`
+///
+/// # Note
+/// An amplifier with a negative offset clamps its output at 0 V below the offset current, so a reading of 0 V is
+/// converted to the offset current, the most the servo can be drawing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CurrentSense {
+    /// The voltage the amplifier's output would be offset by at no current, below 0 V
+    pub offset_mv: u16,
+    /// The inverse of the gain
+    pub ma_per_v: u16,
+}
+impl CurrentSense {
+    /// The current for an amplifier output voltage
+    #[must_use]
+    pub fn current_ma(&self, output_mv: u16) -> u16 {
+        let current_ma = (u32::from(output_mv).saturating_add(u32::from(self.offset_mv)))
+            .saturating_mul(u32::from(self.ma_per_v))
+            / 1000;
+        u16::try_from(current_ma).unwrap_or(u16::MAX)
+    }
+}
+
+/// What the servo is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// Not driven, so it can be back-driven and draws no current
+    Off,
+    /// Moving towards its target
+    Moving,
+    /// At its target, and holding it
+    Holding,
+    /// Cut off after stalling, until it is moved again
+    Stalled,
+}
+
+/// A servo on a PWM output
+#[derive(Debug)]
+pub struct Servo<P> {
+    /// The PWM output
+    pwm: P,
+    /// The pulse widths
+    calibration: Calibration,
+    /// The motion and stall settings
+    config: Config,
+    /// The commanded position
+    position_mdeg: u32,
+    /// Where it is moving to
+    target_mdeg: u32,
+    /// When the position was last updated
+    updated_ms: u32,
+    /// What it is doing
+    status: Status,
+    /// When the current went above the stall limit, or `None` while it is below
+    overcurrent_since_ms: Option<u32>,
+}
+impl<P: PwmPin<Duty = u16>> Servo<P> {
+    /// A servo that is off, and assumed to be at `angle_deg`, e.g. where it was left at rest
+    pub fn new(mut pwm: P, calibration: Calibration, config: Config, angle_deg: u16) -> Self {
+        pwm.disable();
+        let position_mdeg = u32::from(angle_deg).saturating_mul(1000);
+        Self {
+            pwm,
+            calibration,
+            config,
+            position_mdeg,
+            target_mdeg: position_mdeg,
+            updated_ms: 0,
+            status: Status::Off,
+            overcurrent_since_ms: None,
+        }
+    }
+
+    /// Starts moving towards `angle_deg`, driving the servo again if it was off or stalled
+    pub fn move_to(&mut self, angle_deg: u16, now_ms: u32) {
+        self.target_mdeg = u32::from(angle_deg.min(self.calibration.range_deg)).saturating_mul(1000);
+        if matches!(self.status, Status::Off | Status::Stalled) {
+            self.set_pulse();
+            self.pwm.enable();
+            self.overcurrent_since_ms = None;
+        }
+        self.updated_ms = now_ms;
+        self.status = Status::Moving;
+    }
+
+    /// Moves the servo on, and checks the current drawn since the last update if it was measured. A stalled servo is
+    /// cut off.
+    pub fn update(&mut self, now_ms: u32, current_ma: Option<u16>) -> Status {
+        if matches!(self.status, Status::Off | Status::Stalled) {
+            return self.status;
+        }
+        let elapsed_ms = now_ms.wrapping_sub(self.updated_ms);
+        self.updated_ms = now_ms;
+        // Degrees per second are millidegrees per millisecond
+        let step_mdeg = u32::from(self.config.speed_deg_per_s).saturating_mul(elapsed_ms);
+        self.position_mdeg = if self.target_mdeg >= self.position_mdeg {
+            self.position_mdeg.saturating_add(step_mdeg).min(self.target_mdeg)
+        } else {
+            self.position_mdeg.saturating_sub(step_mdeg).max(self.target_mdeg)
+        };
+        self.set_pulse();
+        if self.position_mdeg == self.target_mdeg {
+            self.status = Status::Holding;
+        }
+
+        match current_ma {
+            Some(current_ma) if current_ma > self.config.stall_current_ma => {
+                let since_ms = *self.overcurrent_since_ms.get_or_insert(now_ms);
+                if now_ms.wrapping_sub(since_ms) >= self.config.stall_debounce_ms {
+                    self.pwm.disable();
+                    self.status = Status::Stalled;
+                }
+            }
+            Some(_) => self.overcurrent_since_ms = None,
+            None => (),
+        }
+        self.status
+    }
+
+    /// Stops driving the servo, e.g. once it has finished an action and nothing pushes back on it
+    pub fn power_off(&mut self) {
+        self.pwm.disable();
+        self.status = Status::Off;
+    }
+
+    /// What the servo is doing
+    #[must_use]
+    pub const fn status(&self) -> Status {
+        self.status
+    }
+
+    /// The commanded position in degrees, rounded down
+    #[must_use]
+    pub fn angle_deg(&self) -> u16 {
+        u16::try_from(self.position_mdeg / 1000).unwrap_or(u16::MAX)
+    }
+
+    /// Releases the PWM output
+    pub fn release(self) -> P {
+        self.pwm
+    }
+
+    /// Sets the pulse width for the commanded position, in proportion to the timer period
+    fn set_pulse(&mut self) {
+        let pulse_us = u32::from(self.calibration.pulse_us(self.position_mdeg));
+        let duty = pulse_us.saturating_mul(u32::from(self.pwm.get_max_duty())) / PERIOD_US;
+        self.pwm.set_duty(u16::try_from(duty).unwrap_or(u16::MAX));
+    }
+}
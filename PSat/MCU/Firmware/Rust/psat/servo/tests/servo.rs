@@ -0,0 +1,189 @@
+//! Servo motion, stall detection and the mechanism's actions against a simulated PWM output
+
+use embedded_hal::PwmPin;
+use psat_servo::mechanism::{Action, Config as MechanismConfig, Mechanism, Outcome};
+use psat_servo::servo::{Calibration, Config, CurrentSense, Servo, Status};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// The PWM output's state
+#[derive(Debug, Default)]
+struct Output {
+    /// Whether the output is driven
+    enabled: bool,
+    /// The duty, in timer counts
+    duty: u16,
+}
+
+/// A PWM output on a timer counting microseconds, shared with the test
+#[derive(Clone, Default)]
+struct Pwm(Rc<RefCell<Output>>);
+impl Pwm {
+    /// The pulse width, or `None` while the output is off
+    fn pulse_us(&self) -> Option<u16> {
+        let output = self.0.borrow();
+        output.enabled.then_some(output.duty)
+    }
+}
+impl PwmPin for Pwm {
+    type Duty = u16;
+    fn disable(&mut self) {
+        self.0.borrow_mut().enabled = false;
+    }
+    fn enable(&mut self) {
+        self.0.borrow_mut().enabled = true;
+    }
+    fn get_duty(&self) -> u16 {
+        self.0.borrow().duty
+    }
+    fn get_max_duty(&self) -> u16 {
+        20_000
+    }
+    fn set_duty(&mut self, duty: u16) {
+        self.0.borrow_mut().duty = duty;
+    }
+}
+
+#[test]
+fn calibrated_pulses() {
+    let calibration = Calibration { min_pulse_us: 600, max_pulse_us: 2400, range_deg: 120 };
+    assert_eq!(calibration.pulse_us(0), 600);
+    assert_eq!(calibration.pulse_us(60_000), 1500);
+    assert_eq!(calibration.pulse_us(120_000), 2400);
+    assert_eq!(calibration.pulse_us(150_000), 2400, "clamped to the travel");
+
+    // Mounted the other way round
+    let reversed = Calibration { min_pulse_us: 2400, max_pulse_us: 600, range_deg: 120 };
+    assert_eq!(reversed.pulse_us(30_000), 1950);
+}
+
+#[test]
+fn moves_smoothly() {
+    let pwm = Pwm::default();
+    let mut servo = Servo::new(pwm.clone(), Calibration::default(), Config::default(), 0);
+    assert_eq!((servo.status(), pwm.pulse_us()), (Status::Off, None));
+
+    // 180° per second, from 0° to 90° in half a second
+    servo.move_to(90, 1000);
+    assert_eq!(pwm.pulse_us(), Some(500));
+    let mut pulses = Vec::new();
+    for now_ms in (1100..=1600).step_by(100) {
+        assert_eq!(servo.update(now_ms, Some(250)), if now_ms < 1500 { Status::Moving } else { Status::Holding });
+        pulses.push(pwm.pulse_us().expect("driven"));
+    }
+    assert_eq!(pulses, [700, 900, 1100, 1300, 1500, 1500]);
+    assert_eq!(servo.angle_deg(), 90);
+
+    // Back, past the end of the travel
+    servo.move_to(200, 2000);
+    servo.update(2500, None);
+    assert_eq!(servo.angle_deg(), 180);
+    servo.power_off();
+    assert_eq!((servo.status(), pwm.pulse_us()), (Status::Off, None));
+}
+
+#[test]
+fn cuts_off_on_stall() {
+    let pwm = Pwm::default();
+    let config = Config::default();
+    let mut servo = Servo::new(pwm.clone(), Calibration::default(), config, 0);
+    servo.move_to(180, 0);
+    // The inrush at the start of a move is short enough
+    for (now_ms, current_ma) in [(10, 900), (100, 800), (200, 300)] {
+        assert_eq!(servo.update(now_ms, Some(current_ma)), Status::Moving);
+    }
+    // Jammed part way
+    for now_ms in (300..300 + config.stall_debounce_ms).step_by(50) {
+        assert_eq!(servo.update(now_ms, Some(1200)), Status::Moving);
+    }
+    assert_eq!(servo.update(300 + config.stall_debounce_ms, Some(1200)), Status::Stalled);
+    assert_eq!(pwm.pulse_us(), None);
+    assert_eq!(servo.update(1000, Some(0)), Status::Stalled, "stays off");
+
+    // Driven again from where it stopped
+    let angle_deg = servo.angle_deg();
+    servo.move_to(0, 2000);
+    assert_eq!(servo.status(), Status::Moving);
+    assert_eq!(pwm.pulse_us(), Some(Calibration::default().pulse_us(u32::from(angle_deg) * 1000)));
+}
+
+#[test]
+fn converts_current_sense() {
+    // A 0.5 Ω shunt into a difference amplifier with a gain of 7.7 and a 669 mV negative offset
+    let sense = CurrentSense { offset_mv: 669, ma_per_v: 260 };
+    assert_eq!(sense.current_ma(0), 173, "clamped below the offset");
+    assert_eq!(sense.current_ma(1639), 600);
+    assert_eq!(sense.current_ma(3300), 1031);
+    assert_eq!(CurrentSense { offset_mv: 0, ma_per_v: 60_000 }.current_ma(3300), u16::MAX, "saturates");
+}
+
+/// A mechanism on simulated outputs
+fn mechanism() -> (Mechanism<Pwm, Pwm>, Pwm, Pwm) {
+    let (trigger, shutter) = (Pwm::default(), Pwm::default());
+    let config = MechanismConfig::default();
+    let mechanism = Mechanism::new(
+        config,
+        Servo::new(trigger.clone(), Calibration::default(), Config::default(), config.trigger_rest_deg),
+        Servo::new(shutter.clone(), Calibration::default(), Config::default(), config.shutter_rest_deg),
+    );
+    (mechanism, trigger, shutter)
+}
+
+/// Updates a mechanism every 20 ms until the action ends, and returns the outcome and time
+fn run(mechanism: &mut Mechanism<Pwm, Pwm>, start_ms: u32, current_ma: impl Fn(u32) -> u16) -> (Outcome, u32) {
+    for now_ms in (start_ms..start_ms + 5000).step_by(20) {
+        if let Some(outcome) = mechanism.update(now_ms, Some(current_ma(now_ms))) {
+            return (outcome, now_ms);
+        }
+    }
+    panic!("action did not end");
+}
+
+#[test]
+fn triggers_and_reloads() {
+    let (mut mechanism, trigger, shutter) = mechanism();
+    mechanism.start(Action::TriggerShutter, 0).expect("idle");
+    assert!(mechanism.is_busy());
+    assert_eq!(mechanism.start(Action::Reload, 0), Err("Servo action already under way"));
+    assert_eq!(shutter.pulse_us(), None);
+
+    // 45° out at 180° per second, a 300 ms press, and back
+    let mut pressed_ms = None;
+    for now_ms in (0..2000).step_by(20) {
+        let outcome = mechanism.update(now_ms, Some(300));
+        if trigger.pulse_us() == Some(1000) {
+            pressed_ms.get_or_insert(now_ms);
+        }
+        if let Some(outcome) = outcome {
+            assert_eq!((outcome, now_ms), (Outcome::Done(Action::TriggerShutter), 820));
+            break;
+        }
+    }
+    assert_eq!(pressed_ms, Some(260));
+    assert_eq!(trigger.pulse_us(), None, "off at rest");
+    assert!(!mechanism.is_busy());
+
+    // 120° out and back, with a 200 ms wait
+    mechanism.start(Action::Reload, 1000).expect("idle");
+    assert_eq!(run(&mut mechanism, 1000, |_| 300), (Outcome::Done(Action::Reload), 2560));
+    assert_eq!(mechanism.shutter().angle_deg(), 0);
+}
+
+#[test]
+fn reports_jams() {
+    let (mut mechanism, trigger, _) = mechanism();
+    // The winding sticks on the way back
+    mechanism.start(Action::Reload, 0).expect("idle");
+    let (outcome, jammed_ms) = run(&mut mechanism, 0, |now_ms| if now_ms > 1000 { 1500 } else { 200 });
+    assert_eq!(outcome, Outcome::Jammed(Action::Reload));
+    assert_eq!(jammed_ms, 1280);
+    assert_eq!(mechanism.shutter().status(), Status::Stalled);
+    assert!(!mechanism.is_busy());
+
+    // The trigger still works, and the shutter servo is driven again by the next reload
+    mechanism.start(Action::TriggerShutter, 2000).expect("idle");
+    assert_eq!(run(&mut mechanism, 2000, |_| 300).0, Outcome::Done(Action::TriggerShutter));
+    assert_eq!(trigger.pulse_us(), None);
+    mechanism.start(Action::Reload, 3000).expect("idle");
+    assert_eq!(mechanism.shutter().status(), Status::Moving);
+}
@@ -0,0 +1,25 @@
+//! CRC-16/CCITT-FALSE as used to protect telemetry frames
+
+/// The generator polynomial `x^16 + x^12 + x^5 + 1`
+const POLYNOMIAL: u16 = 0x1021;
+/// The initial register value
+const INIT: u16 = 0xFFFF;
+
+/// Computes the CRC-16/CCITT-FALSE checksum over the given data
+///
+/// # Note
+/// The checksum is computed bitwise instead of with a lookup table, which saves 512 bytes of flash at the cost of some
+/// cycles per byte. Frames are short, so this does not matter.
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc = INIT;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = match crc & 0x8000 {
+                0 => crc << 1,
+                _ => (crc << 1) ^ POLYNOMIAL,
+            };
+        }
+    }
+    crc
+}
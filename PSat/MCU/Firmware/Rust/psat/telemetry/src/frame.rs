@@ -0,0 +1,302 @@
+//! The versioned telemetry frame
+//!
+//! # Layout (version 1)
+//! All multi-byte fields are little-endian.
+//!
+//! | Offset | Size | Field                                         |
+//! |-------:|-----:|-----------------------------------------------|
+//! |      0 |    1 | Magic byte `0xA5`                             |
+//! |      1 |    1 | Format version                                |
+//! |      2 |    2 | Sequence number                               |
+//! |      4 |    4 | Uptime in seconds                             |
+//! |      8 |    1 | Flags, bit 0 is set if the position is valid  |
+//! |      9 |    4 | Latitude in 1e-7 degrees                      |
+//! |     13 |    4 | Longitude in 1e-7 degrees                     |
+//! |     17 |    4 | Altitude above mean sea level in decimetres   |
+//! |     21 |    1 | Satellites in use                             |
+//! |     22 |    2 | Battery voltage in millivolts                 |
+//! |     24 |    2 | Raw light level                               |
+//! |     26 |    6 | Acceleration X, Y and Z in milli-g            |
+//! |     32 |    1 | Flight state                                  |
+//! |     33 |    2 | RSSI of the last received packet in dBm       |
+//! |     35 |    1 | SNR of the last received packet in dB         |
+//! |     36 |    1 | Modem recoveries since boot                   |
+//! |     37 |    2 | CRC-16/CCITT-FALSE over all preceding bytes   |
+
+use crate::crc::crc16;
+
+/// The first byte of every telemetry frame, which tells frames apart from ASCII traffic
+pub const MAGIC: u8 = 0xA5;
+/// The frame format version that is produced by [`Telemetry::encode`]
+pub const VERSION: u8 = 1;
+/// The encoded length of a version 1 frame, including the checksum
+pub const FRAME_LEN: usize = 39;
+
+/// Flag that is set if the frame carries a valid position
+const FLAG_POSITION: u8 = 0b0000_0001;
+
+/// The mission phase
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum FlightState {
+    /// Powered on and waiting on the ground
+    #[default]
+    PreLaunch = 0,
+    /// Ready for launch
+    Armed = 1,
+    /// Launched and climbing
+    Ascent = 2,
+    /// Leaving the capsule
+    Ejection = 3,
+    /// Falling back to the ground
+    Descent = 4,
+    /// Back on the ground
+    Landed = 5,
+    /// Landed for a while; beaconing for recovery
+    Recovery = 6,
+}
+impl TryFrom<u8> for FlightState {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::PreLaunch),
+            1 => Ok(Self::Armed),
+            2 => Ok(Self::Ascent),
+            3 => Ok(Self::Ejection),
+            4 => Ok(Self::Descent),
+            5 => Ok(Self::Landed),
+            6 => Ok(Self::Recovery),
+            _ => Err("Invalid flight state"),
+        }
+    }
+}
+
+/// A GPS position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    /// The latitude in 1e-7 degrees, positive to the north
+    pub latitude_e7: i32,
+    /// The longitude in 1e-7 degrees, positive to the east
+    pub longitude_e7: i32,
+    /// The altitude above mean sea level in decimetres
+    pub altitude_dm: i32,
+}
+impl Position {
+    /// Creates a position from decimal degrees and an altitude in metres
+    ///
+    /// # Note
+    /// Out-of-range values saturate.
+    pub fn from_degrees(latitude: f32, longitude: f32, altitude_m: f32) -> Self {
+        Self {
+            latitude_e7: (latitude * 1e7) as i32,
+            longitude_e7: (longitude * 1e7) as i32,
+            altitude_dm: (altitude_m * 10.0) as i32,
+        }
+    }
+
+    /// The latitude in decimal degrees
+    pub fn latitude(&self) -> f32 {
+        self.latitude_e7 as f32 / 1e7
+    }
+    /// The longitude in decimal degrees
+    pub fn longitude(&self) -> f32 {
+        self.longitude_e7 as f32 / 1e7
+    }
+    /// The altitude above mean sea level in metres
+    pub fn altitude_m(&self) -> f32 {
+        self.altitude_dm as f32 / 10.0
+    }
+}
+
+/// An acceleration vector in milli-g
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Acceleration {
+    /// The X axis
+    pub x_mg: i16,
+    /// The Y axis
+    pub y_mg: i16,
+    /// The Z axis
+    pub z_mg: i16,
+}
+
+/// Radio link statistics
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RadioStats {
+    /// The RSSI of the last received packet in dBm
+    pub rssi_dbm: i16,
+    /// The SNR of the last received packet in dB
+    pub snr_db: i8,
+    /// The amount of modem recoveries since boot, saturating at `255`
+    pub recoveries: u8,
+}
+
+/// A single telemetry record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Telemetry {
+    /// The frame sequence number, which wraps around
+    pub sequence: u16,
+    /// The time since boot in seconds
+    pub uptime_s: u32,
+    /// The last GPS position, if there is a fix
+    pub position: Option<Position>,
+    /// The amount of satellites in use
+    pub satellites: u8,
+    /// The battery voltage in millivolts
+    pub battery_mv: u16,
+    /// The raw light level
+    pub light_level: u16,
+    /// The current acceleration
+    pub acceleration: Acceleration,
+    /// The current mission phase
+    pub flight_state: FlightState,
+    /// The radio link statistics
+    pub radio: RadioStats,
+}
+impl Telemetry {
+    /// Encodes the record into a frame with the current format version
+    pub fn encode(&self) -> [u8; FRAME_LEN] {
+        let (flags, position) = match self.position {
+            Some(position) => (FLAG_POSITION, position),
+            None => (0, Position::default()),
+        };
+
+        let mut writer = Writer::new();
+        writer.put(&[MAGIC, VERSION]);
+        writer.put(&self.sequence.to_le_bytes());
+        writer.put(&self.uptime_s.to_le_bytes());
+        writer.put(&[flags]);
+        writer.put(&position.latitude_e7.to_le_bytes());
+        writer.put(&position.longitude_e7.to_le_bytes());
+        writer.put(&position.altitude_dm.to_le_bytes());
+        writer.put(&[self.satellites]);
+        writer.put(&self.battery_mv.to_le_bytes());
+        writer.put(&self.light_level.to_le_bytes());
+        writer.put(&self.acceleration.x_mg.to_le_bytes());
+        writer.put(&self.acceleration.y_mg.to_le_bytes());
+        writer.put(&self.acceleration.z_mg.to_le_bytes());
+        writer.put(&[self.flight_state as u8]);
+        writer.put(&self.radio.rssi_dbm.to_le_bytes());
+        writer.put(&self.radio.snr_db.to_le_bytes());
+        writer.put(&[self.radio.recoveries]);
+
+        // Append the checksum over everything written so far
+        let crc = crc16(writer.written());
+        writer.put(&crc.to_le_bytes());
+        writer.buf
+    }
+
+    /// Decodes and validates a frame
+    pub fn decode(frame: &[u8]) -> Result<Self, &'static str> {
+        let [MAGIC, version, ..] = frame else {
+            return Err("Not a telemetry frame");
+        };
+        let VERSION = *version else {
+            return Err("Unsupported telemetry version");
+        };
+        let FRAME_LEN = frame.len() else {
+            return Err("Invalid frame length");
+        };
+
+        // Validate the checksum
+        let Some((body, crc)) = frame.split_last_chunk::<2>() else {
+            return Err("Invalid frame length");
+        };
+        let true = crc16(body) == u16::from_le_bytes(*crc) else {
+            return Err("Checksum mismatch");
+        };
+
+        // Parse the fields after magic and version
+        let mut reader = Reader { data: body.get(2..).unwrap_or_default() };
+        let sequence = u16::from_le_bytes(reader.take()?);
+        let uptime_s = u32::from_le_bytes(reader.take()?);
+        let [flags] = reader.take()?;
+        let position = Position {
+            latitude_e7: i32::from_le_bytes(reader.take()?),
+            longitude_e7: i32::from_le_bytes(reader.take()?),
+            altitude_dm: i32::from_le_bytes(reader.take()?),
+        };
+        let [satellites] = reader.take()?;
+        let battery_mv = u16::from_le_bytes(reader.take()?);
+        let light_level = u16::from_le_bytes(reader.take()?);
+        let acceleration = Acceleration {
+            x_mg: i16::from_le_bytes(reader.take()?),
+            y_mg: i16::from_le_bytes(reader.take()?),
+            z_mg: i16::from_le_bytes(reader.take()?),
+        };
+        let [flight_state] = reader.take()?;
+        let radio = RadioStats {
+            rssi_dbm: i16::from_le_bytes(reader.take()?),
+            snr_db: i8::from_le_bytes(reader.take()?),
+            recoveries: u8::from_le_bytes(reader.take()?),
+        };
+
+        // Validate the flags
+        let 0 = flags & !FLAG_POSITION else {
+            return Err("Reserved flags are set");
+        };
+        let position = match flags & FLAG_POSITION {
+            0 => None,
+            _ => Some(position),
+        };
+
+        Ok(Self {
+            sequence,
+            uptime_s,
+            position,
+            satellites,
+            battery_mv,
+            light_level,
+            acceleration,
+            flight_state: FlightState::try_from(flight_state)?,
+            radio,
+        })
+    }
+}
+
+/// A writer for a single frame
+struct Writer {
+    /// The frame buffer
+    buf: [u8; FRAME_LEN],
+    /// The amount of bytes written so far
+    len: usize,
+}
+impl Writer {
+    /// Creates a new writer
+    const fn new() -> Self {
+        Self { buf: [0; FRAME_LEN], len: 0 }
+    }
+
+    /// Appends the given data
+    ///
+    /// # Note
+    /// The frame layout is fixed, so data that does not fit anymore is a programming error and silently dropped.
+    fn put(&mut self, data: &[u8]) {
+        let end = self.len.saturating_add(data.len());
+        if let Some(slot) = self.buf.get_mut(self.len..end) {
+            slot.copy_from_slice(data);
+            self.len = end;
+        }
+    }
+
+    /// All bytes written so far
+    fn written(&self) -> &[u8] {
+        self.buf.get(..self.len).unwrap_or_default()
+    }
+}
+
+/// A reader over the fields of a frame
+struct Reader<'a> {
+    /// The remaining data
+    data: &'a [u8],
+}
+impl Reader<'_> {
+    /// Takes the next `N` bytes
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], &'static str> {
+        let Some((head, rest)) = self.data.split_first_chunk::<N>() else {
+            return Err("Truncated frame");
+        };
+        self.data = rest;
+        Ok(*head)
+    }
+}
@@ -0,0 +1,114 @@
+//! Frame encoding and decoding tests
+
+use psat_telemetry::crc::crc16;
+use psat_telemetry::frame::{Acceleration, FlightState, Position, RadioStats, Telemetry, FRAME_LEN, MAGIC, VERSION};
+
+/// A record with every field set
+fn telemetry() -> Telemetry {
+    Telemetry {
+        sequence: 0x1234,
+        uptime_s: 86_400,
+        position: Some(Position { latitude_e7: -274_698_000, longitude_e7: 1_530_251_000, altitude_dm: 12_345 }),
+        satellites: 11,
+        battery_mv: 3_950,
+        light_level: 812,
+        acceleration: Acceleration { x_mg: -12, y_mg: 3, z_mg: 4_012 },
+        flight_state: FlightState::Ascent,
+        radio: RadioStats { rssi_dbm: -118, snr_db: -7, recoveries: 2 },
+    }
+}
+
+#[test]
+fn crc16_check_value() {
+    assert_eq!(crc16(b"123456789"), 0x29B1);
+    assert_eq!(crc16(&[]), 0xFFFF);
+}
+
+#[test]
+fn known_frame() {
+    let frame = telemetry().encode();
+    let expected: [u8; FRAME_LEN] = [
+        0xA5, 0x01, // Magic and version
+        0x34, 0x12, // Sequence
+        0x80, 0x51, 0x01, 0x00, // Uptime
+        0x01, // Flags
+        0xF0, 0x70, 0xA0, 0xEF, // Latitude
+        0xF8, 0xC6, 0x35, 0x5B, // Longitude
+        0x39, 0x30, 0x00, 0x00, // Altitude
+        0x0B, // Satellites
+        0x6E, 0x0F, // Battery
+        0x2C, 0x03, // Light level
+        0xF4, 0xFF, 0x03, 0x00, 0xAC, 0x0F, // Acceleration
+        0x02, // Flight state
+        0x8A, 0xFF, // RSSI
+        0xF9, // SNR
+        0x02, // Recoveries
+        0x00, 0x00, // Checksum, patched below
+    ];
+    let crc = crc16(&expected[..FRAME_LEN - 2]).to_le_bytes();
+    assert_eq!(frame[..FRAME_LEN - 2], expected[..FRAME_LEN - 2]);
+    assert_eq!(frame[FRAME_LEN - 2..], crc);
+}
+
+#[test]
+fn round_trip() {
+    let telemetry = telemetry();
+    assert_eq!(Telemetry::decode(&telemetry.encode()), Ok(telemetry));
+
+    // Without a position, the position fields are zeroed
+    let telemetry = Telemetry { position: None, flight_state: FlightState::Recovery, ..telemetry };
+    let frame = telemetry.encode();
+    assert_eq!(frame[8..21], [0; 13]);
+    assert_eq!(Telemetry::decode(&frame), Ok(telemetry));
+}
+
+#[test]
+fn position_degrees() {
+    let position = Position::from_degrees(-27.4698, 153.0251, 1234.5);
+    assert!((position.latitude() - -27.4698).abs() < 1e-4);
+    assert!((position.longitude() - 153.0251).abs() < 1e-4);
+    assert_eq!(position.altitude_dm, 12_345);
+}
+
+#[test]
+fn rejects_invalid_frames() {
+    let frame = telemetry().encode();
+
+    // Wrong magic and unknown versions
+    let mut wrong_magic = frame;
+    wrong_magic[0] = b'$';
+    assert_eq!(Telemetry::decode(&wrong_magic), Err("Not a telemetry frame"));
+    let mut future_version = frame;
+    future_version[1] = VERSION + 1;
+    assert_eq!(Telemetry::decode(&future_version), Err("Unsupported telemetry version"));
+
+    // Truncated and overlong frames
+    assert_eq!(Telemetry::decode(&[MAGIC]), Err("Not a telemetry frame"));
+    assert_eq!(Telemetry::decode(&frame[..FRAME_LEN - 1]), Err("Invalid frame length"));
+    let mut overlong = frame.to_vec();
+    overlong.push(0);
+    assert_eq!(Telemetry::decode(&overlong), Err("Invalid frame length"));
+
+    // Corrupted payload
+    let mut corrupted = frame;
+    corrupted[10] ^= 0x40;
+    assert_eq!(Telemetry::decode(&corrupted), Err("Checksum mismatch"));
+}
+
+#[test]
+fn rejects_invalid_fields() {
+    /// Replaces a byte and fixes the checksum
+    fn patch(frame: &mut [u8; FRAME_LEN], offset: usize, value: u8) {
+        frame[offset] = value;
+        let crc = crc16(&frame[..FRAME_LEN - 2]).to_le_bytes();
+        frame[FRAME_LEN - 2..].copy_from_slice(&crc);
+    }
+
+    let mut reserved_flags = telemetry().encode();
+    patch(&mut reserved_flags, 8, 0b1000_0001);
+    assert_eq!(Telemetry::decode(&reserved_flags), Err("Reserved flags are set"));
+
+    let mut flight_state = telemetry().encode();
+    patch(&mut flight_state, 32, 7);
+    assert_eq!(Telemetry::decode(&flight_state), Err("Invalid flight state"));
+}
@@ -0,0 +1,86 @@
+//! Randomized tests with a fixed seed, so failures are reproducible
+
+use psat_telemetry::frame::{Acceleration, FlightState, Position, RadioStats, Telemetry, FRAME_LEN, MAGIC, VERSION};
+
+/// The amount of iterations per test
+const ITERATIONS: usize = 100_000;
+
+/// A xorshift64 PRNG
+struct Rng(u64);
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+    fn bytes<const N: usize>(&mut self) -> [u8; N] {
+        let mut bytes = [0; N];
+        bytes.iter_mut().for_each(|byte| *byte = self.next() as u8);
+        bytes
+    }
+    fn telemetry(&mut self) -> Telemetry {
+        let position = Position {
+            latitude_e7: self.next() as i32,
+            longitude_e7: self.next() as i32,
+            altitude_dm: self.next() as i32,
+        };
+        Telemetry {
+            sequence: self.next() as u16,
+            uptime_s: self.next() as u32,
+            position: self.next().is_multiple_of(2).then_some(position),
+            satellites: self.next() as u8,
+            battery_mv: self.next() as u16,
+            light_level: self.next() as u16,
+            acceleration: Acceleration { x_mg: self.next() as i16, y_mg: self.next() as i16, z_mg: self.next() as i16 },
+            flight_state: FlightState::try_from((self.next() % 7) as u8).expect("invalid flight state"),
+            radio: RadioStats {
+                rssi_dbm: self.next() as i16,
+                snr_db: self.next() as i8,
+                recoveries: self.next() as u8,
+            },
+        }
+    }
+}
+
+#[test]
+fn random_records_round_trip() {
+    let mut rng = Rng(0x5EED_0F7E_1E3E_7E00);
+    for _ in 0..ITERATIONS {
+        let telemetry = rng.telemetry();
+        assert_eq!(Telemetry::decode(&telemetry.encode()), Ok(telemetry));
+    }
+}
+
+#[test]
+fn random_bytes_never_panic() {
+    let mut rng = Rng(0xDEAD_BEEF_CAFE_F00D);
+    for _ in 0..ITERATIONS {
+        // Random lengths, with a valid header half of the time to get past the first checks
+        let mut frame: [u8; FRAME_LEN + 8] = rng.bytes();
+        if rng.next().is_multiple_of(2) {
+            frame[..2].copy_from_slice(&[MAGIC, VERSION]);
+        }
+        let len = (rng.next() as usize) % frame.len();
+        let _ = Telemetry::decode(&frame[..len]);
+    }
+}
+
+#[test]
+fn single_and_double_bit_errors_are_detected() {
+    let mut rng = Rng(0x0123_4567_89AB_CDEF);
+    for _ in 0..ITERATIONS / 100 {
+        let frame = rng.telemetry().encode();
+        for first in 0..FRAME_LEN * 8 {
+            let mut corrupted = frame;
+            corrupted[first / 8] ^= 1 << (first % 8);
+            assert!(Telemetry::decode(&corrupted).is_err(), "bit {first} was not detected");
+
+            let second = (rng.next() as usize) % (FRAME_LEN * 8);
+            if second != first {
+                corrupted[second / 8] ^= 1 << (second % 8);
+                assert!(Telemetry::decode(&corrupted).is_err(), "bits {first} and {second} were not detected");
+            }
+        }
+    }
+}
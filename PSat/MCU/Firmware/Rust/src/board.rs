@@ -3,13 +3,14 @@
 #![allow(dead_code)]
 use msp430fr2x5x_hal::{
     adc::{Adc, AdcConfig, ClockDivider, Predivider, Resolution, SampleTime, SamplingRate}, 
-    clock::{Clock, ClockConfig, DcoclkFreqSel, MclkDiv, SmclkDiv}, delay::Delay, fram::Fram, 
+    clock::{Clock, ClockConfig, DcoclkFreqSel, MclkDiv, Smclk, SmclkDiv}, delay::Delay, fram::Fram, 
     gpio::{Batch, Floating, Input, Pin, Pin0, Pin1, Pin2, Pin3, Pin4, Pin5, Pin6, Pin7, P1, P2, P3, P4, P5, P6}, 
     i2c::{GlitchFilter, I2CBusConfig, I2cBus}, 
-    pac::{E_USCI_B0, PMM, TB0}, pmm::Pmm, pwm::TimerConfig, spi::SpiBusConfig, timer::{Timer, TimerParts3}, watchdog::Wdt
+    pac::{E_USCI_B0, PMM}, pmm::Pmm, spi::SpiBusConfig, watchdog::Wdt
 };
 use embedded_hal::digital::v2::{OutputPin, ToggleableOutputPin};
-use crate::{gps::Gps, lora::Radio, pin_mappings::*, println};
+use psat_nmea::pmtk::{Command, NavigationMode};
+use crate::{espcam::EspCam, gps::Gps, imu::Imu, light::LightSense, lora::Radio, servo::Servos, persistent::InfoFram, pin_mappings::*, println};
 
 /// Top-level object representing the board.
 /// 
@@ -17,16 +18,23 @@ use crate::{gps::Gps, lora::Radio, pin_mappings::*, println};
 pub struct Board {
     pub delay: Delay,
     pub gps: Gps,
+    pub imu: Imu,
+    pub light: LightSense,
+    pub servos: Servos,
+    pub camera: EspCam,
     // pub i2c: I2cBus<E_USCI_B0>,
     pub adc: Adc,
+    /// Uninitialised if the modem didn't respond at boot, see `Radio::init()`
     pub radio: Radio,
     pub gpio: Gpio,
-    pub timer_b0: Timer<TB0>,
+    pub fram: InfoFram,
+    pub smclk: Smclk,
 }
 // This is where you should implement top-level functionality. 
 impl Board {
     pub fn battery_voltage_mv(&mut self) -> u16 {
-        self.adc.read_voltage_mv(&mut self.gpio.half_vbat, 3300).unwrap() * 2
+        // The ADC is shared with the light sensor, so wait for this conversion rather than expecting it to be ready
+        nb::block!(self.adc.read_voltage_mv(&mut self.gpio.half_vbat, 3300)).unwrap_or(0) * 2
     }
 }
 
@@ -42,14 +50,14 @@ pub fn configure() -> Board {
     
     // Configure clocks to get accurate delay timing, and used by other peripherals
     let mut fram = Fram::new(regs.FRCTL);
-    let (smclk, aclk, delay) = ClockConfig::new(regs.CS)
+    let (smclk, aclk, mut delay) = ClockConfig::new(regs.CS)
         .mclk_dcoclk(DcoclkFreqSel::_8MHz, MclkDiv::_1)
         .smclk_on(SmclkDiv::_1)
         .aclk_refoclk() // 32768 Hz
         .freeze(&mut fram);
 
     // Spare UART, useful for debug printing to a computer
-    crate::serial::configure_debug_serial(used.debug_tx_pin, &smclk, regs.E_USCI_A0);
+    crate::serial::configure_debug_serial(used.debug_tx_pin, used.debug_rx_pin, &smclk, regs.E_USCI_A0);
     println!("Serial init"); // Like this!
     
     // SPI, used by the LoRa radio
@@ -57,15 +65,32 @@ pub fn configure() -> Board {
         .use_smclk(&smclk, 32)
         .configure_with_software_cs(used.miso, used.mosi, used.sclk);
     
-    // LoRa radio
-    let radio = crate::lora::new(spi, used.lora_cs, used.lora_reset, delay);
+    // LoRa radio. Keep booting without it, so the payload still logs its flight and beeps an error code. The mission retries it.
+    let mut radio = crate::lora::new(spi, used.lora_cs, used.lora_reset, delay);
+    if let Err(e) = radio.init() {
+        println!("Radio init failed: {}", e);
+    }
 
     // GPS
-    let gps = crate::gps::Gps::new(regs.E_USCI_A1, &smclk, used.gps_tx_pin, used.gps_rx_pin);
+    let mut gps = crate::gps::Gps::new(regs.E_USCI_A1, &smclk, used.gps_tx_pin, used.gps_rx_pin, used.gps_en);
+
+    // Accelerometer and gyroscope, on I2C
+    let imu = crate::imu::new(regs.E_USCI_B1, &smclk, used.accel_scl, used.accel_sda);
+
+    // Photoresistor, on the ADC
+    let light = LightSense::new(used.light_sense);
+
+    // Camera trigger and shutter servos, on Timer_B1
+    let servos = crate::servo::new(regs.TB1, &smclk, used.cam_trig_servo, used.shutter_servo, used.current_sense);
+
+    // Buzzer, on Timer_B2, with Timer_B3 stepping its patterns
+    crate::buzzer::configure(regs.TB2, regs.TB3, &smclk, &aclk, used.buzzer);
+
+    // Mission clock on Timer_B0, also the time base of the camera's single-wire link
+    crate::clock::configure(regs.TB0, &aclk);
 
-    // Timer
-    let timer_parts = TimerParts3::new(regs.TB0, TimerConfig::aclk(&aclk));
-    let timer_b0 = timer_parts.timer;
+    // ESP32 Cam, on the single-wire link
+    let camera = crate::espcam::connect(used.esp_cam);
 
     // I2C
     // const I2C_FREQ: u32 = 100_000; //Hz
@@ -86,7 +111,19 @@ pub fn configure() -> Board {
         .use_modclk()
         .configure(regs.ADC);
 
-    Board {delay, gps, radio, adc, gpio, timer_b0}
+    // Non-volatile storage
+    let fram = InfoFram::new(regs.SYS);
+
+    // Start servicing interrupts, e.g. GPS and debug UART reception
+    // SAFETY: No interrupt handlers rely on state that is set up after this point
+    unsafe { msp430::interrupt::enable() };
+
+    // The other navigation modes lose lock above 18 km. The module forgets this when power cycled, so set it on every boot.
+    if let Err(e) = gps.command(Command::SetNavigationMode(NavigationMode::Balloon), &mut delay) {
+        println!("{}", e);
+    }
+
+    Board {delay, gps, imu, light, servos, camera, radio, adc, gpio, fram, smclk}
 }
 
 /// The RGB LEDs are active low, which can be a little confusing. A helper struct to reduce cognitive load.
@@ -116,7 +153,6 @@ pub struct Gpio {
     pub blue_led:  BlueLed,
     
     pub lora_irq:       LoraIrqPin,
-    pub gps_en:         GpsEnPin,
     pub half_vbat:      HalfVbatPin,
 
     // PSU monitoring and control pins
@@ -128,7 +164,6 @@ pub struct Gpio {
     // Unused UCA0 pins
     // pub pin1_4: Pin<P1, Pin4, Input<Floating>>,
     // pub pin1_5: Pin<P1, Pin5, Input<Floating>>,
-    // pub pin1_6: Pin<P1, Pin6, Input<Floating>>,
 
     // Unused UCA1 pins
     // pub pin4_0: Pin<P4, Pin0, Input<Floating>>,
@@ -142,7 +177,6 @@ pub struct Gpio {
 
     // Unused GPIO pins
     // pub pin2_3: Pin<P2, Pin3, Input<Floating>>,
-    // pub pin2_4: Pin<P2, Pin4, Input<Floating>>,
     // pub pin2_5: Pin<P2, Pin5, Input<Floating>>,
     // pub pin2_6: Pin<P2, Pin6, Input<Floating>>,
     // pub pin2_7: Pin<P2, Pin7, Input<Floating>>,
@@ -173,6 +207,21 @@ impl Gpio {
         let port6 = Batch::new(p6).split(&pmm);
 
         let half_vbat = port5.pin0.to_alternate3(); // ADC pin. Connected to Vbat/2.
+        let light_sense = port5.pin2.to_alternate3(); // ADC pin. Photoresistor.
+        let current_sense = port5.pin3.to_alternate3(); // ADC pin. Servo current.
+
+        // Servo PWM outputs, low whenever the PWM is disabled so the servos are not driven
+        let mut cam_trig_servo = port2.pin1.to_output();
+        let mut shutter_servo = port2.pin0.to_output();
+        cam_trig_servo.set_low().ok();
+        shutter_servo.set_low().ok();
+        let cam_trig_servo = cam_trig_servo.to_alternate1();
+        let shutter_servo = shutter_servo.to_alternate1();
+
+        // Buzzer PWM output, also low whenever it is silent
+        let mut buzzer = port5.pin1.to_output();
+        buzzer.set_low().ok();
+        let buzzer = buzzer.to_alternate1();
 
         // LEDs
         let mut red_led = RedLed::new(port3.pin3.to_output());
@@ -198,21 +247,24 @@ impl Gpio {
         gps_en.set_low().ok();
 
         let debug_tx_pin = port1.pin7.to_alternate1();
+        let debug_rx_pin = port1.pin6.pullup().to_alternate1();
+        let esp_cam = port2.pin4.pullup();
+
+        let accel_scl = port4.pin7.to_alternate1();
+        let accel_sda = port4.pin6.to_alternate1();
 
         // let i2c_sda_pin = port1.pin2.to_alternate1();
         // let i2c_scl_pin = port1.pin3.to_alternate1();
 
         // Pins consumed by other perihperals
-        let used = ConsumedPins {mosi, miso, sclk, lora_cs, lora_reset, gps_rx_pin, gps_tx_pin, debug_tx_pin};
+        let used = ConsumedPins {mosi, miso, sclk, lora_cs, lora_reset, gps_rx_pin, gps_tx_pin, gps_en, debug_tx_pin, debug_rx_pin, esp_cam, accel_scl, accel_sda, light_sense, current_sense, cam_trig_servo, shutter_servo, buzzer};
 
         // let pin1_0 = port1.pin0;
         // let pin1_1 = port1.pin1;
         // let pin1_4 = port1.pin4;
         // let pin1_5 = port1.pin5;
-        // let pin1_6 = port1.pin6;
 
         // let pin2_3 = port2.pin3;
-        // let pin2_4 = port2.pin4;
         // let pin2_5 = port2.pin5;
         // let pin2_6 = port2.pin6;
         // let pin2_7 = port2.pin7;
@@ -230,8 +282,6 @@ impl Gpio {
 
         let pin4_0 = port4.pin0;
 
-        let pin5_1 = port5.pin1;
-
         let pin6_0 = port6.pin0;
         let pin6_1 = port6.pin1;
         let pin6_2 = port6.pin2;
@@ -244,13 +294,12 @@ impl Gpio {
         let gpio = Self {
             red_led, green_led, blue_led, 
             lora_irq, 
-            gps_en, 
             half_vbat, 
             // power_good_1v8, power_good_3v3, 
             // enable_1v8,
             enable_5v,
-            // pin1_0, pin1_1, pin1_4, pin1_5, pin1_6,
-            // pin2_3, pin2_4, pin2_5, pin2_6, pin2_7,
+            // pin1_0, pin1_1, pin1_4, pin1_5,
+            // pin2_3, pin2_5, pin2_6, pin2_7,
             // pin3_4, pin3_5, pin3_6, pin3_7,
             // pin4_0,
             // pin5_1,
@@ -270,7 +319,17 @@ struct ConsumedPins {
     lora_cs:        LoraCSPin,
     gps_tx_pin:     GpsTxPin,
     gps_rx_pin:     GpsRxPin,
+    gps_en:         GpsEnPin,
     debug_tx_pin:   DebugTxPin,
+    debug_rx_pin:   DebugRxPin,
+    esp_cam:        EspCamPin,
+    accel_scl:      AccelSclPin,
+    accel_sda:      AccelSdaPin,
+    light_sense:    LightSensePin,
+    current_sense:  CurrentSensePin,
+    cam_trig_servo: CamTrigServoPin,
+    shutter_servo:  ShutterServoPin,
+    buzzer:         BuzzerPin,
     // i2c_sda_pin:    I2cSdaPin,
     // i2c_scl_pin:    I2cSclPin,
 }
\ No newline at end of file
@@ -0,0 +1,92 @@
+//! The buzzer on P5.1, driven by Timer_B2 PWM at the frequency of each tone.
+//!
+//! Timer_B3 ticks about every millisecond, and its interrupt steps the pattern, so tones keep their timing while the main
+//! loop blocks on the radio.
+
+use core::cell::RefCell;
+
+use embedded_hal::{timer::CountDown, PwmPin};
+use msp430::interrupt::Mutex;
+use msp430fr2355::interrupt;
+use msp430fr2x5x_hal::{clock::{Aclk, Smclk}, pac::{TB2, TB3}, pwm::{Pwm, PwmParts3, TimerConfig, TimerDiv, TimerExDiv, CCR2}, timer::{TBxIV, TimerParts7, TimerVector}};
+use psat_buzzer::{pattern::Pattern, player::{Config, Player}};
+use crate::pin_mappings::BuzzerPin;
+
+/// SMCLK / 8, so tones from 16 Hz up can be played.
+const PWM_CLOCK_HZ: u32 = 1_000_000;
+/// ACLK / 32. A tick is taken to be a millisecond, which plays patterns about 2% fast.
+const TICK_ACLK_CYCLES: u16 = 32;
+
+/// Sent in Morse code by the payload ID pattern.
+const PAYLOAD_ID: &str = "PSAT";
+
+/// Error codes, played as that many long beeps.
+pub const ERROR_IMU: u8 = 1;
+pub const ERROR_SERVO_JAMMED: u8 = 2;
+pub const ERROR_RADIO: u8 = 3;
+
+struct Buzzer {
+    pwm: Pwm<TB2, CCR2>,
+    player: Player,
+    tick: TBxIV<TB3>,
+    now_ms: u32,
+}
+
+/// The buzzer, owned by the tick interrupt handler.
+static BUZZER: Mutex<RefCell<Option<Buzzer>>> = Mutex::new(RefCell::new(None));
+
+/// Set up the PWM and the tick, and hand the buzzer to the interrupt handler. It is silent until a pattern is played.
+pub fn configure(tb2: TB2, tb3: TB3, smclk: &Smclk, aclk: &Aclk, pin: BuzzerPin) {
+    let pwm = PwmParts3::new(tb2, TimerConfig::smclk(smclk).clk_div(TimerDiv::_8, TimerExDiv::_1), 0).pwm2.init(pin);
+    let mut timer = TimerParts7::new(tb3, TimerConfig::aclk(aclk));
+    timer.timer.start(TICK_ACLK_CYCLES - 1);
+    timer.timer.enable_interrupts();
+
+    let mut buzzer = Buzzer { pwm, player: Player::new(Config { payload_id: PAYLOAD_ID, ..Config::default() }), tick: timer.tbxiv, now_ms: 0 };
+    set_frequency(&mut buzzer.pwm, 0);
+    msp430::critical_section::with(|cs| BUZZER.replace(cs, Some(buzzer)));
+}
+
+/// Start a pattern, replacing the one being played.
+pub fn play(pattern: Pattern) {
+    msp430::critical_section::with(|cs| {
+        let Some(ref mut buzzer) = *BUZZER.borrow_ref_mut(cs) else { return };
+        let frequency_hz = buzzer.player.play(pattern, buzzer.now_ms);
+        set_frequency(&mut buzzer.pwm, frequency_hz);
+    });
+}
+
+/// The pattern being played, or `Pattern::Off` once it has finished.
+pub fn pattern() -> Pattern {
+    msp430::critical_section::with(|cs| BUZZER.borrow_ref(cs).as_ref().map_or(Pattern::Off, |buzzer| buzzer.player.pattern()))
+}
+
+/// Play a square wave, or silence for 0 Hz. The output is disabled for silence, which drives the pin low.
+fn set_frequency(pwm: &mut Pwm<TB2, CCR2>, frequency_hz: u16) {
+    if frequency_hz == 0 {
+        pwm.disable();
+        return;
+    }
+    let period = (PWM_CLOCK_HZ / u32::from(frequency_hz)).clamp(2, 0x1_0000) - 1;
+    // SAFETY: TB2 is only used by the buzzer, whose state is behind a critical section
+    let tb2 = unsafe { msp430fr2355::Peripherals::steal() }.TB2;
+    // Restart the count, so it never runs past a shorter period
+    tb2.tb2ctl.modify(|_, w| w.tbclr().set_bit());
+    tb2.tb2ccr0.write(|w| unsafe { w.bits(period as u16) });
+    pwm.set_duty((period / 2) as u16);
+    pwm.enable();
+}
+
+#[interrupt]
+fn TIMER3_B1() {
+    msp430::critical_section::with(|cs| {
+        let Some(ref mut buzzer) = *BUZZER.borrow_ref_mut(cs) else { return };
+        // Reading the vector clears the flag
+        if let TimerVector::MainTimer = buzzer.tick.interrupt_vector() {
+            buzzer.now_ms = buzzer.now_ms.wrapping_add(1);
+            if let Some(frequency_hz) = buzzer.player.update(buzzer.now_ms) {
+                set_frequency(&mut buzzer.pwm, frequency_hz);
+            }
+        }
+    });
+}
@@ -0,0 +1,71 @@
+//! The mission clock. Timer_B0 counts ACLK over a 1 second period, and its overflow interrupt counts the seconds, so the
+//! time keeps up while the main loop blocks on the radio.
+
+use core::cell::RefCell;
+
+use embedded_hal::timer::CountDown;
+use msp430::interrupt::Mutex;
+use msp430fr2355::interrupt;
+use msp430fr2x5x_hal::{clock::Aclk, pac::TB0, pwm::TimerConfig, timer::{TBxIV, TimerParts3, TimerVector}};
+
+/// ACLK runs at 32768 Hz, so a period is a second.
+pub const PERIOD_TICKS: u32 = 32768;
+
+struct Clock {
+    vector: TBxIV<TB0>,
+    seconds: u32,
+}
+
+/// The seconds since boot, counted by the overflow interrupt handler.
+static CLOCK: Mutex<RefCell<Option<Clock>>> = Mutex::new(RefCell::new(None));
+
+/// Start Timer_B0 and its overflow interrupt. The time counts from here.
+pub fn configure(tb0: TB0, aclk: &Aclk) {
+    let mut timer = TimerParts3::new(tb0, TimerConfig::aclk(aclk));
+    // The timer counts from 0 up to and including the period
+    timer.timer.start((PERIOD_TICKS - 1) as u16);
+    timer.timer.enable_interrupts();
+    msp430::critical_section::with(|cs| CLOCK.replace(cs, Some(Clock { vector: timer.tbxiv, seconds: 0 })));
+}
+
+/// The count of Timer_B0 within the current second, in ACLK ticks. TB0 runs from ACLK, asynchronously to the CPU, so the
+/// count is read until two reads agree.
+pub fn ticks() -> u16 {
+    // SAFETY: Only reads TB0, which is otherwise used by the overflow interrupt handler
+    let tb0 = unsafe { msp430fr2355::Peripherals::steal() }.TB0;
+    loop {
+        let ticks = tb0.tb0r.read().bits();
+        if tb0.tb0r.read().bits() == ticks {
+            break ticks;
+        }
+    }
+}
+
+/// Milliseconds since the clock was started. It stops after 49 days, so deadlines are never reached early.
+pub fn now_ms() -> u32 {
+    msp430::critical_section::with(|cs| {
+        let seconds = CLOCK.borrow_ref(cs).as_ref().map_or(0, |clock| clock.seconds);
+        // SAFETY: Only reads TB0, which is otherwise used by the overflow interrupt handler
+        let tb0 = unsafe { msp430fr2355::Peripherals::steal() }.TB0;
+        let mut count = ticks();
+        // An overflow the handler hasn't counted yet, as interrupts are off. If it happened while reading, the count may be
+        // from before it, so read it again.
+        let pending = tb0.tb0ctl.read().tbifg().bit_is_set();
+        if pending {
+            count = ticks();
+        }
+        let ms = (u32::from(count) * 1000) / PERIOD_TICKS;
+        seconds.saturating_add(pending as u32).saturating_mul(1000).saturating_add(ms)
+    })
+}
+
+#[interrupt]
+fn TIMER0_B1() {
+    msp430::critical_section::with(|cs| {
+        let Some(ref mut clock) = *CLOCK.borrow_ref_mut(cs) else { return };
+        // Reading the vector clears the flag
+        if let TimerVector::MainTimer = clock.vector.interrupt_vector() {
+            clock.seconds = clock.seconds.saturating_add(1);
+        }
+    });
+}
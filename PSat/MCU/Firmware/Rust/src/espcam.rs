@@ -0,0 +1,168 @@
+//! The ESP32 Cam, which takes the pictures and saves them to its SD card, on a single-wire link on P2.4, bit-banged as an
+//! open-drain, half-duplex UART and timed from TB0.
+//!
+//! The same wire can be jumpered to P1.7 instead, but that is the debug UART's TX, so only P2.4 is used.
+
+use psat_camera::{client::{Client, Config, Error, Link}, frame::Status};
+use crate::pin_mappings::EspCamPin;
+
+/// Slow enough to time the bits from the 32768 Hz ACLK: a sample is at most a tick, an eighth of a bit, off its middle.
+const SINGLE_WIRE_BAUD: u32 = 2400;
+const ACLK_HZ: u32 = 32768;
+/// P2.4
+const LINE: u8 = 1 << 4;
+
+pub struct EspCam {
+    client: Client<SingleWire>,
+}
+
+/// Ask the camera for its status. The link is kept even if it doesn't answer, e.g. because the camera is still booting.
+pub fn connect(pin: EspCamPin) -> EspCam {
+    let mut camera = EspCam { client: Client::new(SingleWire::new(pin), Config::default()) };
+    match camera.client.status() {
+        Ok(status) => crate::println!("Camera: {} images, SD card {}", status.images, status.storage_ready as u8),
+        Err(Error::NoResponse) => crate::println!("No camera"),
+        Err(e) => crate::println!("Camera status failed: {}", describe(e)),
+    }
+    camera
+}
+
+impl EspCam {
+    /// Take a picture every `interval_ms` until the burst is stopped.
+    pub fn start_burst(&mut self, interval_ms: u16) -> Result<Status, &'static str> {
+        self.client.start_burst(interval_ms, 0).map_err(describe)
+    }
+
+    pub fn stop_burst(&mut self) -> Result<Status, &'static str> {
+        self.client.stop_burst().map_err(describe)
+    }
+}
+
+fn describe(error: Error) -> &'static str {
+    use psat_camera::frame::CameraError;
+    match error {
+        Error::NoResponse => "No response from the camera",
+        Error::Link(e) => e,
+        Error::Rejected(CameraError::UnknownCommand) => "Unknown camera command",
+        Error::Rejected(CameraError::InvalidArgument) => "Invalid camera argument",
+        Error::Rejected(CameraError::Busy) => "Camera busy",
+        Error::Rejected(CameraError::CaptureFailed) => "Capture failed",
+        Error::Rejected(CameraError::StorageFailed) => "Saving the picture failed",
+    }
+}
+
+/// 8N1 at `SINGLE_WIRE_BAUD` on P2.4. The line is only ever driven low, and released to the pull-up for a high, so both ends
+/// can drive it. Each bit is timed from the start of its byte, so the rounding to ACLK ticks doesn't add up.
+pub struct SingleWire {
+    _pin: EspCamPin,
+}
+impl SingleWire {
+    fn new(pin: EspCamPin) -> Self {
+        release_line();
+        Self { _pin: pin }
+    }
+
+    /// Wait for the line to go `high`, and return `false` if it hasn't by `timeout` ticks on the stopwatch.
+    fn wait_for_line(high: bool, stopwatch: &mut Stopwatch, timeout: u32) -> bool {
+        while line_is_high() != high {
+            if stopwatch.lap() >= timeout {
+                return false;
+            }
+        }
+        true
+    }
+}
+impl Link for SingleWire {
+    fn send(&mut self, frame: &[u8]) -> Result<(), &'static str> {
+        for &byte in frame {
+            // Start bit, 8 data bits LSB first, stop bit
+            let bits = (u16::from(byte) << 1) | 1 << 9;
+            let mut stopwatch = Stopwatch::start();
+            for bit in 0..10 {
+                if bits >> bit & 1 == 0 { drive_line_low() } else { release_line() }
+                stopwatch.wait_until(half_bits(2 * (bit + 1)));
+            }
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self, timeout_ms: u32) -> Option<u8> {
+        let timeout = timeout_ms.saturating_mul(ACLK_HZ) / 1000;
+        let mut timeout_stopwatch = Stopwatch::start();
+        loop {
+            // A start bit must still be low in its middle, so a glitch isn't taken for one
+            if !Self::wait_for_line(false, &mut timeout_stopwatch, timeout) {
+                return None;
+            }
+            let mut stopwatch = Stopwatch::start();
+            stopwatch.wait_until(half_bits(1));
+            if line_is_high() {
+                continue;
+            }
+            let mut byte = 0;
+            for bit in 0..8 {
+                stopwatch.wait_until(half_bits(2 * bit + 3));
+                byte |= (line_is_high() as u8) << bit;
+            }
+            stopwatch.wait_until(half_bits(19));
+            if line_is_high() {
+                return Some(byte);
+            }
+            // Framing error, e.g. the line is held low. Look for the next start bit once it's released.
+            if !Self::wait_for_line(true, &mut timeout_stopwatch, timeout) {
+                return None;
+            }
+        }
+    }
+}
+
+fn drive_line_low() {
+    // SAFETY: P2.4 is owned by `SingleWire`, and only its bits are changed
+    let p2 = unsafe { msp430fr2355::Peripherals::steal() }.P2;
+    p2.p2out.modify(|r, w| unsafe { w.bits(r.bits() & !LINE) });
+    p2.p2dir.modify(|r, w| unsafe { w.bits(r.bits() | LINE) });
+}
+
+/// Let the pull-up take the line high.
+fn release_line() {
+    // SAFETY: P2.4 is owned by `SingleWire`, and only its bits are changed
+    let p2 = unsafe { msp430fr2355::Peripherals::steal() }.P2;
+    p2.p2dir.modify(|r, w| unsafe { w.bits(r.bits() & !LINE) });
+    p2.p2out.modify(|r, w| unsafe { w.bits(r.bits() | LINE) });
+}
+
+fn line_is_high() -> bool {
+    // SAFETY: Only reads P2IN
+    let p2 = unsafe { msp430fr2355::Peripherals::steal() }.P2;
+    p2.p2in.read().bits() & LINE != 0
+}
+
+/// The time of `n` half bits on the single-wire link, in ACLK ticks.
+fn half_bits(n: u32) -> u32 {
+    n * ACLK_HZ / (2 * SINGLE_WIRE_BAUD)
+}
+
+/// Measures time in ACLK ticks from the count of the mission clock. Laps must be shorter than its 1 second period, so they
+/// can't be told apart from the next period.
+struct Stopwatch {
+    last: u32,
+    elapsed: u32,
+}
+impl Stopwatch {
+    fn start() -> Self {
+        Self { last: crate::clock::ticks().into(), elapsed: 0 }
+    }
+
+    /// The ticks since the start.
+    fn lap(&mut self) -> u32 {
+        let now = crate::clock::ticks().into();
+        let ticks = if now >= self.last { now - self.last } else { now + crate::clock::PERIOD_TICKS - self.last };
+        self.last = now;
+        self.elapsed = self.elapsed.saturating_add(ticks);
+        self.elapsed
+    }
+
+    fn wait_until(&mut self, ticks: u32) {
+        while self.lap() < ticks {}
+    }
+}
@@ -0,0 +1,342 @@
+//! The mission loop. A telemetry frame goes out every 5 seconds, followed by a window for uplink commands. In between,
+//! every subsystem gets a step: the IMU, light sensor and GPS feed the flight state machine, and the servos, buzzer and
+//! camera follow its state.
+
+use core::time::Duration;
+use psat_buzzer::pattern::Pattern;
+use psat_command::auth::Key;
+use psat_command::frame::{Command, NackReason};
+use psat_command::receiver::Receiver;
+use psat_flight::machine::{Config, FlightStateMachine, Inputs, Snapshot, SNAPSHOT_LEN};
+use psat_nav::kinematics::Sample;
+use psat_nmea::sentence::{Message, Sentence};
+use psat_servo::mechanism::{Action, Outcome};
+use psat_telemetry::frame::{Acceleration, FlightState, Position, Telemetry};
+use crate::board::Board;
+use crate::gps::{Gps, GpsGgaPacket};
+use crate::lora::{RadioError, TX_TIMEOUT};
+use crate::persistent::{slots, CommandCounter, InfoFram};
+use crate::servo::Servos;
+
+const TX_INTERVAL_MS: u32 = 5000;
+/// After each frame the radio listens this long, so the ground station can send commands as soon as it has received one.
+const COMMAND_WINDOW: Duration = Duration::from_millis(1500);
+/// While waiting for recovery the GPS gets one fix every 10 minutes, and is powered off in between.
+const RECOVERY_GPS_OFF_MS: u32 = 10 * 60 * 1000;
+/// The light sensor is read at 20 Hz, so a 200 ms flash is seen in a few readings.
+const LIGHT_PERIOD_MS: u32 = 50;
+/// The camera takes a picture every second from ejection until landing.
+const CAMERA_BURST_INTERVAL_MS: u16 = 1000;
+
+/// The pre-shared uplink command key, set as 32 hex digits in `PSAT_COMMAND_KEY` at build time.
+/// Bench builds without a key need the `dev-key` feature, which uses an all-zero key that must not be flown.
+const COMMAND_KEY: Key = match option_env!("PSAT_COMMAND_KEY") {
+    Some(hex) => match Key::from_hex(hex) {
+        Some(key) => key,
+        None => panic!("PSAT_COMMAND_KEY must be 32 hex digits"),
+    },
+    None if cfg!(feature = "dev-key") => Key([0; 16]),
+    None => panic!("PSAT_COMMAND_KEY is not set; build with the dev-key feature to use the development key"),
+};
+
+/// Run the mission. Fields without a driver yet are left at their defaults in the telemetry.
+pub fn run(board: Board) -> ! {
+    let mut mission = Mission::new(board);
+    loop {
+        // Frames go out on a fixed schedule, however long the radio blocked for
+        let next_tx_ms = crate::clock::now_ms().saturating_add(TX_INTERVAL_MS);
+        mission.telemetry();
+        mission.commands();
+        // Keep draining the GPS and the IMU FIFO while waiting, so their buffers don't overflow
+        loop {
+            let now_ms = crate::clock::now_ms();
+            if now_ms >= next_tx_ms {
+                break;
+            }
+            mission.imu(now_ms);
+            let light = mission.light(now_ms);
+            mission.servos(now_ms);
+            let fix = mission.gps(now_ms);
+            mission.flight(now_ms, fix, light);
+            mission.buzzer();
+            mission.camera();
+        }
+    }
+}
+
+struct Mission {
+    board: Board,
+    telemetry: Telemetry,
+    flight: FlightStateMachine,
+    commands: Receiver,
+    /// The latest time given to the flight state machine, whose inputs must not go back in time
+    last_update_ms: u32,
+    next_light_ms: u32,
+    /// The flight state the buzzer has last played a pattern for
+    buzzer_state: FlightState,
+    /// Whether the camera has last been told to take pictures, so it is only told on a change
+    camera_bursting: Option<bool>,
+    /// The ground station asked for a GPS fix, which is sent as soon as there is one
+    fix_requested: bool,
+}
+impl Mission {
+    fn new(mut board: Board) -> Self {
+        let flight = restore_flight_state(&board.fram);
+        let telemetry = Telemetry { flight_state: flight.state(), ..Telemetry::default() };
+        // A payload that resumes after landing goes straight back to the locator chirp
+        let boot_pattern = match (board.radio.is_initialised(), board.imu.who_am_i()) {
+            (false, _) => Pattern::ErrorCode(crate::buzzer::ERROR_RADIO),
+            (true, Err(_)) => Pattern::ErrorCode(crate::buzzer::ERROR_IMU),
+            (true, Ok(_)) => Pattern::BootOk,
+        };
+        crate::buzzer::play(state_pattern(flight.state()).unwrap_or(boot_pattern));
+        let commands = Receiver::new(COMMAND_KEY, &mut CommandCounter(&mut board.fram));
+        if cfg!(feature = "dev-key") && option_env!("PSAT_COMMAND_KEY").is_none() {
+            crate::println!("Warning: using the development command key");
+        }
+        Self { board, telemetry, buzzer_state: flight.state(), flight, commands, last_update_ms: 0, next_light_ms: 0, camera_bursting: None, fix_requested: false }
+    }
+
+    /// Send a telemetry frame with the latest GPS fix, acceleration and light level.
+    fn telemetry(&mut self) {
+        let telemetry = &mut self.telemetry;
+        telemetry.uptime_s = crate::clock::now_ms() / 1000;
+        telemetry.battery_mv = self.board.battery_voltage_mv();
+        telemetry.light_level = self.board.light.detector().level();
+        match self.board.imu.read() {
+            Ok(sample) => {
+                let [x_mg, y_mg, z_mg] = sample.acceleration_mg;
+                telemetry.acceleration = Acceleration { x_mg, y_mg, z_mg };
+            },
+            Err(e) => crate::println!("IMU read failed: {}", e),
+        }
+        let radio = &mut self.board.radio;
+        // The modem didn't respond at boot, or its last recovery failed
+        if !radio.is_initialised() {
+            if let Err(e) = radio.recover() {
+                crate::println!("Radio recovery failed: {}", e);
+            }
+        }
+        if radio.is_initialised() {
+            telemetry.radio.recoveries = radio.recoveries();
+            if let Err(e) = radio.blocking_transmit_with_recovery(&telemetry.encode(), TX_TIMEOUT, 3) {
+                crate::println!("Telemetry TX failed: {}", e);
+            }
+        }
+        telemetry.sequence = telemetry.sequence.wrapping_add(1);
+        self.board.gpio.green_led.toggle();
+    }
+
+    /// Listen for a command, execute it and send the response. Anything that is not an authentic command frame is ignored
+    /// and not answered.
+    fn commands(&mut self) {
+        let radio = &mut self.board.radio;
+        if !radio.is_initialised() {
+            return;
+        }
+        let mut buf = [0u8; embedded_lora_rfm95::rfm95::RFM95_FIFO_SIZE];
+        let response = match radio.blocking_recieve(&mut buf, COMMAND_WINDOW) {
+            Ok(frame) => {
+                let (flight, servos, gps) = (&mut self.flight, &mut self.board.servos, &mut self.board.gps);
+                let fix_requested = &mut self.fix_requested;
+                self.commands.handle(frame, &mut CommandCounter(&mut self.board.fram), |command| execute_command(command, flight, servos, gps, fix_requested)).ok()
+            },
+            Err(RadioError::Timeout) => None,
+            Err(e) => {
+                crate::println!("Command RX failed: {}", e);
+                None
+            },
+        };
+        sync_flight_state(&self.flight, &mut self.telemetry, &mut self.board.fram);
+        let Some(response) = response else { return };
+        if let Ok(rssi) = radio.driver.get_rssi() {
+            self.telemetry.radio.rssi_dbm = rssi;
+        }
+        if let Ok(snr) = radio.driver.get_packet_snr() {
+            self.telemetry.radio.snr_db = snr;
+        }
+        if let Err(e) = radio.blocking_transmit_with_recovery(&response.encode(), TX_TIMEOUT, 3) {
+            crate::println!("Response TX failed: {}", e);
+        }
+    }
+
+    /// Feed the IMU FIFO to the flight state machine once armed and in flight, so it can detect the boost.
+    fn imu(&mut self, now_ms: u32) {
+        if !matches!(self.flight.state(), FlightState::Armed | FlightState::Ascent | FlightState::Ejection | FlightState::Descent) {
+            return;
+        }
+        // Samples from before the last update, i.e. from before the payload was armed, are clamped to it
+        let (flight, telemetry, fram, last_update_ms) = (&mut self.flight, &mut self.telemetry, &mut self.board.fram, &mut self.last_update_ms);
+        let drained = crate::imu::drain_fifo(&mut self.board.imu, now_ms, |time_ms, sample| {
+            *last_update_ms = (*last_update_ms).max(time_ms);
+            let inputs = Inputs { now_ms: *last_update_ms, acceleration_mg: Some(sample.acceleration_magnitude_mg()), ..Inputs::default() };
+            if flight.update(&inputs).is_some() {
+                sync_flight_state(flight, telemetry, fram);
+            }
+        });
+        if let Err(e) = drained {
+            crate::println!("IMU FIFO read failed: {}", e);
+        }
+    }
+
+    /// Read the light sensor, and return whether it sees light if it was read. The light is read in every phase for the
+    /// telemetry, but the dark baseline is only calibrated once armed, when the payload is sealed in the capsule.
+    fn light(&mut self, now_ms: u32) -> Option<bool> {
+        if now_ms < self.next_light_ms {
+            return None;
+        }
+        self.next_light_ms = now_ms.saturating_add(LIGHT_PERIOD_MS);
+        if self.flight.state() == FlightState::PreLaunch {
+            self.board.light.recalibrate();
+        }
+        self.board.light.update(&mut self.board.adc, now_ms);
+        self.board.light.detector().light()
+    }
+
+    /// Move the servos, and wind the shutter on once a picture has been taken.
+    fn servos(&mut self, now_ms: u32) {
+        match self.board.servos.update(&mut self.board.adc, now_ms) {
+            Some(Outcome::Done(Action::TriggerShutter)) => {
+                if let Err(e) = self.board.servos.start(Action::Reload, now_ms) {
+                    crate::println!("Reload failed: {}", e);
+                }
+            },
+            Some(Outcome::Jammed(_)) => crate::buzzer::play(Pattern::ErrorCode(crate::buzzer::ERROR_SERVO_JAMMED)),
+            _ => (),
+        }
+    }
+
+    /// Supervise the GPS, and copy the latest GGA fix into the telemetry. The position is cleared when the fix is lost,
+    /// but kept while the GPS is powered off between fixes during recovery. A requested fix is sent right away. Returns a new
+    /// fix for the flight state machine.
+    fn gps(&mut self, now_ms: u32) -> Option<Sample> {
+        let gps = &mut self.board.gps;
+        let telemetry = &mut self.telemetry;
+        let off_ms = (telemetry.flight_state == FlightState::Recovery).then_some(RECOVERY_GPS_OFF_MS);
+        gps.set_duty_cycle(now_ms, off_ms);
+        let sentence = gps.update(now_ms);
+        telemetry.satellites = gps.supervisor().satellites();
+
+        let Some(Sentence { message: Message::Gga(gga), .. }) = sentence else { return None };
+        let gga = GpsGgaPacket::from(gga);
+        telemetry.position = gga.fix().map(|fix| Position {
+            latitude_e7: fix.coordinates().latitude_e7,
+            longitude_e7: fix.coordinates().longitude_e7,
+            altitude_dm: (fix.altitude_m().unwrap_or(0.0) * 10.0) as i32,
+        });
+        // Without an altitude the fix is useless for launch, apogee and landing detection
+        let has_altitude = gga.fix().is_some_and(|fix| fix.altitude_m().is_some());
+        let sample = telemetry.position.filter(|_| has_altitude).map(|position| Sample { position, time_ms: now_ms });
+        if sample.is_some() && self.fix_requested {
+            self.fix_requested = false;
+            self.telemetry();
+        }
+        sample
+    }
+
+    /// Give the flight state machine the latest fix and light reading.
+    fn flight(&mut self, now_ms: u32, fix: Option<Sample>, light: Option<bool>) {
+        self.last_update_ms = now_ms;
+        if self.flight.update(&Inputs { now_ms, fix, light, ..Inputs::default() }).is_some() {
+            sync_flight_state(&self.flight, &mut self.telemetry, &mut self.board.fram);
+        }
+    }
+
+    /// Play the pattern for a new flight state: a warning while armed, silence in flight, and the locator chirp once landed.
+    fn buzzer(&mut self) {
+        if self.flight.state() == self.buzzer_state {
+            return;
+        }
+        self.buzzer_state = self.flight.state();
+        match state_pattern(self.buzzer_state) {
+            Some(pattern) => crate::buzzer::play(pattern),
+            // Disarmed
+            None if crate::buzzer::pattern() == Pattern::ArmedWarning => crate::buzzer::play(Pattern::Off),
+            None => (),
+        }
+    }
+
+    /// Start the camera's burst at ejection and stop it on landing. Camera commands block, so they are sent here rather
+    /// than on the state change, which may be mid-drain. A command is only tried once, as a missing camera would hold up
+    /// the loop on every retry.
+    fn camera(&mut self) {
+        let bursting = camera_burst(self.flight.state());
+        if bursting.is_none() || bursting == self.camera_bursting {
+            return;
+        }
+        self.camera_bursting = bursting;
+        let camera = &mut self.board.camera;
+        let status = if bursting == Some(true) { camera.start_burst(CAMERA_BURST_INTERVAL_MS) } else { camera.stop_burst() };
+        match status {
+            Ok(status) => crate::println!("Camera bursting {}, {} images", status.bursting as u8, status.images),
+            Err(e) => crate::println!("Camera command failed: {}", e),
+        }
+    }
+}
+
+/// Resume the flight state machine from the snapshot in FRAM, or start on the pad if there is none.
+fn restore_flight_state(fram: &InfoFram) -> FlightStateMachine {
+    match fram.read::<SNAPSHOT_LEN>(slots::FLIGHT_STATE).map(Snapshot::from_bytes) {
+        Some(Ok(snapshot)) => {
+            crate::println!("Resuming flight in state {}", snapshot.state as u8);
+            FlightStateMachine::restore(Config::default(), snapshot, 0)
+        },
+        Some(Err(e)) => {
+            crate::println!("Flight state in FRAM is invalid: {}", e);
+            FlightStateMachine::new(Config::default(), 0)
+        },
+        None => FlightStateMachine::new(Config::default(), 0),
+    }
+}
+
+/// Copy a changed flight state into the telemetry and persist it. Only transitions are written, so a reset
+/// mid-ascent tracks apogee again from the next fix.
+fn sync_flight_state(flight: &FlightStateMachine, telemetry: &mut Telemetry, fram: &mut InfoFram) {
+    if flight.state() == telemetry.flight_state {
+        return;
+    }
+    telemetry.flight_state = flight.state();
+    crate::println!("Flight state: {}, peak acceleration {} mg", telemetry.flight_state as u8, flight.peak_acceleration_mg());
+    if let Err(e) = fram.write(slots::FLIGHT_STATE, flight.snapshot().to_bytes()) {
+        crate::println!("Flight state not saved: {}", e);
+    }
+}
+
+/// The buzzer pattern for a flight state. On the pad the buzzer is left to play whatever was selected.
+fn state_pattern(state: FlightState) -> Option<Pattern> {
+    match state {
+        FlightState::PreLaunch => None,
+        FlightState::Armed => Some(Pattern::ArmedWarning),
+        FlightState::Ascent | FlightState::Ejection | FlightState::Descent => Some(Pattern::Off),
+        FlightState::Landed | FlightState::Recovery => Some(Pattern::Locator),
+    }
+}
+
+/// Whether the camera should be taking pictures in a flight state, or `None` to leave it as it is.
+fn camera_burst(state: FlightState) -> Option<bool> {
+    match state {
+        FlightState::Ejection | FlightState::Descent => Some(true),
+        FlightState::Landed | FlightState::Recovery => Some(false),
+        FlightState::PreLaunch | FlightState::Armed | FlightState::Ascent => None,
+    }
+}
+
+/// Execute an uplink command.
+fn execute_command(command: Command, flight: &mut FlightStateMachine, servos: &mut Servos, gps: &mut Gps, fix_requested: &mut bool) -> Result<(), NackReason> {
+    let now_ms = crate::clock::now_ms();
+    let armed = flight.state() == FlightState::Armed;
+    match command {
+        Command::Arm => flight.arm(now_ms).map_err(|_| NackReason::InvalidState),
+        Command::Disarm => flight.disarm(now_ms).map_err(|_| NackReason::InvalidState),
+        Command::TriggerServo if !armed => Err(NackReason::InvalidState),
+        // The shutter is wound on once the picture has been taken. A busy mechanism is a state the ground station can wait out.
+        Command::TriggerServo => servos.start(Action::TriggerShutter, now_ms).map_err(|_| NackReason::InvalidState),
+        Command::Buzzer { pattern } => Pattern::try_from(pattern).map(crate::buzzer::play).map_err(|_| NackReason::InvalidArgument),
+        // A GPS that is off between duty cycles is woken, and the fix goes out in a telemetry frame as soon as there is one
+        Command::RequestGpsFix => {
+            gps.wake(now_ms);
+            *fix_requested = true;
+            Ok(())
+        },
+    }
+}
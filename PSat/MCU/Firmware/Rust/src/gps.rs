@@ -1,46 +1,173 @@
 #![allow(dead_code)]
 
-use core::num::{ParseFloatError, ParseIntError};
+use core::cell::RefCell;
 
-use arrayvec::ArrayVec;
+use msp430::interrupt::Mutex;
+use msp430fr2355::interrupt;
 use msp430fr2x5x_hal::{
     clock::Smclk, 
-    serial::{BitCount, BitOrder, Loopback, Parity, SerialConfig, StopBits}};
-use embedded_hal::serial::Read;
-use crate::pin_mappings::{GpsEusci, GpsRx, GpsRxPin, GpsTx, GpsTxPin};
+    hal::blocking::delay::DelayMs,
+    serial::{BitCount, BitOrder, Loopback, Parity, RecvError, SerialConfig, StopBits, UsciA1RxPin, UsciA1TxPin}};
+use embedded_hal::{digital::v2::OutputPin, serial::{Read, Write}};
+use psat_nmea::{parser::{Parser, Raw}, pmtk::{AckResult, BaudRate, Command, NavigationMode, Response}, ring::RingBuffer, sentence::{Coordinates, FixQuality, Gga, Message, Sentence, Time},
+    supervisor::{Config, Event, StartKind, Supervisor}};
+use crate::{pin_mappings::{GpsEnPin, GpsEusci, GpsRx, GpsRxPin, GpsTx, GpsTxPin}, println};
 
 const NMEA_MESSAGE_MAX_LEN: usize = 82;
+/// How long to wait for the GPS to acknowledge a PMTK command.
+const COMMAND_TIMEOUT_MS: u16 = 1000;
+/// How often to check for an acknowledgement while waiting.
+const COMMAND_POLL_MS: u16 = 10;
+
+/// Bytes received from the GPS, filled by the eUSCI_A1 interrupt. 
+/// At 9600 baud this holds about half a second of the default NMEA output, so poll at least that often.
+static RX_BUFFER: RingBuffer<512> = RingBuffer::new();
+/// The receiver, owned by the interrupt handler.
+static RX: Mutex<RefCell<Option<GpsRx>>> = Mutex::new(RefCell::new(None));
+/// Hardware overruns, i.e. bytes lost because the interrupt was held off for more than a byte time.
+static OVERRUNS: Mutex<RefCell<u16>> = Mutex::new(RefCell::new(0));
 
 pub struct Gps {
     tx: GpsTx,
-    rx: GpsRx,
+    parser: Parser,
+    /// The last PMTK response that hasn't been taken yet.
+    response: Option<Response>,
+    /// Active low power enable.
+    enable: GpsEnPin,
+    supervisor: Supervisor,
 }
 impl Gps {
-    pub fn new(eusci_reg: GpsEusci, smclk: &Smclk, tx_pin: GpsTxPin, rx_pin: GpsRxPin) -> Self {
-        // Configure UART peripheral
-        let (tx, rx) = SerialConfig::new(eusci_reg, 
-            BitOrder::LsbFirst, 
-            BitCount::EightBits, 
-            StopBits::OneStopBit, 
-            Parity::NoParity, 
-            Loopback::NoLoop, 
-            9600)
-            .use_smclk(smclk)
-            .split(tx_pin, rx_pin);
-        Self {tx, rx}
+    /// Powers the module on. The supervisor's clock starts at 0 ms, so pass the time since boot to `update`.
+    pub fn new(eusci_reg: GpsEusci, smclk: &Smclk, tx_pin: GpsTxPin, rx_pin: GpsRxPin, mut enable: GpsEnPin) -> Self {
+        // The module always starts at its factory baud rate
+        let tx = configure_uart(eusci_reg, smclk, BaudRate::B9600, tx_pin, rx_pin);
+        enable.set_low().ok();
+        Self {tx, parser: Parser::new(), response: None, enable, supervisor: Supervisor::new(Config::default(), 0)}
     } 
+    /// Supervise the module: poll for a sentence, measure the time to first fix, power cycle the module if it doesn't get 
+    /// a fix in time, and power it on and off when duty cycling. Call this regularly, with the time in milliseconds.
+    pub fn update(&mut self, now_ms: u32) -> Option<Sentence> {
+        let sentence = self.poll();
+        let mut event = match sentence {
+            Some(Sentence { message: Message::Gga(gga), .. }) => {
+                let gga = GpsGgaPacket::from(gga);
+                self.supervisor.update(now_ms, gga.fix().is_some(), gga.num_satellites())
+            },
+            _ => None,
+        };
+        // Settings are lost when the module is powered off, so restore them whenever it (re)starts
+        if let Some(Response::Startup) = self.response {
+            self.response = None;
+            self.send(Command::SetNavigationMode(NavigationMode::Balloon)).ok();
+        }
+        if event.is_none() { event = self.supervisor.poll(now_ms); }
+        if let Some(event) = event { log_event(event); }
+
+        // The enable pin is active low
+        if self.supervisor.is_powered() { self.enable.set_low().ok(); } 
+        else { self.enable.set_high().ok(); }
+        sentence
+    }
+    /// Power the module off for `off_ms` between fixes, e.g. to save battery while waiting for recovery. `None` keeps it on.
+    pub fn set_duty_cycle(&mut self, now_ms: u32, off_ms: Option<u32>) {
+        if let Some(event) = self.supervisor.set_duty_cycle(now_ms, off_ms) { log_event(event); }
+    }
+    /// Power the module on for a fix now, if it is off between duty cycles. `update` powers it off again after the fix.
+    pub fn wake(&mut self, now_ms: u32) {
+        if let Some(event) = self.supervisor.wake(now_ms) { log_event(event); }
+    }
+    /// Fix statistics: time to first fix, start kind, satellites, power cycles.
+    pub fn supervisor(&self) -> &Supervisor {
+        &self.supervisor
+    }
+    /// Return the next valid GGA, RMC, GSA, GSV, VTG or GLL sentence if one has been received, without blocking. 
+    /// Sentences with bad checksums and unsupported sentences are skipped. PMTK responses are kept for `take_response`.
+    pub fn poll(&mut self) -> Option<Sentence> {
+        while let Some(byte) = RX_BUFFER.pop() {
+            let Some(Ok(raw)) = self.parser.push(byte) else { continue };
+            if raw.address.starts_with(b"PMTK") {
+                if let Ok(response) = Response::try_from(raw) { self.response = Some(response); }
+            }
+            else if let Ok(sentence) = Sentence::try_from(raw) {
+                return Some(sentence);
+            }
+        }
+        None
+    }
+    /// Take the last PMTK response, e.g. the startup message after a restart.
+    pub fn take_response(&mut self) -> Option<Response> {
+        self.response.take()
+    }
+    /// Send a PMTK command without waiting for a response.
+    pub fn send(&mut self, command: Command) -> Result<(), &'static str> {
+        let packet = command.encode()?;
+        for &byte in packet.as_bytes() {
+            nb::block!(self.tx.write(byte)).ok();
+        }
+        nb::block!(self.tx.flush()).ok();
+        Ok(())
+    }
+    /// Send a PMTK command and wait for it to be acknowledged, if the GPS acknowledges it at all.
+    /// NMEA sentences that arrive in the meantime are discarded.
+    pub fn command(&mut self, command: Command, delay: &mut impl DelayMs<u16>) -> Result<(), &'static str> {
+        self.response = None;
+        self.send(command)?;
+        if !command.is_acknowledged() { return Ok(()); }
+
+        for _ in 0..COMMAND_TIMEOUT_MS / COMMAND_POLL_MS {
+            while self.poll().is_some() {}
+            if let Some(Response::Ack(ack)) = self.response.take() {
+                if !ack.is_for(&command) { continue; }
+                return match ack.result {
+                    AckResult::Succeeded => Ok(()),
+                    AckResult::Failed => Err("GPS command failed"),
+                    AckResult::Unsupported => Err("GPS command unsupported"),
+                    AckResult::InvalidCommand => Err("GPS command invalid"),
+                };
+            }
+            delay.delay_ms(COMMAND_POLL_MS);
+        }
+        Err("GPS command not acknowledged")
+    }
+    /// Switch the GPS and our UART to another baud rate. 
+    /// This doesn't persist: the module returns to 9600 baud when it is power cycled.
+    pub fn set_baud_rate(&mut self, baud: BaudRate, smclk: &Smclk) -> Result<(), &'static str> {
+        self.send(Command::SetBaudRate(baud))?;
+
+        // SAFETY: eUSCI_A1 was moved into `Gps::new` and is only ever accessed through this struct and the interrupt handler, 
+        // which we stop by taking the receiver first.
+        msp430::critical_section::with(|cs| RX.replace(cs, None));
+        let eusci_reg = unsafe { msp430fr2355::Peripherals::steal() }.E_USCI_A1;
+        self.tx = configure_uart(eusci_reg, smclk, baud, UsciA1TxPin, UsciA1RxPin);
+        self.parser = Parser::new();
+        Ok(())
+    }
+    /// Block until the next valid sentence arrives.
+    pub fn read_sentence(&mut self) -> Sentence {
+        loop {
+            if let Some(sentence) = self.poll() { return sentence; }
+        }
+    }
+    /// Bytes lost because `poll` wasn't called often enough, and bytes lost to UART overruns.
+    pub fn lost_bytes(&self) -> (u16, u16) {
+        (RX_BUFFER.dropped(), msp430::critical_section::with(|cs| *OVERRUNS.borrow_ref(cs)))
+    }
+    /// Block until the next byte arrives.
+    fn read_byte(&mut self) -> u8 {
+        loop {
+            if let Some(byte) = RX_BUFFER.pop() { return byte; }
+        }
+    }
     /// Get a GPS GGA packet as a `&[u8]`. Useful if you're just sending over the radio.
     pub fn get_raw_gga_packet<'a>(&mut self, buf: &'a mut [AsciiChar; NMEA_MESSAGE_MAX_LEN]) -> &'a [AsciiChar] {
         loop {
             // Wait until start of a message. Messages begin with '$'
-            loop {
-                if let Ok(b'$') = self.rx.read() { break }
-            }
+            while self.read_byte() != b'$' {}
             buf[0] = b'$';
 
             // Store message ID
             for i in 1..=5 {
-                buf[i] = self.rx.read().unwrap_or_else(|_| panic!("Err in serial read"));
+                buf[i] = self.read_byte();
             }
 
             // Check if the message is a GGA-type message. If not, wait for the start of the next message.
@@ -49,136 +176,130 @@ impl Gps {
             let mut i = 0;
             for (idx, chr) in buf.iter_mut().enumerate().skip(6) {
                 i = idx;
-                *chr = match self.rx.read() {
-                    Ok(b'\n') => break,
-                    Ok(c) => c,
-                    Err(_) => panic!("Err in serial read"),
+                *chr = match self.read_byte() {
+                    b'\n' => break,
+                    c => c,
                 }
             }
             buf[i] = b'\n';
+
+            // Drop sentences that were corrupted on the wire
+            if Raw::parse(&buf[0..=i]).is_err() { continue; }
             return &buf[0..=i];
         }
     }
-    /// Get a GPS GGA packet as a struct with native fields. Useful for interpreting.
-    pub fn try_get_packet_as_struct(&mut self) -> Result<GpsGgaPacket, IntOrFloatParseError> {
-        let mut buf = [b'\0'; NMEA_MESSAGE_MAX_LEN];
-        let str = self.get_raw_gga_packet(&mut buf);
-        AsciiGpsGgaPacket::try_from(str).unwrap().try_into()
+    /// Block until the next GGA packet, as a struct with native fields. Useful for interpreting.
+    pub fn get_packet_as_struct(&mut self) -> GpsGgaPacket {
+        loop {
+            if let Sentence { message: Message::Gga(gga), .. } = self.read_sentence() { return gga.into(); }
+        }
     }
 }
 
-type AsciiChar = u8;
-
-// A GGA packet in intermediate ASCII form.
-#[derive(Default)]
-pub struct AsciiGpsGgaPacket {
-    utc_time:   [AsciiChar; 10],
-    latitude:   [AsciiChar; 9],
-    north_south_indicator: AsciiChar,
-    longitude:  [AsciiChar; 10],
-    east_west_indicator: AsciiChar,
-    position_fix_indicator: AsciiChar,
-    num_satellites: ArrayVec<AsciiChar, 2>,
-    mean_sea_level_altitude: ArrayVec<AsciiChar, 7>,
-    altitude_units: AsciiChar,
+fn log_event(event: Event) {
+    let start_name = |start| match start {
+        StartKind::Cold => "cold",
+        StartKind::Warm => "warm",
+        StartKind::Hot => "hot",
+    };
+    match event {
+        Event::PoweredOn(start) => println!("GPS on, {} start", start_name(start)),
+        Event::Fixed { start, ttff_ms } => println!("GPS fix after {} ms ({} start)", ttff_ms, start_name(start)),
+        Event::Lost => println!("GPS fix lost"),
+        Event::TimedOut => println!("GPS fix timed out, powering off"),
+        Event::Sleeping => println!("GPS off until next fix"),
+    }
 }
 
-impl TryFrom<&[AsciiChar]> for AsciiGpsGgaPacket {
-    type Error = GgaParseError;
-
-    fn try_from(message: &[AsciiChar]) -> Result<Self, Self::Error> {
-        let sections = message.split(|&c| c == b',');
-        if sections.clone().count() != 15 { return Err(GgaParseError::WrongSectionCount) }
-        let mut packet = Self::default();
-        for (i, section) in sections.enumerate() {
-            match i {
-                1  => if section.len() != 10 {return Err(GgaParseError::WrongSectionLength)} else { packet.utc_time.clone_from_slice(section) },
-                2  => if section.len() != 9  {return Err(GgaParseError::WrongSectionLength)} else { packet.latitude.clone_from_slice(section) },
-                3  => packet.north_south_indicator = section[0],
-                4  => if section.len() != 10 {return Err(GgaParseError::WrongSectionLength)} else { packet.longitude.clone_from_slice(section) },
-                5  => packet.east_west_indicator = section[0],
-                6  => packet.position_fix_indicator = section[0],
-                7  => packet.num_satellites.try_extend_from_slice(section).map_err(|_| GgaParseError::WrongSectionLength)?,
-                9  => packet.mean_sea_level_altitude.try_extend_from_slice(section).map_err(|_| GgaParseError::WrongSectionLength)?,
-                10 => packet.altitude_units = section[0],
-                _ => continue,
-            };
-        } 
-        Ok(packet)
-    }
+/// Configure the UART to the GPS, and hand the receiver to the interrupt handler. 
+/// Interrupts are enabled globally once the board is configured.
+fn configure_uart(eusci_reg: GpsEusci, smclk: &Smclk, baud: BaudRate, tx_pin: impl Into<UsciA1TxPin>, rx_pin: impl Into<UsciA1RxPin>) -> GpsTx {
+    let (tx, mut rx) = SerialConfig::new(eusci_reg, 
+        BitOrder::LsbFirst, 
+        BitCount::EightBits, 
+        StopBits::OneStopBit, 
+        Parity::NoParity, 
+        Loopback::NoLoop, 
+        baud.bits_per_second())
+        .use_smclk(smclk)
+        .split(tx_pin, rx_pin);
+
+    rx.enable_rx_interrupts();
+    msp430::critical_section::with(|cs| RX.replace(cs, Some(rx)));
+    tx
 }
-#[derive(Debug)]
-pub enum GgaParseError {
-    WrongSectionCount,
-    InvalidChecksum,
-    WrongSectionLength,
+
+#[interrupt]
+fn EUSCI_A1() {
+    msp430::critical_section::with(|cs| {
+        let Some(ref mut rx) = *RX.borrow_ref_mut(cs) else { return };
+        match rx.read() {
+            Ok(byte) => { RX_BUFFER.push(byte); },
+            // The byte that caused the overrun is still valid, but the one before it was lost
+            Err(nb::Error::Other(RecvError::Overrun(byte))) => {
+                RX_BUFFER.push(byte);
+                let mut overruns = OVERRUNS.borrow_ref_mut(cs);
+                *overruns = overruns.saturating_add(1);
+            },
+            Err(_) => (),
+        }
+    });
 }
 
-// A GGA packet in native form. Useful for interpreting the results on-device.
+type AsciiChar = u8;
+
+/// A GGA packet in native form. Useful for interpreting the results on-device.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct GpsGgaPacket {
-    utc_time: UtcTime,
-    latitude: f32,
-    north_south_indicator: AsciiChar,
-    longitude: f32,
-    east_west_indicator: AsciiChar,
-    position_fix_indicator: PositionFixIndicator,
+    utc_time: Option<Time>,
+    quality: FixQuality,
     num_satellites: u8,
-    mean_sea_level_altitude: f32,
+    fix: Option<Fix>,
 }
-impl TryFrom<AsciiGpsGgaPacket> for GpsGgaPacket {
-    type Error = IntOrFloatParseError;
-    fn try_from(packet: AsciiGpsGgaPacket) -> Result<Self, Self::Error> {
-        let utc_time = UtcTime {
-            hours:   bytes_to_u8(&packet.utc_time[0..2])?, 
-            minutes: bytes_to_u8(&packet.utc_time[2..4])?, 
-            seconds: bytes_to_u8(&packet.utc_time[4..6])?, 
-            // Full stop at packet.utc_time[6]
-            millis:  bytes_to_u16(&packet.utc_time[7..])?,
-        };
-
-        let latitude: f32  = bytes_to_f32(&packet.latitude)?;
-        let longitude: f32 = bytes_to_f32(&packet.longitude)?;
-        let mean_sea_level_altitude: f32 = bytes_to_f32(&packet.mean_sea_level_altitude)?;
-        let position_fix_indicator: PositionFixIndicator = match packet.position_fix_indicator {
-            b'0' => PositionFixIndicator::None,
-            b'1' => PositionFixIndicator::Gps,
-            b'2' => PositionFixIndicator::DifferentialGps,
-            _ => unreachable!(), // Should be unreachable
+impl GpsGgaPacket {
+    /// The UTC time of the packet. Present shortly after power-up, even without a fix.
+    pub fn utc_time(&self) -> Option<Time> { self.utc_time }
+    /// The fix quality as reported, including invalid, estimated and simulated fixes.
+    pub fn quality(&self) -> FixQuality { self.quality }
+    /// The number of satellites used in the solution.
+    pub fn num_satellites(&self) -> u8 { self.num_satellites }
+    /// The position, if the GPS has a valid fix.
+    pub fn fix(&self) -> Option<&Fix> { self.fix.as_ref() }
+}
+impl From<Gga> for GpsGgaPacket {
+    fn from(gga: Gga) -> Self {
+        let fix = match (gga.quality.is_valid(), gga.position) {
+            (true, Some(coordinates)) => Some(Fix {
+                coordinates, 
+                altitude_m: gga.altitude_m, 
+                hdop: gga.hdop, 
+                geoid_separation_m: gga.geoid_separation_m,
+            }),
+            _ => None,
         };
-        let north_south_indicator = packet.north_south_indicator;
-        let east_west_indicator = packet.east_west_indicator;
-        let num_satellites: u8 = bytes_to_u8(&packet.num_satellites)?;
-
-        Ok( GpsGgaPacket{utc_time, latitude, north_south_indicator, longitude, 
-                east_west_indicator, position_fix_indicator, num_satellites, mean_sea_level_altitude} )
+        Self { utc_time: gga.time, quality: gga.quality, num_satellites: gga.satellites.unwrap_or(0), fix }
     }
 }
 
-fn bytes_to_u8(bytes: &[u8]) -> Result<u8, IntOrFloatParseError> {
-    core::str::from_utf8(bytes).unwrap().parse().map_err(IntOrFloatParseError::IntError)
+/// A position fix. Coordinates are signed: south and west are negative.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fix {
+    coordinates: Coordinates,
+    altitude_m: Option<f32>,
+    hdop: Option<f32>,
+    geoid_separation_m: Option<f32>,
 }
-fn bytes_to_u16(bytes: &[u8]) -> Result<u16, IntOrFloatParseError> {
-    core::str::from_utf8(bytes).unwrap().parse().map_err(IntOrFloatParseError::IntError)
+impl Fix {
+    /// Latitude in decimal degrees.
+    pub fn latitude(&self) -> f32 { self.coordinates.latitude() }
+    /// Longitude in decimal degrees.
+    pub fn longitude(&self) -> f32 { self.coordinates.longitude() }
+    /// The exact coordinates in 1e-7 degrees, as sent in telemetry.
+    pub fn coordinates(&self) -> Coordinates { self.coordinates }
+    /// Altitude above mean sea level in metres.
+    pub fn altitude_m(&self) -> Option<f32> { self.altitude_m }
+    /// Horizontal dilution of precision.
+    pub fn hdop(&self) -> Option<f32> { self.hdop }
+    /// Height of the geoid above the WGS84 ellipsoid in metres. Add to the altitude for the height above the ellipsoid.
+    pub fn geoid_separation_m(&self) -> Option<f32> { self.geoid_separation_m }
 }
-fn bytes_to_f32(bytes: &[u8]) -> Result<f32, IntOrFloatParseError> {
-    core::str::from_utf8(bytes).unwrap().parse().map_err(IntOrFloatParseError::FloatError)
-}
-
-#[derive(Debug)]
-pub enum IntOrFloatParseError {
-    IntError(ParseIntError),
-    FloatError(ParseFloatError),
-}
-
-struct UtcTime {
-    hours: u8,
-    minutes: u8,
-    seconds: u8,
-    millis: u16, 
-}
-
-enum PositionFixIndicator {
-    None = 0,
-    Gps = 1,
-    DifferentialGps = 2,
-}
\ No newline at end of file
@@ -0,0 +1,61 @@
+//! The LSM6DSM accelerometer and gyroscope, on I2C over eUSCI_B1 (SDA P4.6, SCL P4.7) with SA0 tied low.
+//!
+//! Both sensors run at 104 Hz into the FIFO, which holds about 3 seconds of samples, so the main loop can drain it in batches
+//! around the radio. The LSM6DSM's INT1 and INT2 aren't connected on the v2.0 board, so its detectors are left off.
+
+use msp430fr2x5x_hal::{clock::{Clock, Smclk}, i2c::{GlitchFilter, I2CBusConfig}};
+use psat_imu::{config::{AccelScale, Config, DataRate, Fifo, GyroScale}, lsm6dsm::{Lsm6dsm, Sample, ADDRESS_SA0_LOW}};
+use crate::pin_mappings::{AccelEusci, AccelI2c, AccelSclPin, AccelSdaPin};
+
+/// The LSM6DSM supports 400 kHz, but standard mode is more forgiving of weak pull-ups.
+const I2C_FREQ_HZ: u32 = 100_000;
+
+/// The default ranges, with both sensors feeding the FIFO.
+const CONFIG: Config = Config {
+    accel_rate: DataRate::Hz104,
+    accel_scale: AccelScale::G16,
+    gyro_rate: DataRate::Hz104,
+    gyro_scale: GyroScale::Dps2000,
+    fifo: Fifo::Continuous { rate: DataRate::Hz104, watermark: 64 },
+};
+/// The time between FIFO samples at 104 Hz, in microseconds.
+const SAMPLE_PERIOD_US: u32 = 9615;
+
+/// Samples read from the FIFO per I2C burst.
+const BATCH_LEN: usize = 8;
+
+pub type Imu = Lsm6dsm<AccelI2c>;
+
+/// Configure the I2C bus, and initialise the sensor with the FIFO on.
+///
+/// A missing or faulty sensor is reported but not fatal, since the flight logic can fall back on the GPS. Reads will fail until
+/// `init` is retried successfully.
+pub fn new(eusci: AccelEusci, smclk: &Smclk, scl: AccelSclPin, sda: AccelSdaPin) -> Imu {
+    let i2c = I2CBusConfig::new(eusci, GlitchFilter::Max50ns)
+        .use_smclk(smclk, (smclk.freq() / I2C_FREQ_HZ) as u16)
+        .configure(scl, sda);
+    let mut imu = Lsm6dsm::new(i2c, ADDRESS_SA0_LOW);
+    if let Err(e) = imu.init(CONFIG) {
+        crate::println!("IMU init failed: {}", e);
+    }
+    imu
+}
+
+/// Drain the FIFO, passing each sample to `f` oldest first, along with its time. The newest sample is taken to be
+/// from `now_ms`, and the others are timed back from it at the FIFO rate.
+pub fn drain_fifo(imu: &mut Imu, now_ms: u32, mut f: impl FnMut(u32, &Sample)) -> Result<(), &'static str> {
+    let words_per_sample = imu.config().fifo_words_per_sample();
+    let mut remaining = (imu.fifo_status()?.unread_words / words_per_sample) as u32;
+    let mut batch = [Sample::default(); BATCH_LEN];
+    loop {
+        let read = imu.drain_fifo(&mut batch)?;
+        for sample in batch.iter().take(read) {
+            remaining = remaining.saturating_sub(1);
+            f(now_ms.saturating_sub(remaining * SAMPLE_PERIOD_US / 1000), sample);
+        }
+        // Samples that arrive while draining are timed at `now_ms`
+        if read < BATCH_LEN {
+            return Ok(());
+        }
+    }
+}
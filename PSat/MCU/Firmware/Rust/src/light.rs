@@ -0,0 +1,45 @@
+//! The photoresistor on P5.2 (A10), which sees light once the payload leaves the capsule. It shares the ADC with the battery
+//! monitor, and each reading is oversampled to 12 bits, so a dim flash through the capsule stands out from the noise.
+
+use embedded_hal::adc::OneShot;
+use msp430fr2x5x_hal::adc::Adc;
+use psat_flight::light::{Config, LightDetector};
+use crate::pin_mappings::LightSensePin;
+
+/// Each reading sums 16 conversions, and drops 2 bits to get 12 bits from the 10-bit ADC with the noise averaged out.
+const OVERSAMPLING: u16 = 16;
+
+pub struct LightSense {
+    pin: LightSensePin,
+    detector: LightDetector,
+}
+impl LightSense {
+    pub fn new(pin: LightSensePin) -> Self {
+        Self { pin, detector: LightDetector::new(Config::default()) }
+    }
+
+    /// Take an oversampled reading of the light level, 0 to 4092.
+    pub fn read(&mut self, adc: &mut Adc) -> u16 {
+        let sum: u16 = (0..OVERSAMPLING).map(|_| nb::block!(adc.read(&mut self.pin)).unwrap_or(0)).sum();
+        sum >> 2
+    }
+
+    /// Take a reading and feed it to the ejection detector. Returns `true` the first time ejection is detected.
+    pub fn update(&mut self, adc: &mut Adc, now_ms: u32) -> bool {
+        let level = self.read(adc);
+        let ejected = self.detector.update(level, now_ms);
+        if ejected {
+            crate::println!("Light: ejection at level {}, baseline {}", level, self.detector.baseline().unwrap_or(0));
+        }
+        ejected
+    }
+
+    /// Start calibrating the dark baseline from the next reading, e.g. once the payload is sealed in the capsule.
+    pub fn recalibrate(&mut self) {
+        self.detector.recalibrate();
+    }
+
+    pub fn detector(&self) -> &LightDetector {
+        &self.detector
+    }
+}
@@ -2,15 +2,18 @@
 
 use core::time::Duration;
 
-use embedded_lora_rfm95::{lora::types::{Bandwidth, CodingRate, CrcMode, HeaderMode, Polarity, PreambleLength, SpreadingFactor, SyncWord}, rfm95::{self, Rfm95Driver}};
-use embedded_hal_compat::{eh1_0::delay::DelayNs, Forward, ForwardCompat};
+use embedded_lora_rfm95::{lora::{config::Config, types::{AgcMode, Bandwidth, CodingRate, CrcMode, HeaderInfo, HeaderMode, LnaBoost, LnaGain, PayloadLength, Polarity, PreambleLength, SpreadingFactor, SyncWord}}, rfm95::{self, Rfm95Driver}};
+use embedded_hal_compat::{eh1_0::delay::DelayNs, markers::ForwardOutputPin, Forward, ForwardCompat};
 use msp430fr2x5x_hal::{delay::Delay, gpio::{Output, Pin, Pin4}, spi::SpiBus, pac::P4};
 use crate::pin_mappings::{LoraCSPin, LoraEusci, LoraResetPin, LoraSpi};
 
 const LORA_FREQ_HZ: u32 = 915_000_000;
+/// Airtime of the longest frame we send is well under a second at SF10, 62.5kHz
+pub const TX_TIMEOUT: Duration = Duration::from_secs(2);
 
+/// Set up the radio without touching the modem. Call `init()` before using it.
 pub fn new(spi: LoraSpi, cs_pin: LoraCSPin, reset_pin: LoraResetPin, delay: Delay) -> Radio {
-    let mut rfm95 = Rfm95Driver::new(spi.forward(), cs_pin.forward(), reset_pin.forward(), DelayWrapper(delay)).unwrap();
+    let rfm95 = Rfm95Driver::new_uninit(spi.forward(), cs_pin.forward());
 
     // 62.5kHz bandwidth, 4/5 coding rate, SF10 gives a bitrate of about 500bps.
     let lora_config = embedded_lora_rfm95::lora::config::Builder::builder()
@@ -19,99 +22,213 @@ pub fn new(spi: LoraSpi, cs_pin: LoraCSPin, reset_pin: LoraResetPin, delay: Dela
         .set_crc_mode(CrcMode::Disabled)
         .set_frequency(LORA_FREQ_HZ.into())
         .set_header_mode(HeaderMode::Explicit)
+        .set_payload_length(PayloadLength::MAX) // In implicit mode set this to the exact packet length to save airtime
         .set_polarity(Polarity::Normal)
         .set_preamble_length(PreambleLength::L8)
         .set_spreading_factor(SpreadingFactor::S10) // High SF == Best range
-        .set_sync_word(SyncWord::PRIVATE);
-    rfm95.set_config(&lora_config).unwrap();
+        .set_sync_word(SyncWord::PRIVATE)
+        .set_lna_gain(LnaGain::G1) // Only used if the AGC is off. G1 is maximum gain, G6 is minimum gain.
+        .set_lna_boost(LnaBoost::On) // Slightly better sensitivity for a little more current while recieving
+        .set_agc_mode(AgcMode::Auto); // Switch to AgcMode::Manual for a fixed gain in electrically noisy areas
 
-    Radio{driver: rfm95}
+    Radio{driver: rfm95, config: lora_config, reset_pin: reset_pin.forward(), delay: DelayWrapper(delay), initialised: false, recoveries: 0}
 }
 
-pub type RFM95 = Rfm95Driver<Forward<SpiBus<LoraEusci>>, Forward<LoraCSPin, embedded_hal_compat::markers::ForwardOutputPin>>;
+pub type RFM95 = Rfm95Driver<Forward<SpiBus<LoraEusci>>, Forward<LoraCSPin, ForwardOutputPin>>;
 /// Top-level interface for the radio module.
+/// 
+/// None of these methods panic. If the modem stops responding, call `recover()` to reset it.
+/// The radio can't be used until `init()` or `recover()` has succeeded, so keep retrying if the modem didn't respond at boot.
 pub struct Radio {
     pub driver: RFM95,
+    /// The last applied config, re-applied after a reset
+    config: Config,
+    reset_pin: Forward<LoraResetPin, ForwardOutputPin>,
+    delay: DelayWrapper,
+    /// Whether the last reset succeeded and the config was applied
+    initialised: bool,
+    /// The number of successful calls to `recover()`, saturating
+    recoveries: u8,
 }
 impl Radio {
-    /// Transmit data and wait until transmission is complete.
+    /// Transmit data and wait until transmission is complete, or until `timeout` has passed.
     /// 
-    /// Panics upon recieving any error from the radio module.
-    pub fn blocking_transmit(&mut self, data: &[u8]) {
-        self.driver.start_tx(data).unwrap();
+    /// The timeout is counted in 1ms polling steps, so the real time taken may be slightly longer.
+    pub fn blocking_transmit(&mut self, data: &[u8], timeout: Duration) -> Result<(), RadioError> {
+        self.driver.start_tx(data)?;
+        let mut elapsed_ms = 0;
         loop {
-            match self.driver.complete_tx(){
-                Ok(None) => continue,   // Still sending
-                Ok(_) => return,        // Sending complete
-                Err(e) => panic!("{e}"),
+            if self.driver.complete_tx()?.is_some() {
+                return Ok(());
             }
+            if elapsed_ms >= timeout.as_millis() {
+                // Abort the transmission, so the modem doesn't stay in TX
+                self.driver.standby()?;
+                return Err(RadioError::Timeout);
+            }
+            self.delay.delay_ms(1);
+            elapsed_ms += 1;
         }
     }
-    /// Try to recieve data, and don't return until a packet is recieved.
+    /// Try to recieve data, and don't return until a packet is recieved or `timeout` has passed.
     /// 
-    /// Panics upon recieving any non-timeout error from the radio module.
-    pub fn blocking_recieve<'a>(&mut self, buf: &'a mut [u8; rfm95::RFM95_FIFO_SIZE]) -> &'a [u8] {
-        let size;
-        'outer: loop {
-            let max_timeout = self.driver.rx_timeout_max().unwrap();
-            self.driver.start_rx(max_timeout).unwrap();
-            
-            'inner: loop {
+    /// The radio keeps listening through packets that fail their CRC check. 
+    /// The timeout is counted in 1ms polling steps, so the real time taken may be slightly longer.
+    pub fn blocking_recieve<'a>(&mut self, buf: &'a mut [u8; rfm95::RFM95_FIFO_SIZE], timeout: Duration) -> Result<&'a [u8], RadioError> {
+        let max_timeout = self.driver.rx_timeout_max()?;
+        let mut elapsed = Duration::ZERO;
+        loop {
+            // The modem can only time out after a limited number of symbols, so listen in windows until the timeout is reached
+            let remaining = timeout.saturating_sub(elapsed);
+            if remaining.is_zero() {
+                return Err(RadioError::Timeout);
+            }
+            self.driver.start_rx(remaining.min(max_timeout))?;
+
+            loop {
                 match self.driver.complete_rx(buf) {
-                    Ok(Some(n)) => {size = n; break 'outer;},
-                    Ok(None) => continue,
-                    Err("RX timeout") => break 'inner,
-                    Err(e) => panic!("{e}"),
+                    Ok(Some(n)) => return Ok(&buf[0..n]),
+                    Ok(None) => (),
+                    Err("RX timeout") | Err("RX CRC error") => break,
+                    Err(e) => return Err(RadioError::Modem(e)),
                 };
-            };
+                if elapsed >= timeout {
+                    self.driver.standby()?;
+                    return Err(RadioError::Timeout);
+                }
+                self.delay.delay_ms(1);
+                elapsed += Duration::from_millis(1);
+            }
         }
-        &buf[0..size]
     }
     /// Begin transmission and return immediately. Check whether the transmission is complete by calling `async_transmit_is_complete()`.
-    pub fn async_transmit_start(&mut self, data: &[u8]) {
-        self.driver.start_tx(data).unwrap();
+    pub fn async_transmit_start(&mut self, data: &[u8]) -> Result<(), RadioError> {
+        self.driver.start_tx(data)?;
+        Ok(())
     }
 
     /// Check whether the radio has finished sending.
-    /// 
-    /// Panics upon recieving any error from the radio module.
-    pub fn async_transmit_is_complete(&mut self) -> bool {
-        match self.driver.complete_tx(){
-            Ok(None) => false,    // Still sending
-            Ok(_) => true,        // Sending complete
-            Err(e) => panic!("{e}"),
-        }
+    pub fn async_transmit_is_complete(&mut self) -> Result<bool, RadioError> {
+        Ok(self.driver.complete_tx()?.is_some())
     }
     /// Tell the radio to listen for a packet and return immediately. Check whether anything was recieved by calling `async_recieve_is_complete()`.
     /// 
     /// A timeout value is optional, if none is provided the maximum timeout is used. You should prepare to deal with timeouts.
-    pub fn async_recieve_start(&mut self, timeout: Option<Duration>) {
+    pub fn async_recieve_start(&mut self, timeout: Option<Duration>) -> Result<(), RadioError> {
         let timeout = match timeout {
             Some(t) => t,
-            None => self.driver.rx_timeout_max().unwrap(),
+            None => self.driver.rx_timeout_max()?,
         };
-        self.driver.start_rx(timeout).unwrap();
+        self.driver.start_rx(timeout)?;
+        Ok(())
     }
 
     /// Check whether the radio has recieved a packet. If so, returns the packet as a slice of bytes.
     /// 
-    /// If not, returns either `StillRecieving` or `RxTimeout`. In the timeout case you should call `async_recieve_start()` again.
-    /// 
-    /// Panics upon recieving any non-timeout error from the radio module.
+    /// If not, returns either `StillRecieving`, `PacketIncoming`, `RxTimeout`, `CrcError` or `Radio`. 
+    /// In the timeout and CRC error cases you should call `async_recieve_start()` again.
+    /// `PacketIncoming` is returned once per packet, as soon as its header has been recieved. Keep polling to get the payload.
     pub fn async_recieve_is_complete<'a>(&mut self, buf: &'a mut [u8; rfm95::RFM95_FIFO_SIZE]) -> Result<&'a [u8], RadioRecieveError> {
         let size = match self.driver.complete_rx(buf) {
             Ok(Some(n)) => n,
-            Ok(None) => return Err(RadioRecieveError::StillRecieving),
+            Ok(None) => match self.driver.rx_header().map_err(RadioError::Modem)? {
+                Some(header) => return Err(RadioRecieveError::PacketIncoming(header)),
+                None => return Err(RadioRecieveError::StillRecieving),
+            },
             Err("RX timeout") => return Err(RadioRecieveError::RxTimeout),
-            Err(e) => panic!("{e}"),
+            Err("RX CRC error") => return Err(RadioRecieveError::CrcError),
+            Err(e) => return Err(RadioError::Modem(e).into()),
         };
         Ok(&buf[0..size])
     }
+
+    /// Reset the modem and apply the config. If the modem doesn't respond, the radio stays uninitialised until this or
+    /// `recover()` succeeds.
+    /// 
+    /// Blocks for at least 11ms.
+    pub fn init(&mut self) -> Result<(), RadioError> {
+        self.initialised = false;
+        self.driver.reset(&mut self.reset_pin, &mut self.delay)?;
+        self.driver.set_config(&self.config)?;
+        self.initialised = true;
+        Ok(())
+    }
+
+    /// Whether the modem has been reset and configured, i.e. the radio can be used.
+    pub fn is_initialised(&self) -> bool {
+        self.initialised
+    }
+
+    /// Reset the modem and re-apply the last config. Any transmission or reception in progress is abandoned.
+    /// 
+    /// Blocks for at least 11ms.
+    pub fn recover(&mut self) -> Result<(), RadioError> {
+        self.init()?;
+        self.recoveries = self.recoveries.saturating_add(1);
+        Ok(())
+    }
+
+    /// The number of times the modem has been recovered since boot. Useful for telemetry.
+    pub fn recoveries(&self) -> u8 {
+        self.recoveries
+    }
+
+    /// Like `blocking_transmit()`, but if anything goes wrong the modem is recovered and the transmission retried, 
+    /// up to `attempts` times in total. Returns the last error if every attempt failed.
+    pub fn blocking_transmit_with_recovery(&mut self, data: &[u8], timeout: Duration, attempts: u8) -> Result<(), RadioError> {
+        let mut result = Err(RadioError::Timeout);
+        for attempt in 0..attempts {
+            if attempt > 0 {
+                if let Err(e) = self.recover() {
+                    result = Err(e);
+                    continue;
+                }
+            }
+            result = self.blocking_transmit(data, timeout);
+            if result.is_ok() {
+                break;
+            }
+        }
+        result
+    }
+}
+
+/// An error from the radio module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RadioError {
+    /// The modem reported an error, or the SPI bus failed.
+    Modem(&'static str),
+    /// The operation did not complete in time.
+    Timeout,
+}
+impl From<&'static str> for RadioError {
+    fn from(e: &'static str) -> Self {
+        RadioError::Modem(e)
+    }
+}
+impl ufmt::uDisplay for RadioError {
+    fn fmt<W: ufmt::uWrite + ?Sized>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error> {
+        match self {
+            RadioError::Modem(e) => ufmt::uwrite!(f, "Modem error: {}", *e),
+            RadioError::Timeout => ufmt::uwrite!(f, "Timeout"),
+        }
+    }
 }
 
 pub enum RadioRecieveError {
     RxTimeout,
     StillRecieving,
+    /// A valid header was recieved, the payload is on its way. Only reported in explicit header mode.
+    PacketIncoming(HeaderInfo),
+    /// A packet was recieved but failed its CRC check. Only reported if CRCs are enabled.
+    CrcError,
+    /// The radio module reported an error. Consider calling `Radio::recover()`.
+    Radio(RadioError),
+}
+impl From<RadioError> for RadioRecieveError {
+    fn from(e: RadioError) -> Self {
+        RadioRecieveError::Radio(e)
+    }
 }
 
 use embedded_hal::blocking::delay::DelayMs;
@@ -140,13 +257,14 @@ impl DelayNs for DelayWrapper {
 }
 
 pub mod tests {
-    use embedded_hal::timer::CountDown;
-    use ufmt::uwrite;
+    use psat_command::frame::COMMAND_LEN;
+    use super::{Radio, RadioRecieveError, TX_TIMEOUT};
 
     pub fn range_test_tx(mut board: crate::board::Board) -> ! {
-        let mut current_time = Time::default();
-        board.timer_b0.start(msp430fr2x5x_hal::clock::REFOCLK); // 1 second timer
+        let radio = &mut board.radio;
+        let mut uptime_s = crate::clock::now_ms() / 1000;
         loop {
+            let current_time = Time::from_uptime_s(uptime_s);
             let bytes = [
                 current_time.hours / 10 + b'0', 
                 current_time.hours % 10 + b'0', 
@@ -156,58 +274,136 @@ pub mod tests {
                 b':', 
                 current_time.seconds / 10 + b'0', 
                 current_time.seconds % 10 + b'0'];
-            board.radio.blocking_transmit(&bytes);
-            nb::block!(board.timer_b0.wait()).ok();
-            current_time.increment();
+            if let Err(e) = radio.blocking_transmit_with_recovery(&bytes, TX_TIMEOUT, 3) {
+                crate::println!("[{}] TX failed: {}", current_time, e);
+            }
+            uptime_s += 1;
+            while crate::clock::now_ms() / 1000 < uptime_s {}
             board.gpio.green_led.toggle();
         }
     }
 
+    /// The Beacon: print every received packet for the ground station, and transmit the command frames it sends back.
+    /// The ground station sends a frame right after it has read a telemetry frame, so it goes out in the payload's RX window.
     pub fn range_test_rx(mut board: crate::board::Board) -> ! {
+        let radio = &mut board.radio;
         let mut buf = [0u8; embedded_lora_rfm95::rfm95::RFM95_FIFO_SIZE];
-        let mut current_time = Time::default();
-        board.timer_b0.start(msp430fr2x5x_hal::clock::REFOCLK); // 1 second timer
-        board.radio.async_recieve_start(None);
+        let mut uplink = Uplink::new();
+        restart_rx(radio);
         loop {
-            match board.radio.async_recieve_is_complete(&mut buf) {
-                Err(super::RadioRecieveError::StillRecieving) => (),
-                Err(super::RadioRecieveError::RxTimeout) => board.radio.async_recieve_start(None),
+            let current_time = Time::from_uptime_s(crate::clock::now_ms() / 1000);
+            if let Some(frame) = uplink.poll() {
+                match radio.blocking_transmit_with_recovery(&frame, TX_TIMEOUT, 3) {
+                    Ok(()) => crate::println!("[{}] Command sent", current_time),
+                    Err(e) => crate::println!("[{}] Command TX failed: {}", current_time, e),
+                }
+                restart_rx(radio);
+            }
+
+            match radio.async_recieve_is_complete(&mut buf) {
+                Err(RadioRecieveError::StillRecieving) => (),
+                Err(RadioRecieveError::RxTimeout) | Err(RadioRecieveError::CrcError) => restart_rx(radio),
+                Err(RadioRecieveError::PacketIncoming(header)) => {
+                    crate::println!("[{}] Packet incoming: {} bytes, CR 4/{}", current_time, header.payload_len(), header.coding_rate() as u8 + 4);
+                },
+                Err(RadioRecieveError::Radio(e)) => {
+                    crate::println!("[{}] RX failed: {}", current_time, e);
+                    restart_rx(radio);
+                },
                 Ok(packet) => {
-                    let signal_strength = board.radio.driver.get_packet_strength().unwrap();
-                    let rssi = board.radio.driver.get_rssi().unwrap(); 
-                    let snr = board.radio.driver.get_packet_snr().unwrap(); 
-                    crate::println!("[{}] '{}', Strength: {}, RSSI: {}, SNR: {}", current_time, core::str::from_utf8(packet).unwrap(), signal_strength, rssi, snr);
-                    board.radio.async_recieve_start(None)
+                    crate::print!("[{}] ", current_time);
+                    print_payload(packet);
+                    let driver = &mut radio.driver;
+                    match (driver.get_packet_strength(), driver.get_rssi(), driver.get_packet_snr(), driver.get_packet_lna_gain()) {
+                        // RSSI is measured after the LNA, so log the gain step with it
+                        (Ok(signal_strength), Ok(rssi), Ok(snr), Ok(gain)) => crate::println!(", Strength: {}, RSSI: {}, SNR: {}, Gain: G{}", signal_strength, rssi, snr, gain as u8),
+                        _ => crate::println!(""),
+                    }
+                    restart_rx(radio);
                 },
             }
+        }
+    }
+    /// Print text payloads in quotes, and binary payloads (e.g. telemetry frames) as `hex:a501...` so the ground station can decode them.
+    fn print_payload(packet: &[u8]) {
+        const HEX: &[u8; 16] = b"0123456789abcdef";
+        match core::str::from_utf8(packet) {
+            Ok(text) => crate::print!("'{}'", text.trim_end()), // Raw NMEA ends in a newline, keep it on one line
+            Err(_) => {
+                crate::print!("hex:");
+                for byte in packet {
+                    let digits = [HEX[(byte >> 4) as usize], HEX[(byte & 0x0F) as usize]];
+                    crate::print!("{}", core::str::from_utf8(&digits).unwrap_or("??"));
+                }
+            },
+        }
+    }
+
+    /// Command frames from the ground station, one per line as `hex:` and the frame's hex digits.
+    struct Uplink {
+        line: [u8; 4 + 2 * COMMAND_LEN],
+        /// Past the end of `line` for a line that is too long, which is dropped
+        len: usize,
+    }
+    impl Uplink {
+        const fn new() -> Self {
+            Self { line: [0; 4 + 2 * COMMAND_LEN], len: 0 }
+        }
+
+        /// Read what the ground station has sent, and return a frame once its line is complete.
+        fn poll(&mut self) -> Option<[u8; COMMAND_LEN]> {
+            while let Some(byte) = crate::serial::read_byte() {
+                match byte {
+                    b'\r' => (),
+                    b'\n' => {
+                        let frame = self.line.get(..self.len).and_then(parse_frame);
+                        self.len = 0;
+                        if frame.is_some() {
+                            return frame;
+                        }
+                    },
+                    _ => {
+                        if let Some(slot) = self.line.get_mut(self.len) {
+                            *slot = byte;
+                        }
+                        self.len = self.len.saturating_add(1);
+                    },
+                }
+            }
+            None
+        }
+    }
+    fn parse_frame(line: &[u8]) -> Option<[u8; COMMAND_LEN]> {
+        let digits = line.strip_prefix(b"hex:")?;
+        if digits.len() != 2 * COMMAND_LEN {
+            return None;
+        }
+        let mut frame = [0; COMMAND_LEN];
+        for (byte, pair) in frame.iter_mut().zip(digits.chunks(2)) {
+            *byte = u8::from_str_radix(core::str::from_utf8(pair).ok()?, 16).ok()?;
+        }
+        Some(frame)
+    }
 
-            if board.timer_b0.wait().is_ok() {
-                current_time.increment();
+    /// Start listening again, resetting the radio if it doesn't respond.
+    fn restart_rx(radio: &mut Radio) {
+        if let Err(e) = radio.async_recieve_start(None) {
+            crate::println!("RX start failed: {}, recovering", e);
+            if let Err(e) = radio.recover().and_then(|_| radio.async_recieve_start(None)) {
+                crate::println!("Recovery failed: {}", e);
             }
         }
     }
-    #[derive(Default)]
+
     struct Time {
         seconds: u8,
         minutes: u8,
         hours: u8,
     }
     impl Time {
-        /// Add one second to the time.
-        pub fn increment(&mut self) {
-            if self.seconds < 59 {
-                self.seconds += 1;
-                return;
-            }
-
-            self.seconds = 0;
-            if self.minutes < 59 {
-                self.minutes += 1;
-                return;
-            }
-
-            self.minutes = 0;
-            self.hours += 1;
+        /// The time since boot. Hours wrap after 255.
+        pub fn from_uptime_s(uptime_s: u32) -> Self {
+            Self { seconds: (uptime_s % 60) as u8, minutes: (uptime_s / 60 % 60) as u8, hours: (uptime_s / 3600) as u8 }
         }
     }
     impl ufmt::uDisplay for Time {
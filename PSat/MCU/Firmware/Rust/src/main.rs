@@ -1,20 +1,25 @@
 #![no_main]
 #![no_std]
+#![feature(abi_msp430_interrupt)]
 
 // External imports
 use msp430_rt::entry;
-use msp430fr2x5x_hal::hal::blocking::delay::DelayMs;
 
 // Internal modules
 mod pin_mappings { include!("pin_mappings_v2_0.rs"); } // Import 'pin_mappings_v2_0' as 'pin_mappings'
 mod board;
+mod buzzer;
+mod clock;
+mod espcam;
+mod flight;
 mod serial;
 mod panic_handler;
 mod lora;
 mod gps;
-
-// Internal imports
-use board::Board;
+mod imu;
+mod light;
+mod servo;
+mod persistent;
 
 #[entry]
 fn main() -> ! {
@@ -24,32 +29,7 @@ fn main() -> ! {
     // Prints over eUSCI A0. See board::configure() for details.
     println!("Hello world!");
 
-    idle_loop(board);
-}
-
-fn idle_loop(mut board: Board) -> ! {
-    loop {
-        // Snake the LEDs through the rainbow
-        const LED_DELAY_MS: u16 = 250; // ms
-
-        board.gpio.red_led.turn_on();
-        board.delay.delay_ms(LED_DELAY_MS);
-
-        board.gpio.green_led.turn_on();
-        board.delay.delay_ms(LED_DELAY_MS);
-
-        board.gpio.blue_led.turn_on();
-        board.delay.delay_ms(LED_DELAY_MS);
-
-        board.gpio.red_led.turn_off();
-        board.delay.delay_ms(LED_DELAY_MS);
-
-        board.gpio.green_led.turn_off();
-        board.delay.delay_ms(LED_DELAY_MS);
-
-        board.gpio.blue_led.turn_off();
-        board.delay.delay_ms(LED_DELAY_MS);
-    }
+    flight::run(board);
 }
 
 // The compiler will emit calls to the abort() compiler intrinsic if debug assertions are
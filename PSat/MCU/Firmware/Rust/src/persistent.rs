@@ -0,0 +1,90 @@
+//! Non-volatile storage in the MSP430's 512-byte information FRAM, which survives resets and power loss.
+//! 
+//! Each value lives in a fixed slot and is stored with a CRC, so erased or half-written slots read back as `None`.
+
+use msp430fr2355::SYS;
+use psat_command::receiver::CounterStore;
+use psat_telemetry::crc::crc16;
+
+/// Start of information FRAM
+const INFO_FRAM_START: usize = 0x1800;
+/// Size of information FRAM in bytes
+const INFO_FRAM_SIZE: usize = 512;
+/// Password for the FRAM write protection bits in SYSCFG0
+const FRWP_PASSWORD: u8 = 0xA5;
+
+/// Slot offsets. Each slot holds its value followed by a 2-byte CRC.
+pub mod slots {
+    /// Counter of the last accepted uplink command
+    pub const COMMAND_COUNTER: usize = 0;
+    /// Flight state machine snapshot, so a reset mid-flight resumes in the same phase
+    pub const FLIGHT_STATE: usize = 8;
+}
+
+pub struct InfoFram {
+    sys: SYS,
+}
+impl InfoFram {
+    pub fn new(sys: SYS) -> Self {
+        Self { sys }
+    }
+
+    /// Read the value in the slot at `offset`, or `None` if the slot has never been written or failed its CRC.
+    pub fn read<const N: usize>(&self, offset: usize) -> Option<[u8; N]> {
+        let mut value = [0u8; N];
+        let mut crc = [0u8; 2];
+        self.read_raw(offset, &mut value)?;
+        self.read_raw(offset + N, &mut crc)?;
+        (crc16(&value) == u16::from_le_bytes(crc)).then_some(value)
+    }
+
+    /// Write a value and its CRC into the slot at `offset`, and read it back to verify it.
+    pub fn write<const N: usize>(&mut self, offset: usize, value: [u8; N]) -> Result<(), &'static str> {
+        if offset + N + 2 > INFO_FRAM_SIZE {
+            return Err("FRAM slot out of range");
+        }
+        self.set_write_protection(false);
+        for (i, &byte) in value.iter().chain(crc16(&value).to_le_bytes().iter()).enumerate() {
+            // SAFETY: The address is within information FRAM, which holds no code or Rust-managed memory
+            unsafe { core::ptr::write_volatile((INFO_FRAM_START + offset + i) as *mut u8, byte) };
+        }
+        self.set_write_protection(true);
+
+        match self.read::<N>(offset) {
+            Some(stored) if stored == value => Ok(()),
+            _ => Err("FRAM verify failed"),
+        }
+    }
+
+    fn read_raw(&self, offset: usize, buf: &mut [u8]) -> Option<()> {
+        if offset + buf.len() > INFO_FRAM_SIZE {
+            return None;
+        }
+        for (i, byte) in buf.iter_mut().enumerate() {
+            // SAFETY: The address is within information FRAM, which is always readable
+            *byte = unsafe { core::ptr::read_volatile((INFO_FRAM_START + offset + i) as *const u8) };
+        }
+        Some(())
+    }
+
+    /// Information FRAM is write protected by the DFWP bit. Program FRAM protection is left untouched.
+    fn set_write_protection(&mut self, protected: bool) {
+        self.sys.syscfg0.modify(|_, w| {
+            // SAFETY: The password is the only value that unlocks the register, see the family user's guide
+            unsafe { w.frwppw().bits(FRWP_PASSWORD) };
+            if protected { w.dfwp().dfwp_1() } else { w.dfwp().dfwp_0() }
+        });
+    }
+}
+
+/// The uplink command counter, stored in information FRAM so commands cannot be replayed after a reset.
+pub struct CommandCounter<'a>(pub &'a mut InfoFram);
+impl CounterStore for CommandCounter<'_> {
+    fn load(&mut self) -> u32 {
+        // A fresh board has no counter yet, so start from zero
+        self.0.read(slots::COMMAND_COUNTER).map(u32::from_le_bytes).unwrap_or(0)
+    }
+    fn store(&mut self, counter: u32) -> Result<(), &'static str> {
+        self.0.write(slots::COMMAND_COUNTER, counter.to_le_bytes())
+    }
+}
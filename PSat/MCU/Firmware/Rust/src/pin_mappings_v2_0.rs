@@ -3,6 +3,7 @@ use msp430fr2x5x_hal::{
         Alternate1, Alternate3, Floating, Input, Output, Pin, Pin0, Pin1, Pin2, Pin3, Pin4, Pin5,
         Pin6, Pin7, Pullup,
     },
+    i2c::I2cBus,
     pac::{E_USCI_A0, E_USCI_A1, E_USCI_B0, E_USCI_B1, P1, P2, P3, P4, P5, P6},
     serial::{Rx, Tx},
     spi::SpiBus,
@@ -34,19 +35,20 @@ pub type GreenLedPin = Pin<P3, Pin1, Output>; // Status LED3
 
 // Accelerometer I2C
 pub type AccelEusci = E_USCI_B1;
+pub type AccelI2c = I2cBus<E_USCI_B1>;
 pub type AccelSclPin = Pin<P4, Pin7, Alternate1<Input<Floating>>>;
 pub type AccelSdaPin = Pin<P4, Pin6, Alternate1<Input<Floating>>>;
+// INT1 and INT2 aren't connected on this board
 
 // Camera/Servo Control
-pub type CamTrigServoPin = Pin<P2, Pin1, Output>; // May need TimerOutput<TB1, CCR2> for PWM
-pub type ShutterServoPin = Pin<P2, Pin0, Output>; // May need TimerOutput<TB1, CCR1> for PWM
-pub type EspCamPin = Pin<P2, Pin4, Output>; // OneWire communication with ESP32 Cam
-pub type EspCamAltPin = Pin<P1, Pin7, Alternate1<Input<Floating>>>; // Backup serial for ESP32 Cam (UCA0TXD)
+pub type CamTrigServoPin = Pin<P2, Pin1, Alternate1<Output>>; // TB1.2
+pub type ShutterServoPin = Pin<P2, Pin0, Alternate1<Output>>; // TB1.1
+pub type EspCamPin = Pin<P2, Pin4, Input<Pullup>>; // Single-wire link to the ESP32 Cam, open drain with the pull-up
 
 // Sensors
 pub type CurrentSensePin = Pin<P5, Pin3, Alternate3<Input<Floating>>>; // A11
 pub type LightSensePin = Pin<P5, Pin2, Alternate3<Input<Floating>>>; // A10
-pub type BuzzerPin = Pin<P5, Pin1, Output>; // May need TimerOutput<TB2, CCR2> for PWM
+pub type BuzzerPin = Pin<P5, Pin1, Alternate1<Output>>; // TB2.2
 pub type HalfVbatPin = Pin<P5, Pin0, Alternate3<Input<Floating>>>; // A8
 
 // Power Management
@@ -54,7 +56,9 @@ pub type Enable5vPin = Pin<P3, Pin0, Output>; // 5V Enable for battery managemen
 
 // Debug interface (if needed)
 pub type DebugEusci = E_USCI_A0;
-pub type DebugTxPin = Pin<P1, Pin7, Alternate1<Input<Floating>>>;
+pub type DebugTxPin = Pin<P1, Pin7, Alternate1<Input<Floating>>>; // TDO, JTAG header (J1b) pin 1
+pub type DebugRxPin = Pin<P1, Pin6, Alternate1<Input<Pullup>>>; // TDI, JTAG header (J1b) pin 2. The Beacon's command uplink.
+pub type DebugRx = Rx<E_USCI_A0>;
 
 // 1	P1.2	I/O	LVCMOS	DVCC	OFF	TRUE	LoRa	SPI Master Out
 // 2	P1.1	I/O	LVCMOS	DVCC	OFF	TRUE	LoRa	SPI Clock
@@ -69,7 +73,8 @@ pub type DebugTxPin = Pin<P1, Pin7, Alternate1<Input<Floating>>>;
 // 27	P2.3	I/O	LVCMOS	DVCC	OFF	TRUE	GPS Module	GPS Enable
 // 29	P2.1(RD)	I/O	LVCMOS	DVCC	OFF	TRUE	CamTrigServo	Send trigger servo PWM signal
 // 30	P2.0	I/O	LVCMOS	DVCC	OFF	TRUE	CamTrigServo	Shutter reload servo PWM signal
-// 31	P1.7	I/O	LVCMOS	DVCC	OFF	FALSE	JTAG, ESP32 Cam	Dual Use - It's a backup for the ESPCam com. If the DIY OneWire protocol doesn't work, we'll jank together the serial logs with communication to the ESPCam.
+// 31	P1.7	I/O	LVCMOS	DVCC	OFF	FALSE	JTAG, Debug UART, ESP32 Cam	Dual Use - TDO on the JTAG header, and UCA0TXD while the JTAG isn't in use. The B20 solder jumper can tie the ESP32 Cam's single wire here instead of P2.4, but it's open and unused.
+// 32	P1.6	I/O	LVCMOS	DVCC	OFF	FALSE	JTAG, Debug UART	Dual Use - TDI on the JTAG header, and UCA0RXD while the JTAG isn't in use. The Beacon reads the ground station's commands here.
 // 40	P5.3	I/O	LVCMOS	DVCC	OFF	TRUE	Current Sense	Analog current sense for servo controlling the shutter
 // 41	P5.2	I/O	LVCMOS	DVCC	OFF	TRUE	LightSense	Read the photoresiste to detect leaving the capsual
 // 42	P5.1	I/O	LVCMOS	DVCC	OFF	TRUE	Buzzer	PWM output for the buzzer - useful for finding capsual after rocket launch
@@ -1,7 +1,10 @@
-use msp430fr2x5x_hal::{clock::Smclk, serial::{BitCount, BitOrder, Loopback, Parity, StopBits, Tx}};
+use embedded_hal::serial::Read;
+use msp430fr2355::interrupt;
+use msp430fr2x5x_hal::{clock::Smclk, serial::{BitCount, BitOrder, Loopback, Parity, RecvError, StopBits, Tx}};
+use psat_nmea::ring::RingBuffer;
 
-/// Configure the debug UART for use with println!().
-pub fn configure_debug_serial(pin: DebugTxPin, smclk: &Smclk, debug_eusci: DebugEusci) {
+/// Configure the debug UART for use with println!(), and hand the receiver to the interrupt handler for `read_byte`.
+pub fn configure_debug_serial(pin: DebugTxPin, rx_pin: DebugRxPin, smclk: &Smclk, debug_eusci: DebugEusci) {
     pub const DEBUG_SERIAL_BAUD: u32 = 115200;
     let debug_uart = msp430fr2x5x_hal::serial::SerialConfig::new(debug_eusci, 
         BitOrder::LsbFirst, 
@@ -11,14 +14,33 @@ pub fn configure_debug_serial(pin: DebugTxPin, smclk: &Smclk, debug_eusci: Debug
         Loopback::NoLoop, 
         DEBUG_SERIAL_BAUD)
         .use_smclk(smclk)
-        .tx_only(pin);
+        .split(pin, rx_pin);
 
+    let (debug_uart, mut debug_rx) = debug_uart;
     let debug_uart = crate::serial::PrintableSerial(debug_uart);
 
     // Move the UART into a global so it can be called anywhere, including in panics.
     msp430::critical_section::with(|cs| {
         crate::serial::SERIAL.replace(cs, Some(debug_uart))
     });
+    debug_rx.enable_rx_interrupts();
+    msp430::critical_section::with(|cs| RX.replace(cs, Some(debug_rx)));
+}
+
+/// Take the next byte the computer has sent, if there is one.
+pub fn read_byte() -> Option<u8> {
+    RX_BUFFER.pop()
+}
+
+#[interrupt]
+fn EUSCI_A0() {
+    msp430::critical_section::with(|cs| {
+        let Some(ref mut rx) = *RX.borrow_ref_mut(cs) else { return };
+        // A byte lost to an overrun breaks its line, which the reader then drops
+        if let Ok(byte) | Err(nb::Error::Other(RecvError::Overrun(byte))) = rx.read() {
+            RX_BUFFER.push(byte);
+        }
+    });
 }
 
 // A little bit of magic to get println working.
@@ -40,9 +62,13 @@ impl core::fmt::Write for PrintableSerial {
 use msp430::interrupt::Mutex;
 use core::cell::RefCell;
 
-use crate::pin_mappings::{DebugEusci, DebugTxPin};
+use crate::pin_mappings::{DebugEusci, DebugRx, DebugRxPin, DebugTxPin};
 /// Used by println macros to print over UART.
 pub static SERIAL: Mutex<RefCell<Option< PrintableSerial >>> = Mutex::new(RefCell::new(None));
+/// Bytes received from the computer, filled by the eUSCI_A0 interrupt. Holds a few command lines.
+static RX_BUFFER: RingBuffer<128> = RingBuffer::new();
+/// The receiver, owned by the interrupt handler.
+static RX: Mutex<RefCell<Option<DebugRx>>> = Mutex::new(RefCell::new(None));
 
 // Make a macro equivalent to the regular println!() macro.
 /// Prints over `eUSCI_A0` serial. Panics if `board::configure()` hasn't been called yet.
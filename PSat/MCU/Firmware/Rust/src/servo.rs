@@ -0,0 +1,60 @@
+//! The camera trigger (P2.1) and shutter reload (P2.0) servos, driven at 50 Hz from Timer_B1 with 1 µs pulse resolution.
+//! They share a current sense on A11, and a servo that draws its stall current for too long is cut off and reported as
+//! jammed.
+
+use msp430fr2x5x_hal::{adc::Adc, clock::Smclk, pac::TB1, pwm::{Pwm, PwmParts3, TimerConfig, TimerDiv, TimerExDiv, CCR1, CCR2}};
+use psat_servo::{mechanism::{Action, Config, Mechanism, Outcome}, servo::{self, Calibration, CurrentSense, Servo}};
+use crate::pin_mappings::{CamTrigServoPin, CurrentSensePin, ShutterServoPin};
+
+/// SMCLK / 8 counts microseconds, so the pulse widths have 1 µs resolution.
+const TIMER_DIV: TimerDiv = TimerDiv::_8;
+
+/// Per-servo pulse widths. These are nominal until measured against the end stops of the servos as fitted.
+const TRIGGER_CALIBRATION: Calibration = Calibration { min_pulse_us: 550, max_pulse_us: 2450, range_deg: 180 };
+const SHUTTER_CALIBRATION: Calibration = Calibration { min_pulse_us: 600, max_pulse_us: 2400, range_deg: 180 };
+
+/// The servo current sense, from the motherboard schematic: a 0.5 Ω high-side shunt (R9 ∥ R11) into an NCS2333
+/// difference amplifier with R13 = 1 kΩ, R14 = 130 Ω, R15 = 150 Ω and R16 = 1 kΩ, so 3.85 mV/mA less 669 mV. These are
+/// nominal; with 5% resistors the offset may be off by ~70 mA, well within the margin to the stall current.
+const CURRENT_SENSE: CurrentSense = CurrentSense { offset_mv: 669, ma_per_v: 260 };
+
+pub type TriggerPwm = Pwm<TB1, CCR2>;
+pub type ShutterPwm = Pwm<TB1, CCR1>;
+
+pub struct Servos {
+    mechanism: Mechanism<TriggerPwm, ShutterPwm>,
+    current_sense: CurrentSensePin,
+}
+
+/// Start 50 Hz PWM on Timer_B1, with both servos off at rest.
+pub fn new(tb1: TB1, smclk: &Smclk, trigger_pin: CamTrigServoPin, shutter_pin: ShutterServoPin, current_sense: CurrentSensePin) -> Servos {
+    // The timer counts from 0 up to and including the period
+    let pwm = PwmParts3::new(tb1, TimerConfig::smclk(smclk).clk_div(TIMER_DIV, TimerExDiv::_1), (servo::PERIOD_US - 1) as u16);
+    let config = Config::default();
+    let trigger = Servo::new(pwm.pwm2.init(trigger_pin), TRIGGER_CALIBRATION, servo::Config::default(), config.trigger_rest_deg);
+    let shutter = Servo::new(pwm.pwm1.init(shutter_pin), SHUTTER_CALIBRATION, servo::Config::default(), config.shutter_rest_deg);
+    Servos { mechanism: Mechanism::new(config, trigger, shutter), current_sense }
+}
+
+impl Servos {
+    /// Start pressing the shutter button, or winding the shutter on.
+    pub fn start(&mut self, action: Action, now_ms: u32) -> Result<(), &'static str> {
+        self.mechanism.start(action, now_ms)
+    }
+
+    /// Move the current action on, measuring the servo current. Returns how the action ended once it has.
+    pub fn update(&mut self, adc: &mut Adc, now_ms: u32) -> Option<Outcome> {
+        if !self.mechanism.is_busy() {
+            return None;
+        }
+        // The ADC is shared with the light sensor and the battery monitor, so wait for this conversion
+        let current_ma = nb::block!(adc.read_voltage_mv(&mut self.current_sense, 3300)).ok().map(|mv| CURRENT_SENSE.current_ma(mv));
+        let outcome = self.mechanism.update(now_ms, current_ma);
+        match outcome {
+            Some(Outcome::Done(action)) => crate::println!("Servo action {} done", action as u8),
+            Some(Outcome::Jammed(action)) => crate::println!("Servo action {} jammed, servo cut off", action as u8),
+            None => (),
+        }
+        outcome
+    }
+}